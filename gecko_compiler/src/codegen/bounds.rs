@@ -0,0 +1,54 @@
+use cranelift::prelude::{types, AbiParam, Block, FunctionBuilder, InstBuilder, TrapCode, Type, Value};
+use cranelift_codegen::{
+    ir::{FuncRef, Signature},
+    isa::CallConv
+};
+use gecko_parser::ast::Span;
+
+// Indices and slice bounds are compared with the length of what they index before its memory is
+// accessed, calling the runtime with them and their location when they are out of bounds. Unlike
+// overflow checks, release builds keep them, as nothing else keeps memory accesses in bounds
+
+// Runtime procedures reporting an index or slice out of bounds, see gecko_runtime::bounds
+pub const INDEX_HANDLER: &str = "__gecko_index_out_of_bounds";
+pub const SLICE_HANDLER: &str = "__gecko_slice_out_of_bounds";
+
+// (index, length, line, column)
+pub fn index_handler_signature(call_conv: CallConv, pointer_type: Type) -> Signature {
+    handler_signature(call_conv, pointer_type, 2)
+}
+
+// (start, end, length, line, column)
+pub fn slice_handler_signature(call_conv: CallConv, pointer_type: Type) -> Signature {
+    handler_signature(call_conv, pointer_type, 3)
+}
+
+fn handler_signature(call_conv: CallConv, pointer_type: Type, bounds: usize) -> Signature {
+    let mut signature = Signature::new(call_conv);
+    signature.params.extend(vec![AbiParam::new(pointer_type); bounds]);
+    signature.params.push(AbiParam::new(types::I32));
+    signature.params.push(AbiParam::new(types::I32));
+    signature
+}
+
+// Calls 'handler' with 'bounds' and the location of 'span' when 'out_of_bounds' is true.
+// Lowering continues in a new block that is only reached in bounds
+pub fn trap_if(builder: &mut FunctionBuilder, out_of_bounds: Value, handler: FuncRef, bounds: &[Value], span: Span) {
+    let trap: Block = builder.create_block();
+    let next: Block = builder.create_block();
+    builder.set_cold_block(trap);
+    builder.ins().brnz(out_of_bounds, trap, &[]);
+    builder.ins().jump(next, &[]);
+
+    builder.switch_to_block(trap);
+    builder.seal_block(trap);
+    let line = builder.ins().iconst(types::I32, span.line() as i64);
+    let column = builder.ins().iconst(types::I32, span.column() as i64);
+    let args: Vec<Value> = bounds.iter().copied().chain([line, column]).collect();
+    builder.ins().call(handler, &args);
+    // The handler aborts, but the block still needs a terminator
+    builder.ins().trap(TrapCode::HeapOutOfBounds);
+
+    builder.switch_to_block(next);
+    builder.seal_block(next);
+}
@@ -0,0 +1,159 @@
+use cranelift::prelude::{types, AbiParam, Block, FunctionBuilder, InstBuilder, TrapCode, Type, Value};
+use cranelift_codegen::{
+    ir::{FuncRef, Signature},
+    isa::CallConv
+};
+use gecko_parser::ast::Span;
+
+// Assertions, 'requires' and 'ensures' clauses are lowered to a branch on their condition, calling
+// the runtime with the text and location of the condition when it is false. Release builds strip
+// them entirely, see BuildMode::checks_contracts
+
+// Runtime procedure reporting a failed condition, see gecko_runtime::contract
+pub const CONTRACT_HANDLER: &str = "__gecko_contract_failed";
+
+// Kind of condition that failed, as the runtime numbers them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    Assertion = 0,
+    Precondition = 1,
+    Postcondition = 2,
+}
+
+// (check, text, text length, message, message length, line, column), where the message pointer is
+// null if the assertion has none
+pub fn handler_signature(call_conv: CallConv, pointer_type: Type) -> Signature {
+    let mut signature = Signature::new(call_conv);
+    signature.params.push(AbiParam::new(types::I32));
+    signature.params.extend([AbiParam::new(pointer_type); 4]);
+    signature.params.push(AbiParam::new(types::I32));
+    signature.params.push(AbiParam::new(types::I32));
+    signature
+}
+
+pub struct ContractSite {
+    // Imported CONTRACT_HANDLER
+    pub handler: FuncRef,
+    pub check: Check,
+    // Pointer and length of the source text of the condition
+    pub text: (Value, Value),
+    // Pointer and length of the str message of an assertion
+    pub message: Option<(Value, Value)>,
+    pub span: Span,
+}
+
+// Calls the handler unless 'condition' is true. Lowering continues in a new block that is only
+// reached when the condition holds
+pub fn check(builder: &mut FunctionBuilder, condition: Value, pointer_type: Type, site: &ContractSite) {
+    let next: Block = unless(builder, condition);
+    fail(builder, pointer_type, site, next);
+}
+
+// Branches to a new cold block, which lowering continues in, when 'condition' is false. Returns
+// the block the condition holding leads to, see fail
+pub fn unless(builder: &mut FunctionBuilder, condition: Value) -> Block {
+    let failed: Block = builder.create_block();
+    let next: Block = builder.create_block();
+    builder.set_cold_block(failed);
+    builder.ins().brz(condition, failed, &[]);
+    builder.ins().jump(next, &[]);
+
+    builder.switch_to_block(failed);
+    builder.seal_block(failed);
+    next
+}
+
+// Calls the handler from the block 'unless' branched to, then continues lowering in 'next'. The
+// site's values can be computed in between, so messages are only built once a check fails
+pub fn fail(builder: &mut FunctionBuilder, pointer_type: Type, site: &ContractSite, next: Block) {
+    let check = builder.ins().iconst(types::I32, site.check as i64);
+    let (message, message_len) = match site.message {
+        Some(message) => message,
+        None => {
+            let null = builder.ins().iconst(pointer_type, 0);
+            (null, null)
+        }
+    };
+    let line = builder.ins().iconst(types::I32, site.span.line() as i64);
+    let column = builder.ins().iconst(types::I32, site.span.column() as i64);
+    builder.ins().call(site.handler, &[check, site.text.0, site.text.1, message, message_len, line, column]);
+    // The handler aborts, but the block still needs a terminator
+    builder.ins().trap(TrapCode::User(0));
+
+    builder.switch_to_block(next);
+    builder.seal_block(next);
+}
+
+#[cfg(test)]
+mod tests {
+    use cranelift::prelude::{settings, types, AbiParam, FunctionBuilder, FunctionBuilderContext, InstBuilder};
+    use cranelift_codegen::{
+        ir::{ExtFuncData, ExternalName, Function, Signature},
+        isa::{self, CallConv},
+        verify_function,
+        Context
+    };
+    use gecko_parser::ast::parse_gecko;
+    use target_lexicon::Triple;
+
+    use super::{check, handler_signature, Check, ContractSite};
+    use crate::codegen::{BuildMode, DataLayout};
+
+    // Function asserting its bool parameter, given the text of the condition and optionally a
+    // message, then returning 0. Nothing is checked if the build mode strips contracts
+    fn lower(mode: BuildMode, with_message: bool) -> Function {
+        let pointer_type = DataLayout::new(&Triple::host()).pointer_type();
+        let mut signature = Signature::new(CallConv::SystemV);
+        signature.params.push(AbiParam::new(types::B1));
+        signature.params.extend([AbiParam::new(pointer_type); 4]);
+        signature.returns.push(AbiParam::new(types::I32));
+
+        let mut function = Function::with_name_signature(ExternalName::user(0, 0), signature);
+        let mut context = FunctionBuilderContext::new();
+        let mut builder = FunctionBuilder::new(&mut function, &mut context);
+        let handler = builder.import_signature(handler_signature(CallConv::SystemV, pointer_type));
+        let handler = builder.import_function(ExtFuncData {
+            name: ExternalName::user(0, 1),
+            signature: handler,
+            colocated: false
+        });
+        let span = parse_gecko("proc f() -> int { return 1; }").unwrap().span.unwrap();
+
+        let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+        builder.seal_block(entry);
+        let params = builder.block_params(entry).to_vec();
+        if mode.checks_contracts() {
+            let message = if with_message { Some((params[3], params[4])) } else { None };
+            let site = ContractSite{ handler, check: Check::Assertion, text: (params[1], params[2]), message, span };
+            check(&mut builder, params[0], pointer_type, &site);
+        }
+        let zero = builder.ins().iconst(types::I32, 0);
+        builder.ins().return_(&[zero]);
+        builder.finalize();
+        function
+    }
+
+    fn compile(function: Function) {
+        let isa = isa::lookup(Triple::host()).unwrap()
+            .finish(settings::Flags::new(settings::builder()))
+            .unwrap();
+        verify_function(&function, &*isa).unwrap();
+        Context::for_function(function).compile(&*isa).unwrap();
+    }
+
+    #[test]
+    fn checks_compile() {
+        compile(lower(BuildMode::Debug, false));
+        compile(lower(BuildMode::Debug, true));
+        compile(lower(BuildMode::Release, false));
+    }
+
+    #[test]
+    fn release_builds_strip_checks() {
+        let calls = |mode| lower(mode, true).display().to_string().matches("call fn0").count();
+        assert_eq!(calls(BuildMode::Debug), 1);
+        assert_eq!(calls(BuildMode::Release), 0);
+    }
+}
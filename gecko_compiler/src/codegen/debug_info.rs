@@ -0,0 +1,526 @@
+use std::{collections::HashMap, env, path::Path};
+
+use cranelift_codegen::{
+    entity::PrimaryMap,
+    ir::{ExternalName, LabelValueLoc, SourceLoc, StackSlot, ValueLabel},
+    isa::TargetIsa,
+    MachSrcLoc,
+    ValueLabelsRanges
+};
+use gimli::{
+    write::{
+        Address, AttributeValue, DwarfUnit, EndianVec, Expression, FileId, LineProgram, LineString, Location as DwarfLocation,
+        LocationList, Range, RangeList, Sections, UnitEntryId, Writer
+    },
+    constants, Encoding, Format, LineEncoding, Register, RunTimeEndian, SectionId
+};
+use object::{
+    write::{Object, Relocation, SymbolId},
+    RelocationEncoding, RelocationKind, SectionKind
+};
+use target_lexicon::Architecture;
+
+use crate::{
+    codegen::{DataLayout, Program},
+    tree_type::{FundamentalType, Type},
+    error::TypeError
+};
+
+// DWARF debug information, written into object files with '-g' so gdb and lldb can step through
+// Gecko code and show its variables and backtraces. Every procedure gets a subprogram entry and
+// a sequence of the line table, from the source lines its instructions were lowered from, see
+// Lowering::at. Variables in stack slots, as at -O0, are found from the frame pointer for the
+// whole procedure. Variables in registers are found wherever Cranelift reports their register,
+// and debuggers show them as optimised out elsewhere, such as while they are spilled
+
+// Version of DWARF written, which every debugger still reads
+const DWARF_VERSION: u16 = 4;
+
+// Where a variable is kept while its procedure runs
+#[derive(Debug, Clone, Copy)]
+pub enum Location {
+    // Stack slot of the variable alone, at -O0
+    Slot(StackSlot),
+    // Label of the value of the variable, see FunctionBuilder::set_val_label
+    Value(ValueLabel),
+}
+
+// Parameter or 'let' binding of a procedure
+#[derive(Debug, Clone)]
+pub struct Variable {
+    pub name: String,
+    pub ty: Type,
+    pub line: u32,
+    pub parameter: bool,
+    pub location: Location,
+}
+
+// Procedure defined in a file, as debuggers show it. Variables of inlined callees are not
+// recorded, as their code is part of the caller's
+#[derive(Debug, Clone)]
+pub struct Procedure {
+    // Name of the function it was lowered to
+    pub function: ExternalName,
+    pub name: String,
+    pub line: u32,
+    pub output: Type,
+    pub variables: Vec<Variable>,
+}
+
+// Function written to an object, with what its debug information needs from its compilation
+pub struct Compiled {
+    pub function: ExternalName,
+    pub symbol: SymbolId,
+    pub size: u64,
+    // Ranges of machine code compiled from each source line, in order
+    pub srclocs: Vec<MachSrcLoc>,
+    // Distance from the stack pointer after the prologue to the frame pointer
+    pub frame_size: u32,
+    // Offsets of stack slots from the stack pointer after the prologue
+    pub stackslot_offsets: PrimaryMap<StackSlot, u32>,
+    pub value_labels_ranges: ValueLabelsRanges,
+}
+
+// Register holding the frame pointer, which the frame base of every procedure is
+fn frame_pointer(architecture: Architecture) -> Option<Register> {
+    match architecture {
+        Architecture::X86_64 => Some(gimli::X86_64::RBP),
+        Architecture::Aarch64(_) => Some(gimli::AArch64::X29),
+        _ => None
+    }
+}
+
+// What a relocation of a debug section refers to
+#[derive(Clone, Copy)]
+enum Target {
+    // Index of a function in the functions given to 'write'
+    Function(usize),
+    Section(SectionId),
+}
+
+#[derive(Clone)]
+struct DebugRelocation {
+    offset: u64,
+    size: u8,
+    target: Target,
+    addend: i64,
+}
+
+// Debug section being written, with the addresses and offsets into other sections the linker
+// relocates
+#[derive(Clone)]
+struct Section {
+    data: EndianVec<RunTimeEndian>,
+    relocations: Vec<DebugRelocation>,
+}
+
+impl Writer for Section {
+    type Endian = RunTimeEndian;
+
+    fn endian(&self) -> RunTimeEndian {
+        self.data.endian()
+    }
+
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> gimli::write::Result<()> {
+        self.data.write(bytes)
+    }
+
+    fn write_at(&mut self, offset: usize, bytes: &[u8]) -> gimli::write::Result<()> {
+        self.data.write_at(offset, bytes)
+    }
+
+    fn write_address(&mut self, address: Address, size: u8) -> gimli::write::Result<()> {
+        match address {
+            Address::Constant(value) => self.write_udata(value, size),
+            Address::Symbol{ symbol, addend } => {
+                self.relocations.push(DebugRelocation{ offset: self.len() as u64, size, target: Target::Function(symbol), addend });
+                self.write_udata(0, size)
+            }
+        }
+    }
+
+    fn write_offset(&mut self, offset: usize, section: SectionId, size: u8) -> gimli::write::Result<()> {
+        self.relocations.push(DebugRelocation{ offset: self.len() as u64, size, target: Target::Section(section), addend: offset as i64 });
+        self.write_udata(0, size)
+    }
+
+    fn write_offset_at(&mut self, at: usize, offset: usize, section: SectionId, size: u8) -> gimli::write::Result<()> {
+        self.relocations.push(DebugRelocation{ offset: at as u64, size, target: Target::Section(section), addend: offset as i64 });
+        self.write_udata_at(at, 0, size)
+    }
+}
+
+// Debug information of the procedures of a file, being built
+struct Builder<'p> {
+    dwarf: DwarfUnit,
+    data_layout: DataLayout,
+    file: FileId,
+    frame_pointer: Option<Register>,
+    isa: &'p dyn TargetIsa,
+    // Entries of the types named so far, by name
+    types: HashMap<String, Option<UnitEntryId>>,
+}
+
+impl<'p> Builder<'p> {
+    fn string(&mut self, text: &str) -> AttributeValue {
+        AttributeValue::StringRef(self.dwarf.strings.add(text))
+    }
+
+    // Entry of a type, for the types variables can have
+    fn type_entry(&mut self, ty: &Type) -> Option<UnitEntryId> {
+        let name = ty.to_string();
+        if let Some(entry) = self.types.get(&name) {
+            return *entry;
+        }
+        let root = self.dwarf.unit.root();
+        let entry = match &ty.ty {
+            FundamentalType::Pointer(pointee) => {
+                let pointee = self.type_entry(pointee);
+                let entry = self.dwarf.unit.add(root, constants::DW_TAG_pointer_type);
+                let size = self.data_layout.pointer_type().bytes();
+                let die = self.dwarf.unit.get_mut(entry);
+                die.set(constants::DW_AT_byte_size, AttributeValue::Udata(size as u64));
+                // Pointers to values debuggers cannot show yet are shown as 'void *'
+                if let Some(pointee) = pointee {
+                    die.set(constants::DW_AT_type, AttributeValue::UnitRef(pointee));
+                }
+                Some(entry)
+            },
+            scalar => {
+                let encoding = match scalar {
+                    FundamentalType::Boolean => constants::DW_ATE_boolean,
+                    FundamentalType::Character => constants::DW_ATE_unsigned_char,
+                    scalar if scalar.is_float() => constants::DW_ATE_float,
+                    scalar if scalar.is_signed() => constants::DW_ATE_signed,
+                    scalar if scalar.is_integer() => constants::DW_ATE_unsigned,
+                    _ => return None
+                };
+                // Booleans are a byte in memory, as in C
+                let size = match scalar {
+                    FundamentalType::Boolean => 1,
+                    scalar => scalar.to_ir_type(&self.data_layout).bytes()
+                };
+                let name = self.string(&name);
+                let entry = self.dwarf.unit.add(root, constants::DW_TAG_base_type);
+                let die = self.dwarf.unit.get_mut(entry);
+                die.set(constants::DW_AT_name, name);
+                die.set(constants::DW_AT_encoding, AttributeValue::Encoding(encoding));
+                die.set(constants::DW_AT_byte_size, AttributeValue::Udata(size as u64));
+                Some(entry)
+            }
+        };
+        self.types.insert(name, entry);
+        entry
+    }
+
+    // Sequence of the line table for the machine code of a procedure, which starts at the line
+    // of its definition
+    fn lines(&mut self, index: usize, compiled: &Compiled, procedure: &Procedure) {
+        let program = &mut self.dwarf.unit.line_program;
+        program.begin_sequence(Some(Address::Symbol{ symbol: index, addend: 0 }));
+        program.row().file = self.file;
+        program.row().line = procedure.line as u64;
+        program.generate_row();
+        let mut body = false;
+        for srcloc in compiled.srclocs.iter().filter(|srcloc| srcloc.loc != SourceLoc::default()) {
+            let row = program.row();
+            row.address_offset = srcloc.start as u64;
+            row.line = srcloc.loc.bits() as u64;
+            // Breakpoints on the procedure stop after the prologue
+            row.prologue_end = !body && srcloc.start > 0;
+            body |= row.prologue_end;
+            program.generate_row();
+        }
+        program.end_sequence(compiled.size);
+    }
+
+    // Location of a variable, as an expression for the whole procedure or as a list of the
+    // registers its value is in
+    fn location(&mut self, index: usize, compiled: &Compiled, location: Location) -> Option<AttributeValue> {
+        match location {
+            Location::Slot(slot) => {
+                self.frame_pointer?;
+                let offset = *compiled.stackslot_offsets.get(slot)? as i64 - compiled.frame_size as i64;
+                let mut expression = Expression::new();
+                expression.op_fbreg(offset);
+                Some(AttributeValue::Exprloc(expression))
+            },
+            Location::Value(label) => {
+                // Ranges end one byte past the instructions they cover, see
+                // VCode::compute_value_labels_ranges
+                let locations: Vec<DwarfLocation> = compiled.value_labels_ranges.get(&label)?
+                    .iter()
+                    .filter_map(|range| {
+                        let register = match range.loc {
+                            LabelValueLoc::Reg(register) => self.isa.map_regalloc_reg_to_dwarf(register).ok()?,
+                            LabelValueLoc::SPOffset(_) => return None
+                        };
+                        let start = range.start.saturating_sub(1) as u64;
+                        let end = (range.end.saturating_sub(1) as u64).min(compiled.size);
+                        let mut expression = Expression::new();
+                        expression.op_reg(Register(register));
+                        (start < end).then_some(DwarfLocation::StartLength{
+                            begin: Address::Symbol{ symbol: index, addend: start as i64 },
+                            length: end - start,
+                            data: expression
+                        })
+                    })
+                    .collect();
+                let list = self.dwarf.unit.locations.add(LocationList(locations));
+                Some(AttributeValue::LocationListRef(list))
+            }
+        }
+    }
+
+    // Subprogram entry of a procedure, with its parameters and variables
+    fn subprogram(&mut self, index: usize, compiled: &Compiled, procedure: &Procedure, symbol: &str) {
+        let root = self.dwarf.unit.root();
+        let output = self.type_entry(&procedure.output);
+        let name = self.string(&procedure.name);
+        let linkage_name = self.string(symbol);
+        let entry = self.dwarf.unit.add(root, constants::DW_TAG_subprogram);
+        let die = self.dwarf.unit.get_mut(entry);
+        die.set(constants::DW_AT_name, name);
+        // Entry procedures named 'main' are renamed, see entry::ENTRY_SYMBOL
+        if symbol != procedure.name {
+            die.set(constants::DW_AT_linkage_name, linkage_name);
+        }
+        die.set(constants::DW_AT_external, AttributeValue::Flag(true));
+        die.set(constants::DW_AT_decl_file, AttributeValue::FileIndex(Some(self.file)));
+        die.set(constants::DW_AT_decl_line, AttributeValue::Udata(procedure.line as u64));
+        die.set(constants::DW_AT_low_pc, AttributeValue::Address(Address::Symbol{ symbol: index, addend: 0 }));
+        die.set(constants::DW_AT_high_pc, AttributeValue::Udata(compiled.size));
+        if let Some(output) = output {
+            die.set(constants::DW_AT_type, AttributeValue::UnitRef(output));
+        }
+        if let Some(frame_pointer) = self.frame_pointer {
+            let mut expression = Expression::new();
+            expression.op_reg(frame_pointer);
+            die.set(constants::DW_AT_frame_base, AttributeValue::Exprloc(expression));
+        }
+
+        for variable in &procedure.variables {
+            let ty = self.type_entry(&variable.ty);
+            let location = self.location(index, compiled, variable.location);
+            let name = self.string(&variable.name);
+            let tag = match variable.parameter {
+                true => constants::DW_TAG_formal_parameter,
+                false => constants::DW_TAG_variable
+            };
+            let child = self.dwarf.unit.add(entry, tag);
+            let die = self.dwarf.unit.get_mut(child);
+            die.set(constants::DW_AT_name, name);
+            die.set(constants::DW_AT_decl_file, AttributeValue::FileIndex(Some(self.file)));
+            die.set(constants::DW_AT_decl_line, AttributeValue::Udata(variable.line as u64));
+            if let Some(ty) = ty {
+                die.set(constants::DW_AT_type, AttributeValue::UnitRef(ty));
+            }
+            if let Some(location) = location {
+                die.set(constants::DW_AT_location, location);
+            }
+        }
+    }
+}
+
+// Adds the debug sections describing the compiled functions of a program, compiled from
+// 'source', to an object
+pub fn write(object: &mut Object, program: &Program, functions: &[Compiled], isa: &dyn TargetIsa, source: &Path) -> Result<(), TypeError> {
+    let triple = isa.triple();
+    let data_layout = DataLayout::new(triple);
+    let encoding = Encoding{
+        format: Format::Dwarf32,
+        version: DWARF_VERSION,
+        address_size: data_layout.pointer_type().bytes() as u8
+    };
+    let directory = env::current_dir()
+        .map_err(|e| TypeError{ text: format!("Unable to find the current directory: {}", e) })?;
+    let directory = directory.to_string_lossy().into_owned();
+    let file_name = source.to_string_lossy().into_owned();
+
+    let mut dwarf = DwarfUnit::new(encoding);
+    let comp_dir = LineString::new(directory.as_bytes(), encoding, &mut dwarf.line_strings);
+    let comp_file = LineString::new(file_name.as_bytes(), encoding, &mut dwarf.line_strings);
+    dwarf.unit.line_program = LineProgram::new(encoding, LineEncoding::default(), comp_dir, comp_file, None);
+    let file_line_string = LineString::new(file_name.as_bytes(), encoding, &mut dwarf.line_strings);
+    let default_directory = dwarf.unit.line_program.default_directory();
+    let file = dwarf.unit.line_program.add_file(file_line_string, default_directory, None);
+
+    let mut builder = Builder{ dwarf, data_layout, file, frame_pointer: frame_pointer(triple.architecture), isa, types: HashMap::new() };
+    let mut ranges: Vec<Range> = vec!();
+    for (index, compiled) in functions.iter().enumerate() {
+        ranges.push(Range::StartLength{ begin: Address::Symbol{ symbol: index, addend: 0 }, length: compiled.size });
+        // Startup stubs and thunks have no procedure, and are only described by symbols
+        let procedure = match program.debug_info.iter().find(|procedure| procedure.function == compiled.function) {
+            Some(procedure) => procedure,
+            None => continue
+        };
+        let symbol = program.symbol_name(&compiled.function).expect("Functions are named after their symbols");
+        builder.lines(index, compiled, procedure);
+        builder.subprogram(index, compiled, procedure, symbol);
+    }
+
+    let producer = builder.string(&format!("gecko {} {}", env!("CARGO_PKG_VERSION"), program.opt_level));
+    let name = builder.string(&file_name);
+    let comp_dir = builder.string(&directory);
+    let ranges = builder.dwarf.unit.ranges.add(RangeList(ranges));
+    let root = builder.dwarf.unit.root();
+    let die = builder.dwarf.unit.get_mut(root);
+    die.set(constants::DW_AT_producer, producer);
+    // Debuggers show Gecko's scalars and pointers the way they show C's
+    die.set(constants::DW_AT_language, AttributeValue::Language(constants::DW_LANG_C99));
+    die.set(constants::DW_AT_name, name);
+    die.set(constants::DW_AT_comp_dir, comp_dir);
+    die.set(constants::DW_AT_low_pc, AttributeValue::Address(Address::Constant(0)));
+    die.set(constants::DW_AT_ranges, AttributeValue::RangeListRef(ranges));
+
+    let endian = match triple.endianness() {
+        Ok(target_lexicon::Endianness::Big) => RunTimeEndian::Big,
+        _ => RunTimeEndian::Little
+    };
+    let mut sections = Sections::new(Section{ data: EndianVec::new(endian), relocations: vec!() });
+    builder.dwarf.write(&mut sections).map_err(|e| TypeError{ text: format!("Unable to write debug information: {}", e) })?;
+
+    // Every section is added before the relocations referring to them
+    let mut ids = HashMap::new();
+    sections.for_each(|id, section| -> Result<(), TypeError> {
+        if section.len() > 0 {
+            let section_id = object.add_section(vec!(), id.name().as_bytes().to_vec(), SectionKind::Debug);
+            object.set_section_data(section_id, section.data.slice().to_vec(), 1);
+            ids.insert(id, section_id);
+        }
+        Ok(())
+    })?;
+    sections.for_each(|id, section| -> Result<(), TypeError> {
+        for relocation in &section.relocations {
+            let symbol = match relocation.target {
+                Target::Function(index) => functions[index].symbol,
+                Target::Section(target) => object.section_symbol(ids[&target])
+            };
+            object.add_relocation(ids[&id], Relocation{
+                offset: relocation.offset,
+                size: relocation.size * 8,
+                kind: RelocationKind::Absolute,
+                encoding: RelocationEncoding::Generic,
+                symbol,
+                addend: relocation.addend
+            }).map_err(|e| TypeError{ text: e.to_string() })?;
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, collections::HashMap, path::Path};
+
+    use gecko_parser::ast::parse_gecko;
+    use gimli::{constants, EndianSlice, LittleEndian, SectionId};
+    use object::{Object, ObjectSection, ObjectSymbol};
+
+    use crate::{
+        codegen::{emit_object, generate_ir, object_isa, parse_target, BuildMode, OptLevel},
+        error::TypeError,
+        tree_type::annotate_file_for
+    };
+
+    const SOURCE: &str = "proc square(n: int) -> int {
+    let m: int = n * n;
+    m + 1
+}
+proc main(argc: int, argv: **char) -> int {
+    let flag := argc > 1;
+    if flag { square(argc) } else { 0 }
+}";
+
+    fn emit(opt_level: OptLevel, target: &str) -> Result<Vec<u8>, TypeError> {
+        let triple = parse_target(target)?;
+        let file = parse_gecko(SOURCE).unwrap();
+        let (_, symbol_table) = annotate_file_for(&file, &triple).unwrap();
+        let program = generate_ir(&file, symbol_table, BuildMode::Debug, opt_level, &triple)?;
+        emit_object(&program, &*object_isa(triple, opt_level)?, Some(Path::new("square.gk")))
+    }
+
+    // Data of a section with its relocations applied, as the linker would
+    fn relocated<'d>(file: &object::File<'d>, name: &str) -> Vec<u8> {
+        let section = match file.section_by_name(name) {
+            Some(section) => section,
+            None => return vec!()
+        };
+        let mut data: Vec<u8> = section.data().unwrap().to_vec();
+        for (offset, relocation) in section.relocations() {
+            let base = match relocation.target() {
+                object::RelocationTarget::Symbol(symbol) => file.symbol_by_index(symbol).unwrap().address(),
+                target => panic!("Unexpected relocation target {:?}", target)
+            };
+            let value = (base as i64 + relocation.addend()).to_le_bytes();
+            let size = relocation.size() as usize / 8;
+            data[offset as usize..offset as usize + size].copy_from_slice(&value[..size]);
+        }
+        data
+    }
+
+    // Tags and names of the entries of the unit of an object, in order, and the lines of its
+    // line table
+    fn entries(bytes: &[u8]) -> (Vec<(constants::DwTag, String)>, Vec<u64>) {
+        let file = object::File::parse(bytes).unwrap();
+        let sections: HashMap<SectionId, Vec<u8>> = [SectionId::DebugAbbrev, SectionId::DebugInfo, SectionId::DebugLine, SectionId::DebugStr, SectionId::DebugLoc, SectionId::DebugRanges]
+            .into_iter()
+            .map(|id| (id, relocated(&file, id.name())))
+            .collect();
+        let dwarf = gimli::Dwarf::load(|id| Ok::<_, gimli::Error>(Cow::Borrowed(sections.get(&id).map_or(&[][..], Vec::as_slice)))).unwrap();
+        let dwarf = dwarf.borrow(|section| EndianSlice::new(section, LittleEndian));
+
+        let header = dwarf.units().next().unwrap().unwrap();
+        let unit = dwarf.unit(header).unwrap();
+        let mut entries = vec!();
+        let mut cursor = unit.entries();
+        while let Some((_, entry)) = cursor.next_dfs().unwrap() {
+            let name = match entry.attr_value(constants::DW_AT_name).unwrap() {
+                Some(name) => dwarf.attr_string(&unit, name).unwrap().to_string_lossy().into_owned(),
+                None => String::new()
+            };
+            entries.push((entry.tag(), name));
+        }
+        let mut lines = vec!();
+        let mut rows = unit.line_program.clone().unwrap().rows();
+        while let Some((_, row)) = rows.next_row().unwrap() {
+            lines.push(row.line().map_or(0, |line| line.get()));
+        }
+        (entries, lines)
+    }
+
+    #[test]
+    fn procedures_and_variables() {
+        for (opt_level, target) in [(OptLevel::O0, "x86_64-linux"), (OptLevel::O1, "x86_64-linux"), (OptLevel::O0, "aarch64-linux")] {
+            let (entries, lines) = entries(&emit(opt_level, target).unwrap());
+            let named: Vec<(constants::DwTag, &str)> = entries.iter()
+                .filter(|(tag, _)| [constants::DW_TAG_compile_unit, constants::DW_TAG_subprogram, constants::DW_TAG_formal_parameter, constants::DW_TAG_variable].contains(tag))
+                .map(|(tag, name)| (*tag, name.as_str()))
+                .collect();
+            assert_eq!(named, [
+                (constants::DW_TAG_compile_unit, "square.gk"),
+                (constants::DW_TAG_subprogram, "square"),
+                (constants::DW_TAG_formal_parameter, "n"),
+                (constants::DW_TAG_variable, "m"),
+                (constants::DW_TAG_subprogram, "main"),
+                (constants::DW_TAG_formal_parameter, "argc"),
+                (constants::DW_TAG_formal_parameter, "argv"),
+                (constants::DW_TAG_variable, "flag")
+            ], "{} {}", opt_level, target);
+            assert!(entries.contains(&(constants::DW_TAG_base_type, String::from("i32"))));
+            // Optimised code can move the instructions of a line into the next
+            for line in [1, 2, 3, 5, 7] {
+                assert!(lines.contains(&line), "No row for line {} in {:?} at {} for {}", line, lines, opt_level, target);
+            }
+        }
+    }
+
+    #[test]
+    fn unsupported_formats() {
+        assert_eq!(emit(OptLevel::O0, "x86_64-windows").err().unwrap().text, "Debug information for 'x86_64-pc-windows-msvc' cannot be written yet");
+    }
+}
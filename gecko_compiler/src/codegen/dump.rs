@@ -0,0 +1,219 @@
+use std::{env, fmt::{self, Write}, fs, process::Command};
+
+use cranelift::prelude::Value;
+use cranelift_codegen::{
+    entity::SecondaryMap,
+    ir::{entities::AnyEntity, Block, ExternalName, Function, GlobalValueData, Inst, SourceLoc},
+    isa::TargetIsa,
+    write::{decorate_function, FuncWriter, PlainWriter},
+    Context
+};
+
+use crate::{
+    codegen::{emit_object, ir::DATA_NAMESPACE, Program},
+    error::TypeError
+};
+
+// Textual dumps of compiled procedures, for '--emit=clif' and '--emit=asm'. Dumps start with the
+// optimisation level, and instructions are grouped by the source line they were lowered from, see
+// Lowering::at, with a comment holding the line starting every group. Machine code is
+// disassembled by objdump, or $OBJDUMP, from the object file the program would be written to
+
+// Comment introducing the instructions of a source line
+fn line_comment(source: &[&str], srcloc: SourceLoc) -> Option<String> {
+    let line = srcloc.bits() as usize;
+    let text = source.get(line.checked_sub(1)?)?;
+    Some(format!("; {}: {}", line, text.trim()))
+}
+
+// Name a symbol or piece of data is referred to by in the IR of a program
+fn external_name(program: &Program, name: &ExternalName) -> String {
+    match (program.symbol_name(name), name) {
+        (Some(symbol), _) => symbol.to_owned(),
+        (None, ExternalName::User{ namespace: DATA_NAMESPACE, index }) => format!("data {}", index),
+        (None, name) => name.to_string()
+    }
+}
+
+// Functions of a program to dump, with the symbols they are named after
+fn functions<'p>(program: &'p Program, only: Option<&str>) -> Result<Vec<(&'p str, &'p Function)>, TypeError> {
+    let functions: Vec<(&str, &Function)> = program.functions.iter()
+        .map(|function| (program.symbol_name(&function.name).expect("Functions are named after their symbols"), function))
+        .filter(|(name, _)| only.is_none_or(|only| only == *name))
+        .collect();
+    match (only, functions.is_empty()) {
+        (Some(name), true) => Err(TypeError{ text: format!("No procedure named '{}' was compiled", name) }),
+        _ => Ok(functions)
+    }
+}
+
+// Writes IR with the source line before each group of instructions, and the symbols that
+// procedures and data are imported as
+struct Annotated<'a> {
+    program: &'a Program,
+    source: &'a [&'a str],
+    srcloc: SourceLoc,
+}
+
+impl<'a> FuncWriter for Annotated<'a> {
+    fn write_block_header(&mut self, w: &mut dyn Write, func: &Function, block: Block, indent: usize) -> fmt::Result {
+        PlainWriter.write_block_header(w, func, block, indent)
+    }
+
+    fn write_instruction(&mut self, w: &mut dyn Write, func: &Function, aliases: &SecondaryMap<Value, Vec<Value>>, inst: Inst, indent: usize) -> fmt::Result {
+        let srcloc: SourceLoc = func.srclocs[inst];
+        if srcloc != self.srcloc {
+            self.srcloc = srcloc;
+            if let Some(comment) = line_comment(self.source, srcloc) {
+                writeln!(w, "{:indent$}{}", "", comment, indent = indent)?;
+            }
+        }
+        PlainWriter.write_instruction(w, func, aliases, inst, indent)
+    }
+
+    fn write_entity_definition(&mut self, w: &mut dyn Write, func: &Function, entity: AnyEntity, value: &dyn fmt::Display) -> fmt::Result {
+        let name = match entity {
+            AnyEntity::FuncRef(function) => Some(&func.dfg.ext_funcs[function].name),
+            AnyEntity::GlobalValue(global) => match &func.global_values[global] {
+                GlobalValueData::Symbol{ name, .. } => Some(name),
+                _ => None
+            },
+            _ => None
+        };
+        match name {
+            Some(name) => writeln!(w, "    {} = {} ; {}", entity, value, external_name(self.program, name)),
+            None => self.super_entity_definition(w, func, entity, value)
+        }
+    }
+}
+
+// Cranelift IR of the functions of a program, or of the one named 'only'
+pub fn clif(program: &Program, source: &str, only: Option<&str>) -> Result<String, TypeError> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut output = format!("; optimised at {}\n\n", program.opt_level);
+    for (name, function) in functions(program, only)? {
+        writeln!(output, "; proc {}", name).unwrap();
+        let mut writer = Annotated{ program, source: &lines, srcloc: SourceLoc::default() };
+        decorate_function(&mut writer, &mut output, function).unwrap();
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+// Ranges of machine code compiled from each source line of a function, in order
+fn srclocs(function: &Function, isa: &dyn TargetIsa) -> Result<Vec<(u32, u32, SourceLoc)>, TypeError> {
+    let mut context = Context::for_function(function.clone());
+    context.compile(isa)
+        .map_err(|e| TypeError{ text: format!("Procedure could not be compiled: {:?}", e) })?;
+    let buffer = &context.mach_compile_result.as_ref().unwrap().buffer;
+    Ok(buffer.get_srclocs_sorted().iter().map(|srcloc| (srcloc.start, srcloc.end, srcloc.loc)).collect())
+}
+
+// Disassembly of the functions of a program, or of the one named 'only'
+pub fn asm(program: &Program, isa: &dyn TargetIsa, source: &str, only: Option<&str>) -> Result<String, TypeError> {
+    let functions = functions(program, only)?;
+    let object = emit_object(program, isa, None)?;
+    let path = env::temp_dir().join(format!("gecko-asm-{}.o", std::process::id()));
+    fs::write(&path, object).map_err(|e| TypeError{ text: format!("Unable to write '{}': {}", path.display(), e) })?;
+    let objdump = env::var("OBJDUMP").unwrap_or_else(|_| String::from("objdump"));
+    let disassembly = Command::new(&objdump)
+        .args(["-d", "-r", "--no-show-raw-insn"])
+        .arg(&path)
+        .output();
+    fs::remove_file(&path).ok();
+    let disassembly = disassembly.map_err(|e| TypeError{ text: format!("Unable to run '{}': {}", objdump, e) })?;
+    if !disassembly.status.success() {
+        return Err(TypeError{ text: format!("'{}' failed: {}", objdump, String::from_utf8_lossy(&disassembly.stderr).trim()) });
+    }
+    let disassembly = String::from_utf8_lossy(&disassembly.stdout);
+
+    let lines: Vec<&str> = source.lines().collect();
+    let mut output = format!("; optimised at {}\n\n", program.opt_level);
+    for (name, function) in functions {
+        let srclocs = srclocs(function, isa)?;
+        let mut listing = disassembly.lines()
+            .skip_while(|line| !line.ends_with(&format!(" <{}>:", name)));
+        let base = match listing.next().and_then(|header| u64::from_str_radix(header.split(' ').next()?, 16).ok()) {
+            Some(base) => base,
+            None => return Err(TypeError{ text: format!("'{}' did not disassemble '{}'", objdump, name) })
+        };
+
+        writeln!(output, "; proc {}", name).unwrap();
+        let mut current = SourceLoc::default();
+        for line in listing.take_while(|line| !line.is_empty()) {
+            // Instructions start with their offset in the section, relocations with a tab more
+            let offset = line.trim_start().split(':').next()
+                .and_then(|offset| u64::from_str_radix(offset, 16).ok())
+                .filter(|_| !line.contains("R_"));
+            if let Some(offset) = offset {
+                let offset = (offset - base) as u32;
+                let srcloc = srclocs.iter()
+                    .find(|(start, end, _)| (*start..*end).contains(&offset))
+                    .map_or(SourceLoc::default(), |(_, _, srcloc)| *srcloc);
+                if srcloc != current {
+                    current = srcloc;
+                    if let Some(comment) = line_comment(&lines, srcloc) {
+                        writeln!(output, "{}", comment).unwrap();
+                    }
+                }
+            }
+            writeln!(output, "{}", line).unwrap();
+        }
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use gecko_parser::ast::parse_gecko;
+
+    use super::{asm, clif};
+    use crate::{
+        codegen::{generate_ir, object_isa, BuildMode, OptLevel, Program},
+        tree_type::annotate_file
+    };
+    use target_lexicon::Triple;
+
+    const SOURCE: &str = "extern proc abs(n: int) -> int;
+proc square(n: int) -> int {
+    let m: int = n * n;
+    unsafe { abs(m) }
+}
+proc cube(n: int) -> int {
+    square(n) * n
+}";
+
+    fn program() -> Program {
+        let file = parse_gecko(SOURCE).unwrap();
+        let (_, symbol_table) = annotate_file(&file).unwrap();
+        generate_ir(&file, symbol_table, BuildMode::Release, OptLevel::O1, &Triple::host()).unwrap()
+    }
+
+    #[test]
+    fn ir() {
+        let dump = clif(&program(), SOURCE, None).unwrap();
+        assert!(dump.starts_with("; optimised at -O1\n"));
+        for expected in ["; proc square", "; 3: let m: int = n * n;", "; 4: unsafe { abs(m) }", "; abs", "; proc cube", "; square"] {
+            assert!(dump.contains(expected), "'{}' is not in\n{}", expected, dump);
+        }
+        let dump = clif(&program(), SOURCE, Some("cube")).unwrap();
+        assert!(dump.contains("; 7: square(n) * n") && !dump.contains("; proc square"));
+        assert_eq!(clif(&program(), SOURCE, Some("main")).err().unwrap().text, "No procedure named 'main' was compiled");
+    }
+
+    #[test]
+    fn machine_code() {
+        if Command::new("objdump").arg("--version").output().is_err() {
+            return;
+        }
+        let isa = object_isa(Triple::host(), OptLevel::O1).unwrap();
+        let dump = asm(&program(), &*isa, SOURCE, Some("square")).unwrap();
+        let comments: Vec<&str> = dump.lines().filter(|line| line.starts_with(';')).collect();
+        assert_eq!(comments[..2], ["; optimised at -O1", "; proc square"]);
+        assert!(comments.contains(&"; 3: let m: int = n * n;") && comments.contains(&"; 4: unsafe { abs(m) }"), "{}", dump);
+        assert!(dump.contains("abs"));
+    }
+}
@@ -0,0 +1,211 @@
+use cranelift::prelude::{types, AbiParam, FunctionBuilder, FunctionBuilderContext, InstBuilder};
+use cranelift_codegen::{
+    ir::{ExtFuncData, Function, Signature},
+    isa::CallConv
+};
+use gecko_parser::{node::NodeType, statement::FunctionDefinition};
+use target_lexicon::Triple;
+
+use crate::{
+    File,
+    symbol,
+    codegen::{procedure, DataLayout, Program},
+    tree_type::FundamentalType,
+    error::TypeError
+};
+
+// Executables start at the procedure marked '#[entry]', which takes either nothing or
+// (argc: int, argv: **char) and returns the exit code as an int. The C runtime calls a startup
+// stub named 'main', which calls the entry procedure with the arguments it was given
+
+// Attribute marking the procedure an executable starts at
+pub const ENTRY_ATTRIBUTE: &str = "entry";
+// Symbol of the startup stub, which the C runtime calls
+pub const STARTUP: &str = "main";
+// Symbol an entry procedure named 'main' is renamed to, leaving its name to the stub
+pub const ENTRY_SYMBOL: &str = "__gecko_entry";
+
+// The single procedure of a file marked '#[entry]'
+pub fn entry_point(tree: &File) -> Result<&FunctionDefinition, TypeError> {
+    let entries: Vec<&FunctionDefinition> = tree.stmts.iter()
+        .filter_map(|stmt| match &**stmt {
+            NodeType::FunctionDefinition(definition) if definition.has_attribute(ENTRY_ATTRIBUTE) => Some(definition),
+            _ => None
+        })
+        .collect();
+    match entries[..] {
+        [entry] => Ok(entry),
+        [] => Err(TypeError{ text: format!("No procedure is marked '#[{}]'", ENTRY_ATTRIBUTE) }),
+        [first, second, ..] => Err(TypeError{ text: format!(
+            "Only one procedure can be marked '#[{}]', but '{}' at {} and '{}' at {} are",
+            ENTRY_ATTRIBUTE, first.sig.id.name, first.sig.id.span, second.sig.id.name, second.sig.id.span
+        )})
+    }
+}
+
+// The procedure named 'name', which a program starts at instead of its '#[entry]' procedure when it
+// is run as a test, see startup_named
+pub fn entry_named<'a>(tree: &'a File, name: &str) -> Result<&'a FunctionDefinition, TypeError> {
+    tree.stmts.iter()
+        .find_map(|stmt| match &**stmt {
+            NodeType::FunctionDefinition(definition) if definition.sig.id.name == name => Some(definition),
+            _ => None
+        })
+        .ok_or_else(|| TypeError{ text: format!("No procedure is named '{}'", name) })
+}
+
+// Whether the entry procedure takes (argc: int, argv: **char), rather than nothing
+fn takes_arguments(entry: &symbol::Function) -> Result<bool, TypeError> {
+    let params: Vec<&FundamentalType> = entry.params().iter().map(|(_, ty)| &ty.ty).collect();
+    let takes_arguments = match params[..] {
+        [] => false,
+        [FundamentalType::I32, FundamentalType::Pointer(argv)] => match &argv.ty {
+            FundamentalType::Pointer(arg) => arg.ty == FundamentalType::Character,
+            _ => false
+        },
+        _ => false
+    };
+    if entry.output().ty != FundamentalType::I32 || (!params.is_empty() && !takes_arguments) {
+        return Err(TypeError{ text: format!(
+            "The entry procedure '{}' must take '()' or '(argc: int, argv: **char)' and return 'int', at {}",
+            entry.id().name, entry.id().span
+        )});
+    }
+    Ok(takes_arguments)
+}
+
+// Adds the startup stub calling 'entry' to a program, as the C runtime's 'main'
+pub fn startup(program: &mut Program, entry: &symbol::Function, triple: &Triple) -> Result<(), TypeError> {
+    add_startup(program, entry, triple, false)
+}
+
+// Adds the startup stub calling an entry procedure found by entry_named. The program's own entry
+// procedure may be named 'main', so any procedure named 'main' is renamed like an entry would be
+pub fn startup_named(program: &mut Program, entry: &symbol::Function, triple: &Triple) -> Result<(), TypeError> {
+    add_startup(program, entry, triple, true)
+}
+
+fn add_startup(program: &mut Program, entry: &symbol::Function, triple: &Triple, named: bool) -> Result<(), TypeError> {
+    let takes_arguments = takes_arguments(entry)?;
+    let call_conv = CallConv::triple_default(triple);
+    let pointer_type = DataLayout::new(triple).pointer_type();
+
+    // Calls already lowered refer to the entry procedure by index, so renaming it renames them too
+    if let Some(index) = program.symbols.iter().position(|symbol| symbol == STARTUP) {
+        if entry.id().name != STARTUP && !named {
+            return Err(TypeError{ text: format!("'{}' is the startup stub of executables, so only the entry procedure can be named '{}'", STARTUP, STARTUP) });
+        }
+        program.symbols[index] = ENTRY_SYMBOL.to_owned();
+    }
+
+    let mut signature = Signature::new(call_conv);
+    signature.params.push(AbiParam::new(types::I32));
+    signature.params.push(AbiParam::new(pointer_type));
+    signature.returns.push(AbiParam::new(types::I32));
+    let name = program.symbol(STARTUP);
+    let mut function = Function::with_name_signature(name, signature);
+
+    let entry_name = match entry.id().name.as_str() {
+        STARTUP => ENTRY_SYMBOL,
+        name => name
+    };
+    let entry_signature = procedure::signature(entry, call_conv, &DataLayout::new(triple));
+    let mut context = FunctionBuilderContext::new();
+    let mut builder = FunctionBuilder::new(&mut function, &mut context);
+    let entry_signature = builder.import_signature(entry_signature);
+    let callee = builder.import_function(ExtFuncData{ name: program.symbol(entry_name), signature: entry_signature, colocated: false });
+
+    let block = builder.create_block();
+    builder.append_block_params_for_function_params(block);
+    builder.switch_to_block(block);
+    builder.seal_block(block);
+    let args = match takes_arguments {
+        true => builder.block_params(block).to_vec(),
+        false => vec!()
+    };
+    let call = builder.ins().call(callee, &args);
+    let code = builder.inst_results(call)[0];
+    builder.ins().return_(&[code]);
+    builder.finalize();
+
+    program.functions.push(function);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use cranelift::prelude::settings;
+    use cranelift_codegen::{isa, verify_function};
+    use gecko_parser::ast::parse_gecko;
+    use target_lexicon::Triple;
+
+    use super::{entry_named, entry_point, startup, startup_named, ENTRY_SYMBOL, STARTUP};
+    use crate::{
+        codegen::{generate_ir, BuildMode, OptLevel, Program},
+        error::TypeError,
+        tree_type::annotate_file
+    };
+
+    fn build(source: &str) -> Result<Program, TypeError> {
+        let file = parse_gecko(source).unwrap();
+        let (_, symbol_table) = annotate_file(&file).unwrap();
+        let entry = symbol_table.function(&entry_point(&file)?.sig.id)?;
+        let mut program = generate_ir(&file, symbol_table, BuildMode::Debug, OptLevel::O0, &Triple::host())?;
+        startup(&mut program, &entry, &Triple::host())?;
+        Ok(program)
+    }
+
+    #[test]
+    fn stubs() {
+        let isa = isa::lookup(Triple::host()).unwrap()
+            .finish(settings::Flags::new(settings::builder()))
+            .unwrap();
+        for source in [
+            "#[entry] proc start() -> int { 0 }",
+            "#[entry] proc start(argc: int, argv: **char) -> int { argc }",
+            "proc helper() -> int { 1 }\n#[entry] proc main(argc: int, argv: **char) -> int { helper() + argc }"
+        ] {
+            let program = build(source).unwrap();
+            for function in &program.functions {
+                verify_function(function, &*isa).unwrap();
+            }
+            let stub = program.functions.last().unwrap();
+            assert_eq!(program.symbol_name(&stub.name), Some(STARTUP));
+        }
+
+        // An entry procedure named 'main' leaves its name to the stub
+        let program = build("#[entry] proc main() -> int { 0 }").unwrap();
+        assert_eq!(program.symbol_name(&program.functions[0].name), Some(ENTRY_SYMBOL));
+    }
+
+    #[test]
+    fn named_entry_points() {
+        // The program's own entry procedure, named 'main', gives up its name as it would as the entry
+        let file = parse_gecko("#[entry] proc main() -> int { 1 }\nproc test() -> int { main() + 1 }").unwrap();
+        let (_, symbol_table) = annotate_file(&file).unwrap();
+        let entry = symbol_table.function(&entry_named(&file, "test").unwrap().sig.id).unwrap();
+        let mut program = generate_ir(&file, symbol_table, BuildMode::Debug, OptLevel::O0, &Triple::host()).unwrap();
+        startup_named(&mut program, &entry, &Triple::host()).unwrap();
+        assert_eq!(program.symbol_name(&program.functions[0].name), Some(ENTRY_SYMBOL));
+        assert_eq!(program.symbol_name(&program.functions.last().unwrap().name), Some(STARTUP));
+        assert_eq!(entry_named(&file, "missing").err().unwrap().text, "No procedure is named 'missing'");
+    }
+
+    #[test]
+    fn invalid_entry_points() {
+        let error = |source: &str| build(source).err().unwrap().text;
+        assert_eq!(error("proc start() -> int { 0 }"), "No procedure is marked '#[entry]'");
+        assert_eq!(
+            error("#[entry] proc a() -> int { 0 }\n#[entry] proc b() -> int { 0 }"),
+            "Only one procedure can be marked '#[entry]', but 'a' at 1:15 and 'b' at 2:15 are"
+        );
+        assert_eq!(
+            error("#[entry] proc start(n: u8) -> int { 0 }"),
+            "The entry procedure 'start' must take '()' or '(argc: int, argv: **char)' and return 'int', at 1:15"
+        );
+        assert_eq!(
+            error("proc main() -> int { 0 }\n#[entry] proc start() -> int { main() }"),
+            "'main' is the startup stub of executables, so only the entry procedure can be named 'main'"
+        );
+    }
+}
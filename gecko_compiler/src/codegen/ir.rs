@@ -1,40 +1,249 @@
+use std::{cell::RefCell, collections::HashMap, iter};
+
 use cranelift::prelude::*;
 use cranelift_codegen::{
-    ir::{AbiParam, Signature},
-    isa::CallConv
+    ir::{ExternalName, Function, GlobalValueData},
+    Context
 };
+use cranelift_module::{DataContext, FuncId, Linkage, Module, ModuleError};
+use target_lexicon::Triple;
 
 use crate::{
     File,
     symbol::SymbolTable,
     node::NodeType,
-    tree_type::FundamentalType
+    statement::FunctionDefinition,
+    codegen::{
+        numeric,
+        variadic,
+        overflow::{self, BuildMode, OverflowSite},
+        debug_info,
+        procedure::{self, Unit},
+        DataLayout,
+        OptLevel
+    },
+    tree_type::{global_statement, FundamentalType},
+    error::TypeError
 };
 
+// Namespaces of the ExternalName::user names the IR of a program refers to symbols by
+pub const SYMBOL_NAMESPACE: u32 = 0;
+pub const DATA_NAMESPACE: u32 = 1;
+
+// IR of a source file, and the symbols and data its functions refer to
+#[derive(Default)]
+pub struct Program {
+    // Procedure definitions, named after their symbols
+    pub functions: Vec<Function>,
+    // Names of the procedures the functions define or call, by index in SYMBOL_NAMESPACE
+    pub symbols: Vec<String>,
+    // Read-only data such as the text of assertions, by index in DATA_NAMESPACE
+    pub data: Vec<Vec<u8>>,
+    // Variadic procedures called through a thunk, see variadic::needs_thunk
+    pub thunks: Vec<String>,
+    // Level the functions were optimised at, recorded in the files they are written to
+    pub opt_level: OptLevel,
+    // Procedures and their variables as debuggers show them, see codegen::debug_info
+    pub debug_info: Vec<debug_info::Procedure>,
+}
+
+impl Program {
+    // Name of the symbol of a procedure, added to the symbols the first time it is used
+    pub fn symbol(&mut self, name: &str) -> ExternalName {
+        let index = match self.symbols.iter().position(|symbol| symbol == name) {
+            Some(index) => index,
+            None => {
+                self.symbols.push(name.to_owned());
+                self.symbols.len() - 1
+            }
+        };
+        ExternalName::user(SYMBOL_NAMESPACE, index as u32)
+    }
+
+    // Name of a piece of read-only data, shared with identical data used before
+    pub fn data(&mut self, bytes: &[u8]) -> ExternalName {
+        let index = match self.data.iter().position(|data| data == bytes) {
+            Some(index) => index,
+            None => {
+                self.data.push(bytes.to_vec());
+                self.data.len() - 1
+            }
+        };
+        ExternalName::user(DATA_NAMESPACE, index as u32)
+    }
+
+    // Name of the symbol an ExternalName in SYMBOL_NAMESPACE refers to
+    pub fn symbol_name(&self, name: &ExternalName) -> Option<&str> {
+        match *name {
+            ExternalName::User{ namespace: SYMBOL_NAMESPACE, index } => self.symbols.get(index as usize).map(String::as_str),
+            _ => None
+        }
+    }
+
+    // Checks that every ExternalName the IR refers to is a symbol or data of the program, as
+    // modules cannot relocate code against any other name
+    fn check_names(&self) -> Result<(), TypeError> {
+        for function in &self.functions {
+            let procedures = function.dfg.ext_funcs.values().map(|ext_func| &ext_func.name);
+            let data = function.global_values.values().filter_map(|global| match global {
+                GlobalValueData::Symbol{ name, .. } => Some(name),
+                _ => None
+            });
+            for name in iter::once(&function.name).chain(procedures).chain(data) {
+                let known = match *name {
+                    ExternalName::User{ namespace: SYMBOL_NAMESPACE, index } => (index as usize) < self.symbols.len(),
+                    ExternalName::User{ namespace: DATA_NAMESPACE, index } => (index as usize) < self.data.len(),
+                    ExternalName::LibCall(_) => true,
+                    _ => false
+                };
+                if !known {
+                    let procedure = self.symbol_name(&function.name).map_or(function.name.to_string(), str::to_owned);
+                    return Err(TypeError{ text: format!("Unknown external name '{}' in '{}'", name, procedure) });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Declares the symbols of the program in a module in order, so the ids the module gives them
+    // are the indices the IR refers to them by, and defines its data. Procedures the program
+    // defines are exported, thunks are local and the procedures it only calls are imported
+    pub fn declare(&self, module: &mut impl Module) -> Result<(), TypeError> {
+        self.check_names()?;
+        let call_conv = module.isa().default_call_conv();
+        for (index, name) in self.symbols.iter().enumerate() {
+            let defined = self.functions.iter().find(|function| self.symbol_name(&function.name) == Some(name));
+            let thunk = self.thunks.iter().any(|callee| variadic::thunk_name(callee) == *name);
+            let (linkage, signature) = match defined {
+                Some(function) => (Linkage::Export, function.signature.clone()),
+                None if thunk => (Linkage::Local, Signature::new(call_conv)),
+                // Variadic procedures are called with a different signature at every call
+                None => (Linkage::Import, Signature::new(call_conv))
+            };
+            let id = module.declare_function(name, linkage, &signature).map_err(module_error)?;
+            debug_assert_eq!(id.as_u32() as usize, index);
+        }
+        for (index, bytes) in self.data.iter().enumerate() {
+            let id = module.declare_data(&format!("__gecko_data_{}", index), Linkage::Local, false, false).map_err(module_error)?;
+            let mut data = DataContext::new();
+            data.define(bytes.clone().into_boxed_slice());
+            module.define_data(id, &data).map_err(module_error)?;
+        }
+        Ok(())
+    }
+
+    // Compiles the functions of the program into a module it was declared in, returning their
+    // contexts in order with the machine code they were compiled to
+    pub fn define(&self, module: &mut impl Module) -> Result<Vec<(FuncId, Context)>, TypeError> {
+        let mut compiled = vec!();
+        for function in &self.functions {
+            let name = self.symbol_name(&function.name).expect("Functions are named after their symbols");
+            let id = FuncId::from_name(&function.name);
+            let mut context = Context::for_function(function.clone());
+            module.define_function(id, &mut context).map_err(|e| match e {
+                ModuleError::Compilation(e) => TypeError{ text: format!("Procedure '{}' could not be compiled: {:?}", name, e) },
+                e => module_error(e)
+            })?;
+            compiled.push((id, context));
+        }
+        Ok(compiled)
+    }
+
+    // Declares a thunk's callee, which the program itself may not refer to
+    pub fn declare_callee(&self, module: &mut impl Module, callee: &str) -> Result<FuncId, TypeError> {
+        let signature = Signature::new(module.isa().default_call_conv());
+        module.declare_function(callee, Linkage::Import, &signature).map_err(module_error)
+    }
+}
+
+pub fn module_error(error: ModuleError) -> TypeError {
+    TypeError{ text: error.to_string() }
+}
+
 impl FundamentalType {
-    fn to_ir_type(&self) -> Type {
+    // Literals are lowered with their default types, see Type::concrete. Values of the other types
+    // are the address of the memory they are kept in
+    pub fn to_ir_type(&self, data_layout: &DataLayout) -> Type {
         match *self {
-            FundamentalType::Integer => types::I32,
             FundamentalType::Boolean => types::B1,
-            FundamentalType::Character => types::I8,
-            FundamentalType::Float => types::F32,
-            _ => panic!("No conversion of Gecko type to IR type")
+            FundamentalType::Character | FundamentalType::I8 | FundamentalType::U8 => types::I8,
+            FundamentalType::I16 | FundamentalType::U16 => types::I16,
+            FundamentalType::I32 | FundamentalType::U32 | FundamentalType::Integer => types::I32,
+            FundamentalType::I64 | FundamentalType::U64 => types::I64,
+            FundamentalType::Isize | FundamentalType::Usize | FundamentalType::Pointer(_) => data_layout.pointer_type(),
+            FundamentalType::F32 => types::F32,
+            FundamentalType::F64 | FundamentalType::Float => types::F64,
+            FundamentalType::String
+                | FundamentalType::Result(..)
+                | FundamentalType::Array(..)
+                | FundamentalType::Slice(_)
+                | FundamentalType::Tuple(_)
+                | FundamentalType::Never
+                | FundamentalType::DefinedType(_) => data_layout.pointer_type()
         }
     }
 }
 
-pub fn generate_ir(tree: &File, symbol_table: SymbolTable) {
+// Lowers an arithmetic, bitwise or comparison operator on two values of type 'ty', the type the
+// checker gave the operation with Type::arithmetic_type. Integer operators that can overflow
+// trap or wrap depending on the build mode, and need the site of the operator in debug builds
+pub fn binary_operator(builder: &mut FunctionBuilder, op: &str, ty: &FundamentalType, left: Value, right: Value, mode: BuildMode, site: Option<&OverflowSite>) -> Result<Value, TypeError> {
+    if ty.is_integer() && overflow::can_overflow(op) {
+        return Ok(match mode {
+            BuildMode::Debug => {
+                let site = site.expect("Integer operators that can overflow need an overflow site");
+                overflow::operator(builder, op, ty, left, right, mode, site)
+            },
+            BuildMode::Release => overflow::wrapping(builder, op, ty, left, right)
+        });
+    }
+
+    if ty.is_float() {
+        if let Some(condition) = numeric::float_condition(op) {
+            return Ok(builder.ins().fcmp(condition, left, right));
+        }
+    } else if let Some(condition) = numeric::int_condition(op, ty.is_signed()) {
+        return Ok(builder.ins().icmp(condition, left, right));
+    }
+    match numeric::binary_opcode(op, ty) {
+        Some(opcode) => {
+            let ir_type = builder.func.dfg.value_type(left);
+            let (inst, dfg) = builder.ins().Binary(opcode, ir_type, left, right);
+            Ok(dfg.first_result(inst))
+        },
+        None => Err(TypeError{ text: format!("Operator '{}' cannot be compiled for '{}'", op, ty) })
+    }
+}
+
+// Lowers every procedure defined in a type checked file for 'triple', with 'symbol_table' as the
+// checker left it
+pub fn generate_ir<'a>(tree: &'a File, symbol_table: SymbolTable<'a>, mode: BuildMode, opt_level: OptLevel, triple: &Triple) -> Result<Program, TypeError> {
+    let mut program = Program{ opt_level, ..Program::default() };
+    let mut context = FunctionBuilderContext::new();
+    // Calls to any procedure of the file can be inlined, whether it is defined before or after them
+    let definitions: HashMap<&str, &FunctionDefinition> = tree.stmts.iter()
+        .filter_map(|stmt| match &**stmt {
+            NodeType::FunctionDefinition(definition) => Some((definition.sig.id.name.as_str(), definition)),
+            _ => None
+        })
+        .collect();
+    let unit = Unit{ table: &symbol_table, definitions, mode, opt_level, triple, nested: RefCell::default() };
     for stmt in &tree.stmts {
         match &**stmt {
             NodeType::FunctionDefinition(function_definition) => {
-                let fn_sig = &function_definition.sig;
-                let fn_name = &fn_sig.id.name;
-                let symbol =  symbol_table.symbols.get(fn_name).unwrap();
-
-                let mut sig = Signature::new(CallConv::SystemV);
-                //sig.returns.push(AbiParam::new())
+                let function = procedure::lower(function_definition, &unit, &mut program, &mut context)?;
+                program.functions.push(function);
             },
-            _ => panic!("{}", "This statement cannot be in the global scope")
+            // Struct layouts are only needed once their values are lowered
+            NodeType::StructDefinition(_) => {},
+            // Declared procedures are imported when a call to them is lowered
+            NodeType::FunctionDeclaration(_) => {},
+            // Constants are folded into the expressions that use them
+            NodeType::ConstantDefinition(_) => {},
+            // Aliases only name types, which the symbol table resolves wherever they are written
+            NodeType::TypeAlias(_) => {},
+            stmt => return Err(global_statement(stmt))
         }
     }
+    Ok(program)
 }
\ No newline at end of file
@@ -0,0 +1,272 @@
+use std::ffi::CString;
+
+use cranelift::prelude::settings::{self, Configurable};
+use cranelift_codegen::{
+    binemit::Reloc,
+    ir::ExternalName,
+    isa::{self, TargetIsa},
+    MachReloc
+};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, FuncOrDataId, Linkage, Module};
+use target_lexicon::{Architecture, Triple};
+
+use crate::{
+    codegen::{
+        ir::SYMBOL_NAMESPACE,
+        variadic,
+        OptLevel,
+        Program
+    },
+    error::TypeError
+};
+
+// Programs compiled in memory and run in the compiler's process, for 'gecko run'.
+// Programs are loaded with cranelift-jit. Code is compiled without position independence, so the
+// addresses of procedures and data are loaded as absolute values and nothing needs a GOT or PLT.
+// Procedures the program calls without defining are looked up with the resolver first, and then
+// in the process, which finds libc
+
+// ISA of the host, configured for code that runs where it is compiled
+pub fn jit_isa(opt_level: OptLevel) -> Result<Box<dyn TargetIsa>, TypeError> {
+    let mut flags = settings::builder();
+    flags.set("opt_level", opt_level.cranelift()).unwrap();
+    flags.set("is_pic", "false").unwrap();
+    // Probes would call __cranelift_probestack, which nothing defines
+    flags.set("enable_probestack", "false").unwrap();
+    let builder = isa::lookup(Triple::host())
+        .map_err(|_| TypeError{ text: format!("Programs cannot be run on '{}', as Cranelift has no backend for it yet", Triple::host()) })?;
+    builder.finish(settings::Flags::new(flags))
+        .map_err(|e| TypeError{ text: format!("Target '{}' is not supported: {}", Triple::host(), e) })
+}
+
+// Address of a symbol the process defines, such as a procedure of libc
+fn process_symbol(name: &str) -> Option<*const u8> {
+    let name = CString::new(name).ok()?;
+    let address = unsafe { libc::dlsym(libc::RTLD_DEFAULT, name.as_ptr()) };
+    (!address.is_null()).then_some(address as *const u8)
+}
+
+// Program loaded into executable memory, which is freed when it is dropped
+pub struct JitProgram {
+    module: Option<JITModule>,
+}
+
+impl JitProgram {
+    // Address of a procedure the program defines
+    pub fn function(&self, name: &str) -> Option<*const u8> {
+        let module = self.module.as_ref()?;
+        match module.get_name(name)? {
+            FuncOrDataId::Func(id) if module.declarations().get_function_decl(id).linkage.is_definable() => {
+                Some(module.get_finalized_function(id))
+            },
+            _ => None
+        }
+    }
+}
+
+impl Drop for JitProgram {
+    fn drop(&mut self) {
+        if let Some(module) = self.module.take() {
+            // Nothing can call the program's procedures once it is dropped
+            unsafe { module.free_memory() };
+        }
+    }
+}
+
+// Compiles the functions of a program for the host and loads them into memory. 'resolver' gives
+// the addresses of procedures that are not in the process's symbol table, such as the runtime's
+pub fn load(program: &Program, isa: &dyn TargetIsa, resolver: fn(&str) -> Option<*const u8>) -> Result<JitProgram, TypeError> {
+    let triple = isa.triple();
+    if !program.thunks.is_empty() && triple.architecture != Architecture::X86_64 {
+        return Err(TypeError{ text: format!("Variadic procedures cannot be called on '{}' yet", triple) });
+    }
+    // The module owns its ISA, which is configured like the one given
+    let module_isa = isa::lookup(triple.clone())
+        .map_err(|e| TypeError{ text: format!("Target '{}' is not supported: {}", triple, e) })?
+        .finish(isa.flags().clone())
+        .map_err(|e| TypeError{ text: format!("Target '{}' is not supported: {}", triple, e) })?;
+    let mut builder = JITBuilder::with_isa(module_isa, default_libcall_names());
+    builder.symbol_lookup_fn(Box::new(resolver));
+    let mut module = JITModule::new(builder);
+
+    program.declare(&mut module)?;
+    program.define(&mut module)?;
+
+    // Thunks jump to the absolute address of the callee, as it may be far from the program
+    for callee in &program.thunks {
+        let thunk = module.get_name(&variadic::thunk_name(callee));
+        let Some(FuncOrDataId::Func(thunk)) = thunk else {
+            panic!("Thunks are named after their symbols");
+        };
+        let callee = program.declare_callee(&mut module, callee)?;
+        let relocation = MachReloc{
+            offset: variadic::X86_64_ABSOLUTE_THUNK_ADDRESS as u32,
+            kind: Reloc::Abs8,
+            name: ExternalName::user(SYMBOL_NAMESPACE, callee.as_u32()),
+            addend: 0
+        };
+        module.define_function_bytes(thunk, &variadic::X86_64_ABSOLUTE_THUNK, &[relocation])
+            .map_err(|e| TypeError{ text: e.to_string() })?;
+    }
+
+    // The module panics on procedures it cannot find, so they are looked up before it relocates
+    // the code calling them
+    let missing = module.declarations().get_functions()
+        .filter(|(_, declaration)| declaration.linkage == Linkage::Import)
+        .find(|(_, declaration)| resolver(&declaration.name).or_else(|| process_symbol(&declaration.name)).is_none());
+    if let Some((_, declaration)) = missing {
+        return Err(TypeError{ text: format!("Unable to find procedure '{}'", declaration.name) });
+    }
+
+    module.finalize_definitions();
+    Ok(JitProgram{ module: Some(module) })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::CString, os::raw::c_char, ptr};
+
+    use gecko_parser::ast::parse_gecko;
+    use target_lexicon::Triple;
+
+    use super::{jit_isa, load};
+    use crate::{
+        codegen::{entry, generate_ir, BuildMode, OptLevel},
+        tree_type::annotate_file
+    };
+
+    // Runs a program with its startup stub, returning its exit code
+    fn run(source: &str, args: &[&str]) -> i32 {
+        run_at(source, args, OptLevel::O0)
+    }
+
+    fn run_at(source: &str, args: &[&str], opt_level: OptLevel) -> i32 {
+        let file = parse_gecko(source).unwrap();
+        let (_, symbol_table) = annotate_file(&file).unwrap();
+        let entry_point = symbol_table.function(&entry::entry_point(&file).unwrap().sig.id).unwrap();
+        let mut program = generate_ir(&file, symbol_table, BuildMode::Debug, opt_level, &Triple::host()).unwrap();
+        entry::startup(&mut program, &entry_point, &Triple::host()).unwrap();
+        let loaded = load(&program, &*jit_isa(opt_level).unwrap(), gecko_runtime::symbol).unwrap();
+
+        let args: Vec<CString> = args.iter().map(|arg| CString::new(*arg).unwrap()).collect();
+        let mut argv: Vec<*const c_char> = args.iter().map(|arg| arg.as_ptr()).collect();
+        argv.push(ptr::null());
+        let startup: unsafe extern "C" fn(i32, *const *const c_char) -> i32 = unsafe {
+            std::mem::transmute(loaded.function(entry::STARTUP).unwrap())
+        };
+        unsafe { startup(args.len() as i32, argv.as_ptr()) }
+    }
+
+    #[test]
+    fn runs() {
+        let source = "#!/usr/bin/env gecko run
+            extern proc atoi(text: *char) -> int;
+            extern proc snprintf(buffer: *char, size: usize, format: *char, ...) -> int;
+            unsafe proc sum(first: *int, count: int) -> int {
+                assert(count > 0, \"empty\");
+                if count == 1 { *first } else { *first + sum(first, count - 1) }
+            }
+            #[entry]
+            proc main(argc: int, argv: **char) -> int {
+                unsafe {
+                    let value: *int = new int(atoi(*(argv + 1)));
+                    defer delete value;
+                    let buffer: *char = new [char; 16];
+                    defer delete buffer;
+                    let length := snprintf(buffer, 16, \"%d %.1f\", 12, 2.5);
+                    sum(value, argc) + length
+                }
+            }
+        ";
+        assert_eq!(run(source, &["script", "20"]), 20 * 2 + 6);
+        assert_eq!(run("#[entry] proc start() -> int { 3 }", &["start"]), 3);
+    }
+
+    #[test]
+    fn constructs_structs() {
+        let source = "
+            struct Point { x: int, flag: bool, y: i64 }
+            #[entry]
+            proc main() -> int {
+                unsafe {
+                    let p: *Point = new Point(3, true, 40);
+                    defer delete p;
+                    let zeroed: *Point = new Point();
+                    defer delete zeroed;
+                    let Point { x, flag, y } := *p;
+                    let Point { x: z, .. } := *zeroed;
+                    if flag { x + y as int + z } else { 0 }
+                }
+            }
+        ";
+        assert_eq!(run(source, &["main"]), 43);
+    }
+
+    #[test]
+    fn type_aliases() {
+        let source = "
+            type Byte: u8;
+            type Pair: (Byte, int);
+            proc wrap(pair: Pair) -> Byte { let (byte, n) := pair; byte + n as Byte }
+            #[entry]
+            proc main() -> int {
+                type Count: i64;
+                let count: Count = 240;
+                wrap((count as Byte, 10)) as int + sizeof(Pair) as int
+            }
+        ";
+        assert_eq!(run(source, &["main"]), 240 + 10 + 8);
+    }
+
+    #[test]
+    fn macro_globals() {
+        let source = "
+            const LIMIT := 3;
+            proc helper() -> int { 10 }
+            macro limit() { LIMIT + helper() }
+            #[entry]
+            proc main() -> int {
+                let LIMIT := 100;
+                proc helper() -> int { 1000 }
+                limit!() + LIMIT + helper()
+            }
+        ";
+        assert_eq!(run(source, &["main"]), 3 + 10 + 100 + 1000);
+    }
+
+    #[test]
+    fn unknown_procedures() {
+        let file = parse_gecko("extern proc missing_procedure() -> int;\n#[entry] proc main() -> int { unsafe { missing_procedure() } }").unwrap();
+        let (_, symbol_table) = annotate_file(&file).unwrap();
+        let entry_point = symbol_table.function(&entry::entry_point(&file).unwrap().sig.id).unwrap();
+        let mut program = generate_ir(&file, symbol_table, BuildMode::Debug, OptLevel::O0, &Triple::host()).unwrap();
+        entry::startup(&mut program, &entry_point, &Triple::host()).unwrap();
+        let error = load(&program, &*jit_isa(OptLevel::O0).unwrap(), gecko_runtime::symbol).err().unwrap();
+        assert_eq!(error.text, "Unable to find procedure 'missing_procedure'");
+    }
+
+    // Inlined procedures return, run their deferred statements and pass booleans as their callers do
+    #[test]
+    fn optimisation_levels() {
+        let source = "
+            extern proc abs(n: int) -> int;
+            #[inline] proc first(flag: bool, n: int) -> int {
+                defer unsafe { abs(n) };
+                if flag { return n; }
+                let m := n * 2;
+                m + 1
+            }
+            proc twice(n: int) -> int { n + n }
+            #[entry]
+            proc main(argc: int, argv: **char) -> int {
+                let flag := argc > 1;
+                first(flag, twice(argc)) + first(flag == false, 3 * 4 - 10) + (1 << 3)
+            }
+        ";
+        for opt_level in [OptLevel::O0, OptLevel::O1, OptLevel::O2, OptLevel::Os] {
+            assert_eq!(run_at(source, &["main"], opt_level), 2 * 2 + 1 + 2 + 8, "{}", opt_level);
+            assert_eq!(run_at(source, &["main", "a"], opt_level), 4 + 5 + 8, "{}", opt_level);
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use cranelift::prelude::Type as IrType;
+use target_lexicon::Triple;
+
+use crate::{
+    symbol::SymbolTable,
+    tree_type::{FundamentalType, Type},
+    error::TypeError
+};
+
+// Size and alignment of a value in memory, in bytes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub size: u64,
+    pub align: u64,
+}
+
+impl Layout {
+    fn new(size: u64, align: u64) -> Self {
+        Self{ size, align }
+    }
+}
+
+// Target dependent rules for laying out values in memory.
+// The type checker answers sizeof, alignof and offsetof with the same rules codegen lowers values with
+#[derive(Debug, Clone, Copy)]
+pub struct DataLayout {
+    pointer: IrType,
+}
+
+impl Default for DataLayout {
+    fn default() -> Self {
+        Self::new(&Triple::host())
+    }
+}
+
+impl DataLayout {
+    pub fn new(triple: &Triple) -> Self {
+        Self{ pointer: IrType::triple_pointer_type(triple) }
+    }
+
+    pub fn pointer_type(&self) -> IrType {
+        self.pointer
+    }
+
+    pub fn layout_of(&self, ty: &Type, symbol_table: &SymbolTable) -> Result<Layout, TypeError> {
+        self.layout_in(ty, symbol_table, &mut vec!())
+    }
+
+    // Offset of a field from the start of a struct
+    pub fn offset_of(&self, ty: &Type, field: &str, symbol_table: &SymbolTable) -> Result<u64, TypeError> {
+        let name = match &ty.ty {
+            FundamentalType::DefinedType(name) => name,
+            _ => return Err(TypeError{ text: format!("'offsetof' expects a struct, got '{}'", ty) })
+        };
+        let structure = symbol_table.structure(name)?;
+        let index = match structure.fields().iter().position(|(id, _)| id == field) {
+            Some(index) => index,
+            None => return Err(TypeError{ text: format!("Struct '{}' has no field '{}'", name, field) })
+        };
+
+        let types: Vec<Type> = structure.fields().iter().map(|(_, ty)| ty.clone()).collect();
+        let (_, offsets) = self.fields_layout(&types, symbol_table, &mut vec!(name.clone()))?;
+        Ok(offsets[index])
+    }
+
+    // Offsets of the elements of a tuple or the fields of a struct from its start, in order
+    pub fn field_offsets(&self, ty: &Type, symbol_table: &SymbolTable) -> Result<Vec<u64>, TypeError> {
+        let (types, mut visiting) = match &ty.ty {
+            FundamentalType::Tuple(elements) => (elements.clone(), vec!()),
+            FundamentalType::DefinedType(name) => {
+                let structure = symbol_table.structure(name)?;
+                (structure.fields().iter().map(|(_, ty)| ty.clone()).collect(), vec!(name.clone()))
+            },
+            _ => return Err(TypeError{ text: format!("'{}' has no fields", ty) })
+        };
+        Ok(self.fields_layout(&types, symbol_table, &mut visiting)?.1)
+    }
+
+    // 'visiting' holds the structs being laid out, to reject structs that contain themselves
+    fn layout_in(&self, ty: &Type, symbol_table: &SymbolTable, visiting: &mut Vec<String>) -> Result<Layout, TypeError> {
+        let pointer = self.pointer.bytes() as u64;
+        Ok(match &ty.ty {
+            FundamentalType::Boolean | FundamentalType::Character
+                | FundamentalType::I8 | FundamentalType::I16 | FundamentalType::I32 | FundamentalType::I64 | FundamentalType::Isize
+                | FundamentalType::U8 | FundamentalType::U16 | FundamentalType::U32 | FundamentalType::U64 | FundamentalType::Usize
+                | FundamentalType::F32 | FundamentalType::F64
+                | FundamentalType::Integer | FundamentalType::Float => {
+                let bytes = ty.ty.to_ir_type(self).bytes() as u64;
+                Layout::new(bytes, bytes)
+            },
+            // Pointer and length
+            FundamentalType::String | FundamentalType::Slice(_) => Layout::new(2 * pointer, pointer),
+            FundamentalType::Pointer(_) => Layout::new(pointer, pointer),
+            FundamentalType::Never => Layout::new(0, 1),
+            FundamentalType::Array(element, length) => {
+                let element = self.layout_in(element, symbol_table, visiting)?;
+                let size = element.size.checked_mul(*length)
+                    .ok_or_else(|| TypeError{ text: format!("'{}' is too large", ty) })?;
+                Layout::new(size, element.align)
+            },
+            FundamentalType::Tuple(elements) => self.fields_layout(elements, symbol_table, visiting)?.0,
+            // One byte tag, 0 for Ok and 1 for Err, followed by the value or error
+            FundamentalType::Result(value, error) => {
+                let value = self.layout_in(value, symbol_table, visiting)?;
+                let error = self.layout_in(error, symbol_table, visiting)?;
+                let align = value.align.max(error.align);
+                let payload = align_to(value.size.max(error.size), align);
+                Layout::new(align_to(align + payload, align), align)
+            },
+            FundamentalType::DefinedType(name) => {
+                if visiting.contains(name) {
+                    return Err(TypeError{ text: format!("Struct '{}' contains itself, so it has no finite size", name) });
+                }
+                let structure = symbol_table.structure(name)?;
+                let types: Vec<Type> = structure.fields().iter().map(|(_, ty)| ty.clone()).collect();
+
+                visiting.push(name.clone());
+                let layout = self.fields_layout(&types, symbol_table, visiting)?.0;
+                visiting.pop();
+                layout
+            }
+        })
+    }
+
+    // Lays fields out in order, each at the next offset that suits its alignment, like a C struct
+    fn fields_layout(&self, fields: &[Type], symbol_table: &SymbolTable, visiting: &mut Vec<String>) -> Result<(Layout, Vec<u64>), TypeError> {
+        let mut offsets: Vec<u64> = vec!();
+        let mut size: u64 = 0;
+        let mut align: u64 = 1;
+        for field in fields {
+            let layout = self.layout_in(field, symbol_table, visiting)?;
+            let offset = align_to(size, layout.align);
+            offsets.push(offset);
+            size = offset + layout.size;
+            align = align.max(layout.align);
+        }
+        Ok((Layout::new(align_to(size, align), align), offsets))
+    }
+}
+
+fn align_to(offset: u64, align: u64) -> u64 {
+    offset.div_ceil(align) * align
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use gecko_parser::ast::parse_gecko;
+    use target_lexicon::Triple;
+
+    use super::{DataLayout, Layout};
+    use crate::tree_type::{annotate_file, FundamentalType, Type};
+
+    const STRUCTS: &str = "
+        struct Pair { a: bool, b: int }
+        struct Nested { flag: bool, pair: Pair, name: str }
+    ";
+
+    fn for_target(triple: &str) -> DataLayout {
+        DataLayout::new(&Triple::from_str(triple).unwrap())
+    }
+
+    fn of(ty: FundamentalType) -> Type {
+        Type{ ty }
+    }
+
+    #[test]
+    fn primitives() {
+        let file = parse_gecko("").unwrap();
+        let (_, symbol_table) = annotate_file(&file).unwrap();
+        let data_layout = for_target("x86_64-unknown-linux-gnu");
+        let primitives = [
+            (FundamentalType::Boolean, 1), (FundamentalType::Character, 1),
+            (FundamentalType::I8, 1), (FundamentalType::U16, 2), (FundamentalType::I32, 4), (FundamentalType::U64, 8),
+            (FundamentalType::Isize, 8), (FundamentalType::F32, 4), (FundamentalType::F64, 8)
+        ];
+        for (ty, bytes) in primitives {
+            assert_eq!(data_layout.layout_of(&of(ty.clone()), &symbol_table).unwrap(), Layout::new(bytes, bytes), "{}", ty);
+        }
+        let array = of(FundamentalType::Array(Box::new(of(FundamentalType::I32)), 5));
+        assert_eq!(data_layout.layout_of(&array, &symbol_table).unwrap(), Layout::new(20, 4));
+        assert_eq!(data_layout.layout_of(&of(FundamentalType::Never), &symbol_table).unwrap(), Layout::new(0, 1));
+    }
+
+    // Pointers, strings and slices follow the pointer width of the target
+    #[test]
+    fn pointer_width() {
+        let file = parse_gecko("").unwrap();
+        let (_, symbol_table) = annotate_file(&file).unwrap();
+        let types = [
+            of(FundamentalType::String),
+            of(FundamentalType::Slice(Box::new(of(FundamentalType::Character)))),
+            of(FundamentalType::Pointer(Box::new(of(FundamentalType::Character))))
+        ];
+        for (triple, pointer) in [("x86_64-unknown-linux-gnu", 8), ("aarch64-unknown-linux-gnu", 8), ("i686-unknown-linux-gnu", 4)] {
+            let data_layout = for_target(triple);
+            let layouts: Vec<Layout> = types.iter().map(|ty| data_layout.layout_of(ty, &symbol_table).unwrap()).collect();
+            assert_eq!(layouts, [Layout::new(2 * pointer, pointer), Layout::new(2 * pointer, pointer), Layout::new(pointer, pointer)], "{}", triple);
+        }
+    }
+
+    #[test]
+    fn structs() {
+        let file = parse_gecko(STRUCTS).unwrap();
+        let (_, symbol_table) = annotate_file(&file).unwrap();
+        let named = |name: &str| of(FundamentalType::DefinedType(name.to_string()));
+
+        let data_layout = for_target("x86_64-unknown-linux-gnu");
+        assert_eq!(data_layout.layout_of(&named("Pair"), &symbol_table).unwrap(), Layout::new(8, 4));
+        assert_eq!(data_layout.offset_of(&named("Pair"), "b", &symbol_table).unwrap(), 4);
+        assert_eq!(data_layout.layout_of(&named("Nested"), &symbol_table).unwrap(), Layout::new(32, 8));
+        assert_eq!(data_layout.offset_of(&named("Nested"), "pair", &symbol_table).unwrap(), 4);
+        assert_eq!(data_layout.offset_of(&named("Nested"), "name", &symbol_table).unwrap(), 16);
+        assert!(data_layout.offset_of(&named("Nested"), "missing", &symbol_table).is_err());
+        assert_eq!(data_layout.field_offsets(&named("Nested"), &symbol_table).unwrap(), [0, 4, 16]);
+
+        // Fields shrink along with pointers on 32-bit targets
+        let data_layout = for_target("i686-unknown-linux-gnu");
+        assert_eq!(data_layout.layout_of(&named("Nested"), &symbol_table).unwrap(), Layout::new(20, 4));
+        assert_eq!(data_layout.offset_of(&named("Nested"), "name", &symbol_table).unwrap(), 12);
+    }
+
+    #[test]
+    fn tuples_and_results() {
+        let file = parse_gecko("").unwrap();
+        let (_, symbol_table) = annotate_file(&file).unwrap();
+        let data_layout = for_target("x86_64-unknown-linux-gnu");
+        let (boolean, integer) = (of(FundamentalType::Boolean), of(FundamentalType::Integer));
+
+        let tuple = of(FundamentalType::Tuple(vec!(boolean.clone(), integer.clone(), boolean.clone())));
+        assert_eq!(data_layout.layout_of(&tuple, &symbol_table).unwrap(), Layout::new(12, 4));
+        assert_eq!(data_layout.field_offsets(&tuple, &symbol_table).unwrap(), [0, 4, 8]);
+
+        // One byte tag, then the larger of the value and error at the alignment of both
+        let result = of(FundamentalType::Result(Box::new(integer.clone()), Box::new(of(FundamentalType::String))));
+        assert_eq!(data_layout.layout_of(&result, &symbol_table).unwrap(), Layout::new(24, 8));
+        let result = of(FundamentalType::Result(Box::new(boolean), Box::new(integer)));
+        assert_eq!(data_layout.layout_of(&result, &symbol_table).unwrap(), Layout::new(8, 4));
+    }
+}
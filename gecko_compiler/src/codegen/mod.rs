@@ -2,4 +2,23 @@ extern crate cranelift_codegen;
 extern crate cranelift_frontend;
 
 pub mod ir;
-pub use ir::generate_ir;
\ No newline at end of file
+pub mod layout;
+pub mod variadic;
+pub mod numeric;
+pub mod overflow;
+pub mod contract;
+pub mod bounds;
+pub mod procedure;
+pub mod object_file;
+pub mod entry;
+pub mod jit;
+pub mod dump;
+pub mod target;
+pub mod optimise;
+pub mod debug_info;
+pub use ir::{generate_ir, Program};
+pub use object_file::{emit_object, object_isa};
+pub use target::parse_target;
+pub use layout::{DataLayout, Layout};
+pub use overflow::BuildMode;
+pub use optimise::OptLevel;
\ No newline at end of file
@@ -0,0 +1,95 @@
+use cranelift::prelude::{FloatCC, IntCC};
+use cranelift_codegen::ir::Opcode;
+
+use crate::{
+    codegen::DataLayout,
+    tree_type::FundamentalType
+};
+
+// Instructions that numeric operators lower to. Both operands have the type the checker gave
+// the operation with Type::arithmetic_type, and its signedness picks the instruction where
+// signed and unsigned integers differ: division, remainder, right shifts and comparisons
+
+pub fn binary_opcode(op: &str, ty: &FundamentalType) -> Option<Opcode> {
+    if ty.is_float() {
+        return match op {
+            "+" => Some(Opcode::Fadd),
+            "-" => Some(Opcode::Fsub),
+            "*" => Some(Opcode::Fmul),
+            "/" => Some(Opcode::Fdiv),
+            _ => None
+        };
+    }
+
+    let signed = ty.is_signed();
+    Some(match op {
+        "+" => Opcode::Iadd,
+        "-" => Opcode::Isub,
+        "*" => Opcode::Imul,
+        "/" if signed => Opcode::Sdiv,
+        "/" => Opcode::Udiv,
+        "%" if signed => Opcode::Srem,
+        "%" => Opcode::Urem,
+        "<<" => Opcode::Ishl,
+        // Arithmetic shifts keep the sign of signed values
+        ">>" if signed => Opcode::Sshr,
+        ">>" => Opcode::Ushr,
+        "&" => Opcode::Band,
+        "|" => Opcode::Bor,
+        "^" => Opcode::Bxor,
+        _ => return None
+    })
+}
+
+pub fn int_condition(op: &str, signed: bool) -> Option<IntCC> {
+    Some(match (op, signed) {
+        ("==", _) => IntCC::Equal,
+        ("!=", _) => IntCC::NotEqual,
+        ("<", true) => IntCC::SignedLessThan,
+        ("<=", true) => IntCC::SignedLessThanOrEqual,
+        (">", true) => IntCC::SignedGreaterThan,
+        (">=", true) => IntCC::SignedGreaterThanOrEqual,
+        ("<", false) => IntCC::UnsignedLessThan,
+        ("<=", false) => IntCC::UnsignedLessThanOrEqual,
+        (">", false) => IntCC::UnsignedGreaterThan,
+        (">=", false) => IntCC::UnsignedGreaterThanOrEqual,
+        _ => return None
+    })
+}
+
+// Ordered comparisons are false when either operand is NaN, and '!=' is true
+pub fn float_condition(op: &str) -> Option<FloatCC> {
+    Some(match op {
+        "==" => FloatCC::Equal,
+        "!=" => FloatCC::NotEqual,
+        "<" => FloatCC::LessThan,
+        "<=" => FloatCC::LessThanOrEqual,
+        ">" => FloatCC::GreaterThan,
+        ">=" => FloatCC::GreaterThanOrEqual,
+        _ => return None
+    })
+}
+
+// Instruction converting a value with 'as', or None when the bits are reused as they are.
+// Integers are extended according to the signedness of the source, and truncated to narrow them
+pub fn cast_opcode(from: &FundamentalType, to: &FundamentalType, data_layout: &DataLayout) -> Option<Opcode> {
+    let from_bits = from.to_ir_type(data_layout).bits();
+    let to_bits = to.to_ir_type(data_layout).bits();
+    let from_int = from.is_integer() || *from == FundamentalType::Character;
+    let to_int = to.is_integer() || *to == FundamentalType::Character;
+
+    match (from_int, to_int) {
+        (true, true) if to_bits > from_bits && from.is_signed() => Some(Opcode::Sextend),
+        (true, true) if to_bits > from_bits => Some(Opcode::Uextend),
+        (true, true) if to_bits < from_bits => Some(Opcode::Ireduce),
+        (true, true) => None,
+        (true, false) if from.is_signed() => Some(Opcode::FcvtFromSint),
+        (true, false) => Some(Opcode::FcvtFromUint),
+        // Out of range floats saturate instead of trapping
+        (false, true) if to.is_signed() => Some(Opcode::FcvtToSintSat),
+        (false, true) => Some(Opcode::FcvtToUintSat),
+        (false, false) if to_bits > from_bits => Some(Opcode::Fpromote),
+        (false, false) if to_bits < from_bits => Some(Opcode::Fdemote),
+        (false, false) => None
+    }
+}
@@ -0,0 +1,240 @@
+use std::path::Path;
+
+use cranelift::prelude::settings::{self, Configurable};
+use cranelift_codegen::{
+    binemit::Reloc,
+    ir::ExternalName,
+    isa::{self, TargetIsa},
+    MachReloc
+};
+use cranelift_module::{default_libcall_names, FuncId, Module};
+use cranelift_object::{ObjectBuilder, ObjectModule};
+use target_lexicon::{self as lexicon, Triple};
+
+use crate::{
+    codegen::{
+        debug_info::{self, Compiled},
+        ir::{module_error, SYMBOL_NAMESPACE},
+        variadic,
+        OptLevel,
+        Program
+    },
+    error::TypeError
+};
+
+// Relocatable object files, for linking Gecko code with C and Rust.
+// Procedures are exported under their own names, as there is no name mangling, and procedures
+// they call without defining them are left undefined for the linker to resolve. Objects are
+// written with cranelift-object
+
+// Alignment of the code of every procedure
+const FUNCTION_ALIGNMENT: u64 = 16;
+
+// ISA of a target, configured for code that goes into an object file
+pub fn object_isa(triple: Triple, opt_level: OptLevel) -> Result<Box<dyn TargetIsa>, TypeError> {
+    let mut flags = settings::builder();
+    flags.set("opt_level", opt_level.cranelift()).unwrap();
+    // Position independent code can be linked into both executables and shared libraries. COFF
+    // has no GOT, so Windows code refers to symbols by absolute address instead
+    let pic = triple.binary_format == lexicon::BinaryFormat::Elf;
+    flags.set("is_pic", if pic { "true" } else { "false" }).unwrap();
+    // Probes would call __cranelift_probestack, which no runtime defines
+    flags.set("enable_probestack", "false").unwrap();
+    let builder = isa::lookup(triple.clone())
+        .map_err(|_| TypeError{ text: format!("Code cannot be generated for '{}', as Cranelift has no backend for it yet", triple) })?;
+    builder.finish(settings::Flags::new(flags))
+        .map_err(|e| TypeError{ text: format!("Target '{}' is not supported: {}", triple, e) })
+}
+
+// Compiler and optimisation level recorded in an object, terminated by a NUL like the rest of
+// the strings of '.comment'
+fn comment_text(program: &Program) -> String {
+    format!("gecko {} {}\0", env!("CARGO_PKG_VERSION"), program.opt_level)
+}
+
+// Compiles the functions of a program and writes them to a relocatable object file, with debug
+// information for the source file the program was compiled from if there is one, see debug_info
+pub fn emit_object(program: &Program, isa: &dyn TargetIsa, source: Option<&Path>) -> Result<Vec<u8>, TypeError> {
+    let triple = isa.triple();
+    if !matches!(triple.binary_format, lexicon::BinaryFormat::Elf | lexicon::BinaryFormat::Coff) {
+        return Err(TypeError{ text: format!("Object files for '{}' cannot be written yet", triple) });
+    }
+    if source.is_some() && triple.binary_format != lexicon::BinaryFormat::Elf {
+        return Err(TypeError{ text: format!("Debug information for '{}' cannot be written yet", triple) });
+    }
+    // The module owns its ISA, which is configured like the one given
+    let module_isa = isa::lookup(triple.clone())
+        .map_err(|e| TypeError{ text: format!("Target '{}' is not supported: {}", triple, e) })?
+        .finish(isa.flags().clone())
+        .map_err(|e| TypeError{ text: format!("Target '{}' is not supported: {}", triple, e) })?;
+    let name = source.and_then(Path::file_name).map_or(String::from("gecko"), |name| name.to_string_lossy().into_owned());
+    let mut builder = ObjectBuilder::new(module_isa, name, default_libcall_names()).map_err(module_error)?;
+    builder.function_alignment(FUNCTION_ALIGNMENT);
+    let mut module = ObjectModule::new(builder);
+
+    program.declare(&mut module)?;
+    let compiled = program.define(&mut module)?;
+
+    // Thunks are local, so every object calling a variadic procedure has its own. They jump to
+    // the callee through the PLT
+    for callee in &program.thunks {
+        let thunk = program.symbols.iter().position(|symbol| *symbol == variadic::thunk_name(callee))
+            .expect("Thunks are named after their symbols");
+        let callee = program.declare_callee(&mut module, callee)?;
+        let relocation = MachReloc{
+            offset: variadic::X86_64_THUNK_RELOCATION,
+            kind: Reloc::X86CallPLTRel4,
+            name: ExternalName::user(SYMBOL_NAMESPACE, callee.as_u32()),
+            addend: -4
+        };
+        let thunk = FuncId::from_u32(thunk as u32);
+        module.define_function_bytes(thunk, &variadic::X86_64_THUNK, &[relocation]).map_err(module_error)?;
+    }
+
+    // The stack of programs linked with the object does not need to be executable, which the
+    // module records in '.note.GNU-stack'
+    let mut product = module.finish();
+
+    // Linkers gather the '.comment' sections of objects into the executable, where the compiler
+    // and options it was built with can be read with 'readelf -p .comment'
+    let comment = product.object.add_section(vec!(), b".comment".to_vec(), object::SectionKind::OtherString);
+    product.object.append_section_data(comment, comment_text(program).as_bytes(), 1);
+
+    if let Some(source) = source {
+        let compiled: Vec<Compiled> = compiled.into_iter().map(|(id, mut context)| {
+            let result = context.mach_compile_result.take().expect("Defined functions are compiled");
+            Compiled{
+                function: context.func.name.clone(),
+                symbol: product.function_symbol(id),
+                size: result.buffer.data().len() as u64,
+                srclocs: result.buffer.get_srclocs_sorted().to_vec(),
+                frame_size: result.frame_size,
+                stackslot_offsets: result.stackslot_offsets,
+                value_labels_ranges: result.value_labels_ranges
+            }
+        }).collect();
+        debug_info::write(&mut product.object, program, &compiled, isa, source)?;
+    }
+
+    product.emit().map_err(|e| TypeError{ text: e.to_string() })
+}
+
+#[cfg(test)]
+mod tests {
+    use gecko_parser::ast::parse_gecko;
+    use object::{Object as _, ObjectSection, ObjectSymbol, RelocationKind, RelocationTarget, SymbolKind};
+    use super::{emit_object, object_isa};
+    use crate::{
+        codegen::{generate_ir, parse_target, BuildMode, OptLevel},
+        error::TypeError,
+        tree_type::annotate_file_for
+    };
+
+    const SOURCE: &str = "
+        extern proc printf(format: *char, ...) -> i32;
+        proc square(n: int) -> int {
+            n * n
+        }
+        proc greet(n: int) -> int {
+            unsafe {
+                printf(\"%d\\n\", square(n));
+            }
+            n
+        }
+    ";
+
+    fn emit_for(source: &str, mode: BuildMode, opt_level: OptLevel, target: &str) -> Result<Vec<u8>, TypeError> {
+        let triple = parse_target(target)?;
+        let file = parse_gecko(source).unwrap();
+        let (_, symbol_table) = annotate_file_for(&file, &triple).unwrap();
+        let program = generate_ir(&file, symbol_table, mode, opt_level, &triple)?;
+        emit_object(&program, &*object_isa(triple, opt_level)?, None)
+    }
+
+    fn emit(source: &str, mode: BuildMode) -> Vec<u8> {
+        emit_for(source, mode, OptLevel::default_for(mode), "x86_64-linux").unwrap()
+    }
+
+    #[test]
+    fn symbols() {
+        let bytes = emit(SOURCE, BuildMode::Debug);
+        let file = object::File::parse(&*bytes).unwrap();
+        assert_eq!(file.format(), object::BinaryFormat::Elf);
+        let symbol = |name: &str| file.symbols().find(|symbol| symbol.name() == Ok(name))
+            .unwrap_or_else(|| panic!("No symbol '{}'", name));
+
+        // Procedures are exported, and the procedures they call but do not define are imported
+        for name in ["square", "greet"] {
+            let symbol = symbol(name);
+            assert!(symbol.is_global() && symbol.is_definition());
+            assert_eq!(symbol.kind(), SymbolKind::Text);
+        }
+        for name in ["printf", "__gecko_overflow"] {
+            assert!(symbol(name).is_undefined());
+        }
+        let thunk = symbol("__gecko_variadic_printf");
+        assert!(thunk.is_local() && thunk.is_definition());
+        assert!(file.section_by_name(".note.GNU-stack").is_some());
+
+        // The thunk jumps to printf through the PLT
+        let text = file.section_by_name(".text").unwrap();
+        let printf = symbol("printf").index();
+        assert!(text.relocations().any(|(offset, relocation)| {
+            offset == thunk.address() + 3
+                && relocation.kind() == RelocationKind::PltRelative
+                && relocation.target() == RelocationTarget::Symbol(printf)
+        }));
+    }
+
+    // Modules panic on names they have not declared, so they are reported before any is
+    #[test]
+    fn unknown_names() {
+        let triple = parse_target("x86_64-linux").unwrap();
+        let file = parse_gecko(SOURCE).unwrap();
+        let (_, symbol_table) = annotate_file_for(&file, &triple).unwrap();
+        let mut program = generate_ir(&file, symbol_table, BuildMode::Debug, OptLevel::O0, &triple).unwrap();
+        program.symbols.truncate(1);
+        let error = emit_object(&program, &*object_isa(triple, OptLevel::O0).unwrap(), None).unwrap_err();
+        assert_eq!(error.text, "Unknown external name 'u0:1' in 'square'");
+    }
+
+    #[test]
+    fn release_builds_do_not_import_handlers() {
+        let bytes = emit(SOURCE, BuildMode::Release);
+        let file = object::File::parse(&*bytes).unwrap();
+        assert!(file.symbols().all(|symbol| symbol.name() != Ok("__gecko_overflow")));
+    }
+
+    #[test]
+    fn targets() {
+        let targets = [
+            ("x86_64-linux", object::BinaryFormat::Elf, object::Architecture::X86_64),
+            ("aarch64-linux", object::BinaryFormat::Elf, object::Architecture::Aarch64),
+            ("x86_64-windows", object::BinaryFormat::Coff, object::Architecture::X86_64)
+        ];
+        for (target, format, architecture) in targets {
+            let bytes = emit_for(SOURCE, BuildMode::Debug, OptLevel::O1, target).unwrap();
+            let file = object::File::parse(&*bytes).unwrap();
+            assert_eq!((file.format(), file.architecture()), (format, architecture), "{}", target);
+            let symbol = |name: &str| file.symbols().find(|symbol| symbol.name() == Ok(name))
+                .unwrap_or_else(|| panic!("No symbol '{}' for {}", name, target));
+            assert!(symbol("square").is_global() && symbol("square").is_definition());
+            assert!(symbol("greet").is_global() && symbol("greet").is_definition());
+            assert!(symbol("printf").is_undefined());
+        }
+
+        let riscv = emit_for(SOURCE, BuildMode::Debug, OptLevel::O1, "riscv64-linux").unwrap_err().text;
+        assert_eq!(riscv, "Code cannot be generated for 'riscv64gc-unknown-linux-gnu', as Cranelift has no backend for it yet");
+    }
+
+    #[test]
+    fn opt_level_is_recorded() {
+        for (opt_level, target) in [(OptLevel::Os, "x86_64-linux"), (OptLevel::O0, "x86_64-windows")] {
+            let bytes = emit_for(SOURCE, BuildMode::Release, opt_level, target).unwrap();
+            let file = object::File::parse(&*bytes).unwrap();
+            let comment = file.section_by_name(".comment").unwrap().data().unwrap().to_vec();
+            let expected = format!("gecko {} {}\0", env!("CARGO_PKG_VERSION"), opt_level);
+            assert_eq!(String::from_utf8(comment).unwrap(), expected);
+        }
+    }
+}
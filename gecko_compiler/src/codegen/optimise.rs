@@ -0,0 +1,116 @@
+use std::fmt;
+
+use gecko_parser::statement::FunctionDefinition;
+
+use crate::codegen::BuildMode;
+
+// Optimisation levels, chosen with '-O0', '-O1', '-O2' and '-Os'. Each sets Cranelift's opt_level
+// and the optimisations Gecko does itself while lowering:
+// -O0 keeps every variable in a stack slot of its own, so debuggers find it in memory
+// -O1 keeps variables in registers
+// -O2 also folds constant expressions and inlines small procedures and those marked '#[inline]'
+// -Os folds constant expressions and only inlines procedures marked '#[inline]'
+
+// Attribute asking for a procedure to be inlined wherever it is called
+pub const INLINE_ATTRIBUTE: &str = "inline";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OptLevel {
+    O0,
+    #[default]
+    O1,
+    O2,
+    Os
+}
+
+impl OptLevel {
+    // Level of a '-O' flag
+    pub fn parse(flag: &str) -> Option<OptLevel> {
+        Some(match flag {
+            "-O0" => OptLevel::O0,
+            "-O1" => OptLevel::O1,
+            "-O2" => OptLevel::O2,
+            "-Os" => OptLevel::Os,
+            _ => return None
+        })
+    }
+
+    // Level used without a '-O' flag, -O0 for debug builds and -O2 for release builds
+    pub fn default_for(mode: BuildMode) -> OptLevel {
+        match mode {
+            BuildMode::Debug => OptLevel::O0,
+            BuildMode::Release => OptLevel::O2
+        }
+    }
+
+    // Value of Cranelift's opt_level setting
+    pub fn cranelift(&self) -> &'static str {
+        match self {
+            OptLevel::O0 => "none",
+            OptLevel::O1 | OptLevel::O2 => "speed",
+            OptLevel::Os => "speed_and_size"
+        }
+    }
+
+    pub fn keeps_variables_in_memory(&self) -> bool {
+        *self == OptLevel::O0
+    }
+
+    pub fn folds_constants(&self) -> bool {
+        matches!(self, OptLevel::O2 | OptLevel::Os)
+    }
+
+    // Whether calls to a procedure defined in the file are replaced by its body. Recursive calls
+    // are not, see Lowering::inlinable
+    pub fn inlines(&self, definition: &FunctionDefinition) -> bool {
+        match self {
+            OptLevel::O2 => definition.has_attribute(INLINE_ATTRIBUTE) || is_small(definition),
+            OptLevel::Os => definition.has_attribute(INLINE_ATTRIBUTE),
+            _ => false
+        }
+    }
+}
+
+// Procedures whose body is a single expression
+fn is_small(definition: &FunctionDefinition) -> bool {
+    definition.block.stmts.is_empty() && definition.block.tail.is_some()
+}
+
+impl fmt::Display for OptLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "-{:?}", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gecko_parser::{ast::parse_gecko, node::NodeType};
+
+    use super::OptLevel;
+    use crate::codegen::BuildMode;
+
+    #[test]
+    fn levels() {
+        assert_eq!(OptLevel::parse("-Os"), Some(OptLevel::Os));
+        assert_eq!(OptLevel::parse("-O3"), None);
+        assert_eq!(OptLevel::default_for(BuildMode::Release).to_string(), "-O2");
+        assert_eq!(OptLevel::O0.cranelift(), "none");
+
+        let file = parse_gecko("
+            proc small(n: int) -> int { n * 2 }
+            proc large(n: int) -> int { let m := n * 2; m + 1 }
+            #[inline] proc marked(n: int) -> int { let m := n * 2; m + 1 }
+        ").unwrap();
+        let inlined = |level: OptLevel| -> Vec<&str> {
+            file.stmts.iter()
+                .filter_map(|stmt| match &**stmt {
+                    NodeType::FunctionDefinition(definition) if level.inlines(definition) => Some(definition.sig.id.name.as_str()),
+                    _ => None
+                })
+                .collect()
+        };
+        assert_eq!(inlined(OptLevel::O1), Vec::<&str>::new());
+        assert_eq!(inlined(OptLevel::O2), vec!("small", "marked"));
+        assert_eq!(inlined(OptLevel::Os), vec!("marked"));
+    }
+}
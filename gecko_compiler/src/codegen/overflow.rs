@@ -0,0 +1,364 @@
+use cranelift::prelude::{types, AbiParam, Block, FunctionBuilder, InstBuilder, IntCC, TrapCode, Value};
+use cranelift_codegen::{
+    ir::{FuncRef, Signature},
+    isa::CallConv
+};
+use gecko_parser::ast::Span;
+
+use crate::tree_type::FundamentalType;
+
+// Integer arithmetic that leaves the range of its type. Debug builds report the source location
+// of the overflowing operator and abort, release builds wrap around in two's complement. The
+// wrapping_*, checked_* and saturating_* intrinsics behave the same in both.
+// '+', '-', '*' and '**' overflow when the exact result does not fit, and '<<' and '>>' when the
+// shift amount is negative or not less than the width of the shifted value
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BuildMode {
+    // Operators trap on overflow, and assertions and contracts are checked
+    #[default]
+    Debug,
+    // Operators wrap on overflow, and assertions and contracts are stripped
+    Release
+}
+
+impl BuildMode {
+    pub fn checks_contracts(&self) -> bool {
+        *self == BuildMode::Debug
+    }
+}
+
+// Behaviour an arithmetic intrinsic chooses for overflow
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    // Wraps around like release builds
+    Wrapping,
+    // Returns Err(OVERFLOW_ERROR) instead of a value
+    Checked,
+    // Clamps to the bounds of the type
+    Saturating
+}
+
+// Error a checked intrinsic returns when it overflows
+pub const OVERFLOW_ERROR: &str = "integer overflow";
+
+// Runtime procedure reporting an overflow at a line and column, see gecko_runtime::overflow
+pub const OVERFLOW_HANDLER: &str = "__gecko_overflow";
+
+pub fn handler_signature(call_conv: CallConv) -> Signature {
+    let mut signature = Signature::new(call_conv);
+    signature.params.push(AbiParam::new(types::I32));
+    signature.params.push(AbiParam::new(types::I32));
+    signature
+}
+
+// Behaviour and operator of an intrinsic named e.g. 'checked_mul'
+pub fn intrinsic(name: &str) -> Option<(Overflow, &'static str)> {
+    let (behaviour, op) = name.split_once('_')?;
+    let behaviour = match behaviour {
+        "wrapping" => Overflow::Wrapping,
+        "checked" => Overflow::Checked,
+        "saturating" => Overflow::Saturating,
+        _ => return None
+    };
+    let op = match op {
+        "add" => "+",
+        "sub" => "-",
+        "mul" => "*",
+        _ => return None
+    };
+    Some((behaviour, op))
+}
+
+// Operators whose result depends on the build mode
+pub fn can_overflow(op: &str) -> bool {
+    matches!(op, "+" | "-" | "*" | "**" | "<<" | ">>")
+}
+
+// Where an overflow is reported in debug builds
+pub struct OverflowSite {
+    // Imported OVERFLOW_HANDLER
+    pub handler: FuncRef,
+    pub span: Span,
+}
+
+// Lowers an operator that can overflow with the behaviour of the build mode
+pub fn operator(builder: &mut FunctionBuilder, op: &str, ty: &FundamentalType, left: Value, right: Value, mode: BuildMode, site: &OverflowSite) -> Value {
+    match mode {
+        BuildMode::Debug => {
+            let (value, overflow) = overflowing(builder, op, ty, left, right);
+            trap_if(builder, overflow, site);
+            value
+        },
+        BuildMode::Release => wrapping(builder, op, ty, left, right)
+    }
+}
+
+// Result wrapped to the width of the type
+pub fn wrapping(builder: &mut FunctionBuilder, op: &str, ty: &FundamentalType, left: Value, right: Value) -> Value {
+    match op {
+        "+" => builder.ins().iadd(left, right),
+        "-" => builder.ins().isub(left, right),
+        "*" => builder.ins().imul(left, right),
+        "**" => pow(builder, ty, left, right).0,
+        // Cranelift takes the shift amount modulo the width
+        "<<" => builder.ins().ishl(left, right),
+        ">>" if ty.is_signed() => builder.ins().sshr(left, right),
+        ">>" => builder.ins().ushr(left, right),
+        _ => panic!("'{}' cannot overflow", op)
+    }
+}
+
+// Wrapped result, and a boolean that is true when the exact result was out of range
+pub fn overflowing(builder: &mut FunctionBuilder, op: &str, ty: &FundamentalType, left: Value, right: Value) -> (Value, Value) {
+    let signed = ty.is_signed();
+    let value = wrapping(builder, op, ty, left, right);
+    let overflow = match op {
+        // Signed sums overflow when both operands have the sign the result lacks
+        "+" if signed => {
+            let left_sign = builder.ins().bxor(left, value);
+            let right_sign = builder.ins().bxor(right, value);
+            let both = builder.ins().band(left_sign, right_sign);
+            builder.ins().icmp_imm(IntCC::SignedLessThan, both, 0)
+        },
+        "+" => builder.ins().icmp(IntCC::UnsignedLessThan, value, left),
+        // Signed differences overflow when the operands differ in sign and the result has the right's
+        "-" if signed => {
+            let operands = builder.ins().bxor(left, right);
+            let result = builder.ins().bxor(left, value);
+            let both = builder.ins().band(operands, result);
+            builder.ins().icmp_imm(IntCC::SignedLessThan, both, 0)
+        },
+        "-" => builder.ins().icmp(IntCC::UnsignedLessThan, left, right),
+        "*" => multiply_overflow(builder, signed, left, right, value),
+        "**" => return pow(builder, ty, left, right),
+        _ => {
+            // Negative amounts are out of range as unsigned numbers too
+            let bits = builder.func.dfg.value_type(left).bits();
+            builder.ins().icmp_imm(IntCC::UnsignedGreaterThanOrEqual, right, bits as i64)
+        }
+    };
+    (value, overflow)
+}
+
+// Result clamped to the bounds of the type, for '+', '-' and '*'
+pub fn saturating(builder: &mut FunctionBuilder, op: &str, ty: &FundamentalType, left: Value, right: Value) -> Value {
+    let ir_type = builder.func.dfg.value_type(left);
+    let bits = ir_type.bits();
+    let (value, overflow) = overflowing(builder, op, ty, left, right);
+
+    let bound = if ty.is_signed() {
+        let min = builder.ins().iconst(ir_type, bit_mask(bits) & (1 << (bits - 1)));
+        let max = builder.ins().iconst(ir_type, bit_mask(bits - 1));
+        // Sums and differences overflow towards the sign of the left operand,
+        // products towards the sign the exact product has
+        let sign = match op {
+            "*" => builder.ins().bxor(left, right),
+            _ => left
+        };
+        let negative = builder.ins().icmp_imm(IntCC::SignedLessThan, sign, 0);
+        builder.ins().select(negative, min, max)
+    } else {
+        match op {
+            "-" => builder.ins().iconst(ir_type, 0),
+            _ => builder.ins().iconst(ir_type, bit_mask(bits))
+        }
+    };
+    builder.ins().select(overflow, bound, value)
+}
+
+// Lowest 'bits' bits set, as the immediate of a constant of that width
+fn bit_mask(bits: u16) -> i64 {
+    (u64::MAX >> (64 - bits)) as i64
+}
+
+fn multiply_overflow(builder: &mut FunctionBuilder, signed: bool, left: Value, right: Value, value: Value) -> Value {
+    let ir_type = builder.func.dfg.value_type(left);
+    if ir_type.bits() < 64 {
+        // The exact product of narrower integers fits in 64 bits
+        let extend = |builder: &mut FunctionBuilder, value: Value| if signed {
+            builder.ins().sextend(types::I64, value)
+        } else {
+            builder.ins().uextend(types::I64, value)
+        };
+        let wide_left = extend(builder, left);
+        let wide_right = extend(builder, right);
+        let exact = builder.ins().imul(wide_left, wide_right);
+        let wrapped = extend(builder, value);
+        return builder.ins().icmp(IntCC::NotEqual, exact, wrapped);
+    }
+
+    // The high half of the exact product must only repeat the sign of the low half
+    if signed {
+        let high = builder.ins().smulhi(left, right);
+        let sign = builder.ins().sshr_imm(value, 63);
+        builder.ins().icmp(IntCC::NotEqual, high, sign)
+    } else {
+        let high = builder.ins().umulhi(left, right);
+        builder.ins().icmp_imm(IntCC::NotEqual, high, 0)
+    }
+}
+
+// Exponentiation by squaring, with the exponent taken as unsigned. Negative exponents of signed
+// integers overflow. The base is only squared while bits of the exponent remain, so squaring
+// overflows only when the result does
+fn pow(builder: &mut FunctionBuilder, ty: &FundamentalType, base: Value, exponent: Value) -> (Value, Value) {
+    let ir_type = builder.func.dfg.value_type(base);
+    let exponent_type = builder.func.dfg.value_type(exponent);
+    let header = builder.create_block();
+    let square = builder.create_block();
+    let done = builder.create_block();
+    for (block, params) in [(header, &[ir_type, ir_type, exponent_type, types::B1][..]), (square, &[]), (done, &[ir_type, types::B1])] {
+        for param in params {
+            builder.append_block_param(block, *param);
+        }
+    }
+
+    let one = builder.ins().iconst(ir_type, 1);
+    let negative = if ty.is_signed() {
+        builder.ins().icmp_imm(IntCC::SignedLessThan, exponent, 0)
+    } else {
+        builder.ins().bconst(types::B1, false)
+    };
+    builder.ins().brz(exponent, done, &[one, negative]);
+    builder.ins().jump(header, &[one, base, exponent, negative]);
+
+    builder.switch_to_block(header);
+    let [result, power, remaining, overflow]: [Value; 4] = builder.block_params(header).try_into().unwrap();
+    let low_bit = builder.ins().band_imm(remaining, 1);
+    let (product, product_overflow) = overflowing(builder, "*", ty, result, power);
+    let result = builder.ins().select(low_bit, product, result);
+    let product_overflow = builder.ins().select(low_bit, product_overflow, overflow);
+    let overflow = builder.ins().bor(overflow, product_overflow);
+    let remaining = builder.ins().ushr_imm(remaining, 1);
+    builder.ins().brz(remaining, done, &[result, overflow]);
+    builder.ins().jump(square, &[]);
+
+    builder.switch_to_block(square);
+    let (squared, square_overflow) = overflowing(builder, "*", ty, power, power);
+    let overflow = builder.ins().bor(overflow, square_overflow);
+    builder.ins().jump(header, &[result, squared, remaining, overflow]);
+
+    for block in [header, square, done] {
+        builder.seal_block(block);
+    }
+    builder.switch_to_block(done);
+    let [result, overflow]: [Value; 2] = builder.block_params(done).try_into().unwrap();
+    (result, overflow)
+}
+
+// Calls the handler with the location of the operator when 'overflow' is true.
+// Lowering continues in a new block that is only reached without overflow
+pub fn trap_if(builder: &mut FunctionBuilder, overflow: Value, site: &OverflowSite) {
+    let trap: Block = builder.create_block();
+    let next: Block = builder.create_block();
+    builder.set_cold_block(trap);
+    builder.ins().brnz(overflow, trap, &[]);
+    builder.ins().jump(next, &[]);
+
+    builder.switch_to_block(trap);
+    builder.seal_block(trap);
+    let line = builder.ins().iconst(types::I32, site.span.line() as i64);
+    let column = builder.ins().iconst(types::I32, site.span.column() as i64);
+    builder.ins().call(site.handler, &[line, column]);
+    // The handler aborts, but the block still needs a terminator
+    builder.ins().trap(TrapCode::IntegerOverflow);
+
+    builder.switch_to_block(next);
+    builder.seal_block(next);
+}
+
+#[cfg(test)]
+mod tests {
+    use cranelift::prelude::{settings, AbiParam, FunctionBuilder, FunctionBuilderContext, InstBuilder};
+    use cranelift_codegen::{
+        ir::{ExternalName, Function, Signature},
+        isa::{self, CallConv},
+        verify_function,
+        Context
+    };
+    use gecko_parser::ast::parse_gecko;
+    use target_lexicon::Triple;
+
+    use super::{handler_signature, intrinsic, operator, saturating, BuildMode, Overflow, OverflowSite};
+    use crate::{
+        codegen::DataLayout,
+        tree_type::FundamentalType
+    };
+
+    // Function taking two values of 'ty' and returning the result of 'op' on them
+    fn lower(op: &str, ty: &FundamentalType, mode: Option<BuildMode>) -> Function {
+        let data_layout = DataLayout::new(&Triple::host());
+        let ir_type = ty.to_ir_type(&data_layout);
+        let mut signature = Signature::new(CallConv::SystemV);
+        signature.params.extend([AbiParam::new(ir_type), AbiParam::new(ir_type)]);
+        signature.returns.push(AbiParam::new(ir_type));
+
+        let mut function = Function::with_name_signature(ExternalName::user(0, 0), signature);
+        let mut context = FunctionBuilderContext::new();
+        let mut builder = FunctionBuilder::new(&mut function, &mut context);
+        let handler = builder.import_signature(handler_signature(CallConv::SystemV));
+        let handler = builder.import_function(cranelift_codegen::ir::ExtFuncData {
+            name: ExternalName::user(0, 1),
+            signature: handler,
+            colocated: false
+        });
+        let span = parse_gecko("proc f() -> int { return 1; }").unwrap().span.unwrap();
+
+        let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+        builder.seal_block(entry);
+        let (left, right) = (builder.block_params(entry)[0], builder.block_params(entry)[1]);
+        let value = match mode {
+            Some(mode) => operator(&mut builder, op, ty, left, right, mode, &OverflowSite{ handler, span }),
+            None => saturating(&mut builder, op, ty, left, right)
+        };
+        builder.ins().return_(&[value]);
+        builder.finalize();
+        function
+    }
+
+    fn compile(function: Function) {
+        let isa = isa::lookup(Triple::host()).unwrap()
+            .finish(settings::Flags::new(settings::builder()))
+            .unwrap();
+        verify_function(&function, &*isa).unwrap();
+        Context::for_function(function).compile(&*isa).unwrap();
+    }
+
+    const INTEGERS: [FundamentalType; 10] = [
+        FundamentalType::I8, FundamentalType::I16, FundamentalType::I32, FundamentalType::I64, FundamentalType::Isize,
+        FundamentalType::U8, FundamentalType::U16, FundamentalType::U32, FundamentalType::U64, FundamentalType::Usize
+    ];
+
+    #[test]
+    fn operators_compile() {
+        for ty in &INTEGERS {
+            for op in ["+", "-", "*", "**", "<<", ">>"] {
+                for mode in [BuildMode::Debug, BuildMode::Release] {
+                    compile(lower(op, ty, Some(mode)));
+                }
+            }
+            for op in ["+", "-", "*"] {
+                compile(lower(op, ty, None));
+            }
+        }
+    }
+
+    #[test]
+    fn only_debug_builds_report() {
+        let calls = |mode| lower("+", &FundamentalType::I32, Some(mode)).display().to_string().matches("call fn0").count();
+        assert_eq!(calls(BuildMode::Debug), 1);
+        assert_eq!(calls(BuildMode::Release), 0);
+    }
+
+    #[test]
+    fn intrinsics() {
+        assert_eq!(intrinsic("wrapping_add"), Some((Overflow::Wrapping, "+")));
+        assert_eq!(intrinsic("checked_sub"), Some((Overflow::Checked, "-")));
+        assert_eq!(intrinsic("saturating_mul"), Some((Overflow::Saturating, "*")));
+        assert_eq!(intrinsic("wrapping_div"), None);
+        assert_eq!(intrinsic("add"), None);
+    }
+}
@@ -0,0 +1,2249 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use cranelift::prelude::{
+    types, AbiParam, Block, FloatCC, FunctionBuilder, FunctionBuilderContext, Imm64, InstBuilder, IntCC, MemFlags,
+    StackSlotData, StackSlotKind, TrapCode, Type as IrType, Value, Variable
+};
+use cranelift_codegen::{
+    entity::EntityRef,
+    ir::{ExtFuncData, FuncRef, Function, GlobalValueData, Signature, SourceLoc, StackSlot, ValueLabel},
+    isa::{CallConv, TargetFrontendConfig}
+};
+use gecko_parser::{
+    ast::Span,
+    expression::{self, StringSegment},
+    node::{self, NodeType, PatternKind},
+    statement
+};
+use target_lexicon::{PointerWidth, Triple};
+
+use crate::{
+    symbol::{self, Symbol, SymbolTable},
+    codegen::{
+        contract::{self, Check, ContractSite},
+        debug_info,
+        ir::{self, Program},
+        numeric,
+        overflow::{self, BuildMode, Overflow, OverflowSite},
+        bounds,
+        variadic,
+        DataLayout,
+        Layout,
+        OptLevel
+    },
+    interpreter::{self, check_range, Value as Constant},
+    tree_type::{cast_target, result_identifier, type_query_value, value_type, FundamentalType, Type, TypeCheck},
+    error::TypeError
+};
+
+// Procedure bodies are lowered with the symbol table the checker had at each statement, which
+// is rebuilt the same way the checker built it, to find the types it gave to expressions.
+// Variables and parameters become Cranelift variables, or stack slots at -O0. Scalars are kept in
+// IR registers. Booleans are B1 while they are operated on, and a byte in memory and at calls, as
+// in C. Aggregates are kept in memory laid out by DataLayout, and their values are its address:
+// strings and slices are a pointer and a length, while arrays, tuples, structs and Results, a tag
+// followed by their value or error, are stored in the frame of the procedure creating them.
+// Values are never modified in place, so aggregates are only copied out of memory that can
+// change, when they are dereferenced or returned.
+// Inlined calls lower the body of the callee in place, as if it was a block of the caller that
+// its 'return's leave, see Lowering::inline
+
+// Runtime procedures 'new' and 'delete' allocate and free with, see gecko_runtime::alloc
+pub const ALLOC: &str = "__gecko_alloc";
+pub const FREE: &str = "__gecko_free";
+
+// Runtime procedures building interpolated strings, and strings concatenated with '+' or repeated
+// with '*', which are allocated as if by 'new', see gecko_runtime::format
+pub const FORMAT_BEGIN: &str = "__gecko_format_begin";
+pub const FORMAT_STR: &str = "__gecko_format_str";
+pub const FORMAT_REPEAT: &str = "__gecko_format_repeat";
+pub const FORMAT_END: &str = "__gecko_format_end";
+
+type Lowered<T> = Result<T, TypeError>;
+
+fn unsupported<T>(what: &str, span: Option<Span>) -> Lowered<T> {
+    Err(TypeError{ text: match span {
+        Some(span) => format!("{} cannot be compiled yet, at {}", what, span),
+        None => format!("{} cannot be compiled yet", what)
+    }})
+}
+
+// Type a value gets where 'expected' is wanted. Literals take the numeric type they are used as,
+// including those in aggregates, see Type::is_compatible, and have their default types otherwise
+fn resolve(ty: &Type, expected: Option<&Type>) -> Type {
+    use FundamentalType::{Array, Float, Integer, Slice, Tuple};
+    let expected: &Type = match expected {
+        Some(expected) => expected,
+        None => return ty.concrete()
+    };
+    Type{ ty: match (&ty.ty, &expected.ty) {
+        (Integer, to) if to.is_integer() || to.is_float() => return expected.concrete(),
+        (Float, to) if to.is_float() => return expected.concrete(),
+        (Array(element, length), Array(to, _) | Slice(to)) => Array(Box::new(resolve(element, Some(to))), *length),
+        (Slice(element), Slice(to)) => Slice(Box::new(resolve(element, Some(to)))),
+        (Tuple(elements), Tuple(to)) if elements.len() == to.len() => {
+            Tuple(elements.iter().zip(to).map(|(element, to)| resolve(element, Some(to))).collect())
+        },
+        (FundamentalType::Result(value, error), FundamentalType::Result(to_value, to_error)) => {
+            FundamentalType::Result(Box::new(resolve(value, Some(to_value))), Box::new(resolve(error, Some(to_error))))
+        },
+        _ => return ty.concrete()
+    }}
+}
+
+// IR type values of a type are stored and passed to procedures as
+fn abi_type(ty: &Type, data_layout: &DataLayout) -> IrType {
+    match ty.ty {
+        FundamentalType::Boolean => types::I8,
+        _ => ty.ty.to_ir_type(data_layout)
+    }
+}
+
+// Narrow integers are extended to a register by the caller, following the C ABI
+fn abi_param(ty: &Type, data_layout: &DataLayout) -> AbiParam {
+    let param = AbiParam::new(abi_type(ty, data_layout));
+    match ty.ty {
+        FundamentalType::I8 | FundamentalType::I16 => param.sext(),
+        FundamentalType::Boolean | FundamentalType::Character | FundamentalType::U8 | FundamentalType::U16 => param.uext(),
+        _ => param
+    }
+}
+
+// Signature of a procedure in the calling convention of the platform's C compiler. Aggregates are
+// passed by address, and returned by writing them to an address the caller passes first
+pub fn signature(function: &symbol::Function, call_conv: CallConv, data_layout: &DataLayout) -> Signature {
+    let mut signature = Signature::new(call_conv);
+    let output: &Type = function.output();
+    match output.is_scalar() {
+        true => signature.returns.push(abi_param(output, data_layout)),
+        false => signature.params.push(AbiParam::new(data_layout.pointer_type()))
+    }
+    signature.params.extend(function.params().iter().map(|(_, ty)| abi_param(ty, data_layout)));
+    signature
+}
+
+// Text of a string literal without interpolation
+fn string_literal(node: &NodeType) -> Option<String> {
+    match node {
+        NodeType::Term(term) => string_literal(&term.node),
+        NodeType::Str(string) => literal_text(string),
+        _ => None
+    }
+}
+
+fn literal_text(string: &expression::Str) -> Option<String> {
+    string.segments
+        .iter()
+        .map(|segment| match segment {
+            StringSegment::Text(text) => Some(text.as_str()),
+            StringSegment::Interpolation(_) => None
+        })
+        .collect()
+}
+
+// Types of the value and the error of a Result
+fn result_types(ty: &Type) -> Lowered<(&Type, &Type)> {
+    match &ty.ty {
+        FundamentalType::Result(value, error) => Ok((value, error)),
+        _ => Err(TypeError{ text: format!("Expected a Result, got '{}'", ty) })
+    }
+}
+
+// Where the value of a variable is kept
+#[derive(Clone, Copy)]
+enum Local {
+    Register(Variable),
+    // Slot of the variable alone, at -O0
+    Slot(StackSlot),
+}
+
+// Types the operands of an arithmetic, bitwise or comparison operator are lowered as, given the
+// types the checker gave them
+fn operand_types(operator: &expression::BinaryOperator, left: &Type, right: &Type, expected: Option<&Type>) -> Lowered<(Type, Type)> {
+    let op: &str = operator.op.value.as_str();
+    let comparison = numeric::int_condition(op, false).is_some();
+    Ok(match op {
+        // The shift amount keeps its own type
+        "<<" | ">>" => (resolve(left, expected), resolve(right, None)),
+        _ => {
+            let ty = match left.arithmetic_type(right) {
+                Some(ty) => ty,
+                None if comparison => match left.common_type(right) {
+                    Some(ty) => ty,
+                    None => return Err(TypeError{ text: format!("Cannot compare '{}' with '{}'", left, right) })
+                },
+                None => return unsupported(&format!("'{}' on '{}' and '{}'", op, left, right), Some(operator.span))
+            };
+            let ty = resolve(&ty, if comparison { None } else { expected });
+            (ty.clone(), ty)
+        }
+    })
+}
+
+// Variables visible at a point of the procedure, with the symbol table the checker had there
+#[derive(Clone)]
+struct Context<'a> {
+    variables: HashMap<String, (Local, Type)>,
+    table: SymbolTable<'a>,
+}
+
+// Statement of a 'defer', lowered again at every exit of its block
+#[derive(Clone)]
+struct Deferred<'a> {
+    stmt: &'a NodeType,
+    context: Context<'a>,
+}
+
+#[derive(Default)]
+struct Scope<'a> {
+    variables: HashMap<String, (Local, Type)>,
+    deferred: Vec<Deferred<'a>>,
+}
+
+// Procedures of a file, and the settings every one of them is lowered with
+pub struct Unit<'a, 'u> {
+    // Global symbols, as the checker left them
+    pub table: &'u SymbolTable<'a>,
+    // Definitions calls can be inlined from, by name
+    pub definitions: HashMap<&'a str, &'a statement::FunctionDefinition>,
+    pub mode: BuildMode,
+    pub opt_level: OptLevel,
+    pub triple: &'u Triple,
+    // Symbols of the procedures defined inside others, by the span of their name, as each is
+    // lowered once however many times the procedure defining it is inlined, see Lowering::nested
+    pub nested: RefCell<Vec<(Span, String)>>,
+}
+
+// Results of Lowering::fold
+type Folds = HashMap<*const NodeType, Vec<(Option<Type>, Option<Constant>)>>;
+
+struct Lowering<'a, 'b> {
+    builder: FunctionBuilder<'b>,
+    program: &'b mut Program,
+    unit: &'b Unit<'a, 'b>,
+    // Symbol table as the checker saw it at the statement being lowered
+    table: SymbolTable<'a>,
+    scopes: Vec<Scope<'a>>,
+    // Parameters, which 'ensures' clauses are checked with
+    parameters: Context<'a>,
+    variables: u32,
+    // Procedures imported so far, by symbol
+    imports: HashMap<String, FuncRef>,
+    // Signature and output of the procedure whose body is being lowered, which is an inlined
+    // callee while 'exit' is set
+    signature: &'a statement::Signature,
+    output: Type,
+    // Block an inlined callee's 'return's jump to with their value
+    exit: Option<Block>,
+    // Address the caller passed to write an aggregate output to
+    result: Option<Value>,
+    // Procedure being lowered, and the callees being inlined into it, innermost last
+    inlining: Vec<&'a str>,
+    // Symbol of the procedure whose body is being lowered, which the procedures defined in it are
+    // named after
+    symbol: String,
+    call_conv: CallConv,
+    data_layout: DataLayout,
+    // Line of the statement or expression being lowered, see Lowering::at
+    srcloc: SourceLoc,
+    // Variables of the procedure, for its debug information
+    debug_info: debug_info::Procedure,
+    // Values of the operators folded so far, by address and the type they were folded to, so an
+    // operator is not folded again for each operator containing it
+    folds: RefCell<Folds>,
+}
+
+// Lowers a procedure definition of 'unit' to a function named after its symbol in 'program'
+pub fn lower<'a>(definition: &'a statement::FunctionDefinition, unit: &Unit<'a, '_>, program: &mut Program, context: &mut FunctionBuilderContext) -> Lowered<Function> {
+    lower_in(definition, unit, unit.table, &definition.sig.id.name, program, context)
+}
+
+// Lowers a procedure defined where the checker had 'table' to a function named 'symbol'
+fn lower_in<'a>(definition: &'a statement::FunctionDefinition, unit: &Unit<'a, '_>, table: &SymbolTable<'a>, symbol_name: &str, program: &mut Program, context: &mut FunctionBuilderContext) -> Lowered<Function> {
+    let call_conv = CallConv::triple_default(unit.triple);
+    let data_layout = table.data_layout;
+    let symbol = table.function(&definition.sig.id)?;
+    let name = program.symbol(symbol_name);
+    let mut function = Function::with_name_signature(name.clone(), signature(&symbol, call_conv, &data_layout));
+    // Values are labelled with the variables they are the values of, see Lowering::local
+    function.collect_debug_info();
+
+    // Unsafe operations were already allowed or rejected when the procedure was checked
+    let mut table: SymbolTable<'a> = table.clone();
+    table.output = Some(symbol.output().clone());
+    table.in_unsafe = true;
+
+    let mut lowering = Lowering{
+        builder: FunctionBuilder::new(&mut function, context),
+        program,
+        unit,
+        parameters: Context{ variables: HashMap::new(), table: table.clone() },
+        table,
+        scopes: vec!(),
+        variables: 0,
+        imports: HashMap::new(),
+        signature: &definition.sig,
+        output: symbol.output().clone(),
+        exit: None,
+        result: None,
+        inlining: vec!(definition.sig.id.name.as_str()),
+        symbol: symbol_name.to_owned(),
+        call_conv,
+        data_layout,
+        srcloc: SourceLoc::new(definition.span.line() as u32),
+        debug_info: debug_info::Procedure{
+            function: name,
+            name: definition.sig.id.name.clone(),
+            line: definition.span.line() as u32,
+            output: symbol.output().clone(),
+            variables: vec!()
+        },
+        folds: RefCell::default()
+    };
+    lowering.body(definition)?;
+    lowering.builder.finalize();
+    let debug_info = lowering.debug_info;
+    program.debug_info.push(debug_info);
+    Ok(function)
+}
+
+impl<'a, 'b> Lowering<'a, 'b> {
+    fn body(&mut self, definition: &'a statement::FunctionDefinition) -> Lowered<()> {
+        self.builder.set_srcloc(self.srcloc);
+        let entry = self.builder.create_block();
+        self.builder.append_block_params_for_function_params(entry);
+        self.builder.switch_to_block(entry);
+        self.builder.seal_block(entry);
+
+        let mut params: Vec<Value> = self.builder.block_params(entry).to_vec();
+        if !self.output.is_scalar() {
+            self.result = Some(params.remove(0));
+        }
+        let mut args: Vec<Value> = vec!();
+        for ((param, _), value) in definition.sig.params.parameters.iter().zip(params) {
+            let ty: Type = Type::from_specifier(&param.ty, &self.table)?;
+            args.push(self.loaded_value(value, &ty));
+        }
+        self.procedure(definition, args)
+    }
+
+    // Binds the parameters of a procedure to 'args', checks its preconditions and lowers its
+    // block, returning the value it falls through with
+    fn procedure(&mut self, definition: &'a statement::FunctionDefinition, args: Vec<Value>) -> Lowered<()> {
+        self.scopes.push(Scope::default());
+        for ((param, _), value) in definition.sig.params.parameters.iter().zip(args) {
+            let ty: Type = Type::from_specifier(&param.ty, &self.table)?;
+            self.bind(&param.pattern, value, ty, true)?;
+        }
+        definition.sig.check(&mut self.table)?;
+        self.parameters = Context{ variables: self.scopes[0].variables.clone(), table: self.table.clone() };
+
+        if self.unit.mode.checks_contracts() {
+            for contract in definition.sig.contracts.iter().filter(|contract| contract.kind == node::ContractKind::Requires) {
+                self.condition(Check::Precondition, &contract.condition, &contract.text, None, contract.span)?;
+            }
+        }
+
+        let output = self.output.clone();
+        let value = self.block_as(&definition.block, &output)?;
+        // Falling through returns at the closing brace
+        self.srcloc = SourceLoc::new(definition.block.span.end_line() as u32);
+        self.builder.set_srcloc(self.srcloc);
+        if !self.builder.is_filled() {
+            match value {
+                Some(value) => self.ret(value)?,
+                // Falling off the end of a procedure without returning a value
+                None => {
+                    self.builder.ins().trap(TrapCode::UnreachableCodeReached);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Type the checker gives an expression
+    fn type_of(&self, node: &'a NodeType) -> Lowered<Type> {
+        value_type(node, &mut self.table.clone())
+    }
+
+    fn layout(&self, ty: &Type) -> Lowered<Layout> {
+        self.data_layout.layout_of(ty, &self.table)
+    }
+
+    // Value as it is stored in memory and passed to procedures
+    fn abi_value(&mut self, value: Value, ty: &Type) -> Value {
+        match ty.ty {
+            FundamentalType::Boolean => self.builder.ins().bint(types::I8, value),
+            _ => value
+        }
+    }
+
+    // Value as it is operated on, once loaded from memory or received from a procedure
+    fn loaded_value(&mut self, value: Value, ty: &Type) -> Value {
+        match ty.ty {
+            FundamentalType::Boolean => self.builder.ins().icmp_imm(IntCC::NotEqual, value, 0),
+            _ => value
+        }
+    }
+
+    // New variable holding 'value'
+    fn local(&mut self, value: Value, ty: &Type) -> Local {
+        let ir_type = ty.ty.to_ir_type(&self.data_layout);
+        if self.unit.opt_level.keeps_variables_in_memory() {
+            let size = abi_type(ty, &self.data_layout).bytes();
+            let slot = self.builder.create_stack_slot(StackSlotData::new(StackSlotKind::ExplicitSlot, size));
+            let value = self.abi_value(value, ty);
+            self.builder.ins().stack_store(value, slot, 0);
+            return Local::Slot(slot);
+        }
+        let variable = Variable::with_u32(self.variables);
+        self.variables += 1;
+        self.builder.declare_var(variable, ir_type);
+        self.builder.def_var(variable, value);
+        self.builder.set_val_label(value, ValueLabel::new(variable.index()));
+        Local::Register(variable)
+    }
+
+    fn use_local(&mut self, local: Local, ty: &Type) -> Value {
+        match local {
+            Local::Register(variable) => self.builder.use_var(variable),
+            Local::Slot(slot) => {
+                let value = self.builder.ins().stack_load(abi_type(ty, &self.data_layout), slot, 0);
+                self.loaded_value(value, ty)
+            }
+        }
+    }
+
+    // Binds the variables of a pattern to the parts of a value of type 'ty', see tree_type::bind_pattern
+    fn bind(&mut self, pattern: &'a node::Pattern, value: Value, ty: Type, parameter: bool) -> Lowered<()> {
+        match &pattern.kind {
+            PatternKind::Identifier(id) => self.variable(id, value, ty, parameter),
+            PatternKind::Wildcard(_) => Ok(()),
+            PatternKind::Tuple{ elements, .. } => {
+                let types: Vec<Type> = match &ty.ty {
+                    FundamentalType::Tuple(types) => types.clone(),
+                    _ => unreachable!()
+                };
+                let offsets: Vec<u64> = self.data_layout.field_offsets(&ty, &self.table)?;
+                for ((element, element_ty), offset) in elements.iter().zip(types).zip(offsets) {
+                    let element_value = self.load(&element_ty, value, offset);
+                    self.bind(element, element_value, element_ty, parameter)?;
+                }
+                Ok(())
+            },
+            PatternKind::Struct{ id, fields, .. } => {
+                let structure = self.table.structure(&id.name)?;
+                for field in fields {
+                    let field_ty: Type = structure.field(&field.id.name).cloned()
+                        .ok_or_else(|| TypeError{ text: format!("Struct '{}' has no field '{}', at {}", id.name, field.id.name, field.span) })?;
+                    let offset = self.data_layout.offset_of(&ty, &field.id.name, &self.table)?;
+                    let field_value = self.load(&field_ty, value, offset);
+                    match &field.pattern {
+                        Some(field_pattern) => self.bind(field_pattern, field_value, field_ty, parameter)?,
+                        None => self.variable(&field.id, field_value, field_ty, parameter)?
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn variable(&mut self, id: &expression::Identifier, value: Value, ty: Type, parameter: bool) -> Lowered<()> {
+        let local = self.local(value, &ty);
+        // Variables of inlined callees are left to the callee's own debug information, and
+        // aggregates to later versions of it, as their variables hold their address
+        if self.exit.is_none() && ty.is_scalar() {
+            let location = match local {
+                Local::Register(variable) => debug_info::Location::Value(ValueLabel::new(variable.index())),
+                Local::Slot(slot) => debug_info::Location::Slot(slot)
+            };
+            let line = id.span.line() as u32;
+            self.debug_info.variables.push(debug_info::Variable{ name: id.name.clone(), ty: ty.clone(), line, parameter, location });
+        }
+        self.scopes.last_mut().unwrap().variables.insert(id.name.clone(), (local, ty));
+        Ok(())
+    }
+
+    // Lowers code with the variables and symbol table of another point of the procedure
+    fn in_context<T>(&mut self, context: &Context<'a>, lower: impl FnOnce(&mut Self) -> Lowered<T>) -> Lowered<T> {
+        let scope = Scope{ variables: context.variables.clone(), deferred: vec!() };
+        let scopes = std::mem::replace(&mut self.scopes, vec!(scope));
+        let table = std::mem::replace(&mut self.table, context.table.clone());
+        let result = lower(self);
+        self.scopes = scopes;
+        self.table = table;
+        result
+    }
+
+    fn run_deferred(&mut self, deferred: &Deferred<'a>) -> Lowered<()> {
+        self.in_context(&deferred.context, |lowering| {
+            lowering.statement(deferred.stmt)?;
+            lowering.exit_scope()
+        })
+    }
+
+    // Leaves the innermost scope, running its deferred statements in reverse order
+    fn exit_scope(&mut self) -> Lowered<()> {
+        let scope = self.scopes.pop().unwrap();
+        if !self.builder.is_unreachable() {
+            for deferred in scope.deferred.iter().rev() {
+                self.run_deferred(deferred)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Runs every pending deferred statement, checks the 'ensures' clauses and returns, or leaves
+    // the inlined callee. Lowering continues in a block that is never reached
+    fn ret(&mut self, value: Value) -> Lowered<()> {
+        let deferred: Vec<Deferred<'a>> = self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.deferred.iter().rev().cloned())
+            .collect();
+        for deferred in &deferred {
+            self.run_deferred(deferred)?;
+        }
+
+        let output = self.output.clone();
+        if self.unit.mode.checks_contracts() {
+            let signature = self.signature;
+            for contract in signature.contracts.iter().filter(|contract| contract.kind == node::ContractKind::Ensures) {
+                let id = result_identifier(contract);
+                let result = self.local(value, &output);
+                let mut context = self.parameters.clone();
+                context.variables.insert(id.name.clone(), (result, output.clone()));
+                context.table.initialise_variable(&id, output.clone());
+                self.in_context(&context, |lowering| {
+                    lowering.condition(Check::Postcondition, &contract.condition, &contract.text, None, contract.span)
+                })?;
+            }
+        }
+
+        match (self.exit, self.result) {
+            (Some(exit), _) => {
+                self.builder.ins().jump(exit, &[value]);
+            },
+            (None, Some(result)) => {
+                let layout = self.layout(&output)?;
+                self.copy(result, value, layout);
+                self.builder.ins().return_(&[]);
+            },
+            (None, None) => {
+                let value = self.abi_value(value, &output);
+                self.builder.ins().return_(&[value]);
+            }
+        }
+        let unreachable = self.builder.create_block();
+        self.builder.switch_to_block(unreachable);
+        self.builder.seal_block(unreachable);
+        Ok(())
+    }
+
+    // Attributes the instructions 'lower' adds to the line of 'span', so dumps and debug information
+    // can map them back to the source. Instructions of the enclosing node keep its line
+    fn at<T>(&mut self, span: Option<Span>, lower: impl FnOnce(&mut Self) -> T) -> T {
+        let outer: SourceLoc = self.srcloc;
+        if let Some(span) = span {
+            self.srcloc = SourceLoc::new(span.line() as u32);
+            self.builder.set_srcloc(self.srcloc);
+        }
+        let result = lower(self);
+        self.srcloc = outer;
+        self.builder.set_srcloc(outer);
+        result
+    }
+
+    fn statement(&mut self, stmt: &'a NodeType) -> Lowered<()> {
+        self.at(stmt.span(), |lowering| lowering.lower_statement(stmt))
+    }
+
+    fn lower_statement(&mut self, stmt: &'a NodeType) -> Lowered<()> {
+        match stmt {
+            NodeType::Expression(expression) => {
+                self.expression(&expression.expr, None)?;
+            },
+            NodeType::VariableInitialisation(init) => {
+                let ty: Type = match &init.ty {
+                    Some(spec) => Type::from_specifier(spec, &self.table)?,
+                    None => self.type_of(&init.expr)?.concrete()
+                };
+                let value = self.operand(&init.expr, &ty)?;
+                self.bind(&init.pattern, value, ty, false)?;
+            },
+            // Variables cannot be used before they are initialised
+            NodeType::VariableDeclaration(_) => {},
+            NodeType::Return(ret) => {
+                let output = self.output.clone();
+                let value = self.operand(&ret.expr, &output)?;
+                self.ret(value)?;
+            },
+            NodeType::Defer(defer) => {
+                let variables: HashMap<String, (Local, Type)> = self.scopes
+                    .iter()
+                    .flat_map(|scope| scope.variables.iter().map(|(name, variable)| (name.clone(), variable.clone())))
+                    .collect();
+                let deferred = Deferred{ stmt: &defer.stmt, context: Context{ variables, table: self.table.clone() } };
+                self.scopes.last_mut().unwrap().deferred.push(deferred);
+            },
+            NodeType::Assert(assert) => {
+                if self.unit.mode.checks_contracts() {
+                    self.condition(Check::Assertion, &assert.condition, &assert.text, assert.message.as_deref(), assert.span)?;
+                }
+            },
+            NodeType::Delete(delete) => {
+                let ty: Type = self.type_of(&delete.expr)?;
+                let pointer = self.operand(&delete.expr, &ty)?;
+                let mut signature = Signature::new(self.call_conv);
+                signature.params.push(AbiParam::new(self.data_layout.pointer_type()));
+                let free = self.import(FREE, signature);
+                self.builder.ins().call(free, &[pointer]);
+            },
+            // Only change the symbols in scope
+            NodeType::FunctionDeclaration(_) | NodeType::StructDefinition(_) | NodeType::ConstantDefinition(_) | NodeType::TypeAlias(_) => {},
+            NodeType::FunctionDefinition(definition) => self.nested(definition)?,
+            // Blocks, 'if's and 'unsafe' blocks written as statements
+            _ => {
+                self.expression(stmt, None)?;
+            }
+        }
+        stmt.check(&mut self.table)?;
+        Ok(())
+    }
+
+    fn block(&mut self, block: &'a node::Block, expected: Option<&Type>) -> Lowered<Option<Value>> {
+        let table: SymbolTable<'a> = self.table.clone();
+        self.scopes.push(Scope::default());
+        for stmt in &block.stmts {
+            self.statement(stmt)?;
+        }
+        let value = match &block.tail {
+            Some(tail) => self.expression(tail, expected)?,
+            None => None
+        };
+        self.exit_scope()?;
+        self.table = table;
+        Ok(value)
+    }
+
+    // Lowers a block whose value is converted to 'ty', as the body of a procedure or a branch of an
+    // 'if', whose values must all have the same layout
+    fn block_as(&mut self, block: &'a node::Block, ty: &Type) -> Lowered<Option<Value>> {
+        if ty.is_scalar() {
+            return self.block(block, Some(ty));
+        }
+        let own: Option<Type> = block.check(&mut self.table.clone())?;
+        let value = self.block(block, Some(ty))?;
+        match (value, own) {
+            (Some(value), Some(own)) => self.convert(value, &resolve(&own, Some(ty)), ty, Some(block.span)).map(Some),
+            (value, _) => Ok(value)
+        }
+    }
+
+    // Procedures defined in a block are lowered to functions of their own, named after the
+    // procedure they are defined in, as they do not see its variables
+    fn nested(&mut self, definition: &'a statement::FunctionDefinition) -> Lowered<()> {
+        let span: Span = definition.sig.id.span;
+        if self.unit.nested.borrow().iter().any(|(other, _)| *other == span) {
+            return Ok(());
+        }
+        // Procedures of the same name in different blocks are numbered
+        let name = format!("{}.{}", self.symbol, definition.sig.id.name);
+        let symbol: String = (1..)
+            .map(|number| if number == 1 { name.clone() } else { format!("{}.{}", name, number) })
+            .find(|symbol| !self.unit.nested.borrow().iter().any(|(_, other)| other == symbol))
+            .unwrap();
+        self.unit.nested.borrow_mut().push((span, symbol.clone()));
+
+        let mut table: SymbolTable<'a> = self.table.clone();
+        definition.check(&mut table)?;
+        let table = table.procedure_scope();
+        let function = lower_in(definition, self.unit, &table, &symbol, self.program, &mut FunctionBuilderContext::new())?;
+        self.program.functions.push(function);
+        Ok(())
+    }
+
+    // Value of an expression that must produce one
+    fn value(&mut self, node: &'a NodeType, expected: Option<&Type>) -> Lowered<Value> {
+        match self.expression(node, expected)? {
+            Some(value) => Ok(value),
+            None => Err(TypeError{ text: "Expected a value, but the expression has none".to_string() })
+        }
+    }
+
+    // Value of an expression converted to 'ty', the way 'as' converts it
+    fn operand(&mut self, node: &'a NodeType, ty: &Type) -> Lowered<Value> {
+        let own: Type = resolve(&self.type_of(node)?, Some(ty));
+        let value = self.value(node, Some(ty))?;
+        self.convert(value, &own, ty, node.span())
+    }
+
+    fn expression(&mut self, node: &'a NodeType, expected: Option<&Type>) -> Lowered<Option<Value>> {
+        self.at(node.span(), |lowering| lowering.lower_expression(node, expected))
+    }
+
+    fn lower_expression(&mut self, node: &'a NodeType, expected: Option<&Type>) -> Lowered<Option<Value>> {
+        let literal = |ty: FundamentalType| resolve(&Type{ ty }, expected);
+        Ok(Some(match node {
+            NodeType::Term(term) => return self.expression(&term.node, expected),
+            NodeType::Boolean(boolean) => self.builder.ins().bconst(types::B1, boolean.value),
+            NodeType::Character(character) => self.builder.ins().iconst(types::I8, character.value as i64),
+            NodeType::Integer(integer) => self.integer(integer.value, &literal(FundamentalType::Integer)),
+            NodeType::Float(float) => self.float(float.value, &literal(FundamentalType::Float)),
+            NodeType::TypeQuery(query) => {
+                let value = type_query_value(query, &self.table)?;
+                self.integer(value, &literal(FundamentalType::Integer))
+            },
+            NodeType::Identifier(id) => self.identifier(id, expected)?,
+            NodeType::BinaryOperator(operator) => match self.fold(node, expected) {
+                Some(value) => self.constant(&value, &resolve(&self.type_of(node)?, expected), node.span())?,
+                None => self.binary_operator(operator, expected)?
+            },
+            NodeType::Call(call) => self.call(call, expected)?,
+            NodeType::Dereference(deref) => self.dereference(deref)?,
+            NodeType::New(new) => self.allocate(new)?,
+            NodeType::Str(string) => self.string(string)?,
+            NodeType::Array(array) => {
+                let ty: Type = resolve(&self.type_of(node)?, expected);
+                self.array(&array.elements, &ty)?
+            },
+            NodeType::Tuple(tuple) => {
+                let ty: Type = resolve(&self.type_of(node)?, expected);
+                self.tuple(&tuple.elements, &ty)?
+            },
+            NodeType::Index(index) => self.index(index, expected)?,
+            NodeType::Slice(slice) => self.slice(slice)?,
+            NodeType::Member(member) => self.member(member, expected)?,
+            NodeType::Try(try_expr) => self.try_expression(try_expr, expected)?,
+            NodeType::If(if_expr) => return self.if_expression(if_expr, expected),
+            NodeType::Block(block) => return self.block(block, expected),
+            NodeType::Unsafe(unsafe_block) => return self.block(&unsafe_block.block, expected),
+            _ => return unsupported("This expression", node.span())
+        }))
+    }
+
+    fn integer(&mut self, value: i128, ty: &Type) -> Value {
+        if ty.ty.is_float() {
+            return self.float(value as f64, ty);
+        }
+        let ir_type = ty.ty.to_ir_type(&self.data_layout);
+        let bits = ir_type.bits();
+        // Immediates of narrow types hold the bits of the value
+        let value = if bits < 64 { value as i64 & ((1 << bits) - 1) } else { value as i64 };
+        self.builder.ins().iconst(ir_type, value)
+    }
+
+    fn float(&mut self, value: f64, ty: &Type) -> Value {
+        match ty.ty {
+            FundamentalType::F32 => self.builder.ins().f32const(value as f32),
+            _ => self.builder.ins().f64const(value)
+        }
+    }
+
+    // Value known at compile time, of type 'ty'
+    fn constant(&mut self, value: &Constant, ty: &Type, span: Option<Span>) -> Lowered<Value> {
+        Ok(match (value, &ty.ty) {
+            (Constant::Boolean(value), _) => self.builder.ins().bconst(types::B1, *value),
+            (Constant::Character(value), _) => self.builder.ins().iconst(types::I8, *value as i64),
+            (Constant::Integer(value), _) => self.integer(*value, ty),
+            (Constant::Float(value), _) => self.float(*value, ty),
+            (Constant::String(text), _) => {
+                let (pointer, length) = self.str(text);
+                self.pair(pointer, length)?
+            },
+            // Constants of slice types are arrays
+            (Constant::Array(elements), FundamentalType::Slice(element)) => {
+                let array = Type{ ty: FundamentalType::Array(element.clone(), elements.len() as u64) };
+                let address = self.constant(value, &array, span)?;
+                let length = self.builder.ins().iconst(self.data_layout.pointer_type(), elements.len() as i64);
+                self.pair(address, length)?
+            },
+            (Constant::Array(elements), FundamentalType::Array(element, _)) => {
+                let size = self.layout(element)?.size;
+                let address = self.stack_memory(ty)?;
+                for (index, value) in elements.iter().enumerate() {
+                    let value = self.constant(value, element, span)?;
+                    self.store(value, element, address, index as u64 * size)?;
+                }
+                address
+            },
+            (Constant::Tuple(elements), FundamentalType::Tuple(types)) => {
+                let offsets: Vec<u64> = self.data_layout.field_offsets(ty, &self.table)?;
+                let address = self.stack_memory(ty)?;
+                for ((value, element), offset) in elements.iter().zip(types).zip(offsets) {
+                    let value = self.constant(value, element, span)?;
+                    self.store(value, element, address, offset)?;
+                }
+                address
+            },
+            (Constant::Ok(value), FundamentalType::Result(value_ty, _)) => {
+                let value = self.constant(value, value_ty, span)?;
+                self.result(ty, false, value)?
+            },
+            (Constant::Err(error), FundamentalType::Result(_, error_ty)) => {
+                let error = self.constant(error, error_ty, span)?;
+                self.result(ty, true, error)?
+            },
+            _ => return unsupported(&format!("Constants of type '{}'", ty), span)
+        })
+    }
+
+    fn identifier(&mut self, id: &expression::Identifier, expected: Option<&Type>) -> Lowered<Value> {
+        if let Some(Symbol::Constant(constant)) = self.table.symbol(id) {
+            let ty: Type = resolve(&constant.ty, expected);
+            let value = constant.value.clone();
+            return self.constant(&value, &ty, Some(id.span));
+        }
+        let variable = self.scopes.iter().rev().find_map(|scope| scope.variables.get(&id.name).cloned());
+        match variable {
+            Some((local, ty)) => Ok(self.use_local(local, &ty)),
+            None => Err(TypeError{ text: format!("The variable '{}' does not exist", id.name) })
+        }
+    }
+
+    // Converts a value of type 'from' to 'to' the way 'as' does
+    fn convert(&mut self, value: Value, from: &Type, to: &Type, span: Option<Span>) -> Lowered<Value> {
+        use FundamentalType::*;
+        if !from.is_scalar() || !to.is_scalar() {
+            return self.convert_aggregate(value, from, to, span);
+        }
+        let to_ir = to.ty.to_ir_type(&self.data_layout);
+        Ok(match (&from.ty, &to.ty) {
+            (from, to) if from == to => value,
+            (Pointer(_), Pointer(_)) => value,
+            (Boolean, to) if to.is_float() => {
+                let value = self.builder.ins().bint(types::I32, value);
+                self.builder.ins().fcvt_from_uint(to_ir, value)
+            },
+            (Boolean, _) => self.builder.ins().bint(to_ir, value),
+            (from, Boolean) if from.is_float() => {
+                let zero = self.float(0.0, &Type{ ty: from.clone() });
+                self.builder.ins().fcmp(FloatCC::NotEqual, value, zero)
+            },
+            (_, Boolean) => self.builder.ins().icmp_imm(IntCC::NotEqual, value, 0),
+            // Addresses convert like unsigned integers as wide as a pointer
+            (Pointer(_), to) => self.numeric_cast(value, &Usize, to),
+            (from, Pointer(_)) => self.numeric_cast(value, from, &Usize),
+            (from, to) if from.is_float() && to_ir.bits() < 32 => self.narrow_float(value, from, to),
+            (from, to) => self.numeric_cast(value, from, to)
+        })
+    }
+
+    // Compatible aggregates only differ in the types of the literals in them, the arrays in them
+    // used as slices and their parts of type 'never', see resolve and Type::is_compatible, so
+    // converting one copies it to memory laid out for 'to', converting what differs
+    fn convert_aggregate(&mut self, value: Value, from: &Type, to: &Type, span: Option<Span>) -> Lowered<Value> {
+        use FundamentalType::*;
+        let pointer_type = self.data_layout.pointer_type();
+        Ok(match (&from.ty, &to.ty) {
+            _ if from == to => value,
+            // Values of type 'never' are never produced, so they only need a value of the right type
+            (Never, _) => match to.ty.to_ir_type(&self.data_layout) {
+                types::B1 => self.builder.ins().bconst(types::B1, false),
+                types::F32 => self.builder.ins().f32const(0.0),
+                types::F64 => self.builder.ins().f64const(0.0),
+                ir_type => self.builder.ins().iconst(ir_type, 0)
+            },
+            // Slices are a pointer and a length whatever their elements
+            (Slice(_), Slice(_)) => value,
+            (Array(element, length), Slice(to_element)) => {
+                let array = Type{ ty: Array(to_element.clone(), *length) };
+                let address = self.convert_aggregate(value, &Type{ ty: Array(element.clone(), *length) }, &array, span)?;
+                let length = self.builder.ins().iconst(pointer_type, *length as i64);
+                self.pair(address, length)?
+            },
+            (Array(element, length), Array(to_element, _)) => {
+                let (size, to_size) = (self.layout(element)?.size, self.layout(to_element)?.size);
+                let address = self.stack_memory(to)?;
+                for index in 0..*length {
+                    let element_value = self.load(element, value, index * size);
+                    let element_value = self.convert(element_value, element, to_element, span)?;
+                    self.store(element_value, to_element, address, index * to_size)?;
+                }
+                address
+            },
+            (Tuple(elements), Tuple(to_elements)) => {
+                let offsets: Vec<u64> = self.data_layout.field_offsets(from, &self.table)?;
+                let to_offsets: Vec<u64> = self.data_layout.field_offsets(to, &self.table)?;
+                let address = self.stack_memory(to)?;
+                for (index, (element, to_element)) in elements.iter().zip(to_elements).enumerate() {
+                    let element_value = self.load(element, value, offsets[index]);
+                    let element_value = self.convert(element_value, element, to_element, span)?;
+                    self.store(element_value, to_element, address, to_offsets[index])?;
+                }
+                address
+            },
+            (Result(value_ty, error_ty), Result(to_value, to_error)) => {
+                let (offset, to_offset) = (self.layout(from)?.align, self.layout(to)?.align);
+                let address = self.stack_memory(to)?;
+                let tag = self.tag(value);
+                self.builder.ins().store(MemFlags::new(), tag, address, 0);
+                let payload = |lowering: &mut Self, from: &Type, to: &Type| -> Lowered<Vec<Value>> {
+                    let payload = lowering.load(from, value, offset);
+                    let payload = lowering.convert(payload, from, to, span)?;
+                    lowering.store(payload, to, address, to_offset)?;
+                    Ok(vec!())
+                };
+                // Results of Ok and Err hold a part of type 'never', so they are known to hold the other
+                match (&value_ty.ty, &error_ty.ty) {
+                    (Never, _) => payload(self, error_ty, to_error)?,
+                    (_, Never) => payload(self, value_ty, to_value)?,
+                    _ => self.match_tag(tag, &[], |lowering| payload(lowering, value_ty, to_value), |lowering| payload(lowering, error_ty, to_error))?
+                };
+                address
+            },
+            _ => return Err(TypeError{ text: format!("Cannot convert '{}' to '{}'", from, to) })
+        })
+    }
+
+    // Floats convert to 32 bits or more, so narrower integers are clamped to their own bounds
+    fn narrow_float(&mut self, value: Value, from: &FundamentalType, to: &FundamentalType) -> Value {
+        let bits = to.to_ir_type(&self.data_layout).bits();
+        let (wide, min, max, below, above) = match to.is_signed() {
+            true => (FundamentalType::I32, -(1 << (bits - 1)), (1 << (bits - 1)) - 1, IntCC::SignedLessThan, IntCC::SignedGreaterThan),
+            false => (FundamentalType::U32, 0, (1 << bits) - 1, IntCC::UnsignedLessThan, IntCC::UnsignedGreaterThan)
+        };
+        let value = self.numeric_cast(value, from, &wide);
+        let mut clamped = value;
+        for (condition, bound) in [(below, min), (above, max)] {
+            let bound = self.builder.ins().iconst(types::I32, bound);
+            let outside = self.builder.ins().icmp(condition, clamped, bound);
+            clamped = self.builder.ins().select(outside, bound, clamped);
+        }
+        self.builder.ins().ireduce(to.to_ir_type(&self.data_layout), clamped)
+    }
+
+    fn numeric_cast(&mut self, value: Value, from: &FundamentalType, to: &FundamentalType) -> Value {
+        match numeric::cast_opcode(from, to, &self.data_layout) {
+            Some(opcode) => {
+                let (inst, dfg) = self.builder.ins().Unary(opcode, to.to_ir_type(&self.data_layout), value);
+                dfg.first_result(inst)
+            },
+            None => value
+        }
+    }
+
+    fn binary_operator(&mut self, operator: &'a expression::BinaryOperator, expected: Option<&Type>) -> Lowered<Value> {
+        let op: &str = operator.op.value.as_str();
+        match op {
+            "as" => {
+                let ty: Type = cast_target(&operator.right, &self.table)?;
+                return self.operand(&operator.left, &ty);
+            },
+            "&&" | "||" => return self.short_circuit(operator),
+            _ => {}
+        }
+
+        let left: Type = self.type_of(&operator.left)?;
+        let right: Type = self.type_of(&operator.right)?;
+        let is_pointer = |ty: &Type| matches!(ty.ty, FundamentalType::Pointer(_));
+        if (op == "+" || op == "-") && (is_pointer(&left) || is_pointer(&right)) {
+            return self.pointer_arithmetic(operator, &left, &right);
+        }
+        let is_string = |ty: &Type| ty.ty == FundamentalType::String;
+        if (op == "+" || op == "*") && (is_string(&left) || is_string(&right)) {
+            return self.string_operator(operator, &left);
+        }
+
+        let (left_ty, right_ty): (Type, Type) = operand_types(operator, &left, &right, expected)?;
+        let mut left = self.operand(&operator.left, &left_ty)?;
+        let mut right = self.operand(&operator.right, &right_ty)?;
+        if !left_ty.is_scalar() {
+            let equal = self.equal(left, right, &left_ty)?;
+            return Ok(match op {
+                "!=" => self.builder.ins().bnot(equal),
+                _ => equal
+            });
+        }
+
+        let ty: FundamentalType = match &left_ty.ty {
+            // Compared as the integers they are stored as
+            FundamentalType::Boolean => {
+                left = self.builder.ins().bint(types::I8, left);
+                right = self.builder.ins().bint(types::I8, right);
+                FundamentalType::U8
+            },
+            FundamentalType::Pointer(_) => FundamentalType::Usize,
+            ty if ty.is_float() && (op == "%" || op == "**") => return Ok(self.float_libcall(op, ty, left, right)),
+            ty => ty.clone()
+        };
+        // Release builds wrap, so they do not import the handler
+        let site: Option<OverflowSite> = if ty.is_integer() && overflow::can_overflow(op) && self.unit.mode == BuildMode::Debug {
+            let handler = self.import(overflow::OVERFLOW_HANDLER, overflow::handler_signature(self.call_conv));
+            Some(OverflowSite{ handler, span: operator.op.span })
+        } else {
+            None
+        };
+        ir::binary_operator(&mut self.builder, op, &ty, left, right, self.unit.mode, site.as_ref())
+    }
+
+    // Value of an operator whose operands are all known at compile time, computed the way the
+    // lowered code would compute it where 'expected' is wanted. Operators whose integer results
+    // leave the range of their type are not folded, as they trap or wrap at runtime, nor are f32
+    // operators, whose intermediate results are rounded at runtime
+    fn fold(&self, node: &'a NodeType, expected: Option<&Type>) -> Option<Constant> {
+        if !self.unit.opt_level.folds_constants() {
+            return None;
+        }
+        let key = node as *const NodeType;
+        let folded = self.folds.borrow().get(&key)
+            .and_then(|folds| folds.iter().find(|(ty, _)| ty.as_ref() == expected).map(|(_, value)| value.clone()));
+        if let Some(value) = folded {
+            return value;
+        }
+        let value = self.fold_uncached(node, expected);
+        self.folds.borrow_mut().entry(key).or_default().push((expected.cloned(), value.clone()));
+        value
+    }
+
+    fn fold_uncached(&self, node: &'a NodeType, expected: Option<&Type>) -> Option<Constant> {
+        let ty: Type = resolve(&self.type_of(node).ok()?, expected);
+        if ty.ty == FundamentalType::F32 || ty.ty == FundamentalType::Character {
+            return None;
+        }
+        let foldable = match node {
+            NodeType::Term(term) => return self.fold(&term.node, expected),
+            NodeType::Boolean(_) | NodeType::Integer(_) | NodeType::Float(_) | NodeType::TypeQuery(_) => true,
+            NodeType::Identifier(id) => self.table.constant_value(id).is_some(),
+            NodeType::BinaryOperator(operator) => match operator.op.value.as_str() {
+                "as" => self.fold(&operator.left, Some(&ty)).is_some(),
+                "&&" | "||" => self.fold(&operator.left, Some(&ty)).is_some() && self.fold(&operator.right, Some(&ty)).is_some(),
+                op => {
+                    let left: Type = self.type_of(&operator.left).ok()?;
+                    let right: Type = self.type_of(&operator.right).ok()?;
+                    let (left_ty, right_ty) = operand_types(operator, &left, &right, expected).ok()?;
+                    match (self.fold(&operator.left, Some(&left_ty)), self.fold(&operator.right, Some(&right_ty))) {
+                        // Shift amounts are masked at runtime
+                        (Some(_), Some(Constant::Integer(amount))) if op == "<<" || op == ">>" => {
+                            (0..left_ty.ty.to_ir_type(&self.data_layout).bits() as i128).contains(&amount)
+                        },
+                        (Some(_), Some(_)) => !matches!(left_ty.ty, FundamentalType::Character | FundamentalType::Pointer(_)),
+                        _ => false
+                    }
+                }
+            },
+            _ => false
+        };
+        let value = interpreter::evaluate(node, &self.table).ok().filter(|_| foldable)?;
+        match value {
+            // Integer operators on literals used as floats are float operators at runtime
+            Constant::Integer(_) if ty.ty.is_float() => None,
+            _ => check_range(&value, &ty, &self.data_layout).ok().map(|_| value)
+        }
+    }
+
+    // Float remainder and exponentiation, which Cranelift has no instructions for, call the C library
+    fn float_libcall(&mut self, op: &str, ty: &FundamentalType, left: Value, right: Value) -> Value {
+        let ir_type = ty.to_ir_type(&self.data_layout);
+        let name = match (op, ir_type) {
+            ("%", types::F32) => "fmodf",
+            ("%", _) => "fmod",
+            (_, types::F32) => "powf",
+            _ => "pow"
+        };
+        let mut signature = Signature::new(self.call_conv);
+        signature.params.extend([AbiParam::new(ir_type), AbiParam::new(ir_type)]);
+        signature.returns.push(AbiParam::new(ir_type));
+        let callee = self.import(name, signature);
+        let call = self.builder.ins().call(callee, &[left, right]);
+        self.builder.inst_results(call)[0]
+    }
+
+    // Offsets are counted in elements, so they are scaled by the size of the pointee
+    fn pointer_arithmetic(&mut self, operator: &'a expression::BinaryOperator, left: &Type, right: &Type) -> Lowered<Value> {
+        let (pointer, pointer_ty, offset) = match (&left.ty, &right.ty) {
+            (FundamentalType::Pointer(pointee), FundamentalType::Pointer(_)) => {
+                let size = self.data_layout.layout_of(pointee, &self.table)?.size as i64;
+                let left = self.operand(&operator.left, left)?;
+                let right = self.operand(&operator.right, right)?;
+                let distance = self.builder.ins().isub(left, right);
+                return Ok(if size > 1 { self.builder.ins().sdiv_imm(distance, size) } else { distance });
+            },
+            (FundamentalType::Pointer(_), _) => (&operator.left, left, &operator.right),
+            _ => (&operator.right, right, &operator.left)
+        };
+        let size = match &pointer_ty.ty {
+            FundamentalType::Pointer(pointee) => self.data_layout.layout_of(pointee, &self.table)?.size as i64,
+            _ => unreachable!()
+        };
+        let base = self.operand(pointer, pointer_ty)?;
+        let offset = self.operand(offset, &Type{ ty: FundamentalType::Isize })?;
+        let offset = self.builder.ins().imul_imm(offset, size);
+        Ok(match operator.op.value.as_str() {
+            "-" => self.builder.ins().isub(base, offset),
+            _ => self.builder.ins().iadd(base, offset)
+        })
+    }
+
+    // The right operand is only evaluated when the left does not decide the result
+    fn short_circuit(&mut self, operator: &'a expression::BinaryOperator) -> Lowered<Value> {
+        let boolean = Type{ ty: FundamentalType::Boolean };
+        let left = self.operand(&operator.left, &boolean)?;
+        let right_block = self.builder.create_block();
+        let merge = self.builder.create_block();
+        self.builder.append_block_param(merge, types::B1);
+        match operator.op.value.as_str() {
+            "&&" => self.builder.ins().brz(left, merge, &[left]),
+            _ => self.builder.ins().brnz(left, merge, &[left])
+        };
+        self.builder.ins().jump(right_block, &[]);
+
+        self.builder.switch_to_block(right_block);
+        self.builder.seal_block(right_block);
+        let right = self.operand(&operator.right, &boolean)?;
+        self.builder.ins().jump(merge, &[right]);
+
+        self.builder.switch_to_block(merge);
+        self.builder.seal_block(merge);
+        Ok(self.builder.block_params(merge)[0])
+    }
+
+    fn if_expression(&mut self, if_expr: &'a expression::If, expected: Option<&Type>) -> Lowered<Option<Value>> {
+        let ty: Option<Type> = if_expr.check(&mut self.table.clone())?.map(|ty| resolve(&ty, expected));
+        let condition = self.operand(&if_expr.condition, &Type{ ty: FundamentalType::Boolean })?;
+
+        let then_block = self.builder.create_block();
+        let else_block = self.builder.create_block();
+        let merge = self.builder.create_block();
+        if let Some(ty) = &ty {
+            self.builder.append_block_param(merge, ty.ty.to_ir_type(&self.data_layout));
+        }
+        self.builder.ins().brz(condition, else_block, &[]);
+        self.builder.ins().jump(then_block, &[]);
+
+        self.builder.switch_to_block(then_block);
+        self.builder.seal_block(then_block);
+        let value = match &ty {
+            Some(ty) => self.block_as(&if_expr.block, ty)?,
+            None => self.block(&if_expr.block, None)?
+        };
+        self.builder.ins().jump(merge, value.as_slice());
+
+        self.builder.switch_to_block(else_block);
+        self.builder.seal_block(else_block);
+        let value = match (&if_expr.else_branch, &ty) {
+            (Some(else_branch), Some(ty)) => {
+                let own: Type = resolve(&self.type_of(else_branch)?, Some(ty));
+                match self.expression(else_branch, Some(ty))? {
+                    Some(value) => Some(self.convert(value, &own, ty, else_branch.span())?),
+                    None => None
+                }
+            },
+            (Some(else_branch), None) => self.expression(else_branch, None)?,
+            (None, _) => None
+        };
+        self.builder.ins().jump(merge, value.as_slice());
+
+        self.builder.switch_to_block(merge);
+        self.builder.seal_block(merge);
+        Ok(ty.map(|_| self.builder.block_params(merge)[0]))
+    }
+
+    fn dereference(&mut self, deref: &'a expression::Dereference) -> Lowered<Value> {
+        let ty: Type = self.type_of(&deref.expr)?;
+        let pointee: Type = match &ty.ty {
+            FundamentalType::Pointer(pointee) => (**pointee).clone(),
+            _ => return Err(TypeError{ text: format!("Cannot dereference a value of type '{}', at {}", ty, deref.span) })
+        };
+        let address = self.operand(&deref.expr, &ty)?;
+        if pointee.is_scalar() {
+            return Ok(self.load(&pointee, address, 0));
+        }
+        // Copied, as the memory can be changed or freed while the value is in use
+        let layout = self.layout(&pointee)?;
+        let copy = self.stack_memory(&pointee)?;
+        self.copy(copy, address, layout);
+        Ok(copy)
+    }
+
+    // Memory from the runtime's allocator, which is zeroed
+    fn allocate(&mut self, new: &'a expression::New) -> Lowered<Value> {
+        let ty: Type = Type::from_specifier(&new.ty, &self.table)?;
+        let layout = self.data_layout.layout_of(&ty, &self.table)?;
+        let pointer_type = self.data_layout.pointer_type();
+        let size = match &new.length {
+            // Negative lengths, and sizes a usize cannot hold, trap like an overflowing operator.
+            // Release builds trap too, as wrapping would allocate less memory than is used
+            Some(length) => {
+                let usize = Type{ ty: FundamentalType::Usize };
+                let own: Type = resolve(&self.type_of(length)?, Some(&usize));
+                let value = self.operand(length, &own)?;
+                let negative = match own.ty.is_signed() {
+                    true => self.builder.ins().icmp_imm(IntCC::SignedLessThan, value, 0),
+                    false => self.builder.ins().bconst(types::B1, false)
+                };
+                let length = self.convert(value, &own, &usize, length.span())?;
+                let element = self.builder.ins().iconst(pointer_type, layout.size as i64);
+                let (size, overflow) = overflow::overflowing(&mut self.builder, "*", &usize.ty, length, element);
+                let invalid = self.builder.ins().bor(negative, overflow);
+                let handler = self.import(overflow::OVERFLOW_HANDLER, overflow::handler_signature(self.call_conv));
+                overflow::trap_if(&mut self.builder, invalid, &OverflowSite{ handler, span: new.span });
+                size
+            },
+            None => self.builder.ins().iconst(pointer_type, layout.size as i64)
+        };
+        let align = self.builder.ins().iconst(pointer_type, layout.align as i64);
+
+        let mut signature = Signature::new(self.call_conv);
+        signature.params.extend([AbiParam::new(pointer_type); 2]);
+        signature.returns.push(AbiParam::new(pointer_type));
+        let alloc = self.import(ALLOC, signature);
+        let call = self.builder.ins().call(alloc, &[size, align]);
+        let pointer = self.builder.inst_results(call)[0];
+
+        // Structs run their '__init__', which stores each field
+        if let FundamentalType::DefinedType(name) = &ty.ty {
+            let fields: Vec<Type> = self.table.structure(name)?.fields().iter().map(|(_, ty)| ty.clone()).collect();
+            let offsets: Vec<u64> = self.data_layout.field_offsets(&ty, &self.table)?;
+            for ((arg, field), offset) in new.args.iter().zip(&fields).zip(offsets) {
+                let value = self.operand(arg, field)?;
+                self.store(value, field, pointer, offset)?;
+            }
+        } else if let [value] = new.args.as_slice() {
+            let value = self.operand(value, &ty)?;
+            self.store(value, &ty, pointer, 0)?;
+        }
+        Ok(pointer)
+    }
+
+    fn string(&mut self, string: &'a expression::Str) -> Lowered<Value> {
+        if let Some(text) = literal_text(string) {
+            let (pointer, length) = self.str(&text);
+            return self.pair(pointer, length);
+        }
+        let formatter = self.format_begin();
+        for segment in &string.segments {
+            match segment {
+                StringSegment::Text(text) if text.is_empty() => {},
+                StringSegment::Text(text) => {
+                    let (pointer, length) = self.str(text);
+                    self.format_str(formatter, pointer, length);
+                },
+                StringSegment::Interpolation(expr) => self.append(formatter, expr)?
+            }
+        }
+        self.format_end(formatter)
+    }
+
+    // Strings concatenated with '+' to a value that can be formatted, or repeated with '*' a
+    // number of times, which negative numbers repeat none
+    fn string_operator(&mut self, operator: &'a expression::BinaryOperator, left: &Type) -> Lowered<Value> {
+        let formatter = self.format_begin();
+        if operator.op.value == "+" {
+            self.append(formatter, &operator.left)?;
+            self.append(formatter, &operator.right)?;
+            return self.format_end(formatter);
+        }
+        let (string, count) = (Type{ ty: FundamentalType::String }, Type{ ty: FundamentalType::I64 });
+        let (text, count) = match left.ty {
+            FundamentalType::String => (self.operand(&operator.left, &string)?, self.operand(&operator.right, &count)?),
+            _ => {
+                let count = self.operand(&operator.left, &count)?;
+                (self.operand(&operator.right, &string)?, count)
+            }
+        };
+        let (pointer, length) = self.pointer_and_length(text);
+        let pointer_type = self.data_layout.pointer_type();
+        let mut signature = Signature::new(self.call_conv);
+        signature.params.extend([AbiParam::new(pointer_type); 3]);
+        signature.params.push(AbiParam::new(types::I64));
+        let repeat = self.import(FORMAT_REPEAT, signature);
+        self.builder.ins().call(repeat, &[formatter, pointer, length, count]);
+        self.format_end(formatter)
+    }
+
+    // Starts building a string, returning the runtime's formatter
+    fn format_begin(&mut self) -> Value {
+        let mut signature = Signature::new(self.call_conv);
+        signature.returns.push(AbiParam::new(self.data_layout.pointer_type()));
+        let begin = self.import(FORMAT_BEGIN, signature);
+        let call = self.builder.ins().call(begin, &[]);
+        self.builder.inst_results(call)[0]
+    }
+
+    fn format_str(&mut self, formatter: Value, pointer: Value, length: Value) {
+        let mut signature = Signature::new(self.call_conv);
+        signature.params.extend([AbiParam::new(self.data_layout.pointer_type()); 3]);
+        let append = self.import(FORMAT_STR, signature);
+        self.builder.ins().call(append, &[formatter, pointer, length]);
+    }
+
+    // Appends the value of an expression to a string being built. Integers are appended as 64 bit
+    // ones and floats as f64s, as the interpreter formats them
+    fn append(&mut self, formatter: Value, node: &'a NodeType) -> Lowered<()> {
+        let ty: Type = resolve(&self.type_of(node)?, None);
+        let value = self.value(node, None)?;
+        let (name, as_ty) = match &ty.ty {
+            FundamentalType::String => {
+                let (pointer, length) = self.pointer_and_length(value);
+                self.format_str(formatter, pointer, length);
+                return Ok(());
+            },
+            FundamentalType::Boolean => ("__gecko_format_bool", FundamentalType::Boolean),
+            FundamentalType::Character => ("__gecko_format_char", FundamentalType::Character),
+            ty if ty.is_float() => ("__gecko_format_float", FundamentalType::F64),
+            ty if ty.is_signed() => ("__gecko_format_int", FundamentalType::I64),
+            ty if ty.is_integer() => ("__gecko_format_uint", FundamentalType::U64),
+            _ => return Err(TypeError{ text: format!("Cannot format a value of type '{}' into a string", ty) })
+        };
+        let as_ty = Type{ ty: as_ty };
+        let value = self.convert(value, &ty, &as_ty, node.span())?;
+        let value = self.abi_value(value, &as_ty);
+        let mut signature = Signature::new(self.call_conv);
+        signature.params.push(AbiParam::new(self.data_layout.pointer_type()));
+        signature.params.push(abi_param(&as_ty, &self.data_layout));
+        let append = self.import(name, signature);
+        self.builder.ins().call(append, &[formatter, value]);
+        Ok(())
+    }
+
+    // Finishes building a string, which the runtime allocated
+    fn format_end(&mut self, formatter: Value) -> Lowered<Value> {
+        let pointer_type = self.data_layout.pointer_type();
+        let length = self.stack_memory(&Type{ ty: FundamentalType::Usize })?;
+        let mut signature = Signature::new(self.call_conv);
+        signature.params.extend([AbiParam::new(pointer_type); 2]);
+        signature.returns.push(AbiParam::new(pointer_type));
+        let end = self.import(FORMAT_END, signature);
+        let call = self.builder.ins().call(end, &[formatter, length]);
+        let pointer = self.builder.inst_results(call)[0];
+        let length = self.builder.ins().load(pointer_type, MemFlags::new(), length, 0);
+        self.pair(pointer, length)
+    }
+
+    // Elements are stored one after another, in memory of the procedure
+    fn array(&mut self, elements: &'a [Box<NodeType>], ty: &Type) -> Lowered<Value> {
+        let element: Type = match &ty.ty {
+            FundamentalType::Array(element, _) => (**element).clone(),
+            _ => unreachable!()
+        };
+        let size = self.layout(&element)?.size;
+        let address = self.stack_memory(ty)?;
+        for (index, expr) in elements.iter().enumerate() {
+            let value = self.operand(expr, &element)?;
+            self.store(value, &element, address, index as u64 * size)?;
+        }
+        Ok(address)
+    }
+
+    fn tuple(&mut self, elements: &'a [Box<NodeType>], ty: &Type) -> Lowered<Value> {
+        let types: Vec<Type> = match &ty.ty {
+            FundamentalType::Tuple(types) => types.clone(),
+            _ => unreachable!()
+        };
+        let offsets: Vec<u64> = self.data_layout.field_offsets(ty, &self.table)?;
+        let address = self.stack_memory(ty)?;
+        for ((expr, element), offset) in elements.iter().zip(&types).zip(offsets) {
+            let value = self.operand(expr, element)?;
+            self.store(value, element, address, offset)?;
+        }
+        Ok(address)
+    }
+
+    // Address of the first element of an array or slice, their number and the type of the
+    // elements, whose literals take the type 'expected' if given
+    fn elements(&mut self, node: &'a NodeType, expected: Option<&Type>) -> Lowered<(Value, Value, Type)> {
+        let ty: Type = match self.type_of(node)?.ty {
+            FundamentalType::Array(element, length) => Type{ ty: FundamentalType::Array(Box::new(resolve(&element, expected)), length) },
+            FundamentalType::Slice(element) => Type{ ty: FundamentalType::Slice(Box::new(resolve(&element, expected))) },
+            ty => return Err(TypeError{ text: format!("Cannot index into '{}'", Type{ ty }) })
+        };
+        let value = self.operand(node, &ty)?;
+        Ok(match ty.ty {
+            FundamentalType::Array(element, length) => {
+                let length = self.builder.ins().iconst(self.data_layout.pointer_type(), length as i64);
+                (value, length, *element)
+            },
+            FundamentalType::Slice(element) => {
+                let (pointer, length) = self.pointer_and_length(value);
+                (pointer, length, *element)
+            },
+            _ => unreachable!()
+        })
+    }
+
+    // Indices of any integer type are converted to an isize, so negative indices are as far out
+    // of bounds as the largest unsigned ones
+    fn index(&mut self, index: &'a expression::Index, expected: Option<&Type>) -> Lowered<Value> {
+        let isize = Type{ ty: FundamentalType::Isize };
+        let (pointer, length, element) = self.elements(&index.expr, expected)?;
+        let position = self.operand(&index.index, &isize)?;
+        let in_bounds = match (self.array_length(&index.expr), self.constant_position(&index.index, &isize)) {
+            (Some(length), Some(position)) => (0..length as i128).contains(&position),
+            _ => false
+        };
+        if !in_bounds {
+            let out_of_bounds = self.builder.ins().icmp(IntCC::UnsignedGreaterThanOrEqual, position, length);
+            let signature = bounds::index_handler_signature(self.call_conv, self.data_layout.pointer_type());
+            let handler = self.import(bounds::INDEX_HANDLER, signature);
+            bounds::trap_if(&mut self.builder, out_of_bounds, handler, &[position, length], index.span);
+        }
+        let size = self.layout(&element)?.size as i64;
+        let offset = self.builder.ins().imul_imm(position, size);
+        let address = self.builder.ins().iadd(pointer, offset);
+        Ok(self.load(&element, address, 0))
+    }
+
+    fn slice(&mut self, slice: &'a expression::Slice) -> Lowered<Value> {
+        let (pointer, length, element) = self.elements(&slice.expr, None)?;
+        let usize = Type{ ty: FundamentalType::Usize };
+        let start = match &slice.start {
+            Some(start) => self.operand(start, &usize)?,
+            None => self.builder.ins().iconst(self.data_layout.pointer_type(), 0)
+        };
+        let end = match &slice.end {
+            Some(end) => self.operand(end, &usize)?,
+            None => length
+        };
+        let constant = |lowering: &Self, bound: &'a Option<Box<NodeType>>, default: i128| match bound {
+            Some(bound) => lowering.constant_position(bound, &usize),
+            None => Some(default)
+        };
+        let in_bounds = match self.array_length(&slice.expr) {
+            Some(length) => match (constant(self, &slice.start, 0), constant(self, &slice.end, length as i128)) {
+                (Some(start), Some(end)) => start <= end && end <= length as i128,
+                _ => false
+            },
+            None => false
+        };
+        if !in_bounds {
+            let reversed = self.builder.ins().icmp(IntCC::UnsignedGreaterThan, start, end);
+            let past_end = self.builder.ins().icmp(IntCC::UnsignedGreaterThan, end, length);
+            let out_of_bounds = self.builder.ins().bor(reversed, past_end);
+            let signature = bounds::slice_handler_signature(self.call_conv, self.data_layout.pointer_type());
+            let handler = self.import(bounds::SLICE_HANDLER, signature);
+            bounds::trap_if(&mut self.builder, out_of_bounds, handler, &[start, end, length], slice.span);
+        }
+        let size = self.layout(&element)?.size as i64;
+        let offset = self.builder.ins().imul_imm(start, size);
+        let pointer = self.builder.ins().iadd(pointer, offset);
+        let length = self.builder.ins().isub(end, start);
+        self.pair(pointer, length)
+    }
+
+    // Length of 'node' if it is an array, which constant indices can be checked against when lowering
+    fn array_length(&self, node: &'a NodeType) -> Option<u64> {
+        match self.type_of(node).ok()?.ty {
+            FundamentalType::Array(_, length) => Some(length),
+            _ => None
+        }
+    }
+
+    // Value of an index or slice bound known at compile time, whether or not constants are folded
+    fn constant_position(&self, node: &'a NodeType, ty: &Type) -> Option<i128> {
+        match self.fold_uncached(node, Some(ty))? {
+            Constant::Integer(position) => Some(position),
+            _ => None
+        }
+    }
+
+    fn member(&mut self, member: &'a expression::Member, expected: Option<&Type>) -> Lowered<Value> {
+        let ty: Type = self.type_of(&member.expr)?;
+        match &ty.ty {
+            // Lengths are used like integer literals
+            FundamentalType::Array(..) | FundamentalType::Slice(_) => {
+                let (_, length, _) = self.elements(&member.expr, None)?;
+                let length_ty: Type = resolve(&Type{ ty: FundamentalType::Integer }, expected);
+                self.convert(length, &Type{ ty: FundamentalType::Usize }, &length_ty, Some(member.span))
+            },
+            FundamentalType::DefinedType(name) => {
+                let field: Type = match self.table.structure(name)?.field(&member.id.name) {
+                    Some(field) => field.clone(),
+                    None => return Err(TypeError{ text: format!("'{}' has no member '{}'", ty, member.id.name) })
+                };
+                let offset = self.data_layout.offset_of(&ty, &member.id.name, &self.table)?;
+                let address = self.operand(&member.expr, &ty)?;
+                Ok(self.load(&field, address, offset))
+            },
+            _ => Err(TypeError{ text: format!("'{}' has no member '{}'", ty, member.id.name) })
+        }
+    }
+
+    // Whether two values of type 'ty' are equal, comparing aggregates part by part
+    fn equal(&mut self, left: Value, right: Value, ty: &Type) -> Lowered<Value> {
+        use FundamentalType::*;
+        Ok(match &ty.ty {
+            Boolean => {
+                let left = self.builder.ins().bint(types::I8, left);
+                let right = self.builder.ins().bint(types::I8, right);
+                self.builder.ins().icmp(IntCC::Equal, left, right)
+            },
+            ty if ty.is_float() => self.builder.ins().fcmp(FloatCC::Equal, left, right),
+            Character | Pointer(_) => self.builder.ins().icmp(IntCC::Equal, left, right),
+            ty if ty.is_integer() => self.builder.ins().icmp(IntCC::Equal, left, right),
+            String => self.equal_elements(left, right, &Type{ ty: Character }, None)?,
+            Slice(element) => self.equal_elements(left, right, element, None)?,
+            Array(element, length) => self.equal_elements(left, right, element, Some(*length))?,
+            Tuple(_) | DefinedType(_) => {
+                let types: Vec<Type> = match &ty.ty {
+                    Tuple(types) => types.clone(),
+                    _ => self.table.structure(&ty.to_string())?.fields().iter().map(|(_, ty)| ty.clone()).collect()
+                };
+                let offsets: Vec<u64> = self.data_layout.field_offsets(ty, &self.table)?;
+                let mut equal = self.builder.ins().bconst(types::B1, true);
+                for (field, offset) in types.iter().zip(offsets) {
+                    let left_field = self.load(field, left, offset);
+                    let right_field = self.load(field, right, offset);
+                    let field_equal = self.equal(left_field, right_field, field)?;
+                    equal = self.builder.ins().band(equal, field_equal);
+                }
+                equal
+            },
+            // Payloads are only compared if the tags are equal, as only then are they of the same type
+            Result(value_ty, error_ty) => {
+                let offset = self.layout(ty)?.align;
+                let (left_tag, right_tag) = (self.tag(left), self.tag(right));
+                let same = self.builder.create_block();
+                let merge = self.builder.create_block();
+                self.builder.append_block_param(merge, types::B1);
+                let same_tag = self.builder.ins().icmp(IntCC::Equal, left_tag, right_tag);
+                let unequal = self.builder.ins().bconst(types::B1, false);
+                self.builder.ins().brz(same_tag, merge, &[unequal]);
+                self.builder.ins().jump(same, &[]);
+
+                self.builder.switch_to_block(same);
+                self.builder.seal_block(same);
+                let payload = |lowering: &mut Self, ty: &Type| -> Lowered<Vec<Value>> {
+                    let (left, right) = (lowering.load(ty, left, offset), lowering.load(ty, right, offset));
+                    Ok(vec!(lowering.equal(left, right, ty)?))
+                };
+                let equal = self.match_tag(left_tag, &[types::B1], |lowering| payload(lowering, value_ty), |lowering| payload(lowering, error_ty))?;
+                self.builder.ins().jump(merge, &equal);
+
+                self.builder.switch_to_block(merge);
+                self.builder.seal_block(merge);
+                self.builder.block_params(merge)[0]
+            },
+            // Values of type 'never' are never compared
+            _ => self.builder.ins().bconst(types::B1, true)
+        })
+    }
+
+    // Compares the elements of two arrays of 'length' elements in a loop, or of two slices or
+    // strings if there is no length, which are only equal if their lengths are
+    fn equal_elements(&mut self, left: Value, right: Value, element: &Type, length: Option<u64>) -> Lowered<Value> {
+        let pointer_type = self.data_layout.pointer_type();
+        let header = self.builder.create_block();
+        let body = self.builder.create_block();
+        let merge = self.builder.create_block();
+        self.builder.append_block_param(header, pointer_type);
+        self.builder.append_block_param(merge, types::B1);
+        let unequal = self.builder.ins().bconst(types::B1, false);
+        let zero = self.builder.ins().iconst(pointer_type, 0);
+        let (left, right, length) = match length {
+            Some(length) => (left, right, self.builder.ins().iconst(pointer_type, length as i64)),
+            None => {
+                let (left, left_length) = self.pointer_and_length(left);
+                let (right, right_length) = self.pointer_and_length(right);
+                let same_length = self.builder.ins().icmp(IntCC::Equal, left_length, right_length);
+                self.builder.ins().brz(same_length, merge, &[unequal]);
+                (left, right, left_length)
+            }
+        };
+        self.builder.ins().jump(header, &[zero]);
+
+        self.builder.switch_to_block(header);
+        let index = self.builder.block_params(header)[0];
+        let done = self.builder.ins().icmp(IntCC::UnsignedGreaterThanOrEqual, index, length);
+        let equal = self.builder.ins().bconst(types::B1, true);
+        self.builder.ins().brnz(done, merge, &[equal]);
+        self.builder.ins().jump(body, &[]);
+
+        self.builder.switch_to_block(body);
+        self.builder.seal_block(body);
+        let size = self.layout(element)?.size as i64;
+        let offset = self.builder.ins().imul_imm(index, size);
+        let left_address = self.builder.ins().iadd(left, offset);
+        let right_address = self.builder.ins().iadd(right, offset);
+        let left_element = self.load(element, left_address, 0);
+        let right_element = self.load(element, right_address, 0);
+        let element_equal = self.equal(left_element, right_element, element)?;
+        let next = self.builder.ins().iadd_imm(index, 1);
+        self.builder.ins().brz(element_equal, merge, &[unequal]);
+        self.builder.ins().jump(header, &[next]);
+        self.builder.seal_block(header);
+
+        self.builder.switch_to_block(merge);
+        self.builder.seal_block(merge);
+        Ok(self.builder.block_params(merge)[0])
+    }
+
+    // Value of a Result holding one, or returns its error from the procedure otherwise, through
+    // Lowering::ret so deferred statements and 'ensures' clauses run
+    fn try_expression(&mut self, try_expr: &'a expression::Try, expected: Option<&Type>) -> Lowered<Value> {
+        let ty: Type = self.type_of(&try_expr.expr)?;
+        let output: Type = self.output.clone();
+        let (value_ty, _) = result_types(&ty)?;
+        let (_, output_error) = result_types(&output)?;
+        // Literals in the value take the type wanted, and in the error the type returned
+        let wanted = Type{ ty: FundamentalType::Result(Box::new(resolve(value_ty, expected)), Box::new(output_error.clone())) };
+        let ty: Type = resolve(&ty, Some(&wanted));
+        let (value_ty, error_ty) = result_types(&ty)?;
+        let result = self.value(&try_expr.expr, Some(&wanted))?;
+        let offset = self.layout(&ty)?.align;
+
+        let tag = self.tag(result);
+        let failed = self.builder.create_block();
+        let next = self.builder.create_block();
+        self.builder.set_cold_block(failed);
+        self.builder.ins().brnz(tag, failed, &[]);
+        self.builder.ins().jump(next, &[]);
+
+        self.builder.switch_to_block(failed);
+        self.builder.seal_block(failed);
+        let error = self.load(error_ty, result, offset);
+        let error = self.convert(error, error_ty, output_error, Some(try_expr.span))?;
+        let returned = self.result(&output, true, error)?;
+        self.ret(returned)?;
+
+        self.builder.switch_to_block(next);
+        self.builder.seal_block(next);
+        Ok(self.load(value_ty, result, offset))
+    }
+
+    // Result of type 'ty' of checked arithmetic, holding an error if the operator overflows
+    fn checked(&mut self, op: &str, ty: &Type, left: Value, right: Value) -> Lowered<Value> {
+        let (value_ty, error_ty) = result_types(ty)?;
+        let (value, overflow) = overflow::overflowing(&mut self.builder, op, &value_ty.ty, left, right);
+        let offset = self.layout(ty)?.align;
+        let address = self.stack_memory(ty)?;
+        let tag = self.builder.ins().bint(types::I8, overflow);
+        self.builder.ins().store(MemFlags::new(), tag, address, 0);
+        self.match_tag(tag, &[], |lowering| {
+            lowering.store(value, value_ty, address, offset)?;
+            Ok(vec!())
+        }, |lowering| {
+            let (pointer, length) = lowering.str(overflow::OVERFLOW_ERROR);
+            let error = lowering.pair(pointer, length)?;
+            lowering.store(error, error_ty, address, offset)?;
+            Ok(vec!())
+        })?;
+        Ok(address)
+    }
+
+    fn call(&mut self, call: &'a expression::Call, expected: Option<&Type>) -> Lowered<Value> {
+        if let Some((behaviour, op)) = overflow::intrinsic(&call.id.name) {
+            let ty: Type = match call.check(&mut self.table.clone())? {
+                Some(ty) => resolve(&ty, expected),
+                None => return Err(TypeError{ text: format!("'{}' has no type", call.id.name) })
+            };
+            // Checked arithmetic gives a Result of the type of its operands
+            let operands: Type = match result_types(&ty) {
+                Ok((value, _)) => value.clone(),
+                Err(_) => ty.clone()
+            };
+            let left = self.operand(&call.args[0], &operands)?;
+            let right = self.operand(&call.args[1], &operands)?;
+            return match behaviour {
+                Overflow::Checked => self.checked(op, &ty, left, right),
+                Overflow::Saturating => Ok(overflow::saturating(&mut self.builder, op, &ty.ty, left, right)),
+                _ => Ok(overflow::wrapping(&mut self.builder, op, &ty.ty, left, right))
+            };
+        }
+        if call.id.name == "Ok" || call.id.name == "Err" {
+            let ty: Type = match call.check(&mut self.table.clone())? {
+                Some(ty) => resolve(&ty, expected),
+                None => return Err(TypeError{ text: format!("'{}' has no type", call.id.name) })
+            };
+            let error = call.id.name == "Err";
+            let payload_ty: Type = match result_types(&ty)? {
+                (_, error_ty) if error => error_ty.clone(),
+                (value_ty, _) => value_ty.clone()
+            };
+            let payload = self.operand(&call.args[0], &payload_ty)?;
+            return self.result(&ty, error, payload);
+        }
+
+        let function = self.table.function(&call.id)?;
+        let nested: Option<String> = self.unit.nested.borrow().iter()
+            .find(|(span, _)| *span == function.id().span)
+            .map(|(_, symbol)| symbol.clone());
+        let signature = signature(&function, self.call_conv, &self.data_layout);
+        let c_string = Type{ ty: FundamentalType::Pointer(Box::new(Type{ ty: FundamentalType::Character })) };
+        let mut args: Vec<Value> = vec!();
+        for ((_, ty), arg) in function.params().iter().zip(&call.args) {
+            let value = match string_literal(arg) {
+                Some(text) if *ty == c_string => self.c_string(&text),
+                _ => self.operand(arg, ty)?
+            };
+            args.push(value);
+        }
+        if let Some(definition) = self.inlinable(&call.id.name).filter(|_| nested.is_none()) {
+            return self.inline(definition, args);
+        }
+        let mut args: Vec<Value> = function.params().iter()
+            .zip(args)
+            .map(|((_, ty), value)| self.abi_value(value, ty))
+            .collect();
+        let output: &Type = function.output();
+        let result: Option<Value> = match output.is_scalar() {
+            true => None,
+            false => Some(self.stack_memory(output)?)
+        };
+        if let Some(result) = result {
+            args.insert(0, result);
+        }
+
+        let callee: FuncRef = if function.is_variadic() {
+            // Extra arguments are passed with the C default argument promotions
+            let mut extra: Vec<IrType> = vec!();
+            for arg in &call.args[function.params().len()..] {
+                let value = match string_literal(arg) {
+                    Some(text) => self.c_string(&text),
+                    None => {
+                        let ty: Type = self.type_of(arg)?;
+                        match ty.promote_variadic() {
+                            Some(promoted) => self.operand(arg, &promoted)?,
+                            None => return unsupported(&format!("Extra arguments of type '{}'", ty), arg.span())
+                        }
+                    }
+                };
+                let value = match self.builder.func.dfg.value_type(value) {
+                    types::F64 if variadic::floats_as_integers(self.unit.triple) => self.builder.ins().bitcast(types::I64, value),
+                    _ => value
+                };
+                extra.push(self.builder.func.dfg.value_type(value));
+                args.push(value);
+            }
+            let name = match variadic::needs_thunk(self.unit.triple) {
+                true => {
+                    if !self.program.thunks.contains(&call.id.name) {
+                        self.program.thunks.push(call.id.name.clone());
+                    }
+                    variadic::thunk_name(&call.id.name)
+                },
+                false => call.id.name.clone()
+            };
+            self.import_uncached(&name, variadic::call_signature(&signature, &extra))
+        } else {
+            self.import(nested.as_ref().unwrap_or(&call.id.name), signature)
+        };
+
+        let inst = self.builder.ins().call(callee, &args);
+        if let Some(result) = result {
+            return Ok(result);
+        }
+        let value = self.builder.inst_results(inst)[0];
+        Ok(self.loaded_value(value, output))
+    }
+
+    // Definition of a procedure whose calls are replaced by its body at the optimisation level,
+    // unless it is already being lowered, as inlining recursive calls would never end
+    fn inlinable(&self, name: &str) -> Option<&'a statement::FunctionDefinition> {
+        let definition: &'a statement::FunctionDefinition = self.unit.definitions.get(name)?;
+        (self.unit.opt_level.inlines(definition) && !self.inlining.contains(&name)).then_some(definition)
+    }
+
+    // Lowers the body of a procedure in place of a call to it with 'args'. The callee is lowered
+    // with its own symbol table, variables and contracts, and its 'return's jump to a block
+    // following it, where its value is a block parameter
+    fn inline(&mut self, definition: &'a statement::FunctionDefinition, args: Vec<Value>) -> Lowered<Value> {
+        let output: Type = self.unit.table.function(&definition.sig.id)?.output().clone();
+        let exit = self.builder.create_block();
+        self.builder.append_block_param(exit, output.ty.to_ir_type(&self.data_layout));
+
+        let mut table: SymbolTable<'a> = self.unit.table.clone();
+        table.output = Some(output.clone());
+        table.in_unsafe = true;
+        let context = Context{ variables: HashMap::new(), table: table.clone() };
+        let caller = (
+            std::mem::take(&mut self.scopes),
+            std::mem::replace(&mut self.table, table),
+            std::mem::replace(&mut self.parameters, context),
+            std::mem::replace(&mut self.signature, &definition.sig),
+            std::mem::replace(&mut self.output, output),
+            self.exit.replace(exit),
+            std::mem::replace(&mut self.symbol, definition.sig.id.name.clone())
+        );
+        self.inlining.push(&definition.sig.id.name);
+        let result = self.procedure(definition, args);
+        self.inlining.pop();
+        (self.scopes, self.table, self.parameters, self.signature, self.output, self.exit, self.symbol) = caller;
+        result?;
+
+        self.builder.switch_to_block(exit);
+        self.builder.seal_block(exit);
+        Ok(self.builder.block_params(exit)[0])
+    }
+
+    // Checks a condition unless the build mode strips checks, see codegen::contract. The message
+    // of an assertion is only evaluated once it fails
+    fn condition(&mut self, check: Check, condition: &'a NodeType, text: &str, message: Option<&'a NodeType>, span: Span) -> Lowered<()> {
+        let condition = self.operand(condition, &Type{ ty: FundamentalType::Boolean })?;
+        let next: Block = contract::unless(&mut self.builder, condition);
+        let text = self.str(text);
+        let message = match message {
+            Some(message) => {
+                let message = self.operand(message, &Type{ ty: FundamentalType::String })?;
+                Some(self.pointer_and_length(message))
+            },
+            None => None
+        };
+        let pointer_type = self.data_layout.pointer_type();
+        let handler = self.import(contract::CONTRACT_HANDLER, contract::handler_signature(self.call_conv, pointer_type));
+        contract::fail(&mut self.builder, pointer_type, &ContractSite{ handler, check, text, message, span }, next);
+        Ok(())
+    }
+
+    // Address of read-only data
+    fn data(&mut self, bytes: &[u8]) -> Value {
+        let name = self.program.data(bytes);
+        let global = self.builder.create_global_value(GlobalValueData::Symbol{ name, offset: Imm64::new(0), colocated: false, tls: false });
+        self.builder.ins().symbol_value(self.data_layout.pointer_type(), global)
+    }
+
+    // Memory for a value of type 'ty' in the frame of the procedure, which slots are word aligned
+    // in, as values are at most
+    fn stack_memory(&mut self, ty: &Type) -> Lowered<Value> {
+        let size: u32 = match u32::try_from(self.layout(ty)?.size) {
+            Ok(size) => size,
+            Err(_) => return Err(TypeError{ text: format!("Values of type '{}' are too large to be kept on the stack", ty) })
+        };
+        let slot = self.builder.create_stack_slot(StackSlotData::new(StackSlotKind::ExplicitSlot, size));
+        Ok(self.builder.ins().stack_addr(self.data_layout.pointer_type(), slot, 0))
+    }
+
+    // Loads a value of type 'ty' from 'offset' past 'address'. Aggregates are their address, as
+    // memory holding values is never changed while they are in use
+    fn load(&mut self, ty: &Type, address: Value, offset: u64) -> Value {
+        if !ty.is_scalar() {
+            return match offset {
+                0 => address,
+                offset => self.builder.ins().iadd_imm(address, offset as i64)
+            };
+        }
+        let value = self.builder.ins().load(abi_type(ty, &self.data_layout), MemFlags::new(), address, offset as i32);
+        self.loaded_value(value, ty)
+    }
+
+    // Stores a value of type 'ty' at 'offset' past 'address', copying aggregates
+    fn store(&mut self, value: Value, ty: &Type, address: Value, offset: u64) -> Lowered<()> {
+        if ty.is_scalar() {
+            let value = self.abi_value(value, ty);
+            self.builder.ins().store(MemFlags::new(), value, address, offset as i32);
+            return Ok(());
+        }
+        let layout = self.layout(ty)?;
+        let destination = self.load(ty, address, offset);
+        self.copy(destination, value, layout);
+        Ok(())
+    }
+
+    // Copies a value laid out as 'layout' between memory that does not overlap
+    fn copy(&mut self, destination: Value, source: Value, layout: Layout) {
+        let pointer_width = match self.data_layout.pointer_type().bytes() {
+            2 => PointerWidth::U16,
+            4 => PointerWidth::U32,
+            _ => PointerWidth::U64
+        };
+        let config = TargetFrontendConfig{ default_call_conv: self.call_conv, pointer_width };
+        let align = layout.align as u8;
+        self.builder.emit_small_memory_copy(config, destination, source, layout.size, align, align, true, MemFlags::new());
+    }
+
+    // Value of a str or slice with the given pointer and length
+    fn pair(&mut self, pointer: Value, length: Value) -> Lowered<Value> {
+        let address = self.stack_memory(&Type{ ty: FundamentalType::String })?;
+        let pointer_size = self.data_layout.pointer_type().bytes() as i32;
+        self.builder.ins().store(MemFlags::new(), pointer, address, 0);
+        self.builder.ins().store(MemFlags::new(), length, address, pointer_size);
+        Ok(address)
+    }
+
+    // Result of type 'ty' holding 'payload', which is its error if 'error' is true and its value
+    // otherwise. The payload follows a one byte tag, at the alignment of the Result
+    fn result(&mut self, ty: &Type, error: bool, payload: Value) -> Lowered<Value> {
+        let (value_ty, error_ty) = result_types(ty)?;
+        let payload_ty = if error { error_ty } else { value_ty };
+        let offset = self.layout(ty)?.align;
+        let address = self.stack_memory(ty)?;
+        let tag = self.builder.ins().iconst(types::I8, error as i64);
+        self.builder.ins().store(MemFlags::new(), tag, address, 0);
+        self.store(payload, payload_ty, address, offset)?;
+        Ok(address)
+    }
+
+    // Tag of a Result, 0 if it holds a value and 1 if it holds an error
+    fn tag(&mut self, result: Value) -> Value {
+        self.builder.ins().load(types::I8, MemFlags::new(), result, 0)
+    }
+
+    // Lowers 'ok' where a Result's tag is 0 and 'error' where it is 1, then continues in a block
+    // taking the values they give, of the types 'merged'
+    fn match_tag(&mut self, tag: Value, merged: &[IrType], ok: impl FnOnce(&mut Self) -> Lowered<Vec<Value>>, error: impl FnOnce(&mut Self) -> Lowered<Vec<Value>>) -> Lowered<Vec<Value>> {
+        let ok_block = self.builder.create_block();
+        let error_block = self.builder.create_block();
+        let merge = self.builder.create_block();
+        for ir_type in merged {
+            self.builder.append_block_param(merge, *ir_type);
+        }
+        self.builder.ins().brnz(tag, error_block, &[]);
+        self.builder.ins().jump(ok_block, &[]);
+
+        self.builder.switch_to_block(ok_block);
+        self.builder.seal_block(ok_block);
+        let values = ok(self)?;
+        self.builder.ins().jump(merge, &values);
+
+        self.builder.switch_to_block(error_block);
+        self.builder.seal_block(error_block);
+        let values = error(self)?;
+        self.builder.ins().jump(merge, &values);
+
+        self.builder.switch_to_block(merge);
+        self.builder.seal_block(merge);
+        Ok(self.builder.block_params(merge).to_vec())
+    }
+
+    // Pointer and length of a str or slice
+    fn pointer_and_length(&mut self, value: Value) -> (Value, Value) {
+        let pointer_type = self.data_layout.pointer_type();
+        let pointer = self.builder.ins().load(pointer_type, MemFlags::new(), value, 0);
+        let length = self.builder.ins().load(pointer_type, MemFlags::new(), value, pointer_type.bytes() as i32);
+        (pointer, length)
+    }
+
+    // Pointer and length of UTF-8 text
+    fn str(&mut self, text: &str) -> (Value, Value) {
+        let pointer = self.data(text.as_bytes());
+        let length = self.builder.ins().iconst(self.data_layout.pointer_type(), text.len() as i64);
+        (pointer, length)
+    }
+
+    // String literals passed to C are terminated with a NUL
+    fn c_string(&mut self, text: &str) -> Value {
+        let mut bytes: Vec<u8> = text.as_bytes().to_vec();
+        bytes.push(0);
+        self.data(&bytes)
+    }
+
+    fn import(&mut self, name: &str, signature: Signature) -> FuncRef {
+        if let Some(function) = self.imports.get(name) {
+            return *function;
+        }
+        let function = self.import_uncached(name, signature);
+        self.imports.insert(name.to_owned(), function);
+        function
+    }
+
+    // Variadic procedures are imported again for every call, with the signature of the call
+    fn import_uncached(&mut self, name: &str, signature: Signature) -> FuncRef {
+        let signature = self.builder.import_signature(signature);
+        let name = self.program.symbol(name);
+        self.builder.import_function(ExtFuncData{ name, signature, colocated: false })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cranelift::prelude::settings::{self, Configurable};
+    use std::str::FromStr;
+
+    use cranelift::prelude::{types, Type as IrType};
+    use cranelift_codegen::{isa, verify_function, Context};
+    use gecko_parser::ast::parse_gecko;
+    use target_lexicon::Triple;
+
+    use crate::{
+        codegen::{bounds, contract::CONTRACT_HANDLER, generate_ir, BuildMode, OptLevel, Program},
+        symbol::SymbolTable,
+        tree_type::{annotate_file, annotate_file_for},
+        error::TypeError
+    };
+
+    fn lower_at(source: &str, mode: BuildMode, opt_level: OptLevel) -> Result<Program, TypeError> {
+        let file = parse_gecko(source).unwrap();
+        let (_, symbol_table) = annotate_file(&file)?;
+        generate_ir(&file, symbol_table, mode, opt_level, &Triple::host())
+    }
+
+    fn lower(source: &str, mode: BuildMode) -> Result<Program, TypeError> {
+        lower_at(source, mode, OptLevel::O1)
+    }
+
+    // Lowers a program in both build modes at every optimisation level, and verifies and compiles
+    // every function for the host
+    fn compile(source: &str) {
+        for opt_level in [OptLevel::O0, OptLevel::O1, OptLevel::O2, OptLevel::Os] {
+            let mut flags = settings::builder();
+            flags.set("opt_level", opt_level.cranelift()).unwrap();
+            let isa = isa::lookup(Triple::host()).unwrap()
+                .finish(settings::Flags::new(flags))
+                .unwrap();
+            for mode in [BuildMode::Debug, BuildMode::Release] {
+                for function in lower_at(source, mode, opt_level).unwrap().functions {
+                    if let Err(errors) = verify_function(&function, &*isa) {
+                        panic!("{} {:?}\n{}\n{}", opt_level, mode, function.display(), errors);
+                    }
+                    Context::for_function(function).compile(&*isa).unwrap();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn examples() {
+        compile(include_str!("../../../examples/numbers.gk"));
+    }
+
+    #[test]
+    fn operators() {
+        compile("
+            const LIMIT: u8 = 200;
+            proc integers(a: i64, b: u8, c: char) -> i64 {
+                let sum := a + 1 - a * 2 / 3 % 4 ** 2;
+                let bits: u8 = (b & 15 | LIMIT ^ 3) << 2 >> 1;
+                let mixed: i64 = c + sum + bits as i64;
+                if sum < 0 && mixed >= 1 || b != LIMIT || c == 'x' { mixed } else { 0 - 1 }
+            }
+            proc floats(x: f32, y: f64) -> f64 {
+                let z: f32 = x * 2 - 0.5 % x ** 2.0;
+                (z as f64 + y / 3) as i8 as u64 as f64
+            }
+            proc booleans(flag: bool, n: u16) -> bool {
+                let as_int: i32 = flag as i32 + n as i32;
+                flag == (as_int > 2) && (0.5 as bool || n as bool)
+            }
+        ");
+    }
+
+    #[test]
+    fn statements_and_calls() {
+        compile("
+            extern proc printf(format: *char, ...) -> i32;
+            extern proc abs(n: int) -> int;
+            const GREETING: int = 4;
+            unsafe proc half(n: int) -> int
+                requires n >= 0
+                ensures result * 2 <= n
+            {
+                defer abs(n);
+                if n == 0 {
+                    return 0;
+                }
+                if n > 1000 {
+                    assert(false, \"too large\");
+                }
+                let _ := n;
+                n / 2
+            }
+            unsafe proc memory(length: usize) -> i64 {
+                let buffer: *i64 = new [i64; length];
+                defer delete buffer;
+                let one := new i64(1);
+                let last: *i64 = buffer + length - 1;
+                assert(last - buffer >= 0, \"in bounds\");
+                let value: i64 = *one + *last + sizeof(i64);
+                delete one;
+                value
+            }
+            proc main(argc: int) -> int {
+                let flag := true;
+                unsafe {
+                    printf(\"%d %c %f %d %s\\n\", half(argc), 'g', 1.5 as f32, flag, \"text\");
+                    memory(GREETING as usize);
+                }
+                let _ := wrapping_add(argc, 1) + saturating_mul(argc, 2);
+                { argc }
+            }
+        ");
+    }
+
+    #[test]
+    fn win64_variadic_floats() {
+        let source = "
+            extern proc printf(format: *char, ...) -> i32;
+            proc main() -> int { unsafe { printf(\"%f %d\\n\", 1.5, 2) } }
+        ";
+        let file = parse_gecko(source).unwrap();
+        for (target, float) in [("x86_64-pc-windows-msvc", types::I64), ("x86_64-unknown-linux-gnu", types::F64)] {
+            let triple = Triple::from_str(target).unwrap();
+            let (_, symbol_table) = annotate_file_for(&file, &triple).unwrap();
+            let program = generate_ir(&file, symbol_table, BuildMode::Debug, OptLevel::O0, &triple).unwrap();
+            let function = &program.functions[0];
+            let call: Vec<IrType> = function.dfg.signatures.values()
+                .find(|signature| signature.params.len() == 3)
+                .unwrap().params.iter().map(|param| param.value_type).collect();
+            assert_eq!(call, vec!(types::I64, float, types::I32), "{}", target);
+        }
+    }
+
+    #[test]
+    fn global_statements() {
+        // The checker rejects them before they are lowered
+        let file = parse_gecko("let x := 1;").unwrap();
+        let error = generate_ir(&file, SymbolTable::new(), BuildMode::Debug, OptLevel::O1, &Triple::host()).err().unwrap();
+        assert_eq!(error.text, "Only procedures, structs, type aliases and constants can be defined in the global scope, at 1:1");
+    }
+
+    #[test]
+    fn release_builds_strip_checks() {
+        let source = "
+            proc check(n: int) -> int
+                requires n > 0
+                ensures result > 0
+            {
+                assert(n != 3);
+                n
+            }
+        ";
+        let calls = |mode| {
+            let program = lower(source, mode).unwrap();
+            let function = &program.functions[0];
+            function.dfg.ext_funcs
+                .iter()
+                .filter(|(_, data)| program.symbol_name(&data.name) == Some(CONTRACT_HANDLER))
+                .map(|(handler, _)| function.display().to_string().matches(&format!("call {}(", handler)).count())
+                .sum::<usize>()
+        };
+        assert_eq!(calls(BuildMode::Debug), 3);
+        assert_eq!(calls(BuildMode::Release), 0);
+    }
+
+    // Constant indices and slice bounds within an array are not checked at runtime, in any build
+    #[test]
+    fn bounds_checks() {
+        let source = "
+            const LAST: int = 2;
+            proc get(array: [int; 3], slice: [int], n: int) -> int {
+                let parts := (array[..LAST], array[1..], array[n..], slice[..1]);
+                array[0] + array[LAST] + array[n] + slice[0] + array[3 - n]
+            }
+        ";
+        for mode in [BuildMode::Debug, BuildMode::Release] {
+            let program = lower(source, mode).unwrap();
+            let function = &program.functions[0];
+            let calls = |name: &str| function.dfg.ext_funcs
+                .iter()
+                .filter(|(_, data)| program.symbol_name(&data.name) == Some(name))
+                .map(|(handler, _)| function.display().to_string().matches(&format!("call {}(", handler)).count())
+                .sum::<usize>();
+            assert_eq!(calls(bounds::INDEX_HANDLER), 3, "{:?}", mode);
+            assert_eq!(calls(bounds::SLICE_HANDLER), 2, "{:?}", mode);
+        }
+        compile(source);
+    }
+
+    #[test]
+    fn optimisations() {
+        let source = "
+            proc double(n: int) -> int { n * 2 }
+            #[inline] proc clamp(n: int) -> int
+                requires n > 0 - 100
+            {
+                if n > 10 { return 10; }
+                n
+            }
+            proc count(n: int) -> int { if n == 0 { 0 } else { count(n - 1) + 1 } }
+            const BIG: u8 = 200;
+            proc main(n: int) -> int {
+                let limit: u8 = 2 * 3 + 1;
+                let wrapped: u8 = BIG + 100;
+                clamp(double(n)) + count(limit as int) + wrapped as int
+            }
+        ";
+        compile(source);
+        let ir = |opt_level| {
+            let program = lower_at(source, BuildMode::Debug, opt_level).unwrap();
+            program.functions.last().unwrap().display().to_string()
+        };
+
+        // Every variable is kept in memory at -O0
+        let unoptimised = ir(OptLevel::O0);
+        assert_eq!(unoptimised.matches("stack_store").count(), 3, "{}", unoptimised);
+        assert!(!ir(OptLevel::O1).contains("stack_store"));
+
+        // -O2 folds constants whose arithmetic does not overflow, and inlines every call but the
+        // recursive one, with the callee's contracts
+        let optimised = ir(OptLevel::O2);
+        assert!(optimised.contains("iconst.i8 7") && !optimised.contains("iconst.i8 3\n"), "{}", optimised);
+        assert!(optimised.contains("iconst.i8 200") && optimised.contains("iconst.i8 100"));
+        assert_eq!(optimised.matches(" = call ").count(), 1, "{}", optimised);
+
+        // -Os only inlines procedures marked '#[inline]'
+        assert_eq!(ir(OptLevel::Os).matches(" = call ").count(), 2);
+    }
+
+    #[test]
+    fn aggregates() {
+        compile("
+            struct Point { x: int, y: f64 }
+            struct Line { from: Point, to: Point, name: str }
+            const ORIGIN: (int, int) = (0, 0);
+            const NAME: str = \"gecko\";
+            proc sum(values: [i64]) -> i64 { values[0] + values[values.len - 1] }
+            proc pair(n: int) -> (int, [int; 2]) { (n, [n, n + 1]) }
+            proc name(long: bool) -> str { if long { NAME } else { \"g\" } }
+            unsafe proc length(line: *Line) -> f64 {
+                let Line{ from: Point{ x, .. }, to, name } := *line;
+                let copy: Line = *line;
+                assert(name == copy.name, name);
+                (to.x - x) as f64 + copy.to.y - copy.from.y
+            }
+            proc main(n: int) -> int {
+                let array: [i64; 3] = [1, 2, 3];
+                let total: i64 = sum(array) + sum(array[1..]) + sum(array[..n]) + array[n];
+                let (first, rest) := pair(n);
+                let (_, empty) := ([true][..0], []);
+                let same := name(true) == NAME && pair(1) != pair(2) && ORIGIN == (0, 0) && empty.len == 0;
+                if same { first + total as int + array.len } else { rest[1] }
+            }
+        ");
+    }
+
+    #[test]
+    fn strings() {
+        compile("
+            const GREETING: str = \"hello \" + 1.5;
+            proc describe(name: str, count: u16, ratio: f32, flag: bool, initial: char) -> str {
+                let line := \"{name}: {count * 2} {ratio} {flag} {initial} {1 - 2}{\"\"}\";
+                line + GREETING + (count > 2) + \"=\" * count + 3 * \"-\"
+            }
+        ");
+    }
+
+    #[test]
+    fn results() {
+        compile("
+            const DONE: Result<[i64; 2], str> = Ok([1, 2]);
+            #[inline] proc half(n: u8) -> Result<u8, str> { if n % 2 == 0 { Ok(n / 2) } else { Err(\"odd\") } }
+            proc quarter(n: u8) -> Result<(u8, bool), str>
+                ensures result != Err(\"odd\")
+            {
+                defer half(n);
+                let halved := try half(n);
+                let product := checked_mul(halved, 3)?;
+                Ok((try half(halved), product > 10))
+            }
+            proc main(n: u8) -> bool {
+                let parts: Result<[i64], str> = if n > 2 { DONE } else { Err(\"small\") };
+                let pair := quarter(n);
+                parts == Ok([1, 2][..]) && pair != Err(\"odd\") && checked_add(n, 1) == Ok(n + 1)
+            }
+        ");
+    }
+
+    // Aggregates are passed by address, and returned through an address the caller passes
+    #[test]
+    fn aggregate_signatures() {
+        let program = lower("proc swap(pair: (int, bool)) -> (bool, int) { let (a, b) := pair; (b, a) }", BuildMode::Debug).unwrap();
+        let signature = program.functions[0].signature.to_string();
+        assert!(signature.starts_with("(i64, i64)") && !signature.contains("->"), "{}", signature);
+    }
+
+    #[test]
+    fn nested_procedures() {
+        let source = "
+            #[inline] proc outer(n: int) -> int {
+                proc inner(m: int) -> int { if m == 0 { 0 } else { inner(m - 1) + 1 } }
+                { proc inner(m: int) -> int { m } }
+                inner(n)
+            }
+            proc main(n: int) -> int { outer(n) + outer(1) }
+        ";
+        compile(source);
+        let program = lower_at(source, BuildMode::Debug, OptLevel::O2).unwrap();
+        let names: Vec<&str> = program.functions.iter().filter_map(|function| program.symbol_name(&function.name)).collect();
+        assert_eq!(names, ["outer.inner", "outer.inner.2", "outer", "main"]);
+    }
+}
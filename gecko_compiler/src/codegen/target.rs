@@ -0,0 +1,62 @@
+use std::str::FromStr;
+
+use target_lexicon::Triple;
+
+use crate::error::TypeError;
+
+// Platforms object files can be written for, by the short names '--target' accepts and the
+// triples they stand for. Any other triple can be given in full, and is rejected where Cranelift
+// or the object writer cannot handle it
+pub const TARGETS: [(&str, &str); 4] = [
+    ("x86_64-linux", "x86_64-unknown-linux-gnu"),
+    ("aarch64-linux", "aarch64-unknown-linux-gnu"),
+    ("riscv64-linux", "riscv64gc-unknown-linux-gnu"),
+    ("x86_64-windows", "x86_64-pc-windows-msvc"),
+];
+
+// Triple of a target given by its short name or in full
+pub fn parse_target(name: &str) -> Result<Triple, TypeError> {
+    let triple = TARGETS.iter()
+        .find(|(short, _)| *short == name)
+        .map_or(name, |(_, triple)| triple);
+    Triple::from_str(triple).map_err(|e| TypeError{ text: format!("Unknown target '{}': {}", name, e) })
+}
+
+#[cfg(test)]
+mod tests {
+    use cranelift::prelude::types;
+    use gecko_parser::ast::parse_gecko;
+    use target_lexicon::{Architecture, BinaryFormat, OperatingSystem};
+
+    use super::{parse_target, TARGETS};
+    use crate::{
+        codegen::{generate_ir, BuildMode, OptLevel},
+        tree_type::annotate_file_for
+    };
+
+    #[test]
+    fn targets() {
+        for (name, triple) in TARGETS {
+            assert_eq!(parse_target(name).unwrap().to_string(), triple);
+        }
+        let windows = parse_target("x86_64-windows").unwrap();
+        assert_eq!((windows.operating_system, windows.binary_format), (OperatingSystem::Windows, BinaryFormat::Coff));
+        assert_eq!(parse_target("i686-unknown-linux-gnu").unwrap().architecture, Architecture::X86_32(target_lexicon::X86_32Architecture::I686));
+        assert!(parse_target("gecko").is_err());
+    }
+
+    // usize, pointers and sizeof follow the pointer width of the target
+    #[test]
+    fn pointer_width() {
+        let source = "struct Node { value: u8, next: *Node }\nproc size(n: usize) -> usize { n + sizeof(Node) }";
+        for (target, pointer, size) in [("x86_64-windows", types::I64, 16), ("aarch64-linux", types::I64, 16), ("i686-unknown-linux-gnu", types::I32, 8)] {
+            let triple = parse_target(target).unwrap();
+            let file = parse_gecko(source).unwrap();
+            let (_, symbol_table) = annotate_file_for(&file, &triple).unwrap();
+            let program = generate_ir(&file, symbol_table, BuildMode::Release, OptLevel::O2, &triple).unwrap();
+            let function = &program.functions[0];
+            assert_eq!(function.signature.returns[0].value_type, pointer, "{}", target);
+            assert!(function.display().to_string().contains(&format!("iconst.{} {}", pointer, size)), "{}\n{}", target, function.display());
+        }
+    }
+}
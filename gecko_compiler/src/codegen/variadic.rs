@@ -0,0 +1,102 @@
+use cranelift::prelude::{AbiParam, Type as IrType};
+use cranelift_codegen::ir::Signature;
+use target_lexicon::{Architecture, OperatingSystem, Triple};
+
+use crate::{
+    codegen::DataLayout,
+    tree_type::Type
+};
+
+// Calls to C variadic procedures, following the System V and Win64 ABIs.
+// Cranelift has no variadic signatures, so every call gets a signature of its own with the
+// promoted extra arguments appended to the fixed parameters. The System V ABI passes extra
+// arguments in the same registers and stack slots as fixed ones, and so does Win64 but for
+// floating point ones, see floats_as_integers.
+
+// IR type an extra argument is passed as, after the checker applied the default argument
+// promotions with Type::promote_variadic
+pub fn promoted_ir_type(ty: &Type, data_layout: &DataLayout) -> IrType {
+    match ty.promote_variadic() {
+        Some(promoted) => promoted.ty.to_ir_type(data_layout),
+        // String literals are passed as a pointer to their first character
+        None => data_layout.pointer_type()
+    }
+}
+
+// Signature of a single call, with the extra arguments appended to the callee's own signature
+pub fn call_signature(callee: &Signature, extra: &[IrType]) -> Signature {
+    let mut signature = callee.clone();
+    signature.params.extend(extra.iter().map(|ty| AbiParam::new(*ty)));
+    signature
+}
+
+// On x86-64, the caller must also set %al to an upper bound of the number of vector registers
+// holding arguments, which Cranelift cannot do. Calls are made through a thunk instead, which
+// sets %al to the maximum of 8 and jumps to the callee with the arguments untouched
+pub fn needs_thunk(triple: &Triple) -> bool {
+    triple.architecture == Architecture::X86_64 && triple.operating_system != OperatingSystem::Windows
+}
+
+// Win64 passes floating point extra arguments in the integer register of their position as
+// well as the vector register, since variadic callees read them from the integer registers.
+// Cranelift only fills the vector register, so they are passed as integers with the same bits,
+// which leaves those on the stack where they would have been too
+pub fn floats_as_integers(triple: &Triple) -> bool {
+    triple.architecture == Architecture::X86_64 && triple.operating_system == OperatingSystem::Windows
+}
+
+// Symbol of the thunk for calls to 'callee'
+pub fn thunk_name(callee: &str) -> String {
+    format!("__gecko_variadic_{}", callee)
+}
+
+// mov al, 8; jmp rel32 to the callee
+pub const X86_64_THUNK: [u8; 7] = [0xb0, 0x08, 0xe9, 0x00, 0x00, 0x00, 0x00];
+// Offset of the rel32 operand, relocated against the callee with an addend of -4
+pub const X86_64_THUNK_RELOCATION: u32 = 3;
+// mov al, 8; movabs r11, imm64; jmp r11, for code in memory that may be far from the callee
+pub const X86_64_ABSOLUTE_THUNK: [u8; 15] = [0xb0, 0x08, 0x49, 0xbb, 0, 0, 0, 0, 0, 0, 0, 0, 0x41, 0xff, 0xe3];
+// Offset of the imm64 operand, the address of the callee
+pub const X86_64_ABSOLUTE_THUNK_ADDRESS: usize = 4;
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use cranelift::prelude::{AbiParam, types};
+    use cranelift_codegen::{ir::Signature, isa::CallConv};
+    use target_lexicon::Triple;
+
+    use super::{call_signature, floats_as_integers, needs_thunk, promoted_ir_type};
+    use crate::{
+        codegen::DataLayout,
+        tree_type::{FundamentalType, Type}
+    };
+
+    #[test]
+    fn promotions() {
+        let data_layout = DataLayout::new(&Triple::from_str("x86_64-unknown-linux-gnu").unwrap());
+        let of = |ty: FundamentalType| promoted_ir_type(&Type{ ty }, &data_layout);
+        assert_eq!(of(FundamentalType::Float), types::F64);
+        assert_eq!(of(FundamentalType::Boolean), types::I32);
+        assert_eq!(of(FundamentalType::Character), types::I32);
+        assert_eq!(of(FundamentalType::Pointer(Box::new(Type{ ty: FundamentalType::Integer }))), types::I64);
+    }
+
+    #[test]
+    fn signatures() {
+        let mut callee = Signature::new(CallConv::SystemV);
+        callee.params.push(AbiParam::new(types::I64));
+        callee.returns.push(AbiParam::new(types::I32));
+        let signature = call_signature(&callee, &[types::F64, types::I32]);
+        let params: Vec<_> = signature.params.iter().map(|param| param.value_type).collect();
+        assert_eq!(params, vec!(types::I64, types::F64, types::I32));
+        assert_eq!(signature.returns, callee.returns);
+
+        assert!(needs_thunk(&Triple::from_str("x86_64-unknown-linux-gnu").unwrap()));
+        assert!(!needs_thunk(&Triple::from_str("x86_64-pc-windows-msvc").unwrap()));
+        assert!(!needs_thunk(&Triple::from_str("aarch64-unknown-linux-gnu").unwrap()));
+        assert!(floats_as_integers(&Triple::from_str("x86_64-pc-windows-msvc").unwrap()));
+        assert!(!floats_as_integers(&Triple::from_str("x86_64-unknown-linux-gnu").unwrap()));
+    }
+}
@@ -0,0 +1,959 @@
+use std::collections::HashMap;
+
+use gecko_parser::{
+    ast::Span,
+    expression::{self, Identifier},
+    node::{self, NodeType, PatternKind},
+    statement
+};
+
+use crate::{
+    symbol::SymbolTable,
+    codegen::{
+        overflow::{self, Overflow},
+        DataLayout
+    },
+    tree_type::{result_identifier, type_query_value, value_type, FundamentalType, Type, TypeCheck},
+    error::TypeError
+};
+
+pub mod value;
+pub use value::Value;
+
+// Bounds on a single compile-time evaluation
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    // Number of expressions and statements evaluated
+    pub steps: u64,
+    // Bytes allocated for strings, arrays, tuples and Results
+    pub memory: usize,
+    // Depth of nested 'const proc' calls
+    pub depth: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self{ steps: 1_000_000, memory: 64 * 1024 * 1024, depth: 256 }
+    }
+}
+
+// Reason evaluation stopped before producing a value
+enum Unwind {
+    // 'return' or a failed 'try', unwinding to the enclosing call
+    Return(Value),
+    Error(TypeError),
+}
+
+impl From<TypeError> for Unwind {
+    fn from(error: TypeError) -> Self {
+        Unwind::Error(error)
+    }
+}
+
+type Eval<T> = Result<T, Unwind>;
+
+// Diagnostic pointing at the operation that could not be evaluated
+fn error<T>(span: Option<Span>, text: String) -> Eval<T> {
+    Err(Unwind::Error(TypeError{ text: match span {
+        Some(span) => format!("{}, at {}", text, span),
+        None => text
+    }}))
+}
+
+// Variable or constant of the 'const proc' being evaluated, with the type the checker gave it
+#[derive(Clone)]
+struct Local {
+    id: Identifier,
+    ty: Type,
+    value: Value,
+    constant: bool,
+}
+
+// Evaluates expressions of the type-checked AST at compile time, e.g. the initialisers of
+// constants, array lengths and the 'const proc's they call
+pub struct Interpreter<'s, 'a> {
+    symbol_table: &'s SymbolTable<'a>,
+    limits: Limits,
+    steps: u64,
+    memory: usize,
+    depth: usize,
+    // Variables of the 'const proc' being evaluated, innermost scope last
+    scopes: Vec<HashMap<String, Local>>,
+    // Output type of the 'const proc' being evaluated
+    output: Option<Type>,
+}
+
+// Evaluates a constant expression with the default limits
+pub fn evaluate(node: &NodeType, symbol_table: &SymbolTable) -> Result<Value, TypeError> {
+    Interpreter::new(symbol_table, Limits::default()).evaluate(node)
+}
+
+impl<'s, 'a> Interpreter<'s, 'a> {
+    pub fn new(symbol_table: &'s SymbolTable<'a>, limits: Limits) -> Self {
+        Self{ symbol_table, limits, steps: 0, memory: 0, depth: 0, scopes: vec!(HashMap::new()), output: None }
+    }
+
+    pub fn evaluate(&mut self, node: &NodeType) -> Result<Value, TypeError> {
+        match self.expression(node) {
+            Ok(value) => Ok(value),
+            Err(Unwind::Error(error)) => Err(error),
+            Err(Unwind::Return(_)) => Err(TypeError{ text: format!("'return' outside of a procedure, at {}", span_text(node)) })
+        }
+    }
+
+    // Runs a procedure that takes no arguments, e.g. the wrapper of a doc-test
+    pub fn run(&mut self, definition: &statement::FunctionDefinition) -> Result<Value, TypeError> {
+        match self.procedure(definition, vec!()) {
+            Ok(value) | Err(Unwind::Return(value)) => Ok(value),
+            Err(Unwind::Error(error)) => Err(error)
+        }
+    }
+
+    fn step(&mut self, node: &NodeType) -> Eval<()> {
+        self.steps += 1;
+        if self.steps > self.limits.steps {
+            return error(node.span(), format!("Compile-time evaluation exceeded the limit of {} steps", self.limits.steps));
+        }
+        Ok(())
+    }
+
+    fn allocate(&mut self, value: Value, span: Span) -> Eval<Value> {
+        self.memory += value.heap_size();
+        if self.memory > self.limits.memory {
+            return error(Some(span), format!("Compile-time evaluation exceeded the limit of {} bytes of memory", self.limits.memory));
+        }
+        Ok(value)
+    }
+
+    fn expression(&mut self, node: &NodeType) -> Eval<Value> {
+        self.step(node)?;
+        match node {
+            NodeType::Boolean(boolean) => Ok(Value::Boolean(boolean.value)),
+            NodeType::Character(character) => Ok(Value::Character(character.value)),
+            NodeType::Integer(integer) => Ok(Value::Integer(integer.value)),
+            NodeType::Float(float) => Ok(Value::Float(float.value)),
+            NodeType::Str(string) => {
+                let mut text = String::new();
+                for segment in &string.segments {
+                    match segment {
+                        expression::StringSegment::Text(segment) => text.push_str(segment),
+                        expression::StringSegment::Interpolation(expr) => text.push_str(&self.expression(expr)?.to_string())
+                    }
+                }
+                self.allocate(Value::String(text), string.span)
+            },
+            NodeType::Identifier(id) => self.variable(id),
+            NodeType::Term(term) => self.expression(&term.node),
+            NodeType::Tuple(tuple) => {
+                let elements: Vec<Value> = tuple.elements
+                    .iter()
+                    .map(|element| self.expression(element))
+                    .collect::<Eval<_>>()?;
+                self.allocate(Value::Tuple(elements), tuple.span)
+            },
+            NodeType::Array(array) => {
+                let elements: Vec<Value> = array.elements
+                    .iter()
+                    .map(|element| self.expression(element))
+                    .collect::<Eval<_>>()?;
+                self.allocate(Value::Array(elements), array.span)
+            },
+            NodeType::Index(index) => self.index(index),
+            NodeType::Slice(slice) => self.slice(slice),
+            NodeType::Member(member) => match (self.expression(&member.expr)?, member.id.name.as_str()) {
+                (Value::Array(elements), "len") => Ok(Value::Integer(elements.len() as i128)),
+                (value, name) => error(Some(member.span), format!("Cannot access member '{}' of a {} at compile time", name, value.kind()))
+            },
+            NodeType::If(if_expr) => self.if_expression(if_expr),
+            NodeType::Block(block) => self.block(block),
+            NodeType::Unsafe(unsafe_block) => self.block(&unsafe_block.block),
+            NodeType::Dereference(deref) => error(Some(deref.span), "Raw pointers cannot be dereferenced at compile time".to_string()),
+            NodeType::Call(call) => self.call(call),
+            NodeType::Try(try_expr) => match self.expression(&try_expr.expr)? {
+                Value::Ok(value) => Ok(*value),
+                Value::Err(error) => Err(Unwind::Return(Value::Err(error))),
+                value => error(Some(try_expr.span), format!("'try' expects a Result, got a {}", value.kind()))
+            },
+            NodeType::TypeQuery(query) => match type_query_value(query, self.symbol_table) {
+                Ok(value) => Ok(Value::Integer(value)),
+                Err(TypeError{ text }) => error(Some(query.span), text)
+            },
+            NodeType::BinaryOperator(operator) => self.binary_operator(operator),
+            NodeType::New(new) => error(Some(new.span), "'new' cannot allocate memory at compile time".to_string()),
+            _ => error(node.span(), "This expression cannot be evaluated at compile time".to_string())
+        }
+    }
+
+    fn variable(&mut self, id: &expression::Identifier) -> Eval<Value> {
+        let scopes = if self.symbol_table.is_global(id) { &self.scopes[..0] } else { &self.scopes[..] };
+        for scope in scopes.iter().rev() {
+            if let Some(local) = scope.get(&id.name) {
+                return Ok(local.value.clone());
+            }
+        }
+        match self.symbol_table.constant_value(id) {
+            Some(value) => Ok(value),
+            None => error(Some(id.span), format!("'{}' is not a constant, so its value is not known at compile time", id.name))
+        }
+    }
+
+    fn statement(&mut self, stmt: &NodeType) -> Eval<()> {
+        self.step(stmt)?;
+        match stmt {
+            NodeType::Expression(expr) => self.expression(&expr.expr).map(drop),
+            NodeType::VariableInitialisation(init) => {
+                let value = self.expression(&init.expr)?;
+                let mut table: SymbolTable = self.scope_table();
+                stmt.check(&mut table)?;
+                self.bind(&init.pattern, value, &table)
+            },
+            NodeType::ConstantDefinition(constant) => {
+                let value = self.expression(&constant.expr)?;
+                let mut table: SymbolTable = self.scope_table();
+                let ty: Type = match &constant.ty {
+                    Some(spec) => Type::from_specifier(spec, &table)?,
+                    None => value_type(&constant.expr, &mut table)?
+                };
+                let local = Local{ id: constant.id.clone(), ty, value, constant: true };
+                self.scopes.last_mut().unwrap().insert(constant.id.name.clone(), local);
+                Ok(())
+            },
+            // Variables can only be read once initialised, so there is nothing to bind yet
+            NodeType::VariableDeclaration(_) => Ok(()),
+            NodeType::Return(ret) => Err(Unwind::Return(self.expression(&ret.expr)?)),
+            NodeType::Assert(assert) => self.condition("Assertion", &assert.condition, &assert.text, assert.message.as_deref(), assert.span),
+            NodeType::If(_) | NodeType::Block(_) | NodeType::Unsafe(_) => self.expression(stmt).map(drop),
+            // Nested definitions were already checked and take no effect when evaluated
+            NodeType::FunctionDefinition(_)
+                | NodeType::FunctionDeclaration(_)
+                | NodeType::StructDefinition(_)
+                | NodeType::TypeAlias(_) => Ok(()),
+            NodeType::Defer(defer) => error(Some(defer.span), "'defer' cannot be evaluated at compile time".to_string()),
+            NodeType::Delete(delete) => error(Some(delete.span), "'delete' cannot free memory at compile time".to_string()),
+            _ => error(stmt.span(), "This statement cannot be evaluated at compile time".to_string())
+        }
+    }
+
+    // Symbol table as the checker saw it at this point of the 'const proc', used to find the types
+    // of the values it binds and of the operands of arithmetic intrinsics
+    fn scope_table(&self) -> SymbolTable<'a> {
+        let mut table: SymbolTable<'a> = self.symbol_table.clone();
+        table.output = self.output.clone();
+        // Unsafe operations were already allowed or rejected when the procedure was checked
+        table.in_unsafe = true;
+        for local in self.scopes.iter().flat_map(|scope| scope.values()) {
+            if local.constant {
+                table.define_constant(&local.id, local.ty.clone(), local.value.clone());
+            } else {
+                table.initialise_variable(&local.id, local.ty.clone());
+            }
+        }
+        table
+    }
+
+    // Binds the variables of a pattern with the types 'table' gives them after checking the binding
+    fn bind(&mut self, pattern: &node::Pattern, value: Value, table: &SymbolTable) -> Eval<()> {
+        match (&pattern.kind, value) {
+            (PatternKind::Identifier(id), value) => {
+                let ty: Type = table.variable_type(id)?
+                    .ok_or_else(|| TypeError{ text: format!("'{}' has no type", id.name) })?;
+                let local = Local{ id: id.clone(), ty, value, constant: false };
+                self.scopes.last_mut().unwrap().insert(id.name.clone(), local);
+                Ok(())
+            },
+            (PatternKind::Wildcard(_), _) => Ok(()),
+            (PatternKind::Tuple{ elements, .. }, Value::Tuple(values)) if elements.len() == values.len() => {
+                for (element, value) in elements.iter().zip(values) {
+                    self.bind(element, value, table)?;
+                }
+                Ok(())
+            },
+            (_, value) => error(Some(pattern.span), format!("Pattern '{}' cannot bind a {} at compile time", pattern, value.kind()))
+        }
+    }
+
+    fn block(&mut self, block: &node::Block) -> Eval<Value> {
+        self.scopes.push(HashMap::new());
+        let result = self.block_in_scope(block);
+        self.scopes.pop();
+        result
+    }
+
+    fn block_in_scope(&mut self, block: &node::Block) -> Eval<Value> {
+        for stmt in &block.stmts {
+            self.statement(stmt)?;
+        }
+        match &block.tail {
+            Some(tail) => self.expression(tail),
+            None => Ok(Value::Unit)
+        }
+    }
+
+    fn if_expression(&mut self, if_expr: &expression::If) -> Eval<Value> {
+        match self.expression(&if_expr.condition)? {
+            Value::Boolean(true) => self.block(&if_expr.block),
+            Value::Boolean(false) => match &if_expr.else_branch {
+                Some(else_branch) => self.expression(else_branch),
+                None => Ok(Value::Unit)
+            },
+            value => error(if_expr.condition.span(), format!("'if' condition must be a bool, got a {}", value.kind()))
+        }
+    }
+
+    fn call(&mut self, call: &expression::Call) -> Eval<Value> {
+        let mut args: Vec<Value> = call.args
+            .iter()
+            .map(|arg| self.expression(arg))
+            .collect::<Eval<_>>()?;
+
+        // Built-in Result constructors
+        match (call.id.name.as_str(), args.len()) {
+            ("Ok", 1) => return self.allocate(Value::Ok(Box::new(args.remove(0))), call.span),
+            ("Err", 1) => return self.allocate(Value::Err(Box::new(args.remove(0))), call.span),
+            _ => {}
+        }
+        if let Some((behaviour, op)) = overflow::intrinsic(&call.id.name) {
+            return self.arithmetic_intrinsic(call, behaviour, op, args);
+        }
+
+        let definition = match self.symbol_table.const_procs.get(&call.id.name) {
+            Some(definition) => *definition,
+            None => return error(Some(call.span), format!("'{}' is not a 'const proc', so it cannot be called at compile time", call.id.name))
+        };
+        if self.depth >= self.limits.depth {
+            return error(Some(call.span), format!("Compile-time evaluation exceeded the limit of {} nested calls", self.limits.depth));
+        }
+
+        // The callee only sees its parameters and the constants in scope
+        let caller_scopes = std::mem::replace(&mut self.scopes, vec!(HashMap::new()));
+        let caller_output = self.output.take();
+        self.depth += 1;
+        let result = self.procedure(definition, args);
+        self.depth -= 1;
+        self.scopes = caller_scopes;
+        self.output = caller_output;
+
+        match result {
+            Ok(value) | Err(Unwind::Return(value)) => Ok(value),
+            Err(error) => Err(error)
+        }
+    }
+
+    fn procedure(&mut self, definition: &statement::FunctionDefinition, args: Vec<Value>) -> Eval<Value> {
+        let sig = &definition.sig;
+        // Procedures nested in others are not in the symbol table
+        let function = self.symbol_table.function(&sig.id).ok();
+        let mut table: SymbolTable = self.scope_table();
+        self.output = Some(Type::from_specifier(&sig.output.ty, &table)?);
+        sig.params.check(&mut table)?;
+        for (index, ((param, _), arg)) in sig.params.parameters.iter().zip(args).enumerate() {
+            if let Some((_, ty)) = function.as_ref().and_then(|function| function.params().get(index)) {
+                self.fits(&arg, ty, param.span, || format!("Argument '{}' of '{}'", param.pattern, sig.id.name))?;
+            }
+            self.bind(&param.pattern, arg, &table)?;
+        }
+        for contract in sig.contracts.iter().filter(|contract| contract.kind == node::ContractKind::Requires) {
+            self.condition(contract.kind.description(), &contract.condition, &contract.text, None, contract.span)?;
+        }
+        let result = match self.block_in_scope(&definition.block) {
+            Ok(value) | Err(Unwind::Return(value)) => value,
+            Err(error) => return Err(error)
+        };
+        if let Some(function) = &function {
+            self.fits(&result, function.output(), sig.id.span, || format!("Result of '{}'", sig.id.name))?;
+        }
+        self.ensures(sig, &result)?;
+        Ok(result)
+    }
+
+    // Evaluates the 'ensures' clauses of a procedure with its return value bound to 'result'
+    fn ensures(&mut self, sig: &statement::Signature, result: &Value) -> Eval<()> {
+        for contract in sig.contracts.iter().filter(|contract| contract.kind == node::ContractKind::Ensures) {
+            let id: Identifier = result_identifier(contract);
+            let local = Local{ id, ty: self.output.clone().unwrap(), value: result.clone(), constant: false };
+            self.scopes.push(HashMap::from([(local.id.name.clone(), local)]));
+            let holds = self.condition(contract.kind.description(), &contract.condition, &contract.text, None, contract.span);
+            self.scopes.pop();
+            holds?;
+        }
+        Ok(())
+    }
+
+    // Evaluates an assertion or contract, stopping evaluation if it does not hold
+    fn condition(&mut self, description: &str, condition: &NodeType, text: &str, message: Option<&NodeType>, span: Span) -> Eval<()> {
+        match self.expression(condition)? {
+            Value::Boolean(true) => Ok(()),
+            Value::Boolean(false) => {
+                let failure = format!("{} `{}` failed", description, text);
+                match message {
+                    Some(message) => {
+                        let message: Value = self.expression(message)?;
+                        error(Some(span), format!("{}: {}", failure, message))
+                    },
+                    None => error(Some(span), failure)
+                }
+            },
+            value => error(Some(span), format!("{} expects a bool, got a {}", description, value.kind()))
+        }
+    }
+
+    // Evaluates e.g. 'checked_mul(a, b)' on integers of the type the checker gave the operands
+    fn arithmetic_intrinsic(&mut self, call: &expression::Call, behaviour: Overflow, op: &str, args: Vec<Value>) -> Eval<Value> {
+        let span = Some(call.span);
+        let (left, right) = match args.as_slice() {
+            [left, right] => match (as_integer(left), as_integer(right)) {
+                (Some(left), Some(right)) => (left, right),
+                _ => return error(span, format!("Invalid operands {} and {} for '{}'", left.kind(), right.kind(), call.id.name))
+            },
+            _ => return error(span, format!("'{}' expects 2 arguments, got {}", call.id.name, args.len()))
+        };
+        let ty: Type = match call.check(&mut self.scope_table())? {
+            Some(Type{ ty: FundamentalType::Result(ty, _) }) => ty.concrete(),
+            Some(ty) => ty.concrete(),
+            None => return error(span, format!("'{}' has no type", call.id.name))
+        };
+
+        let data_layout = &self.symbol_table.data_layout;
+        let (min, max) = integer_range(&ty.ty, data_layout);
+        // Operands are at most 64 bits wide, so only products can leave the range of i128
+        let exact = match op {
+            "+" => left.checked_add(right),
+            "-" => left.checked_sub(right),
+            _ => left.checked_mul(right)
+        }.filter(|exact| (min..=max).contains(exact));
+
+        match (behaviour, exact) {
+            (Overflow::Checked, Some(exact)) => self.allocate(Value::Ok(Box::new(Value::Integer(exact))), call.span),
+            (Overflow::Checked, None) => {
+                let error = Value::Err(Box::new(Value::String(overflow::OVERFLOW_ERROR.to_string())));
+                self.allocate(error, call.span)
+            },
+            (_, Some(exact)) => Ok(Value::Integer(exact)),
+            // Truncating the result of wrapping i128 arithmetic to fewer bits gives the same bits
+            (Overflow::Wrapping, None) => Ok(Value::Integer(wrap(match op {
+                "+" => left.wrapping_add(right),
+                "-" => left.wrapping_sub(right),
+                _ => left.wrapping_mul(right)
+            }, &ty.ty, data_layout))),
+            (Overflow::Saturating, None) => {
+                // Sums and differences overflow towards the sign of the left operand, products
+                // towards the sign of the exact product
+                let negative = match op {
+                    "*" => (left < 0) != (right < 0),
+                    _ => left < 0
+                };
+                Ok(Value::Integer(if negative { min } else { max }))
+            }
+        }
+    }
+
+    fn fits(&self, value: &Value, ty: &Type, span: Span, what: impl FnOnce() -> String) -> Eval<()> {
+        match check_range(value, ty, &self.symbol_table.data_layout) {
+            Ok(()) => Ok(()),
+            Err(e) => error(Some(span), format!("{}: {}", what(), e.text))
+        }
+    }
+
+    fn index(&mut self, index: &expression::Index) -> Eval<Value> {
+        let value = self.expression(&index.expr)?;
+        let position = self.expression(&index.index)?;
+        match (value, position) {
+            (Value::Array(mut elements), Value::Integer(position)) => {
+                if position < 0 || position >= elements.len() as i128 {
+                    return error(Some(index.span), format!("Index {} is out of bounds for an array of length {}", position, elements.len()));
+                }
+                Ok(elements.swap_remove(position as usize))
+            },
+            (value, position) => error(Some(index.span), format!("Cannot index a {} with a {} at compile time", value.kind(), position.kind()))
+        }
+    }
+
+    fn slice(&mut self, slice: &expression::Slice) -> Eval<Value> {
+        let elements = match self.expression(&slice.expr)? {
+            Value::Array(elements) => elements,
+            value => return error(Some(slice.span), format!("Cannot slice a {} at compile time", value.kind()))
+        };
+        let mut bound = |bound: &Option<Box<NodeType>>, default: usize| -> Eval<i128> {
+            match bound {
+                Some(bound) => match self.expression(bound)? {
+                    Value::Integer(bound) => Ok(bound),
+                    value => error(bound.span(), format!("Slice bound must be an int, got a {}", value.kind()))
+                },
+                None => Ok(default as i128)
+            }
+        };
+        let start = bound(&slice.start, 0)?;
+        let end = bound(&slice.end, elements.len())?;
+        if start < 0 || end > elements.len() as i128 || start > end {
+            return error(Some(slice.span), format!("Slice {}..{} is out of bounds for an array of length {}", start, end, elements.len()));
+        }
+        self.allocate(Value::Array(elements[start as usize..end as usize].to_vec()), slice.span)
+    }
+
+    fn binary_operator(&mut self, operator: &expression::BinaryOperator) -> Eval<Value> {
+        let op = operator.op.value.as_str();
+        let span = Some(operator.span);
+
+        if op == "as" {
+            let value = self.expression(&operator.left)?;
+            return match &*operator.right {
+                NodeType::Identifier(ty) => cast(value, &ty.name, &self.symbol_table.data_layout, span),
+                _ => error(span, "Type specifier expected after 'as'".to_string())
+            };
+        }
+
+        // Logical operators only evaluate their right operand when needed
+        let left = self.expression(&operator.left)?;
+        match (op, &left) {
+            ("&&", Value::Boolean(false)) => return Ok(Value::Boolean(false)),
+            ("||", Value::Boolean(true)) => return Ok(Value::Boolean(true)),
+            _ => {}
+        }
+        let right = self.expression(&operator.right)?;
+
+        match (op, left, right) {
+            ("&&", Value::Boolean(_), Value::Boolean(right))
+                | ("||", Value::Boolean(_), Value::Boolean(right)) => Ok(Value::Boolean(right)),
+            ("==", left, right) => Ok(Value::Boolean(left == right)),
+            ("!=", left, right) => Ok(Value::Boolean(left != right)),
+            ("+", Value::String(left), right) => self.allocate(Value::String(format!("{}{}", left, right)), operator.span),
+            ("+", left, Value::String(right)) => self.allocate(Value::String(format!("{}{}", left, right)), operator.span),
+            ("*", Value::String(text), Value::Integer(count)) | ("*", Value::Integer(count), Value::String(text)) => {
+                match usize::try_from(count) {
+                    // The repeated string is counted against the memory limit before it is built
+                    Ok(count) if text.len().saturating_mul(count) <= self.limits.memory => {
+                        self.allocate(Value::String(text.repeat(count)), operator.span)
+                    },
+                    _ => error(span, format!("Cannot repeat a string {} times at compile time", count))
+                }
+            },
+            (op, Value::Float(left), right) => match as_float(&right) {
+                Some(right) => float_operator(op, left, right, span),
+                None => error(span, format!("Invalid operands float and {} for '{}'", right.kind(), op))
+            },
+            (op, left, Value::Float(right)) => match as_float(&left) {
+                Some(left) => float_operator(op, left, right, span),
+                None => error(span, format!("Invalid operands {} and float for '{}'", left.kind(), op))
+            },
+            (op, Value::Character(left), Value::Character(right)) => match integer_operator(op, left as i128, right as i128, None, &self.symbol_table.data_layout, span)? {
+                Value::Integer(value) => match u8::try_from(value) {
+                    Ok(value) => Ok(Value::Character(value)),
+                    Err(_) => error(span, format!("Character arithmetic overflowed in '{}'", op))
+                },
+                value => Ok(value)
+            },
+            (op, left, right) => match (as_integer(&left), as_integer(&right)) {
+                (Some(left), Some(right)) => {
+                    let ty: Option<FundamentalType> = self.integer_type(operator)?;
+                    integer_operator(op, left, right, ty.as_ref(), &self.symbol_table.data_layout, span)
+                },
+                _ => error(span, format!("Invalid operands {} and {} for '{}'", left.kind(), right.kind(), op))
+            }
+        }
+    }
+
+    // Integer type an operator computes in once lowered: that of the shifted value for shifts, and
+    // the common type of the operands otherwise. None for operators on integer literals alone
+    fn integer_type(&self, operator: &expression::BinaryOperator) -> Eval<Option<FundamentalType>> {
+        let left: Type = self.type_of(&operator.left)?;
+        let ty: Option<Type> = match operator.op.value.as_str() {
+            "<<" | ">>" => Some(left),
+            _ => left.arithmetic_type(&self.type_of(&operator.right)?)
+        };
+        Ok(ty.map(|ty| ty.ty).filter(|ty| ty.is_integer() && *ty != FundamentalType::Integer))
+    }
+
+    // Type the checker gave an expression, or that it gives it in the current scope if it was not kept
+    fn type_of(&self, node: &NodeType) -> Eval<Type> {
+        let checked: Option<Type> = self.symbol_table.types.borrow().get(&(node as *const NodeType)).cloned();
+        match checked {
+            Some(ty) => Ok(ty),
+            None => Ok(value_type(node, &mut self.scope_table())?)
+        }
+    }
+}
+
+fn span_text(node: &NodeType) -> String {
+    match node.span() {
+        Some(span) => span.to_string(),
+        None => "unknown location".to_string()
+    }
+}
+
+fn as_integer(value: &Value) -> Option<i128> {
+    match value {
+        Value::Integer(value) => Some(*value),
+        Value::Character(value) => Some(*value as i128),
+        _ => None
+    }
+}
+
+fn as_float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Float(value) => Some(*value),
+        _ => as_integer(value).map(|value| value as f64)
+    }
+}
+
+// Operator on integers of type 'ty', computed the way debug builds compute it at runtime: results
+// that leave the range of the type overflow, as do shifts by amounts not less than its width, while
+// bits shifted out of its width are lost. Operators on integer literals alone compute exact results
+fn integer_operator(op: &str, left: i128, right: i128, ty: Option<&FundamentalType>, data_layout: &DataLayout, span: Option<Span>) -> Eval<Value> {
+    let ((min, max), bits) = match ty {
+        Some(ty) => (integer_range(ty, data_layout), ty.to_ir_type(data_layout).bits() as i128),
+        None => ((i128::MIN, i128::MAX), 128)
+    };
+    let value = match op {
+        "<" => return Ok(Value::Boolean(left < right)),
+        ">" => return Ok(Value::Boolean(left > right)),
+        "<=" => return Ok(Value::Boolean(left <= right)),
+        ">=" => return Ok(Value::Boolean(left >= right)),
+        "/" | "%" if right == 0 => return error(span, format!("Division by zero in '{}'", op)),
+        "+" => left.checked_add(right),
+        "-" => left.checked_sub(right),
+        "*" => left.checked_mul(right),
+        "/" => left.checked_div(right),
+        "%" => left.checked_rem(right),
+        "**" => power(left, right, min, max),
+        "<<" | ">>" if !(0..bits).contains(&right) => None,
+        // Operands are at most 64 bits wide, so shifting them by less than 64 stays within i128
+        "<<" => Some(match ty {
+            Some(ty) => wrap(left << right, ty, data_layout),
+            None => left << right
+        }),
+        ">>" => Some(left >> right),
+        "&" => Some(left & right),
+        "|" => Some(left | right),
+        "^" => Some(left ^ right),
+        _ => return error(span, format!("Invalid operands int and int for '{}'", op))
+    };
+    match value.filter(|value| (min..=max).contains(value)) {
+        Some(value) => Ok(Value::Integer(value)),
+        None => match ty {
+            Some(ty) => error(span, format!("Integer overflow in '{}' on '{}'", op, Type{ ty: ty.clone() })),
+            None => error(span, format!("Integer overflow in '{}'", op))
+        }
+    }
+}
+
+// Exponentiation by squaring as the lowered code does it, which overflows only when the result
+// leaves [min, max]. Negative exponents overflow
+fn power(base: i128, exponent: i128, min: i128, max: i128) -> Option<i128> {
+    let fits = |value: Option<i128>| value.filter(|value| (min..=max).contains(value));
+    if exponent < 0 {
+        return None;
+    }
+    let (mut result, mut power, mut remaining): (i128, i128, i128) = (1, base, exponent);
+    while remaining > 0 {
+        if remaining & 1 == 1 {
+            result = fits(result.checked_mul(power))?;
+        }
+        remaining >>= 1;
+        if remaining > 0 {
+            power = fits(power.checked_mul(power))?;
+        }
+    }
+    Some(result)
+}
+
+fn float_operator(op: &str, left: f64, right: f64, span: Option<Span>) -> Eval<Value> {
+    Ok(match op {
+        "+" => Value::Float(left + right),
+        "-" => Value::Float(left - right),
+        "*" => Value::Float(left * right),
+        "/" => Value::Float(left / right),
+        "%" => Value::Float(left % right),
+        "**" => Value::Float(left.powf(right)),
+        "<" => Value::Boolean(left < right),
+        ">" => Value::Boolean(left > right),
+        "<=" => Value::Boolean(left <= right),
+        ">=" => Value::Boolean(left >= right),
+        _ => return error(span, format!("Invalid operands float and float for '{}'", op))
+    })
+}
+
+fn cast(value: Value, ty: &str, data_layout: &DataLayout, span: Option<Span>) -> Eval<Value> {
+    let target = Type::from_string(ty).ty;
+    Ok(match (&target, &value) {
+        (target, Value::Float(value)) if target.is_integer() => Value::Integer(saturate(*value, target, data_layout)),
+        (target, Value::Boolean(value)) if target.is_integer() => Value::Integer(*value as i128),
+        (target, _) if target.is_integer() && as_integer(&value).is_some() => {
+            Value::Integer(wrap(as_integer(&value).unwrap(), target, data_layout))
+        },
+        (FundamentalType::F32, _) if as_float(&value).is_some() => Value::Float(as_float(&value).unwrap() as f32 as f64),
+        (FundamentalType::F64, _) if as_float(&value).is_some() => Value::Float(as_float(&value).unwrap()),
+        (FundamentalType::Character, Value::Integer(integer)) => match u8::try_from(*integer) {
+            Ok(character) => Value::Character(character),
+            Err(_) => return error(span, format!("{} does not fit in a char", integer))
+        },
+        (FundamentalType::Character, Value::Character(_))
+            | (FundamentalType::Boolean, Value::Boolean(_))
+            | (FundamentalType::String, Value::String(_)) => value,
+        _ => return error(span, format!("Cannot cast a {} to '{}' at compile time", value.kind(), ty))
+    })
+}
+
+// Range of values of an integer type
+fn integer_range(ty: &FundamentalType, data_layout: &DataLayout) -> (i128, i128) {
+    let bits = ty.to_ir_type(data_layout).bits();
+    if ty.is_signed() {
+        (-(1 << (bits - 1)), (1 << (bits - 1)) - 1)
+    } else {
+        (0, (1 << bits) - 1)
+    }
+}
+
+// Integers evaluated at compile time must fit in the type they are given, as the arithmetic that
+// left its range would have trapped in a debug build
+pub(crate) fn check_range(value: &Value, ty: &Type, data_layout: &DataLayout) -> Result<(), TypeError> {
+    match (value, &ty.ty) {
+        (Value::Integer(_), FundamentalType::Integer) => Ok(()),
+        (Value::Integer(integer), integer_ty) if integer_ty.is_integer() => {
+            let (min, max) = integer_range(integer_ty, data_layout);
+            if *integer < min || *integer > max {
+                return Err(TypeError{ text: format!("{} does not fit in '{}'", integer, ty) });
+            }
+            Ok(())
+        },
+        (Value::Array(values), FundamentalType::Array(element, _) | FundamentalType::Slice(element)) => {
+            values.iter().try_for_each(|value| check_range(value, element, data_layout))
+        },
+        (Value::Tuple(values), FundamentalType::Tuple(types)) => {
+            values.iter().zip(types).try_for_each(|(value, ty)| check_range(value, ty, data_layout))
+        },
+        (Value::Ok(value), FundamentalType::Result(ty, _)) | (Value::Err(value), FundamentalType::Result(_, ty)) => {
+            check_range(value, ty, data_layout)
+        },
+        _ => Ok(())
+    }
+}
+
+// Two's complement truncation of an integer to the width of 'ty', as casts do at runtime
+fn wrap(value: i128, ty: &FundamentalType, data_layout: &DataLayout) -> i128 {
+    let bits = ty.to_ir_type(data_layout).bits();
+    let truncated = value & ((1 << bits) - 1);
+    let (_, max) = integer_range(ty, data_layout);
+    if truncated > max { truncated - (1 << bits) } else { truncated }
+}
+
+// Float to integer casts saturate at the bounds of the integer type, and NaN becomes 0
+fn saturate(value: f64, ty: &FundamentalType, data_layout: &DataLayout) -> i128 {
+    let (min, max) = integer_range(ty, data_layout);
+    if value.is_nan() {
+        return 0;
+    }
+    (value as i128).clamp(min, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use gecko_parser::{
+        ast::parse_gecko,
+        node::NodeType
+    };
+
+    use super::{Interpreter, Limits, Value};
+    use crate::{error::TypeError, tree_type::TypeCheck, symbol::SymbolTable};
+
+    // Value the 'const proc test' of a file returns, evaluated with the given limits
+    fn evaluate_with(source: &str, limits: Limits) -> Result<Value, TypeError> {
+        let file = parse_gecko(source).unwrap();
+        let mut symbol_table: SymbolTable = SymbolTable::new();
+        file.check(&mut symbol_table)?;
+        let definition = file.stmts
+            .iter()
+            .find_map(|stmt| match &**stmt {
+                NodeType::FunctionDefinition(definition) if definition.sig.id.name == "test" => Some(definition),
+                _ => None
+            })
+            .unwrap();
+        Interpreter::new(&symbol_table, limits).run(definition)
+    }
+
+    fn evaluate(source: &str) -> Result<Value, TypeError> {
+        evaluate_with(source, Limits::default())
+    }
+
+    #[test]
+    fn arithmetic_and_calls() {
+        let source = "
+            const proc factorial(n: int) -> int {
+                if n <= 1 { return 1; }
+                n * factorial(n - 1)
+            }
+            const proc test() -> int { factorial(10) + 7 / 2 - (1 << 3) }
+        ";
+        assert_eq!(evaluate(source).unwrap(), Value::Integer(3628800 + 3 - 8));
+    }
+
+    #[test]
+    fn strings() {
+        let source = "
+            const proc test() -> str {
+                let n: int = 4;
+                \"n is {n}, \" + \"ab\" * 2
+            }
+        ";
+        assert_eq!(evaluate(source).unwrap(), Value::String("n is 4, abab".to_string()));
+    }
+
+    #[test]
+    fn arrays_tuples_and_results() {
+        let source = "
+            const proc half(n: int) -> Result<int, str> {
+                if n < 0 { return Err(\"negative\"); }
+                Ok(n / 2)
+            }
+            const proc test() -> Result<(int, [int]), str> {
+                let values: [int; 4] = [10, 20, 30, 40];
+                let h := try half(values[3]);
+                Ok((h, values[1..3]))
+            }
+        ";
+        let expected = Value::Ok(Box::new(Value::Tuple(vec!(
+            Value::Integer(20),
+            Value::Array(vec!(Value::Integer(20), Value::Integer(30)))
+        ))));
+        assert_eq!(evaluate(source).unwrap(), expected);
+
+        let source = "
+            const proc half(n: int) -> Result<int, str> {
+                if n < 0 { return Err(\"negative\"); }
+                Ok(n / 2)
+            }
+            const proc test() -> Result<int, str> {
+                let h := half(0 - 1)?;
+                Ok(h + 1)
+            }
+        ";
+        assert_eq!(evaluate(source).unwrap(), Value::Err(Box::new(Value::String("negative".to_string()))));
+    }
+
+    #[test]
+    fn out_of_bounds_is_an_error() {
+        let source = "
+            const proc test() -> int {
+                let values: [int; 3] = [1, 2, 3];
+                let slice: [int] = values[1..];
+                slice[2]
+            }
+        ";
+        let error = evaluate(source).unwrap_err();
+        assert!(error.text.contains("out of bounds"), "{}", error.text);
+    }
+
+    #[test]
+    fn only_const_procs_are_called() {
+        let source = "
+            proc runtime() -> int { 1 }
+            const proc test() -> int { runtime() }
+        ";
+        let error = evaluate(source).unwrap_err();
+        assert!(error.text.contains("is not a 'const proc'"), "{}", error.text);
+    }
+
+    #[test]
+    fn overflow_intrinsics() {
+        let source = "
+            const proc test() -> (u8, i8, i64, Result<u16, str>, Result<i32, str>) {
+                let big: u8 = 200;
+                let (low, high) := (0 - 100 as i8, 9223372036854775807 as i64);
+                (wrapping_add(big, 100), saturating_sub(low, 100), saturating_mul(high, 0 - 2), checked_mul(300 as u16, 300), checked_add(1, 2))
+            }
+        ";
+        let expected = Value::Tuple(vec!(
+            Value::Integer(44),
+            Value::Integer(-128),
+            Value::Integer(i64::MIN as i128),
+            Value::Err(Box::new(Value::String("integer overflow".to_string()))),
+            Value::Ok(Box::new(Value::Integer(3)))
+        ));
+        assert_eq!(evaluate(source).unwrap(), expected);
+
+        // Parameters take the types of their signature
+        let source = "
+            const proc add(a: u64, b: u64) -> u64 { wrapping_add(a, b) }
+            const proc test() -> u64 { add(18446744073709551615, 2) }
+        ";
+        assert_eq!(evaluate(source).unwrap(), Value::Integer(1));
+    }
+
+    #[test]
+    fn typed_arithmetic() {
+        // Operators compute in the type of their operands, as they do at runtime
+        let source = "
+            const proc test() -> (u8, i8, u16, i32, i64) {
+                let (x, y, z): (u8, i8, u16) = (255, 0 - 128, 3);
+                ((x << 4) >> 4, y >> 7, z ** 10 / 7, (0 - 7) / 2 % 3, 1 << 40)
+            }
+        ";
+        let expected = Value::Tuple(vec!(
+            Value::Integer(15),
+            Value::Integer(-1),
+            Value::Integer(8435),
+            Value::Integer(0),
+            Value::Integer(1 << 40)
+        ));
+        assert_eq!(evaluate(source).unwrap(), expected);
+
+        // Overflow traps at runtime, so it is an error at compile time
+        let overflow = |body: &str| evaluate(&format!("const proc test() -> int {{ {} }}", body)).unwrap_err().text;
+        assert_eq!(overflow("let a: u8 = 200; (a + 100) as int"), "Integer overflow in '+' on 'u8', at 1:46");
+        assert_eq!(overflow("let a: i8 = 0 - 128; (a / (0 - 1)) as int"), "Integer overflow in '/' on 'i8', at 1:50");
+        assert_eq!(overflow("let a: i16 = 3; (a ** 10) as int"), "Integer overflow in '**' on 'i16', at 1:45");
+        assert_eq!(overflow("let a: u32 = 1; (a << 32) as int"), "Integer overflow in '<<' on 'u32', at 1:45");
+        assert_eq!(overflow("let a: int = 2; a ** (0 - 1)"), "Integer overflow in '**' on 'i32', at 1:44");
+    }
+
+    #[test]
+    fn contracts() {
+        let half = "const proc half(n: int) -> int requires n >= 0 ensures result * 2 == n { n / 2 }";
+        assert_eq!(evaluate(&format!("{} const proc test() -> int {{ half(8) }}", half)).unwrap(), Value::Integer(4));
+        assert_eq!(
+            evaluate(&format!("{} const proc test() -> int {{ half(0 - 2) }}", half)).unwrap_err().text,
+            "Precondition `n >= 0` failed, at 1:32"
+        );
+        assert_eq!(
+            evaluate("const proc test() -> int ensures result > 0 { 0 }").unwrap_err().text,
+            "Postcondition `result > 0` failed, at 1:26"
+        );
+        assert_eq!(
+            evaluate("const proc test() -> int { let n := 2; assert(n < 2, \"n is {n}\"); n }").unwrap_err().text,
+            "Assertion `n < 2` failed: n is 2, at 1:40"
+        );
+    }
+
+    #[test]
+    fn limits() {
+        let source = "
+            const proc forever(n: int) -> int { forever(n + 1) }
+            const proc test() -> int { forever(0) }
+        ";
+        // Unoptimised builds of the interpreter need more stack for the default depth than test threads have
+        let error = evaluate_with(source, Limits{ depth: 32, ..Limits::default() }).unwrap_err();
+        assert!(error.text.contains("limit of 32 nested calls"), "{}", error.text);
+
+        let source = "
+            const proc count(n: int) -> int {
+                if n == 0 { return 0; }
+                count(n - 1) + 1
+            }
+            const proc test() -> int { count(100) }
+        ";
+        let error = evaluate_with(source, Limits{ steps: 100, ..Limits::default() }).unwrap_err();
+        assert!(error.text.contains("limit of 100 steps"), "{}", error.text);
+        let error = evaluate_with(source, Limits{ depth: 10, ..Limits::default() }).unwrap_err();
+        assert!(error.text.contains("limit of 10 nested calls"), "{}", error.text);
+        assert_eq!(evaluate(source).unwrap(), Value::Integer(100));
+
+        let source = "
+            const proc grow(text: str, n: int) -> str {
+                if n == 0 { return text; }
+                grow(text + text, n - 1)
+            }
+            const proc test() -> str { grow(\"ab\", 10) }
+        ";
+        let error = evaluate_with(source, Limits{ memory: 64, ..Limits::default() }).unwrap_err();
+        assert!(error.text.contains("limit of 64 bytes"), "{}", error.text);
+    }
+}
@@ -0,0 +1,68 @@
+use std::fmt;
+
+// Value computed by the compile-time interpreter
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Boolean(bool),
+    Character(u8),
+    Integer(i128),
+    Float(f64),
+    String(String),
+    // Fixed size arrays and the slices taken of them
+    Array(Vec<Value>),
+    Tuple(Vec<Value>),
+    Ok(Box<Value>),
+    Err(Box<Value>),
+    // Result of statements and blocks without a tail expression
+    Unit,
+}
+
+impl Value {
+    // Bytes a value holds beyond its own, counted against the memory limit when it is created
+    pub fn heap_size(&self) -> usize {
+        match self {
+            Value::String(string) => string.len(),
+            Value::Array(elements) | Value::Tuple(elements) => elements.len() * std::mem::size_of::<Value>(),
+            Value::Ok(_) | Value::Err(_) => std::mem::size_of::<Value>(),
+            _ => 0
+        }
+    }
+
+    // Name of the kind of value, used in diagnostics
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Value::Boolean(_) => "bool",
+            Value::Character(_) => "char",
+            Value::Integer(_) => "int",
+            Value::Float(_) => "float",
+            Value::String(_) => "str",
+            Value::Array(_) => "array",
+            Value::Tuple(_) => "tuple",
+            Value::Ok(_) | Value::Err(_) => "Result",
+            Value::Unit => "no value"
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Boolean(value) => write!(fmt, "{}", value),
+            Value::Character(value) => write!(fmt, "{}", *value as char),
+            Value::Integer(value) => write!(fmt, "{}", value),
+            Value::Float(value) => write!(fmt, "{}", value),
+            Value::String(value) => fmt.write_str(value),
+            Value::Array(elements) => {
+                let elements: Vec<String> = elements.iter().map(|element| element.to_string()).collect();
+                write!(fmt, "[{}]", elements.join(", "))
+            },
+            Value::Tuple(elements) => {
+                let elements: Vec<String> = elements.iter().map(|element| element.to_string()).collect();
+                write!(fmt, "({})", elements.join(", "))
+            },
+            Value::Ok(value) => write!(fmt, "Ok({})", value),
+            Value::Err(error) => write!(fmt, "Err({})", error),
+            Value::Unit => fmt.write_str("()")
+        }
+    }
+}
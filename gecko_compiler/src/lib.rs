@@ -3,6 +3,7 @@ extern crate gecko_parser;
 pub mod tree_type;
 pub mod symbol;
 pub mod codegen;
+pub mod interpreter;
 
 pub mod error;
 
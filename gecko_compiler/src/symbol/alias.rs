@@ -0,0 +1,16 @@
+use gecko_parser::expression::Identifier;
+
+use crate::Type;
+
+// Name given to a type by 'type', which stands for that type wherever it is written
+#[derive(Clone)]
+pub struct Alias {
+    pub id: Identifier,
+    pub ty: Type,
+}
+
+impl Alias {
+    pub fn new(id: Identifier, ty: Type) -> Self {
+        Self{ id, ty }
+    }
+}
@@ -0,0 +1,20 @@
+use gecko_parser::expression::Identifier;
+
+use crate::{
+    Type,
+    interpreter::Value
+};
+
+// Value of a 'const', known at compile time
+#[derive(Clone)]
+pub struct Constant {
+    pub id: Identifier,
+    pub ty: Type,
+    pub value: Value,
+}
+
+impl Constant {
+    pub fn new(id: Identifier, ty: Type, value: Value) -> Self {
+        Self{ id, ty, value }
+    }
+}
@@ -10,11 +10,20 @@ pub struct Function {
     output: Type,
 
     body: bool,
+    // Defined outside Gecko and called with the C calling convention
+    external: bool,
+    // Accepts extra arguments after its parameters, like C's printf
+    variadic: bool,
+    // Declared 'unsafe proc', so it can only be called from unsafe code
+    unsafe_call: bool,
 }
 
 impl Function {
-    pub fn new(body: bool, id: Identifier, params: Vec<(String, Type)>, output: Type) -> Self {
-        Self{ body, id, output, params }
+    pub fn new(body: bool, id: Identifier, params: Vec<(String, Type)>, output: Type, unsafe_call: bool) -> Self {
+        Self{ body, id, output, params, external: false, variadic: false, unsafe_call }
+    }
+    pub fn declaration(id: Identifier, params: Vec<(String, Type)>, output: Type, external: bool, variadic: bool, unsafe_call: bool) -> Self {
+        Self{ body: false, id, output, params, external, variadic, unsafe_call }
     }
 
     pub fn define(&mut self) {
@@ -23,4 +32,24 @@ impl Function {
     pub fn has_body(&self) -> bool {
         self.body
     }
+
+    pub fn id(&self) -> &Identifier {
+        &self.id
+    }
+    pub fn params(&self) -> &Vec<(String, Type)> {
+        &self.params
+    }
+    pub fn output(&self) -> &Type {
+        &self.output
+    }
+    pub fn is_external(&self) -> bool {
+        self.external
+    }
+    pub fn is_variadic(&self) -> bool {
+        self.variadic
+    }
+    // Calls to 'extern' procedures are unsafe too, as the checker cannot see their bodies
+    pub fn is_unsafe(&self) -> bool {
+        self.unsafe_call || self.external
+    }
 }
\ No newline at end of file
@@ -1,32 +1,73 @@
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use target_lexicon::Triple;
 
 pub mod variable;
 pub mod function;
+pub mod structure;
+pub mod constant;
+pub mod alias;
 pub use variable::*;
 pub use function::*;
+pub use structure::*;
+pub use constant::*;
+pub use alias::*;
 
 use gecko_parser::{
+    ast::Span,
+    node::NodeType,
     expression::Identifier,
-    statement::function_definition::Signature,
+    statement::FunctionDefinition
 };
 use crate::{
     Type,
+    codegen::DataLayout,
+    interpreter::Value,
     error::TypeError
 };
 
 #[derive(Clone)]
 pub enum Symbol {
     Variable(Variable),
-    Function(Function)
+    Function(Function),
+    Struct(Structure),
+    Constant(Constant),
+    Alias(Alias)
 }
 
-#[derive(Clone)]
-pub struct SymbolTable {
+#[derive(Clone, Default)]
+pub struct SymbolTable<'a> {
     // Need to use a map instead of a vector for symbol names
-    pub symbols: HashMap<String, Symbol>
+    pub symbols: HashMap<String, Symbol>,
+
+    // Output type of the procedure whose body is being checked
+    pub output: Option<Type>,
+    // Set while checking the statement of a 'defer'
+    pub in_defer: bool,
+    // Set while checking an 'unsafe' block or the body of an 'unsafe proc'
+    pub in_unsafe: bool,
+    // Layout rules of the target, used to answer sizeof, alignof and offsetof
+    pub data_layout: DataLayout,
+    // Definitions of the 'const proc's in scope, run by the compile-time interpreter
+    pub const_procs: HashMap<String, &'a FunctionDefinition>,
+    // Types of the expressions checked so far, by address, shared with the clones of the table so
+    // an expression is not checked again for each expression containing it, see value_type
+    pub types: Rc<RefCell<HashMap<*const NodeType, Type>>>,
+    // Symbols defined at the top level of the file, shared with the clones of the table so the
+    // identifiers macro expansion marks global can skip the locals of the same name, see symbol
+    pub globals: Rc<RefCell<HashMap<String, Symbol>>>,
 }
 
-impl SymbolTable {
+impl<'a> SymbolTable<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Symbol table laying out values for 'triple' rather than the host
+    pub fn for_target(triple: &Triple) -> Self {
+        Self{ data_layout: DataLayout::new(triple), ..Self::default() }
+    }
+
     pub fn declare_variable(&mut self, id: &Identifier, ty: Type) {
         let symbol: Symbol = Symbol::Variable(Variable::new(false, id.clone(), ty));
         self.symbols.insert(id.name.clone(), symbol);
@@ -36,28 +77,117 @@ impl SymbolTable {
         self.symbols.insert(id.name.clone(), symbol);
     }
 
+    // Symbol an identifier names. One marked global by macro expansion names the global symbol of
+    // that name, if there is one, whatever the scope has of that name
+    pub fn symbol(&self, id: &Identifier) -> Option<Symbol> {
+        if id.global {
+            if let Some(symbol) = self.globals.borrow().get(&id.name) {
+                return Some(symbol.clone());
+            }
+        }
+        self.symbols.get(&id.name).cloned()
+    }
+
+    // Whether an identifier names a global symbol rather than a local of the same name
+    pub fn is_global(&self, id: &Identifier) -> bool {
+        id.global && self.globals.borrow().contains_key(&id.name)
+    }
+
+    // Makes the symbol just defined as 'name' at the top level of the file global
+    pub fn define_global(&mut self, name: &str) {
+        if let Some(symbol) = self.symbols.get(name) {
+            self.globals.borrow_mut().insert(name.to_owned(), symbol.clone());
+        }
+    }
+
     // Checks if variable exists and is initialised
     pub fn variable_type(&self, id: &Identifier) -> Result<Option<Type>, TypeError> {
-        for (name, symbol) in &self.symbols {
-            if name.as_str() == id.name {   
-                match symbol {
-                    Symbol::Variable(variable) => {
-                        if !variable.is_initialised() {
-                            // Attempt to use uninitialised variable
-                            return Err(TypeError{ text: format!("Attempt to access uninitialised variable '{}'", name)});
-                        }
-                        return Ok(variable.ty.clone());
-                    },
-                    _ => return Err(TypeError{ text: format!("'{}', is not a variable", id.name)})
+        match self.symbol(id) {
+            Some(Symbol::Variable(variable)) => {
+                if !variable.is_initialised() {
+                    // Attempt to use uninitialised variable
+                    return Err(TypeError{ text: format!("Attempt to access uninitialised variable '{}'", id.name)});
                 }
-            }
+                Ok(variable.ty.clone())
+            },
+            Some(Symbol::Constant(constant)) => Ok(Some(constant.ty.clone())),
+            Some(_) => Err(TypeError{ text: format!("'{}', is not a variable", id.name)}),
+            None => Err(TypeError{ text: format!("The variable '{}' does not exist", id.name)})
         }
+    }
 
-        Err(TypeError{ text: format!("The variable '{}' does not exist", id.name)})
+    // Checks if function exists
+    pub fn function(&self, id: &Identifier) -> Result<Function, TypeError> {
+        match self.symbol(id) {
+            Some(Symbol::Function(function)) => Ok(function),
+            Some(_) => Err(TypeError{ text: format!("'{}', is not a function", id.name)}),
+            None => Err(TypeError{ text: format!("The function '{}' does not exist", id.name)})
+        }
+    }
+
+    pub fn define_function(&mut self, id: &Identifier, params: Vec<(String, Type)>, output: Type, unsafe_call: bool) {
+        let symbol: Symbol = Symbol::Function(Function::new(true, id.clone(), params, output, unsafe_call));
+        self.symbols.insert(id.name.clone(), symbol);
+    }
+
+    pub fn declare_function(&mut self, id: &Identifier, params: Vec<(String, Type)>, output: Type, external: bool, variadic: bool, unsafe_call: bool) {
+        let symbol: Symbol = Symbol::Function(Function::declaration(id.clone(), params, output, external, variadic, unsafe_call));
+        self.symbols.insert(id.name.clone(), symbol);
+    }
+
+    // Only allows an unsafe operation inside an 'unsafe' block or 'unsafe proc' body
+    pub fn require_unsafe(&self, operation: &str, span: Span) -> Result<(), TypeError> {
+        if self.in_unsafe {
+            return Ok(());
+        }
+        Err(TypeError{ text: format!("{} is unsafe and requires an 'unsafe' block or 'unsafe proc', at {}", operation, span) })
+    }
+
+    // Value of a constant, if the identifier names one
+    pub fn constant_value(&self, id: &Identifier) -> Option<Value> {
+        match self.symbol(id) {
+            Some(Symbol::Constant(constant)) => Some(constant.value),
+            _ => None
+        }
+    }
+
+    pub fn define_constant(&mut self, id: &Identifier, ty: Type, value: Value) {
+        let symbol: Symbol = Symbol::Constant(Constant::new(id.clone(), ty, value));
+        self.symbols.insert(id.name.clone(), symbol);
+    }
+
+    // Symbols the body of a procedure defined in this scope sees, which are all but the variables
+    // of the procedure it is nested in, as the interpreter and codegen call it like any other
+    pub fn procedure_scope(&self) -> SymbolTable<'a> {
+        let mut table: SymbolTable<'a> = self.clone();
+        table.symbols.retain(|_, symbol| !matches!(symbol, Symbol::Variable(_)));
+        table
+    }
+
+    // Checks if struct exists
+    pub fn structure(&self, name: &str) -> Result<Structure, TypeError> {
+        match self.symbols.get(name) {
+            Some(Symbol::Struct(structure)) => Ok(structure.clone()),
+            Some(_) => Err(TypeError{ text: format!("'{}', is not a struct", name)}),
+            None => Err(TypeError{ text: format!("The struct '{}' does not exist", name)})
+        }
+    }
+
+    pub fn define_struct(&mut self, id: &Identifier, fields: Vec<(String, Type)>) {
+        let symbol: Symbol = Symbol::Struct(Structure::new(id.clone(), fields));
+        self.symbols.insert(id.name.clone(), symbol);
+    }
+
+    // Type a 'type' alias stands for, if 'name' is one
+    pub fn alias(&self, name: &str) -> Option<Type> {
+        match self.symbols.get(name) {
+            Some(Symbol::Alias(alias)) => Some(alias.ty.clone()),
+            _ => None
+        }
     }
 
-    pub fn define_function(&mut self, id: &Identifier, params: Vec<(String, Type)>, output: Type) {
-        let symbol: Symbol = Symbol::Function(Function::new(true, id.clone(), params, output));
+    pub fn define_alias(&mut self, id: &Identifier, ty: Type) {
+        let symbol: Symbol = Symbol::Alias(Alias::new(id.clone(), ty));
         self.symbols.insert(id.name.clone(), symbol);
     }
 }
\ No newline at end of file
@@ -0,0 +1,28 @@
+use gecko_parser::expression::Identifier;
+
+use crate::Type;
+
+#[derive(Clone)]
+pub struct Structure {
+    id: Identifier,
+    fields: Vec<(String, Type)>,
+}
+
+impl Structure {
+    pub fn new(id: Identifier, fields: Vec<(String, Type)>) -> Self {
+        Self{ id, fields }
+    }
+
+    pub fn id(&self) -> &Identifier {
+        &self.id
+    }
+    pub fn fields(&self) -> &Vec<(String, Type)> {
+        &self.fields
+    }
+    pub fn field(&self, name: &str) -> Option<&Type> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, ty)| ty)
+    }
+}
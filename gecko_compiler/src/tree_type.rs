@@ -1,8 +1,10 @@
 //#[allow(dead_code)]
-use std::{collections::HashMap, fmt};
+use std::fmt;
 
 use crate::{
-    symbol::SymbolTable
+    symbol::SymbolTable,
+    codegen::overflow::{self, Overflow},
+    interpreter::{self, Value}
 };
 
 use gecko_parser::{
@@ -14,6 +16,7 @@ use gecko_parser::{
     },
     Token
 };
+use target_lexicon::Triple;
 
 use crate::error::{TypeError};
 
@@ -21,44 +24,461 @@ use crate::error::{TypeError};
 pub enum FundamentalType {
     Boolean,
     Character,
+    // Signed integers, 'int' is i32
+    I8,
+    I16,
+    I32,
+    I64,
+    Isize,
+    // Unsigned integers
+    U8,
+    U16,
+    U32,
+    U64,
+    Usize,
+    // Floating point numbers, 'float' is f32 and 'double' is f64
+    F32,
+    F64,
+    // Integer literal, usable as any integer type and i32 when nothing decides its type
     Integer,
+    // Float literal, usable as any float type and f64 when nothing decides its type
     Float,
     String,
-    DefinedType
+    // Value of the first type, or an error of the second type
+    Result(Box<Type>, Box<Type>),
+    // Fixed size array of elements
+    Array(Box<Type>, u64),
+    // View into an array, stored as a pointer and a length
+    Slice(Box<Type>),
+    // Address of a value
+    Pointer(Box<Type>),
+    // Fixed number of values of possibly different types
+    Tuple(Vec<Type>),
+    // Type of an expression that never produces a value, e.g. the error of Ok(value)
+    Never,
+    // Struct, named by its definition
+    DefinedType(String)
 }
 
 impl fmt::Display for FundamentalType {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt.write_str(match self {
-            FundamentalType::Boolean => "bool",
-            FundamentalType::Character => "char",
-            FundamentalType::Integer => "int",
-            FundamentalType::Float => "float",
-            FundamentalType::String => "str",
-            FundamentalType::DefinedType => "defined type"
-        })
+        match self {
+            FundamentalType::Result(value, error) => write!(fmt, "Result<{}, {}>", value, error),
+            FundamentalType::Array(element, length) => write!(fmt, "[{}; {}]", element, length),
+            FundamentalType::Slice(element) => write!(fmt, "[{}]", element),
+            FundamentalType::Pointer(pointee) => write!(fmt, "*{}", pointee),
+            FundamentalType::Tuple(elements) => {
+                let elements: Vec<String> = elements.iter().map(|element| element.to_string()).collect();
+                write!(fmt, "({})", elements.join(", "))
+            },
+            FundamentalType::DefinedType(name) => fmt.write_str(name),
+            _ => fmt.write_str(match self {
+                FundamentalType::Boolean => "bool",
+                FundamentalType::Character => "char",
+                FundamentalType::I8 => "i8",
+                FundamentalType::I16 => "i16",
+                FundamentalType::I32 => "i32",
+                FundamentalType::I64 => "i64",
+                FundamentalType::Isize => "isize",
+                FundamentalType::U8 => "u8",
+                FundamentalType::U16 => "u16",
+                FundamentalType::U32 => "u32",
+                FundamentalType::U64 => "u64",
+                FundamentalType::Usize => "usize",
+                FundamentalType::F32 => "f32",
+                FundamentalType::F64 => "f64",
+                FundamentalType::Integer => "{integer}",
+                FundamentalType::Float => "{float}",
+                FundamentalType::String => "str",
+                FundamentalType::Never => "never",
+                _ => unreachable!()
+            })
+        }
+    }
+}
+
+impl FundamentalType {
+    pub fn is_integer(&self) -> bool {
+        matches!(self,
+            FundamentalType::I8 | FundamentalType::I16 | FundamentalType::I32 | FundamentalType::I64 | FundamentalType::Isize
+            | FundamentalType::U8 | FundamentalType::U16 | FundamentalType::U32 | FundamentalType::U64 | FundamentalType::Usize
+            | FundamentalType::Integer)
+    }
+    pub fn is_float(&self) -> bool {
+        matches!(self, FundamentalType::F32 | FundamentalType::F64 | FundamentalType::Float)
+    }
+    // Signed integers compare, divide and shift right as two's complement numbers
+    pub fn is_signed(&self) -> bool {
+        matches!(self,
+            FundamentalType::I8 | FundamentalType::I16 | FundamentalType::I32 | FundamentalType::I64 | FundamentalType::Isize
+            | FundamentalType::Integer)
     }
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Type {
-    ty: FundamentalType
+    pub(crate) ty: FundamentalType
 }
 
 impl Type {
     fn from_id(id: &expression::Identifier) -> Self {
         Type::from_string(&id.name)
     }
-    fn from_string(string: &String) -> Self {
-        match string.as_str() {
+    pub(crate) fn from_string(string: &str) -> Self {
+        match string {
             "bool" => Type{ ty: FundamentalType::Boolean },
             "char" => Type{ ty: FundamentalType::Character },
-            "int" => Type{ ty: FundamentalType::Integer },
-            "float" => Type{ ty: FundamentalType::Float },
+            "i8" => Type{ ty: FundamentalType::I8 },
+            "i16" => Type{ ty: FundamentalType::I16 },
+            "i32" | "int" => Type{ ty: FundamentalType::I32 },
+            "i64" => Type{ ty: FundamentalType::I64 },
+            "isize" => Type{ ty: FundamentalType::Isize },
+            "u8" => Type{ ty: FundamentalType::U8 },
+            "u16" => Type{ ty: FundamentalType::U16 },
+            "u32" => Type{ ty: FundamentalType::U32 },
+            "u64" => Type{ ty: FundamentalType::U64 },
+            "usize" => Type{ ty: FundamentalType::Usize },
+            "f32" | "float" => Type{ ty: FundamentalType::F32 },
+            "f64" | "double" => Type{ ty: FundamentalType::F64 },
             "str" => Type{ ty: FundamentalType::String },
-            _ => Type{ ty: FundamentalType::DefinedType },
+            name => Type{ ty: FundamentalType::DefinedType(name.to_string()) },
+        }
+    }
+    pub(crate) fn from_specifier(specifier: &node::TypeSpecifier, symbol_table: &SymbolTable) -> Result<Self, TypeError> {
+        let (id, generics) = match &specifier.kind {
+            node::TypeKind::Named{ id, generics } => (id, generics),
+            node::TypeKind::Array{ element, length, .. } => {
+                let element = Box::new(Type::from_specifier(element, symbol_table)?);
+                return Ok(Type{ ty: match length {
+                    Some(length) => {
+                        // Bindings made while checking the length do not outlive the type
+                        let mut scope: SymbolTable = symbol_table.clone();
+                        let length_ty: Type = value_type(length, &mut scope)?;
+                        if !length_ty.ty.is_integer() {
+                            return Err(TypeError{ text: format!("Array length must be an integer, got '{}'", length_ty) });
+                        }
+                        match interpreter::evaluate(length, symbol_table)? {
+                            Value::Integer(length) => FundamentalType::Array(element, array_length(length)?),
+                            value => return Err(TypeError{ text: format!("Array length must be an integer, got {}", value) })
+                        }
+                    },
+                    None => FundamentalType::Slice(element)
+                }});
+            },
+            node::TypeKind::Pointer{ pointee, .. } => {
+                return Ok(Type{ ty: FundamentalType::Pointer(Box::new(Type::from_specifier(pointee, symbol_table)?)) });
+            },
+            node::TypeKind::Tuple{ elements, .. } => {
+                let elements: Vec<Type> = elements
+                    .iter()
+                    .map(|element| Type::from_specifier(element, symbol_table))
+                    .collect::<Result<_, _>>()?;
+                return Ok(Type{ ty: FundamentalType::Tuple(elements) });
+            }
+        };
+        let generics: Vec<Type> = generics
+            .iter()
+            .map(|generic| Type::from_specifier(generic, symbol_table))
+            .collect::<Result<_, _>>()?;
+
+        match (id.name.as_str(), generics.as_slice()) {
+            ("Result", [value, error]) => Ok(Type{ ty: FundamentalType::Result(Box::new(value.clone()), Box::new(error.clone())) }),
+            ("Result", _) => Err(TypeError{ text: format!("'Result' expects 2 type arguments, got {}", generics.len()) }),
+            (_, []) => Type::from_name(id, symbol_table),
+            (name, _) => Err(TypeError{ text: format!("Type '{}' does not take type arguments", name) })
+        }
+    }
+
+    // Type a name without type arguments stands for. Names other than the fundamental types must
+    // be type aliases or defined structs
+    fn from_name(id: &expression::Identifier, symbol_table: &SymbolTable) -> Result<Self, TypeError> {
+        match Type::from_id(id) {
+            Type{ ty: FundamentalType::DefinedType(name) } => {
+                if let Some(ty) = symbol_table.alias(&name) {
+                    return Ok(ty);
+                }
+                symbol_table.structure(&name)
+                    .map_err(|_| TypeError{ text: format!("Unknown type '{}' at {}", name, id.span) })?;
+                Ok(Type{ ty: FundamentalType::DefinedType(name) })
+            },
+            ty => Ok(ty)
+        }
+    }
+
+    // Whether values of this type fit in a register, which numbers, characters, booleans and
+    // pointers do. Values of other types are aggregates, kept in memory
+    pub fn is_scalar(&self) -> bool {
+        self.ty.is_integer()
+            || self.ty.is_float()
+            || matches!(self.ty, FundamentalType::Boolean | FundamentalType::Character | FundamentalType::Pointer(_))
+    }
+
+    // Whether 'as' can convert values of this type, which are the scalars
+    fn is_castable(&self) -> bool {
+        self.is_scalar()
+    }
+
+    // Type an extra argument of a C variadic call is passed as, after the default argument
+    // promotions: integers narrower than a C int are widened to one, and floats to a double
+    pub fn promote_variadic(&self) -> Option<Type> {
+        match &self.ty {
+            FundamentalType::Boolean
+                | FundamentalType::Character
+                | FundamentalType::I8
+                | FundamentalType::I16
+                | FundamentalType::U8
+                | FundamentalType::U16
+                | FundamentalType::Integer => Some(Type{ ty: FundamentalType::I32 }),
+            FundamentalType::F32 | FundamentalType::Float => Some(Type{ ty: FundamentalType::F64 }),
+            ty if ty.is_integer() || ty.is_float() => Some(self.clone()),
+            FundamentalType::Pointer(_) => Some(self.clone()),
+            _ => None
+        }
+    }
+
+    // Type a value gets when nothing else decides it, with literals given their default types
+    pub fn concrete(&self) -> Type {
+        Type{ ty: match &self.ty {
+            FundamentalType::Integer => FundamentalType::I32,
+            FundamentalType::Float => FundamentalType::F64,
+            FundamentalType::Result(value, error) => FundamentalType::Result(Box::new(value.concrete()), Box::new(error.concrete())),
+            FundamentalType::Array(element, length) => FundamentalType::Array(Box::new(element.concrete()), *length),
+            FundamentalType::Slice(element) => FundamentalType::Slice(Box::new(element.concrete())),
+            FundamentalType::Pointer(pointee) => FundamentalType::Pointer(Box::new(pointee.concrete())),
+            FundamentalType::Tuple(elements) => FundamentalType::Tuple(elements.iter().map(Type::concrete).collect()),
+            ty => ty.clone()
+        }}
+    }
+
+    // Type of an arithmetic or comparison operator's operands once combined. Distinct numeric
+    // types are never mixed implicitly, but literals take the type of the other operand and
+    // characters are promoted to the integer type they are combined with
+    pub fn arithmetic_type(&self, other: &Type) -> Option<Type> {
+        use FundamentalType::*;
+        match (&self.ty, &other.ty) {
+            (ty, other_ty) if ty == other_ty && (ty.is_integer() || ty.is_float() || *ty == Character) => Some(self.clone()),
+            (Integer, ty) if ty.is_integer() || ty.is_float() => Some(other.clone()),
+            (ty, Integer) if ty.is_integer() || ty.is_float() => Some(self.clone()),
+            (Float, ty) if ty.is_float() => Some(other.clone()),
+            (ty, Float) if ty.is_float() => Some(self.clone()),
+            (Character, ty) if ty.is_integer() => Some(other.clone()),
+            (ty, Character) if ty.is_integer() => Some(self.clone()),
+            _ => None
+        }
+    }
+
+    // Whether the type has an integer literal's type in it, which a value must fit in once it is used
+    fn has_literal(&self) -> bool {
+        match &self.ty {
+            FundamentalType::Integer => true,
+            FundamentalType::Array(element, _) | FundamentalType::Slice(element) => element.has_literal(),
+            FundamentalType::Tuple(elements) => elements.iter().any(Type::has_literal),
+            FundamentalType::Result(value, error) => value.has_literal() || error.has_literal(),
+            _ => false
+        }
+    }
+
+    // Whether values of this type can be interpolated into strings
+    pub fn is_formattable(&self) -> bool {
+        self.ty.is_integer()
+            || self.ty.is_float()
+            || matches!(self.ty, FundamentalType::Boolean | FundamentalType::Character | FundamentalType::String)
+    }
+
+    // Type that values of both types can be used as, e.g. the type of an 'if' with two branches
+    pub fn common_type(&self, other: &Type) -> Option<Type> {
+        match (&self.ty, &other.ty) {
+            (FundamentalType::Never, _) => Some(other.clone()),
+            (_, FundamentalType::Never) => Some(self.clone()),
+            (FundamentalType::Result(ok, err), FundamentalType::Result(other_ok, other_err)) => {
+                let ok = ok.common_type(other_ok)?;
+                let err = err.common_type(other_err)?;
+                Some(Type{ ty: FundamentalType::Result(Box::new(ok), Box::new(err)) })
+            },
+            (FundamentalType::Array(element, length), FundamentalType::Array(other_element, other_length)) if length == other_length => {
+                Some(Type{ ty: FundamentalType::Array(Box::new(element.common_type(other_element)?), *length) })
+            },
+            (FundamentalType::Tuple(elements), FundamentalType::Tuple(other_elements)) if elements.len() == other_elements.len() => {
+                let elements: Vec<Type> = elements
+                    .iter()
+                    .zip(other_elements)
+                    .map(|(element, other)| element.common_type(other))
+                    .collect::<Option<_>>()?;
+                Some(Type{ ty: FundamentalType::Tuple(elements) })
+            },
+            _ if self.is_compatible(other) => Some(self.clone()),
+            _ if other.is_compatible(self) => Some(other.clone()),
+            _ => None
         }
     }
+
+    // Whether values of this type store a value of the named struct inline
+    fn contains(&self, name: &str) -> bool {
+        match &self.ty {
+            FundamentalType::DefinedType(defined) => defined == name,
+            FundamentalType::Array(element, _) => element.contains(name),
+            FundamentalType::Tuple(elements) => elements.iter().any(|element| element.contains(name)),
+            FundamentalType::Result(value, error) => value.contains(name) || error.contains(name),
+            _ => false
+        }
+    }
+
+    // Whether a value of type 'value' can be used where this type is expected
+    pub fn is_compatible(&self, value: &Type) -> bool {
+        match (&self.ty, &value.ty) {
+            (_, FundamentalType::Never) => true,
+            // Literals adapt to the numeric type they are used as
+            (ty, FundamentalType::Integer) if ty.is_integer() => true,
+            (ty, FundamentalType::Float) if ty.is_float() => true,
+            (FundamentalType::Result(ok, err), FundamentalType::Result(value_ok, value_err)) => {
+                ok.is_compatible(value_ok) && err.is_compatible(value_err)
+            },
+            (FundamentalType::Array(element, length), FundamentalType::Array(value_element, value_length)) => {
+                length == value_length && element.is_compatible(value_element)
+            },
+            (FundamentalType::Tuple(elements), FundamentalType::Tuple(value_elements)) => {
+                elements.len() == value_elements.len()
+                    && elements.iter().zip(value_elements).all(|(element, value)| element.is_compatible(value))
+            },
+            // Arrays coerce to slices of the same element type
+            (FundamentalType::Slice(element), FundamentalType::Array(value_element, _))
+                | (FundamentalType::Slice(element), FundamentalType::Slice(value_element)) => {
+                element.is_compatible(value_element)
+            },
+            _ => self == value
+        }
+    }
+}
+
+fn array_length(length: i128) -> Result<u64, TypeError> {
+    u64::try_from(length)
+        .map_err(|_| TypeError{ text: format!("Invalid array length {}", length) })
+}
+
+// Value of an integer expression, if it is known at compile time
+fn constant_integer(node: &NodeType, symbol_table: &SymbolTable) -> Option<i128> {
+    match interpreter::evaluate(node, symbol_table) {
+        Ok(Value::Integer(value)) => Some(value),
+        _ => None
+    }
+}
+
+// Integer literals take the type they are used as, so one whose value is known at compile time
+// must fit in its range, as the value of a constant must
+fn check_literal<'a>(node: &'a NodeType, value_ty: &Type, ty: &Type, symbol_table: &SymbolTable<'a>) -> Result<(), TypeError> {
+    if !value_ty.has_literal() {
+        return Ok(());
+    }
+    match interpreter::evaluate(node, symbol_table) {
+        Ok(value) => interpreter::check_range(&value, ty, &symbol_table.data_layout).map_err(|e| match node.span() {
+            Some(span) => TypeError{ text: format!("{}, at {}", e.text, span) },
+            None => e
+        }),
+        // Other expressions are only known at runtime
+        Err(_) => Ok(())
+    }
+}
+
+// Answers sizeof, alignof and offsetof with the layout rules of the target
+pub(crate) fn type_query_value(query: &expression::TypeQuery, symbol_table: &SymbolTable) -> Result<i128, TypeError> {
+    let ty: Type = Type::from_specifier(&query.ty, symbol_table)?;
+    let data_layout = symbol_table.data_layout;
+    Ok(match &query.kind {
+        expression::TypeQueryKind::Size => data_layout.layout_of(&ty, symbol_table)?.size,
+        expression::TypeQueryKind::Align => data_layout.layout_of(&ty, symbol_table)?.align,
+        expression::TypeQueryKind::Offset{ field, .. } => data_layout.offset_of(&ty, &field.name, symbol_table)?
+    } as i128)
+}
+
+// Checks that the condition of an assertion or contract is a bool
+fn check_condition<'a>(token: &Token, condition: &'a NodeType, symbol_table: &mut SymbolTable<'a>) -> Result<(), TypeError> {
+    let ty: Type = value_type(condition, symbol_table)?;
+    if ty.ty != FundamentalType::Boolean {
+        return Err(TypeError{ text: format!("'{}' expects a bool condition, got '{}'", token.value, ty) });
+    }
+    Ok(())
+}
+
+// Whether a node is a string literal without interpolation
+fn is_string_literal(node: &NodeType) -> bool {
+    match node {
+        NodeType::Term(term) => is_string_literal(&term.node),
+        NodeType::Str(string) => string.segments
+            .iter()
+            .all(|segment| matches!(segment, expression::StringSegment::Text(_))),
+        _ => false
+    }
+}
+
+// Binds the variables of a pattern to the parts of a value of type 'ty'
+fn bind_pattern(pattern: &node::Pattern, ty: &Type, symbol_table: &mut SymbolTable) -> Result<(), TypeError> {
+    bind_parts(pattern, ty, symbol_table, &mut vec!())
+}
+
+// 'names' holds the variables the pattern bound so far, as each may only be bound once
+fn bind_parts(pattern: &node::Pattern, ty: &Type, symbol_table: &mut SymbolTable, names: &mut Vec<String>) -> Result<(), TypeError> {
+    use node::PatternKind;
+    match &pattern.kind {
+        PatternKind::Identifier(id) => bind_variable(id, ty, symbol_table, names),
+        PatternKind::Wildcard(_) => Ok(()),
+        PatternKind::Tuple{ elements, .. } => {
+            let types = match &ty.ty {
+                FundamentalType::Tuple(types) => types,
+                _ => return Err(TypeError{ text: format!("Tuple pattern '{}' at {} cannot match a value of type '{}'", pattern, pattern.span, ty) })
+            };
+            if elements.len() != types.len() {
+                return Err(TypeError{ text: format!("Tuple pattern '{}' at {} has {} elements, but '{}' has {}", pattern, pattern.span, elements.len(), ty, types.len()) });
+            }
+            for (element, ty) in elements.iter().zip(types) {
+                bind_parts(element, ty, symbol_table, names)?;
+            }
+            Ok(())
+        },
+        PatternKind::Struct{ id, fields, rest, .. } => {
+            match &ty.ty {
+                FundamentalType::DefinedType(name) if *name == id.name => {},
+                _ => return Err(TypeError{ text: format!("Struct pattern '{}' at {} cannot match a value of type '{}'", pattern, pattern.span, ty) })
+            }
+            let structure = symbol_table.structure(&id.name)?;
+
+            let mut bound: Vec<&str> = vec!();
+            for field in fields {
+                let field_ty: Type = match structure.field(&field.id.name) {
+                    Some(field_ty) => field_ty.clone(),
+                    None => return Err(TypeError{ text: format!("Struct '{}' has no field '{}', at {}", id.name, field.id.name, field.span) })
+                };
+                if bound.contains(&field.id.name.as_str()) {
+                    return Err(TypeError{ text: format!("Field '{}' is bound more than once in pattern '{}' at {}", field.id.name, pattern, field.span) });
+                }
+                bound.push(&field.id.name);
+
+                // Shorthand fields bind a variable of the same name
+                match &field.pattern {
+                    Some(field_pattern) => bind_parts(field_pattern, &field_ty, symbol_table, names)?,
+                    None => bind_variable(&field.id, &field_ty, symbol_table, names)?
+                }
+            }
+
+            let missing: Vec<&str> = structure.fields()
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .filter(|name| !bound.contains(name))
+                .collect();
+            if rest.is_none() && !missing.is_empty() {
+                return Err(TypeError{ text: format!("Pattern '{}' at {} does not mention field(s) {} of '{}', use '..' to ignore them", pattern, pattern.span, missing.join(", "), id.name) });
+            }
+            Ok(())
+        }
+    }
+}
+
+fn bind_variable(id: &expression::Identifier, ty: &Type, symbol_table: &mut SymbolTable, names: &mut Vec<String>) -> Result<(), TypeError> {
+    if names.contains(&id.name) {
+        return Err(TypeError{ text: format!("Variable '{}' is bound more than once in the same pattern, at {}", id.name, id.span) });
+    }
+    names.push(id.name.clone());
+    symbol_table.initialise_variable(id, ty.clone());
+    Ok(())
 }
 
 impl fmt::Display for Type {
@@ -68,35 +488,67 @@ impl fmt::Display for Type {
 }
 
 pub trait TypeCheck {
-    fn check(&self, symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError>;
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError>;
+}
+
+// Type of an expression that must produce a value
+pub(crate) fn value_type<'a>(node: &'a NodeType, symbol_table: &mut SymbolTable<'a>) -> Result<Type, TypeError> {
+    // Nodes outlive the table, so no other node is at the same address
+    if let Some(ty) = symbol_table.types.borrow().get(&(node as *const NodeType)) {
+        return Ok(ty.clone());
+    }
+    let ty = node.check(symbol_table)?
+        .ok_or_else(|| TypeError{ text: "Expected a value, but the expression has none".to_string() })?;
+    symbol_table.types.borrow_mut().insert(node, ty.clone());
+    Ok(ty)
 }
 
 impl TypeCheck for NodeType {
-    fn check(&self, symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
         use NodeType::*;
         match self {
             Token(token) => token.check(symbol_table),
 
             BinaryOperator(binary_operator) => binary_operator.check(symbol_table),
             Boolean(boolean) => boolean.check(symbol_table),
+            Call(call) => call.check(symbol_table),
+            Array(array) => array.check(symbol_table),
             Character(character) => character.check(symbol_table),
             Float(float) => float.check(symbol_table),
             Integer(integer) => integer.check(symbol_table),
             Identifier(identifier) => identifier.check(symbol_table),
+            If(if_expr) => if_expr.check(symbol_table),
+            Index(index) => index.check(symbol_table),
+            Member(member) => member.check(symbol_table),
+            New(new) => new.check(symbol_table),
+            Slice(slice) => slice.check(symbol_table),
             Str(string) => string.check(symbol_table),
+            Try(try_expr) => try_expr.check(symbol_table),
+            Dereference(deref) => deref.check(symbol_table),
+            Unsafe(unsafe_block) => unsafe_block.check(symbol_table),
+            Tuple(tuple) => tuple.check(symbol_table),
+            TypeQuery(query) => query.check(symbol_table),
 
             Block(block) => block.check(symbol_table),
             File(file) => file.check(symbol_table),
             Output(output) => output.check(symbol_table),
             ParameterList(parameter_list) => parameter_list.check(symbol_table),
             Parameter(parameter) => parameter.check(symbol_table),
+            Pattern(pattern) => pattern.check(symbol_table),
             Term(term) => term.check(symbol_table),
             TypeSpecifier(type_specifier) => type_specifier.check(symbol_table),
 
+            Assert(assert_statement) => assert_statement.check(symbol_table),
+            Defer(defer_statement) => defer_statement.check(symbol_table),
+            Delete(delete_statement) => delete_statement.check(symbol_table),
             Expression(expression) => expression.check(symbol_table),
             Signature(signature) => signature.check(symbol_table),
             FunctionDefinition(function_definition) => function_definition.check(symbol_table),
+            FunctionDeclaration(function_declaration) => function_declaration.check(symbol_table),
             Return(return_statement) => return_statement.check(symbol_table),
+            StructDefinition(struct_definition) => struct_definition.check(symbol_table),
+            TypeAlias(type_alias) => type_alias.check(symbol_table),
+            ConstantDefinition(constant_definition) => constant_definition.check(symbol_table),
             VariableDeclaration(variable_declaration) => variable_declaration.check(symbol_table),
             VariableInitialisation(variable_initialisation) => variable_initialisation.check(symbol_table)
         }
@@ -104,309 +556,1217 @@ impl TypeCheck for NodeType {
 }
 
 impl TypeCheck for Token {
-    fn check(&self, _symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
+    fn check<'a>(&'a self, _symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
         Ok(None)
     }
 }
 
 impl TypeCheck for expression::Boolean {
-    fn check(&self, _symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
+    fn check<'a>(&'a self, _symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
         Ok(Some(Type{ ty: FundamentalType::Boolean }))
     }
 }
 
 impl TypeCheck for expression::Character {
-    fn check(&self, _symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
+    fn check<'a>(&'a self, _symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
         Ok(Some(Type{ ty: FundamentalType::Character }))
     }
 }
 
 impl TypeCheck for expression::Float {
-    fn check(&self, _symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
+    fn check<'a>(&'a self, _symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
         Ok(Some(Type{ ty: FundamentalType::Float }))
     }
 }
 
 impl TypeCheck for expression::Integer {
-    fn check(&self, _symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
+    fn check<'a>(&'a self, _symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
         Ok(Some(Type{ ty: FundamentalType::Integer }))
     }
 }
 
 impl TypeCheck for expression::Str {
-    fn check(&self, _symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        for segment in &self.segments {
+            if let expression::StringSegment::Interpolation(expr) = segment {
+                let ty: Type = value_type(expr, symbol_table)?;
+                if !ty.is_formattable() {
+                    return Err(TypeError{ text: format!("Cannot format a value of type '{}' into a string", ty) });
+                }
+            }
+        }
         Ok(Some(Type{ ty: FundamentalType::String }))
     }
 }
 
 impl TypeCheck for expression::Identifier {
-    fn check(&self, symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
-        symbol_table.variable_type(&self)
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        symbol_table.variable_type(self)
+    }
+}
+
+impl TypeCheck for expression::Array {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        let mut element = Type{ ty: FundamentalType::Never };
+        for expr in &self.elements {
+            let ty: Type = value_type(expr, symbol_table)?;
+            element = match element.common_type(&ty) {
+                Some(common) => common,
+                None => return Err(TypeError{ text: format!("Array elements must have the same type. Expected '{}', got '{}'", element, ty) })
+            };
+        }
+        Ok(Some(Type{ ty: FundamentalType::Array(Box::new(element), self.elements.len() as u64) }))
+    }
+}
+
+impl TypeCheck for expression::Tuple {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        let elements: Vec<Type> = self.elements
+            .iter()
+            .map(|expr| value_type(expr, symbol_table))
+            .collect::<Result<_, _>>()?;
+        Ok(Some(Type{ ty: FundamentalType::Tuple(elements) }))
+    }
+}
+
+impl TypeCheck for expression::TypeQuery {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        type_query_value(self, symbol_table)?;
+        Ok(Some(Type{ ty: FundamentalType::Integer }))
+    }
+}
+
+// Checks an index or slice bound, returning its value if it is known at compile time
+fn check_array_index<'a>(node: &'a NodeType, symbol_table: &mut SymbolTable<'a>) -> Result<Option<i128>, TypeError> {
+    let ty: Type = value_type(node, symbol_table)?;
+    if !ty.ty.is_integer() {
+        return Err(TypeError{ text: format!("Array index must be an integer, got '{}'", ty) });
+    }
+    Ok(constant_integer(node, symbol_table))
+}
+
+impl TypeCheck for expression::Index {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        let ty: Type = value_type(&self.expr, symbol_table)?;
+        let index = check_array_index(&self.index, symbol_table)?;
+
+        match ty.ty {
+            FundamentalType::Array(element, length) => {
+                // Constant indices are checked here instead of at runtime
+                if let Some(index) = index {
+                    if index < 0 || index >= length as i128 {
+                        return Err(TypeError{ text: format!("Index {} is out of bounds for '{}'", index, Type{ ty: FundamentalType::Array(element, length) }) });
+                    }
+                }
+                Ok(Some(*element))
+            },
+            FundamentalType::Slice(element) => Ok(Some(*element)),
+            _ => Err(TypeError{ text: format!("Cannot index into '{}'", ty) })
+        }
     }
 }
 
-pub fn binary_operator_prec(prec: Vec<FundamentalType>, left: &Type, right: &Type) -> Result<Option<Type>, TypeError> {
-    if prec.contains(&left.ty) && prec.contains(&right.ty) {
-        let left_prec = prec
-                        .iter()
-                        .position(|t| t == &left.ty);
-        let right_prec = prec
-                        .iter()
-                        .position(|t| t == &right.ty);
+impl TypeCheck for expression::Slice {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        let ty: Type = value_type(&self.expr, symbol_table)?;
+        let start = match &self.start {
+            Some(start) => check_array_index(start, symbol_table)?,
+            None => Some(0)
+        };
+        let end = match &self.end {
+            Some(end) => check_array_index(end, symbol_table)?,
+            None => None
+        };
+
+        let element = match ty.ty {
+            FundamentalType::Array(ref element, length) => {
+                let end = if self.end.is_none() { Some(length as i128) } else { end };
+                for bound in [start, end].into_iter().flatten() {
+                    if bound < 0 || bound > length as i128 {
+                        return Err(TypeError{ text: format!("Slice bound {} is out of bounds for '{}'", bound, ty) });
+                    }
+                }
+                element.clone()
+            },
+            FundamentalType::Slice(ref element) => element.clone(),
+            _ => return Err(TypeError{ text: format!("Cannot slice '{}'", ty) })
+        };
+        if let (Some(start), Some(end)) = (start, end) {
+            if start > end {
+                return Err(TypeError{ text: format!("Slice start {} is greater than its end {}", start, end) });
+            }
+        }
+
+        Ok(Some(Type{ ty: FundamentalType::Slice(element) }))
+    }
+}
 
-        if left_prec < right_prec {
-            return Ok(Some((*left).clone()));
+impl TypeCheck for expression::Member {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        let ty: Type = value_type(&self.expr, symbol_table)?;
+        match (&ty.ty, self.id.name.as_str()) {
+            // Lengths are used like integer literals, taking the integer type they are combined with
+            (FundamentalType::Array(..), "len") | (FundamentalType::Slice(_), "len") => Ok(Some(Type{ ty: FundamentalType::Integer })),
+            (FundamentalType::DefinedType(name), field) => {
+                let structure = symbol_table.structure(name)?;
+                match structure.field(field) {
+                    Some(field_ty) => Ok(Some(field_ty.clone())),
+                    None => Err(TypeError{ text: format!("'{}' has no member '{}'", ty, field) })
+                }
+            },
+            _ => Err(TypeError{ text: format!("'{}' has no member '{}'", ty, self.id.name) })
         }
-        else if left_prec > right_prec {
-            return Ok(Some((*right).clone()));
+    }
+}
+
+impl TypeCheck for expression::If {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        let condition: Type = value_type(&self.condition, symbol_table)?;
+        if condition.ty != FundamentalType::Boolean {
+            return Err(TypeError{ text: format!("'if' condition must be 'bool', got '{}'", condition) });
         }
-        else {
-            return Ok(Some((*left).clone()));
+
+        let then_ty = self.block.check(symbol_table)?;
+        let else_ty = match &self.else_branch {
+            Some(else_branch) => else_branch.check(symbol_table)?,
+            None => {
+                if let Some(ty) = then_ty {
+                    return Err(TypeError{ text: format!("'if' without 'else' cannot produce a value of type '{}'", ty) });
+                }
+                return Ok(None);
+            }
+        };
+
+        // Both branches must produce the same type of value, or no value at all
+        match (then_ty, else_ty) {
+            (None, None) => Ok(None),
+            (Some(then_ty), Some(else_ty)) => match then_ty.common_type(&else_ty) {
+                Some(ty) => Ok(Some(ty)),
+                None => Err(TypeError{ text: format!("'if' and 'else' branches have different types: '{}' and '{}'", then_ty, else_ty) })
+            },
+            _ => Err(TypeError{ text: "'if' and 'else' branches must both produce a value or neither".to_string() })
         }
     }
+}
 
-    return Ok(None)
+impl TypeCheck for expression::UnsafeBlock {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        let symbol_table: &mut SymbolTable = &mut symbol_table.clone();
+        symbol_table.in_unsafe = true;
+        self.block.check(symbol_table)
+    }
 }
 
-impl TypeCheck for expression::BinaryOperator {
-    fn check(&self, symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
-        let left = match self.left.check(symbol_table) {
-                Ok(l) => l,
-                Err(error) => panic!("{}", error)
+impl TypeCheck for expression::Dereference {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        let ty: Type = value_type(&self.expr, symbol_table)?;
+        match ty.ty {
+            FundamentalType::Pointer(pointee) => {
+                symbol_table.require_unsafe("Dereferencing a raw pointer", self.span)?;
+                Ok(Some(*pointee))
+            },
+            _ => Err(TypeError{ text: format!("Cannot dereference a value of type '{}', at {}", ty, self.span) })
+        }
+    }
+}
+
+impl TypeCheck for expression::New {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        let ty: Type = Type::from_specifier(&self.ty, symbol_table)?;
+
+        // Arrays are allocated zeroed and returned as a pointer to their first element
+        if let Some(length) = &self.length {
+            let length_ty: Type = value_type(length, symbol_table)?;
+            if !length_ty.ty.is_integer() {
+                return Err(TypeError{ text: format!("Array length must be an integer, got '{}'", length_ty) });
+            }
+            return Ok(Some(Type{ ty: FundamentalType::Pointer(Box::new(ty)) }));
+        }
+
+        let mut args: Vec<Type> = vec!();
+        for arg in &self.args {
+            args.push(value_type(arg, symbol_table)?);
+        }
+
+        // Fundamental types are constructed from an optional initial value, and structs by their
+        // '__init__', which until structs can define their own takes every field in order
+        match (&ty.ty, args.as_slice()) {
+            (FundamentalType::DefinedType(name), _) => {
+                let structure = symbol_table.structure(name)?;
+                if !args.is_empty() && args.len() != structure.fields().len() {
+                    return Err(TypeError{ text: format!("'new {}' expects {} arguments, one for each field, got {}", ty, structure.fields().len(), args.len()) });
+                }
+                for ((arg, value), (field, field_ty)) in self.args.iter().zip(&args).zip(structure.fields()) {
+                    if !field_ty.is_compatible(value) {
+                        return Err(TypeError{ text: format!("Type mismatch for field '{}' of '{}'. Expected '{}', got '{}'", field, ty, field_ty, value) });
+                    }
+                    check_literal(arg, value, field_ty, symbol_table)?;
+                }
+            },
+            (_, []) => {},
+            (_, [value]) if ty.is_compatible(value) => check_literal(&self.args[0], value, &ty, symbol_table)?,
+            (_, [value]) => {
+                return Err(TypeError{ text: format!("Type mismatch. Expected '{}', got '{}'", ty, value) });
+            },
+            _ => return Err(TypeError{ text: format!("'new {}' expects at most 1 argument, got {}", ty, args.len()) })
+        }
+
+        Ok(Some(Type{ ty: FundamentalType::Pointer(Box::new(ty)) }))
+    }
+}
+
+impl TypeCheck for expression::Call {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        let mut args: Vec<Type> = vec!();
+        for arg in &self.args {
+            args.push(value_type(arg, symbol_table)?);
+        }
+
+        // Built-in Result constructors
+        if self.id.name == "Ok" || self.id.name == "Err" {
+            if args.len() != 1 {
+                return Err(TypeError{ text: format!("'{}' expects 1 argument, got {}", self.id.name, args.len()) });
+            }
+            let arg = Box::new(args.remove(0));
+            let never = Box::new(Type{ ty: FundamentalType::Never });
+            return Ok(Some(Type{ ty: match self.id.name.as_str() {
+                "Ok" => FundamentalType::Result(arg, never),
+                _ => FundamentalType::Result(never, arg)
+            }}));
+        }
+
+        // Integer arithmetic with a chosen overflow behaviour, e.g. 'wrapping_add(a, b)'
+        if let Some((behaviour, _)) = overflow::intrinsic(&self.id.name) {
+            if args.len() != 2 {
+                return Err(TypeError{ text: format!("'{}' expects 2 arguments, got {}", self.id.name, args.len()) });
             }
-            .unwrap();
+            let ty: Type = match args[0].arithmetic_type(&args[1]) {
+                Some(ty) if ty.ty.is_integer() => ty,
+                _ => return Err(TypeError{ text: format!("'{}' expects two integers of the same type, got '{}' and '{}'", self.id.name, args[0], args[1]) })
+            };
+            for (arg, arg_ty) in self.args.iter().zip(&args) {
+                check_literal(arg, arg_ty, &ty, symbol_table)?;
+            }
+            return Ok(Some(match behaviour {
+                Overflow::Checked => Type{ ty: FundamentalType::Result(Box::new(ty), Box::new(Type{ ty: FundamentalType::String })) },
+                _ => ty
+            }));
+        }
+
+        let function = symbol_table.function(&self.id)?;
+        let arity_matches = if function.is_variadic() {
+            args.len() >= function.params().len()
+        } else {
+            args.len() == function.params().len()
+        };
+        if !arity_matches {
+            let at_least = if function.is_variadic() { "at least " } else { "" };
+            return Err(TypeError{ text: format!("'{}' expects {}{} arguments, got {}", self.id.name, at_least, function.params().len(), args.len()) });
+        }
+        if function.is_unsafe() {
+            let kind = if function.is_external() { "extern" } else { "unsafe" };
+            symbol_table.require_unsafe(&format!("Calling the {} procedure '{}'", kind, self.id.name), self.span)?;
+        }
+        for (((name, param_ty), arg_ty), arg) in function.params().iter().zip(&args).zip(&self.args) {
+            // String literals are stored with a terminating NUL, so C procedures can take them as '*char'
+            let c_string = function.is_external()
+                && param_ty.ty == FundamentalType::Pointer(Box::new(Type{ ty: FundamentalType::Character }))
+                && is_string_literal(arg);
+            if !param_ty.is_compatible(arg_ty) && !c_string {
+                return Err(TypeError{ text: format!("Type mismatch for parameter '{}' of '{}'. Expected '{}', got '{}'", name, self.id.name, param_ty, arg_ty) });
+            }
+            check_literal(arg, arg_ty, param_ty, symbol_table)?;
+        }
+        // Extra arguments of a variadic call are passed with the C default argument promotions
+        for (arg_ty, arg) in args.iter().zip(&self.args).skip(function.params().len()) {
+            if arg_ty.promote_variadic().is_none() && !is_string_literal(arg) {
+                return Err(TypeError{ text: format!("Cannot pass a value of type '{}' as an extra argument of '{}'", arg_ty, self.id.name) });
+            }
+        }
+
+        Ok(Some(function.output().clone()))
+    }
+}
+
+impl TypeCheck for expression::Try {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        if symbol_table.in_defer {
+            return Err(TypeError{ text: "'try' is not allowed inside a deferred statement".to_string() });
+        }
+
+        let ty: Type = value_type(&self.expr, symbol_table)?;
+        let (value, error) = match ty.ty {
+            FundamentalType::Result(value, error) => (value, error),
+            _ => return Err(TypeError{ text: format!("'try' expects a Result, got '{}'", ty) })
+        };
+
+        // The error is returned as is, so the procedure must return a compatible Result
+        match &symbol_table.output {
+            Some(Type{ ty: FundamentalType::Result(_, output_error) }) if output_error.is_compatible(&error) => Ok(Some(*value)),
+            Some(output) => Err(TypeError{ text: format!("'try' returns an error of type '{}' from a procedure returning '{}'", error, output) }),
+            None => Err(TypeError{ text: "'try' outside of a procedure".to_string() })
+        }
+    }
+}
+
+// Type named on the right of 'as', where '*T' parses as a dereference of T
+pub(crate) fn cast_target(node: &NodeType, symbol_table: &SymbolTable) -> Result<Type, TypeError> {
+    match node {
+        NodeType::Identifier(id) => Type::from_name(id, symbol_table),
+        NodeType::Term(term) => cast_target(&term.node, symbol_table),
+        NodeType::Dereference(deref) => cast_target(&deref.expr, symbol_table)
+            .map(|pointee| Type{ ty: FundamentalType::Pointer(Box::new(pointee)) }),
+        _ => Err(TypeError{ text: "Type specifier expected after 'as'".to_string() })
+    }
+}
+
+impl TypeCheck for expression::BinaryOperator {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        let left = value_type(&self.left, symbol_table)?;
         let right: Type;
         if self.op.value.as_str() == "as" {
-            use NodeType::*;
-            match &*self.right {
-                Identifier(id) => {
-                    right = Type::from_id(&id);
-                },
-                _ => return Err(TypeError{ text: format!("Type specifier expected after 'as'") })
+            right = cast_target(&self.right, symbol_table)?;
+            if !(right.is_compatible(&left) || left.is_castable() && right.is_castable()) {
+                return Err(TypeError{ text: format!("Cannot cast '{}' to '{}'", left, right) });
             }
-            
-        } else {
-            right = match self.right.check(symbol_table) {
-                Ok(r) => r,
-                Err(error) => panic!("{}", error)
+            // Casts between pointers and integers forge or expose addresses
+            let is_pointer = |ty: &Type| matches!(ty.ty, FundamentalType::Pointer(_));
+            if is_pointer(&left) != is_pointer(&right) {
+                symbol_table.require_unsafe(&format!("Casting '{}' to '{}'", left, right), self.span)?;
             }
-            .unwrap();
+        } else {
+            right = value_type(&self.right, symbol_table)?;
         }
 
         match self.op.value.as_str() {
-            "+" | "-" => {
-                let prec = vec![
-                    FundamentalType::Float,
-                    FundamentalType::Integer,
-                    FundamentalType::Character,
-                ];
-
-                let prec_type = binary_operator_prec(prec, &left, &right);
-                if !prec_type.as_ref().unwrap().is_none() {
-                    return prec_type;
+            "+" | "-" if matches!(left.ty, FundamentalType::Pointer(_)) || matches!(right.ty, FundamentalType::Pointer(_)) => {
+                symbol_table.require_unsafe("Pointer arithmetic", self.span)?;
+                match (&left.ty, &right.ty) {
+                    (FundamentalType::Pointer(_), offset) if offset.is_integer() => Ok(Some(left)),
+                    (offset, FundamentalType::Pointer(_)) if offset.is_integer() && self.op.value == "+" => Ok(Some(right)),
+                    // Distance between two pointers, in elements
+                    (FundamentalType::Pointer(_), FundamentalType::Pointer(_)) if self.op.value == "-" && left.is_compatible(&right) => {
+                        Ok(Some(Type{ ty: FundamentalType::Isize }))
+                    },
+                    _ => Err(TypeError{ text: format!("Invalid operand types '{}' and '{}' for operator '{}'", left, right, self.op.value) })
+                }
+            },
+            "+" | "-" | "*" | "/" | "%" | "**" => {
+                if let Some(ty) = left.arithmetic_type(&right) {
+                    check_literal(&self.left, &left, &ty, symbol_table)?;
+                    check_literal(&self.right, &right, &ty, symbol_table)?;
+                    return Ok(Some(ty));
                 }
 
-                if self.op.value == "+" {
-                    if left.ty == FundamentalType::String || right.ty == FundamentalType::String {
-                        return Ok(Some(Type{ ty: FundamentalType::String }));
-                    }
+                // Strings are concatenated with '+' to values that can be formatted, and repeated
+                // with '*' a number of times
+                let string: Option<&Type> = match (&left.ty, &right.ty) {
+                    (FundamentalType::String, _) => Some(&right),
+                    (_, FundamentalType::String) => Some(&left),
+                    _ => None
+                };
+                match (self.op.value.as_str(), string) {
+                    ("+", Some(other)) if other.is_formattable() => return Ok(Some(Type{ ty: FundamentalType::String })),
+                    ("*", Some(other)) if other.ty.is_integer() => return Ok(Some(Type{ ty: FundamentalType::String })),
+                    _ => {}
                 }
 
-                Err(TypeError{ text: format!("Invalid operand type for operator '{}'", self.op.value) })
+                Err(TypeError{ text: format!("Invalid operand types '{}' and '{}' for operator '{}'", left, right, self.op.value) })
             },
-            "*" | "/" | ">" | "<" | ">=" | "<=" => {
-                let prec = vec![
-                    FundamentalType::Float,
-                    FundamentalType::Integer,
-                    FundamentalType::Character,
-                ];
-
-                let prec_type = binary_operator_prec(prec, &left, &right);
-                if !prec_type.as_ref().unwrap().is_none() {
-                    return prec_type;
+            ">" | "<" | ">=" | "<=" => {
+                if let Some(ty) = left.arithmetic_type(&right) {
+                    check_literal(&self.left, &left, &ty, symbol_table)?;
+                    check_literal(&self.right, &right, &ty, symbol_table)?;
+                    return Ok(Some(Type{ ty: FundamentalType::Boolean }));
                 }
 
-                if self.op.value == "*" {
-                    if left.ty == FundamentalType::String || right.ty == FundamentalType::String {
-                        return Ok(Some(Type{ ty: FundamentalType::String }));
-                    }
+                Err(TypeError{ text: format!("Invalid operand types '{}' and '{}' for operator '{}'", left, right, self.op.value) })
+            },
+            "==" | "!=" => {
+                if left.arithmetic_type(&right).is_some() || left.common_type(&right).is_some() {
+                    return Ok(Some(Type{ ty: FundamentalType::Boolean }));
                 }
 
-                Err(TypeError{ text: format!("Invalid operand type for operator '{}'", self.op.value) })
+                Err(TypeError{ text: format!("Cannot compare '{}' with '{}'", left, right) })
             },
-            "&&" | "||" | "==" | "!=" => {
+            "&&" | "||" => {
                 if left.ty == FundamentalType::Boolean && right.ty == FundamentalType::Boolean {
                     return Ok(Some(Type{ ty: FundamentalType::Boolean })); 
                 }
 
                 Err(TypeError{ text: format!("Invalid operand type for operator '{}'", self.op.value) })
-            }
+            },
+            // The shift amount may be any integer, the result has the type of the shifted value
+            "<<" | ">>" => {
+                if left.ty.is_integer() && right.ty.is_integer() {
+                    return Ok(Some(left));
+                }
+
+                Err(TypeError{ text: format!("Invalid operand types '{}' and '{}' for operator '{}'", left, right, self.op.value) })
+            },
             "as" => {
                 Ok(Some(right))
             },
             _ => {
-                let prec = vec![
-                    FundamentalType::Float,
-                    FundamentalType::Integer,
-                    FundamentalType::Character,
-                ];
-
-                let prec_type = binary_operator_prec(prec, &left, &right);
-                if !prec_type.as_ref().unwrap().is_none() {
-                    return prec_type;
+                match left.arithmetic_type(&right) {
+                    Some(ty) if ty.ty.is_integer() => Ok(Some(ty)),
+                    _ => Err(TypeError{ text: format!("Invalid operand types '{}' and '{}' for operator '{}'", left, right, self.op.value) })
                 }
-
-                Err(TypeError{ text: format!("Invalid operand type for operator '{}'", self.op.value) })
             }
         }
     }
 }
 
 impl TypeCheck for node::Block {
-    fn check(&self, symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        // Bindings made in the block do not outlive it
+        let symbol_table: &mut SymbolTable = &mut symbol_table.clone();
+
         for stmt in &self.stmts {
-            stmt.check(symbol_table)
-                .unwrap();
+            stmt.check(symbol_table)?;
+        }
+        match &self.tail {
+            Some(tail) => tail.check(symbol_table),
+            None => Ok(None)
         }
-        Ok(None)
     }
 }
 
 impl TypeCheck for node::File {
-    fn check(&self, symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
         for stmt in &self.stmts {
-            stmt.check(symbol_table)
-                .unwrap();   
+            stmt.check(symbol_table)?;
+            match &**stmt {
+                NodeType::FunctionDefinition(definition) => symbol_table.define_global(&definition.sig.id.name),
+                NodeType::FunctionDeclaration(declaration) => symbol_table.define_global(&declaration.sig.id.name),
+                NodeType::ConstantDefinition(definition) => symbol_table.define_global(&definition.id.name),
+                NodeType::StructDefinition(_) | NodeType::TypeAlias(_) => {},
+                stmt => return Err(global_statement(stmt))
+            }
         }
         Ok(None)
     }
 }
 
+// Programs start at their '#[entry]' procedure, so statements outside of a procedure would never run
+pub(crate) fn global_statement(stmt: &NodeType) -> TypeError {
+    let text = String::from("Only procedures, structs, type aliases and constants can be defined in the global scope");
+    TypeError{ text: match stmt.span() {
+        Some(span) => format!("{}, at {}", text, span),
+        None => text
+    }}
+}
+
 impl TypeCheck for node::Output {
-    fn check(&self, symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
-        self.ty.check(symbol_table)
-            .unwrap();
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        self.ty.check(symbol_table)?;
         Ok(None)
     }
 }
 
 impl TypeCheck for node::ParameterList {
-    fn check(&self, symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
         for (param, _) in &self.parameters {
-            param.check(symbol_table)
-                .unwrap();
+            param.check(symbol_table)?;
         }
         Ok(None)
     }
 }
 
 impl TypeCheck for node::Parameter {
-    fn check(&self, symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
-        let param_ty = self.ty.check(symbol_table)
-            .unwrap()
-            .unwrap();
-        symbol_table.initialise_variable(&self.id, param_ty);
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        let param_ty: Type = Type::from_specifier(&self.ty, symbol_table)?;
+        bind_pattern(&self.pattern, &param_ty, symbol_table)?;
+        Ok(None)
+    }
+}
+
+// Patterns are checked against the type of the value they bind, see bind_pattern
+impl TypeCheck for node::Pattern {
+    fn check<'a>(&'a self, _symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
         Ok(None)
     }
 }
 
 impl TypeCheck for node::Term {
-    fn check(&self, symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
         self.node.check(symbol_table)
     }
 }
 
 impl TypeCheck for node::TypeSpecifier {
-    fn check(&self, _symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
-        Ok(Some(Type::from_id(&self.id)))
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        Type::from_specifier(self, symbol_table).map(Some)
     }
 }
 
 impl TypeCheck for statement::ExpressionStatement {
-    fn check(&self, symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
-        self.expr.check(symbol_table)
-            .unwrap();
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        self.expr.check(symbol_table)?;
         Ok(None)
     }
 }
 
 impl TypeCheck for statement::function_definition::Signature {
-    fn check(&self, symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
-        self.params.check(symbol_table)
-            .unwrap();
-        self.output.check(symbol_table)
-            .unwrap();
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        self.params.check(symbol_table)?;
+        self.output.check(symbol_table)?;
         Ok(None)
     }
 }
 
 impl TypeCheck for statement::FunctionDefinition {
-    fn check(&self, symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
-        let symbol_table: &mut SymbolTable = &mut symbol_table.clone();
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        if self.sig.params.variadic.is_some() {
+            return Err(TypeError{ text: format!("'{}' cannot take '...', only 'extern' procedures can be variadic", self.sig.id.name) });
+        }
+
+        let params: Vec<(String, Type)> = self.sig.params.parameters
+            .iter()
+            .map(|(param, _)| Ok((param.pattern.to_string(), Type::from_specifier(&param.ty, symbol_table)?)))
+            .collect::<Result<_, TypeError>>()?;
+        let output: Type = Type::from_specifier(&self.sig.output.ty, symbol_table)?;
+        let unsafe_call = self.sig.unsafe_token.is_some();
+        symbol_table.define_function(&self.sig.id, params, output.clone(), unsafe_call);
+        if self.sig.const_token.is_some() {
+            symbol_table.const_procs.insert(self.sig.id.name.clone(), self);
+        } else {
+            symbol_table.const_procs.remove(&self.sig.id.name);
+        }
 
-        self.sig.check(symbol_table)
-            .unwrap();
-        // TODO: Handle checking of params and output of function signature
-        //       within this function so that all the types can be added to
-        //       the symbol table entry.
+        let symbol_table: &mut SymbolTable = &mut symbol_table.procedure_scope();
+        symbol_table.output = Some(output);
+        symbol_table.in_unsafe = unsafe_call;
 
-        self.block.check(symbol_table)
-            .unwrap();
+        self.sig.check(symbol_table)?;
+        for contract in &self.sig.contracts {
+            contract.check(&mut symbol_table.clone())?;
+        }
 
+        // The block is checked in the procedure's own scope, so the value it falls through with is
+        // checked like a returned one
+        for stmt in &self.block.stmts {
+            stmt.check(symbol_table)?;
+        }
+        if let Some(tail) = &self.block.tail {
+            if let Some(ty) = tail.check(symbol_table)? {
+                check_output(tail, &ty, symbol_table)?;
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+// Checked with the parameters in scope, and for 'ensures' the return value bound to 'result'
+impl TypeCheck for node::Contract {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        if self.kind == node::ContractKind::Ensures {
+            let output: Type = symbol_table.output.clone().unwrap();
+            symbol_table.initialise_variable(&result_identifier(self), output);
+        }
+        check_condition(&self.token, &self.condition, symbol_table)?;
+        // No call could satisfy a contract the constant folder finds to be always false. Conditions
+        // that depend on variables are only known at runtime, and 'assert(false)' marks code that
+        // must not be reached, so assertions are not folded
+        if let Ok(Value::Boolean(false)) = interpreter::evaluate(&self.condition, symbol_table) {
+            return Err(TypeError{ text: format!("{} `{}` is always false, at {}", self.kind.description(), self.text, self.token.span) });
+        }
+        Ok(None)
+    }
+}
+
+// Variable through which an 'ensures' clause refers to the return value
+pub(crate) fn result_identifier(contract: &node::Contract) -> expression::Identifier {
+    expression::Identifier{ name: String::from("result"), span: contract.span, global: false }
+}
+
+impl TypeCheck for statement::FunctionDeclaration {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        let params: Vec<(String, Type)> = self.sig.params.parameters
+            .iter()
+            .map(|(param, _)| Ok((param.pattern.to_string(), Type::from_specifier(&param.ty, symbol_table)?)))
+            .collect::<Result<_, TypeError>>()?;
+        let output: Type = Type::from_specifier(&self.sig.output.ty, symbol_table)?;
+
+        let external = self.extern_token.is_some();
+        // Aggregates are passed by address, which C procedures do not expect
+        if let Some(ty) = params.iter().map(|(_, ty)| ty).chain([&output]).find(|ty| external && !ty.is_scalar()) {
+            return Err(TypeError{ text: format!("The extern procedure '{}' cannot take or return '{}', as C has no such type", self.sig.id.name, ty) });
+        }
+        let variadic = self.sig.params.variadic.is_some();
+        if variadic && !external {
+            return Err(TypeError{ text: format!("'{}' can only take '...' if it is an 'extern' procedure", self.sig.id.name) });
+        }
+        symbol_table.declare_function(&self.sig.id, params, output, external, variadic, self.sig.unsafe_token.is_some());
         Ok(None)
     }
 }
 
 impl TypeCheck for statement::ReturnStatement {
-    fn check(&self, _symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
-        //  NOT IMPLEMENTED
-        Err(TypeError{ text: format!("Not yet implemented") })
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        if symbol_table.in_defer {
+            return Err(TypeError{ text: "'return' is not allowed inside a deferred statement".to_string() });
+        }
+
+        if symbol_table.output.is_none() {
+            return Err(TypeError{ text: "'return' outside of a procedure".to_string() });
+        }
+        let ty: Type = value_type(&self.expr, symbol_table)?;
+        check_output(&self.expr, &ty, symbol_table).map(|_| None)
+    }
+}
+
+// Checks a value of type 'ty' leaving the current procedure, by 'return' or by falling through its block
+fn check_output<'a>(node: &'a NodeType, ty: &Type, symbol_table: &SymbolTable<'a>) -> Result<(), TypeError> {
+    let output: &Type = symbol_table.output.as_ref().unwrap();
+    if !output.is_compatible(ty) {
+        return Err(TypeError{ text: format!("Type mismatch. Expected return type '{}', got '{}'", output, ty) });
+    }
+    check_literal(node, ty, output, symbol_table)
+}
+
+impl TypeCheck for statement::DeferStatement {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        if symbol_table.output.is_none() {
+            return Err(TypeError{ text: "'defer' is only allowed inside a procedure body".to_string() });
+        }
+
+        // Bindings made by the deferred statement do not leak into the block
+        let symbol_table: &mut SymbolTable = &mut symbol_table.clone();
+        symbol_table.in_defer = true;
+
+        self.stmt.check(symbol_table)
+            .map(|_| None)
+    }
+}
+
+impl TypeCheck for statement::AssertStatement {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        if symbol_table.output.is_none() {
+            return Err(TypeError{ text: "'assert' is only allowed inside a procedure body".to_string() });
+        }
+        check_condition(&self.assert_token, &self.condition, symbol_table)?;
+        if let Some(message) = &self.message {
+            let ty: Type = value_type(message, symbol_table)?;
+            if ty.ty != FundamentalType::String {
+                return Err(TypeError{ text: format!("'assert' expects a str message, got '{}'", ty) });
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl TypeCheck for statement::DeleteStatement {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        let ty: Type = value_type(&self.expr, symbol_table)?;
+        match ty.ty {
+            // Pointers returned by 'new [T; n]' free the whole array
+            FundamentalType::Pointer(_) => Ok(None),
+            _ => Err(TypeError{ text: format!("'delete' expects a pointer, got '{}'", ty) })
+        }
+    }
+}
+
+impl TypeCheck for statement::StructDefinition {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        // Declared without fields first, so fields can point to the struct itself
+        symbol_table.define_struct(&self.id, vec!());
+        let mut fields: Vec<(String, Type)> = vec!();
+        for field in &self.fields {
+            if fields.iter().any(|(name, _)| *name == field.id.name) {
+                return Err(TypeError{ text: format!("Field '{}' of '{}' is defined more than once", field.id.name, self.id.name) });
+            }
+            let ty: Type = Type::from_specifier(&field.ty, symbol_table)?;
+            if ty.contains(&self.id.name) {
+                return Err(TypeError{ text: format!("Field '{}' of '{}' contains '{}' itself, which needs a pointer", field.id.name, self.id.name, self.id.name) });
+            }
+            fields.push((field.id.name.clone(), ty));
+        }
+        symbol_table.define_struct(&self.id, fields);
+        Ok(None)
+    }
+}
+
+impl TypeCheck for statement::TypeAlias {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        if !matches!(Type::from_id(&self.id).ty, FundamentalType::DefinedType(_)) {
+            return Err(TypeError{ text: format!("'{}' is a built-in type and cannot be redefined, at {}", self.id.name, self.id.span) });
+        }
+        // Resolved before the alias is defined, so an alias cannot stand for itself
+        let ty: Type = Type::from_specifier(&self.ty, symbol_table)?;
+        symbol_table.define_alias(&self.id, ty);
+        Ok(None)
+    }
+}
+
+impl TypeCheck for statement::ConstantDefinition {
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        let mut ty: Type = value_type(&self.expr, symbol_table)?;
+        if let Some(spec) = &self.ty {
+            let spec_ty: Type = Type::from_specifier(spec, symbol_table)?;
+            if !spec_ty.is_compatible(&ty) {
+                return Err(TypeError{ text: format!("Type mismatch. Expected '{}', got '{}'", spec_ty, ty) });
+            }
+            ty = spec_ty;
+        }
+
+        let value: Value = interpreter::evaluate(&self.expr, symbol_table)?;
+        interpreter::check_range(&value, &ty, &symbol_table.data_layout)
+            .map_err(|e| TypeError{ text: format!("Constant '{}': {}, at {}", self.id.name, e.text, self.id.span) })?;
+        symbol_table.define_constant(&self.id, ty, value);
+        Ok(None)
     }
 }
 
 impl TypeCheck for statement::VariableDeclaration {
-    fn check(&self, symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
-        let ty: Type = self.ty.check(symbol_table)
-            .unwrap()
-            .unwrap();
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
+        let ty: Type = Type::from_specifier(&self.ty, symbol_table)?;
         symbol_table.declare_variable(&self.id, ty);
         Ok(None)
     }
 }
 
 impl TypeCheck for statement::VariableInitialisation {
-    fn check(&self, symbol_table: &mut SymbolTable) -> Result<Option<Type>, TypeError> {
-        let spec_ty = &self.ty.as_ref();
+    fn check<'a>(&'a self, symbol_table: &mut SymbolTable<'a>) -> Result<Option<Type>, TypeError> {
         let ty: Type;
-        if !spec_ty.is_none() {
-            ty = spec_ty.unwrap()
-                .check(symbol_table)
-                .unwrap()
-                .unwrap();
-            let val_ty: Type = self.expr.check(symbol_table)
-                .unwrap()
-                .unwrap();
-            if ty != val_ty {
+        if let Some(spec) = &self.ty {
+            ty = Type::from_specifier(spec, symbol_table)?;
+            let val_ty: Type = value_type(&self.expr, symbol_table)?;
+            if !ty.is_compatible(&val_ty) {
                 return Err(TypeError{ text: format!("Type mismatch. Expected '{}', got '{}'", ty, val_ty) });
             }
+            check_literal(&self.expr, &val_ty, &ty, symbol_table)?;
         }
         else {
-            ty = self.expr.check(symbol_table)
-                .unwrap()
-                .unwrap();
+            let val_ty: Type = value_type(&self.expr, symbol_table)?;
+            ty = val_ty.concrete();
+            check_literal(&self.expr, &val_ty, &ty, symbol_table)?;
         }
-        symbol_table.initialise_variable(&self.id, ty.clone());
+        bind_pattern(&self.pattern, &ty, symbol_table)?;
         Ok(None)
     }
 }
 
-pub fn annotate_file(tree: &node::File) -> Result<(Option<node::File>, SymbolTable), TypeError> {
-    let mut symbol_table: &mut SymbolTable = &mut SymbolTable{ symbols: HashMap::new() };
+pub fn annotate_file(tree: &node::File) -> Result<(Option<node::File>, SymbolTable<'_>), TypeError> {
+    annotate_file_for(tree, &Triple::host())
+}
+
+// Checks a file compiled for 'triple', whose layout rules sizeof, alignof and offsetof follow
+pub fn annotate_file_for<'a>(tree: &'a node::File, triple: &Triple) -> Result<(Option<node::File>, SymbolTable<'a>), TypeError> {
+    let symbol_table: &mut SymbolTable = &mut SymbolTable::for_target(triple);
     
-    tree.check(&mut symbol_table)
-        .unwrap();
+    tree.check(symbol_table)?;
     Ok((None, symbol_table.clone()))
 
         // Have to work out how to go about annotating the original AST and returning that
         // instead of just checking types 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use gecko_parser::ast::parse_gecko;
+
+    use super::TypeCheck;
+    use crate::{error::TypeError, symbol::SymbolTable};
+
+    fn check(source: &str) -> Result<(), TypeError> {
+        let file = parse_gecko(source).unwrap();
+        file.check(&mut SymbolTable::new()).map(|_| ())
+    }
+
+    fn error(source: &str) -> String {
+        check(source).unwrap_err().text
+    }
+
+    #[test]
+    fn defer() {
+        check("proc f() -> int { defer 1 + 2; defer let x: int = 3; return 4; }").unwrap();
+        // Bindings of a deferred statement do not leak into the block
+        assert!(check("proc f() -> int { defer let x: int = 3; return x; }").is_err());
+    }
+
+    #[test]
+    fn return_inside_defer() {
+        assert_eq!(error("proc f() -> int { defer return 1; return 2; }"), "'return' is not allowed inside a deferred statement");
+    }
+
+    #[test]
+    fn global_statements() {
+        assert_eq!(error("let x := 1;"), "Only procedures, structs, type aliases and constants can be defined in the global scope, at 1:1");
+        assert_eq!(error("proc f() -> int { return 1; }\nf();"), "Only procedures, structs, type aliases and constants can be defined in the global scope, at 2:1");
+    }
+
+    #[test]
+    fn defer_outside_procedure() {
+        assert_eq!(error("defer 1 + 2;"), "'defer' is only allowed inside a procedure body");
+    }
+
+    #[test]
+    fn return_type() {
+        check("proc f() -> bool { return true; }").unwrap();
+        assert_eq!(error("proc f() -> int { return true; }"), "Type mismatch. Expected return type 'i32', got 'bool'");
+    }
+
+    #[test]
+    fn literal_ranges() {
+        check("proc f(a: u8) -> u8 { let b: i8 = 0 - 128; return a + 255; }").unwrap();
+        assert_eq!(error("proc f() -> u8 { return 300; }"), "300 does not fit in 'u8', at 1:25");
+        assert_eq!(error("proc f() -> int { return 2147483648; }"), "2147483648 does not fit in 'i32', at 1:26");
+        assert_eq!(error("proc f() -> int { let x := 2147483647 + 1; return x; }"), "2147483648 does not fit in 'i32', at 1:28");
+        assert_eq!(error("proc f(a: u8) -> bool { return a < 256; }"), "256 does not fit in 'u8', at 1:36");
+        assert_eq!(error("proc f() -> [u8; 2] { let a: [u8; 2] = [1, 256]; return a; }"), "256 does not fit in 'u8', at 1:40");
+        assert_eq!(error("proc g(a: i8) -> i8 { return a; } proc f() -> i8 { return g(0 - 129); }"), "-129 does not fit in 'i8', at 1:61");
+    }
+
+    #[test]
+    fn annotate_reports_errors() {
+        let file = parse_gecko("proc f() -> int { return true; }").unwrap();
+        let text = super::annotate_file(&file).err().unwrap().text;
+        assert_eq!(text, "Type mismatch. Expected return type 'i32', got 'bool'");
+    }
+
+    #[test]
+    fn unknown_types() {
+        assert_eq!(error("let x: Foo = 1;"), "Unknown type 'Foo' at 1:8");
+        assert_eq!(error("let bot: Client;"), "Unknown type 'Client' at 1:10");
+        assert_eq!(error("proc f(x: Foo) -> int { return 1; }"), "Unknown type 'Foo' at 1:11");
+        assert_eq!(error("proc f() -> Foo { return 1; }"), "Unknown type 'Foo' at 1:13");
+    }
+
+    const PARSE: &str = "proc parse(n: int) -> Result<int, str> { return Ok(n); }";
+
+    #[test]
+    fn try_expression() {
+        check(&format!("{} proc twice(n: int) -> Result<int, str> {{ let x: int = try parse(n); return Ok(x * 2); }}", PARSE)).unwrap();
+        check(&format!("{} proc twice(n: int) -> Result<int, str> {{ let x: int = parse(n)? * 2; return Ok(x); }}", PARSE)).unwrap();
+    }
+
+    #[test]
+    fn try_requires_compatible_output() {
+        assert_eq!(
+            error(&format!("{} proc f() -> Result<int, bool> {{ return Ok(parse(1)?); }}", PARSE)),
+            "'try' returns an error of type 'str' from a procedure returning 'Result<i32, bool>'"
+        );
+        assert_eq!(
+            error(&format!("{} proc f() -> int {{ return try parse(1); }}", PARSE)),
+            "'try' returns an error of type 'str' from a procedure returning 'i32'"
+        );
+        assert_eq!(error("proc f() -> Result<int, str> { return Ok(1?); }"), "'try' expects a Result, got '{integer}'");
+    }
+
+    #[test]
+    fn try_inside_defer() {
+        assert_eq!(
+            error(&format!("{} proc f() -> Result<int, str> {{ defer parse(1)?; return Ok(1); }}", PARSE)),
+            "'try' is not allowed inside a deferred statement"
+        );
+    }
+
+    #[test]
+    fn arrays_and_slices() {
+        check("proc f() -> int { let a: [int; 3] = [1, 2, 3]; let s: [int] = a[1..]; return a[0] + s[1] + s.len + a[..2].len; }").unwrap();
+        // Arrays coerce to slices of the same element type
+        check("proc sum(values: [int]) -> int { return values[0]; } proc f() -> int { return sum([1, 2]); }").unwrap();
+        assert_eq!(error("proc f() -> int { return [1, true][0]; }"), "Array elements must have the same type. Expected '{integer}', got 'bool'");
+        assert_eq!(error("proc f() -> [int; 3] { return [1, 2]; }"), "Type mismatch. Expected return type '[i32; 3]', got '[{integer}; 2]'");
+    }
+
+    #[test]
+    fn constant_bounds() {
+        let array = "let a: [int; 3] = [1, 2, 3];";
+        assert_eq!(error(&format!("proc f() -> int {{ {} return a[3]; }}", array)), "Index 3 is out of bounds for '[i32; 3]'");
+        assert_eq!(error(&format!("proc f() -> int {{ {} return a[1..4].len; }}", array)), "Slice bound 4 is out of bounds for '[i32; 3]'");
+        assert_eq!(error(&format!("proc f() -> int {{ {} return a[2..1].len; }}", array)), "Slice start 2 is greater than its end 1");
+        // Slices only know their length at run time
+        check("proc f(s: [int]) -> int { return s[10]; }").unwrap();
+    }
+
+    #[test]
+    fn members() {
+        assert_eq!(error("proc f(s: [int]) -> int { return s.size; }"), "'[i32]' has no member 'size'");
+        assert_eq!(error("proc f(n: int) -> int { return n[0]; }"), "Cannot index into 'i32'");
+    }
+
+    #[test]
+    fn new_and_delete() {
+        check("proc f() -> *int { let p: *int = new int(7); delete p; return new int(); }").unwrap();
+        check("proc f(n: int) -> *bool { let p: *bool = new [bool; n * 2]; return p; }").unwrap();
+        assert_eq!(error("proc f() -> *int { return new int(true); }"), "Type mismatch. Expected 'i32', got 'bool'");
+        assert_eq!(error("proc f() -> *int { return new int(1, 2); }"), "'new i32' expects at most 1 argument, got 2");
+        assert_eq!(error("proc f() -> *int { return new [int; true]; }"), "Array length must be an integer, got 'bool'");
+
+        let point = "struct Point { x: int, y: u8 }";
+        check(&format!("{} proc f() -> *Point {{ let p: *Point = new Point(1, 2); delete p; return new Point(); }}", point)).unwrap();
+        assert_eq!(error(&format!("{} proc f() -> *Point {{ return new Point(1); }}", point)), "'new Point' expects 2 arguments, one for each field, got 1");
+        assert_eq!(error(&format!("{} proc f() -> *Point {{ return new Point(1, true); }}", point)), "Type mismatch for field 'y' of 'Point'. Expected 'u8', got 'bool'");
+        assert_eq!(error(&format!("{} proc f() -> *Point {{ return new Point(1, 256); }}", point)), "256 does not fit in 'u8', at 1:73");
+    }
+
+    #[test]
+    fn delete_requires_pointer() {
+        // Slices may view memory that 'new' did not allocate
+        assert_eq!(error("proc f(s: [int]) -> int { delete s; return 0; }"), "'delete' expects a pointer, got '[i32]'");
+        assert_eq!(error("proc f(n: int) -> int { delete n; return 0; }"), "'delete' expects a pointer, got 'i32'");
+    }
+
+    #[test]
+    fn interpolation() {
+        check(r#"proc f(x: int, ok: bool) -> str { return "x = {x}, sum = {x + 1}, {ok} {{braces}}"; }"#).unwrap();
+        check(r#"proc f(x: float) -> str { return "a" + x + true + "-" * 3; }"#).unwrap();
+        assert_eq!(
+            error(r#"proc f(x: int) -> str { return "{new int(x)}"; }"#),
+            "Cannot format a value of type '*i32' into a string"
+        );
+    }
+
+    #[test]
+    fn string_operators() {
+        assert_eq!(error(r#"unsafe proc f(p: *int) -> str { return "p = " + p; }"#), "Invalid operand types 'str' and '*i32' for operator '+'");
+        assert_eq!(error(r#"proc f() -> str { return "a" * "b"; }"#), "Invalid operand types 'str' and 'str' for operator '*'");
+    }
+
+    #[test]
+    fn block_expressions() {
+        check("proc f() -> int { let y: int = { let t: int = 3; t * t }; return y; }").unwrap();
+        check("proc f(c: bool, d: bool) -> int { let x := if c { 1 } else if d { 2 } else { 3 }; return x; }").unwrap();
+        // Bindings made in a block do not outlive it
+        assert!(check("proc f() -> int { let y: int = { let t: int = 3; t }; return t; }").is_err());
+    }
+
+    #[test]
+    fn block_values() {
+        // The value a procedure falls through with is checked like a returned one
+        check("proc f() -> i64 { let b: i64 = 2; b }").unwrap();
+        check("proc f(n: int) -> Result<int, str> { if n > 0 { Ok(n) } else { Err(\"negative\") } }").unwrap();
+        assert_eq!(error("proc f() -> int { true }"), "Type mismatch. Expected return type 'i32', got 'bool'");
+        assert_eq!(error("proc f(a: i64) -> i8 { a }"), "Type mismatch. Expected return type 'i8', got 'i64'");
+        assert_eq!(error("proc f() -> u8 { let a := 1; 300 }"), "300 does not fit in 'u8', at 1:30");
+        assert_eq!(
+            error("proc f() -> Result<int, str> { Ok(true) }"),
+            "Type mismatch. Expected return type 'Result<i32, str>', got 'Result<bool, never>'"
+        );
+    }
+
+    #[test]
+    fn if_branches() {
+        check("proc f(c: bool) -> int { if c { 1; } else { 2; } return 0; }").unwrap();
+        assert_eq!(error("proc f(c: bool) -> int { return if c { 1 }; }"), "'if' without 'else' cannot produce a value of type '{integer}'");
+        assert_eq!(
+            error("proc f(c: bool) -> int { return if c { 1 } else { true }; }"),
+            "'if' and 'else' branches have different types: '{integer}' and 'bool'"
+        );
+        assert_eq!(error("proc f(n: int) -> int { return if n { 1 } else { 2 }; }"), "'if' condition must be 'bool', got 'i32'");
+    }
+
+    #[test]
+    fn valueless_operands() {
+        assert_eq!(error("proc f() -> int { return {} + 1; }"), "Expected a value, but the expression has none");
+    }
+
+    const POINT: &str = "struct Point { x: int, y: (int, bool) }";
+
+    #[test]
+    fn patterns() {
+        check(&format!("{} proc f(Point {{ x, y: (a, _) }}: Point) -> int {{ return x + a; }}", POINT)).unwrap();
+        check(&format!("{} proc f(p: Point) -> bool {{ let Point {{ y: (_, b), .. }}: = p; return b; }}", POINT)).unwrap();
+        check("proc f(t: (int, (bool, int))) -> int { let (a, (_, c)): (int, (bool, int)) = t; return a + c; }").unwrap();
+    }
+
+    #[test]
+    fn pattern_shapes() {
+        let text = error("proc f((a, b, c): (int, int)) -> int { return a; }");
+        assert!(text.starts_with("Tuple pattern '(a, b, c)' at "), "{}", text);
+        assert!(text.ends_with(" has 3 elements, but '(i32, i32)' has 2"), "{}", text);
+
+        let text = error("proc f((a, b): int) -> int { return a; }");
+        assert!(text.ends_with("cannot match a value of type 'i32'"), "{}", text);
+
+        let text = error(&format!("{} proc f(Point {{ z }}: Point) -> int {{ return 0; }}", POINT));
+        assert!(text.starts_with("Struct 'Point' has no field 'z', at "), "{}", text);
+
+        let text = error(&format!("{} proc f(Point {{ x, x: _, .. }}: Point) -> int {{ return 0; }}", POINT));
+        assert!(text.starts_with("Field 'x' is bound more than once"), "{}", text);
+
+        assert_eq!(error("proc f() -> int { let (a, a) := (1, 2); return a; }"), "Variable 'a' is bound more than once in the same pattern, at 1:27");
+        let text = error(&format!("{} proc f((Point {{ x, .. }}, x): (Point, int)) -> int {{ return x; }}", POINT));
+        assert!(text.starts_with("Variable 'x' is bound more than once in the same pattern, at "), "{}", text);
+        check("proc f() -> int { let (a, _, _) := (1, 2, 3); let (a, b) := (a, 4); return a + b; }").unwrap();
+
+        let text = error(&format!("{} proc f(Point {{ x }}: Point) -> int {{ return x; }}", POINT));
+        assert!(text.ends_with("does not mention field(s) y of 'Point', use '..' to ignore them"), "{}", text);
+    }
+
+    #[test]
+    fn struct_fields() {
+        check(&format!("{} proc f(p: Point) -> int {{ return p.x; }}", POINT)).unwrap();
+        assert_eq!(error(&format!("{} proc f(p: Point) -> int {{ return p.z; }}", POINT)), "'Point' has no member 'z'");
+        assert_eq!(error("struct Twice { a: int, a: bool }"), "Field 'a' of 'Twice' is defined more than once");
+    }
+
+    #[test]
+    fn type_aliases() {
+        let source = "
+            type Meters: u16;
+            type Path: [Meters; 2];
+            proc f(path: Path) -> Meters { path[0] + path[1] }
+            proc g(n: int) -> (Meters, u16) {
+                type Steps: Meters;
+                let steps: Steps = n as Steps;
+                (steps, f([steps, 300]) + sizeof(Path) as Meters)
+            }
+        ";
+        check(source).unwrap();
+        assert_eq!(error("type Meters: u16; proc f() -> Meters { return true; }"), "Type mismatch. Expected return type 'u16', got 'bool'");
+        assert_eq!(error("type Meters: u16; proc f() -> Meters { return 70000; }"), "70000 does not fit in 'u16', at 1:47");
+        assert_eq!(error("type Loop: *Loop;"), "Unknown type 'Loop' at 1:13");
+        assert_eq!(error("type int: u8;"), "'int' is a built-in type and cannot be redefined, at 1:6");
+    }
+
+    #[test]
+    fn type_queries() {
+        let source = "
+            struct Pair { a: bool, b: int }
+            const SIZE := sizeof(Pair) * 2;
+            proc f(bytes: [char; SIZE + offsetof(Pair, b)]) -> [char; 20] { return bytes; }
+            proc g() -> [bool; alignof((bool, str))] { return [true, false, true, false, true, false, true, false]; }
+        ";
+        check(source).unwrap();
+        assert_eq!(error("struct Pair { a: bool } proc f() -> int { return offsetof(Pair, c); }"), "Struct 'Pair' has no field 'c'");
+        let text = error("proc f(n: int, a: [int; n]) -> int { return 0; }");
+        assert!(text.starts_with("The variable 'n' does not exist"), "{}", text);
+    }
+
+    #[test]
+    fn constants() {
+        check("const A := 4; const B := A * sizeof(int); proc f() -> [int; B] { return [A, A, A, A, A, A, A, A, A, A, A, A, A, A, A, A]; }").unwrap();
+        check("const A := true; const B := (A, \"b\"); proc f() -> (bool, str) { return B; }").unwrap();
+        // Diagnostics point at the operation that cannot run at compile time
+        assert_eq!(error("proc f(n: int) -> int { const A := n; return A; }"), "'n' is not a constant, so its value is not known at compile time, at 1:36");
+        assert_eq!(error("proc g() -> int { return 1; } const A := g();"), "'g' is not a 'const proc', so it cannot be called at compile time, at 1:42");
+        assert_eq!(error("const proc g() -> int { return 1 / 0; } const A := g();"), "Division by zero in '/', at 1:32");
+        assert_eq!(error("const proc g() -> *int { return new int(); } const A := g();"), "'new' cannot allocate memory at compile time, at 1:33");
+    }
+
+    #[test]
+    fn variadic_calls() {
+        let printf = "extern proc printf(fmt: *char, ...) -> int;";
+        check(&format!("{} unsafe proc f(p: *int) -> int {{ return printf(\"%d %f %c %p\", 1, 2.5, true, p); }}", printf)).unwrap();
+        check(&format!("{} unsafe proc f() -> int {{ return printf(\"%s\", \"text\"); }}", printf)).unwrap();
+        assert_eq!(error(&format!("{} unsafe proc f() -> int {{ return printf(); }}", printf)), "'printf' expects at least 1 arguments, got 0");
+        assert_eq!(
+            error(&format!("{} unsafe proc f(a: [int; 2]) -> int {{ return printf(\"%d\", a); }}", printf)),
+            "Cannot pass a value of type '[i32; 2]' as an extra argument of 'printf'"
+        );
+        assert_eq!(error("proc f(a: int, ...) -> int { return a; }"), "'f' cannot take '...', only 'extern' procedures can be variadic");
+        assert_eq!(error("proc f(a: int, ...) -> int;"), "'f' can only take '...' if it is an 'extern' procedure");
+        assert_eq!(error("extern proc puts(s: str) -> int;"), "The extern procedure 'puts' cannot take or return 'str', as C has no such type");
+        assert_eq!(error("extern proc pair() -> (int, int);"), "The extern procedure 'pair' cannot take or return '(i32, i32)', as C has no such type");
+    }
+
+    // Procedures defined in others see the symbols in scope, but not the variables of the
+    // procedure they are defined in
+    #[test]
+    fn nested_procedures() {
+        check("const K := 2; proc f(n: int) -> int { proc g(m: int) -> int { if m == 0 { 0 } else { g(m - 1) + K } } g(n) }").unwrap();
+        assert_eq!(error("proc f(n: int) -> int { proc g() -> int { n } g() }"), "The variable 'n' does not exist");
+    }
+
+    #[test]
+    fn casts() {
+        check("struct P { x: int } proc f(p: P, n: u8) -> f64 { let q: P = p as P; return n as char as f64 + q.x as f64; }").unwrap();
+        assert_eq!(error("proc f(n: int) -> int { return n as Foo; }"), "Unknown type 'Foo' at 1:37");
+        assert_eq!(error("proc f(n: int) -> int { return unsafe { n as *Foo } as int; }"), "Unknown type 'Foo' at 1:47");
+        assert_eq!(error("proc f(t: (int, int)) -> int { return t as int; }"), "Cannot cast '(i32, i32)' to 'i32'");
+        assert_eq!(error("proc f() -> int { return 1 as 2; }"), "Type specifier expected after 'as'");
+    }
+
+    #[test]
+    fn unsafe_operations() {
+        check("proc f(p: *int) -> int { return unsafe { *(p + 1) }; }").unwrap();
+        check("unsafe proc f(p: *int) -> int { return p as int; } proc g(p: *int) -> int { return unsafe { f(p) }; }").unwrap();
+        check("proc f(p: *int, q: *int) -> isize { return unsafe { q - p }; }").unwrap();
+        assert_eq!(
+            error("proc f(p: *int) -> int { return *p; }"),
+            "Dereferencing a raw pointer is unsafe and requires an 'unsafe' block or 'unsafe proc', at 1:33"
+        );
+        assert_eq!(
+            error("proc f(p: *int) -> *int { return p + 1; }"),
+            "Pointer arithmetic is unsafe and requires an 'unsafe' block or 'unsafe proc', at 1:34"
+        );
+        assert_eq!(
+            error("proc f(n: int) -> *int { return n as *int; }"),
+            "Casting 'i32' to '*i32' is unsafe and requires an 'unsafe' block or 'unsafe proc', at 1:33"
+        );
+        assert_eq!(
+            error("extern proc abs(n: int) -> int; proc f() -> int { return abs(1); }"),
+            "Calling the extern procedure 'abs' is unsafe and requires an 'unsafe' block or 'unsafe proc', at 1:58"
+        );
+        assert_eq!(
+            error("unsafe proc g() -> int { return 1; } proc f() -> int { return g(); }"),
+            "Calling the unsafe procedure 'g' is unsafe and requires an 'unsafe' block or 'unsafe proc', at 1:63"
+        );
+        assert_eq!(error("proc f(n: int) -> int { return unsafe { *n }; }"), "Cannot dereference a value of type 'i32', at 1:41");
+    }
+
+    #[test]
+    fn overflow_intrinsics() {
+        check("proc f(a: u8, b: u8) -> u8 { return wrapping_add(a, b) + saturating_mul(a, 2); }").unwrap();
+        check("proc f(a: i64) -> Result<i64, str> { return Ok(checked_sub(a, 1)? * 2); }").unwrap();
+        assert_eq!(error("proc f(a: u8) -> u8 { return wrapping_add(a); }"), "'wrapping_add' expects 2 arguments, got 1");
+        assert_eq!(
+            error("proc f(a: u8, b: i8) -> u8 { return wrapping_add(a, b); }"),
+            "'wrapping_add' expects two integers of the same type, got 'u8' and 'i8'"
+        );
+        assert_eq!(
+            error("proc f(a: f64) -> f64 { return saturating_add(a, 1.0); }"),
+            "'saturating_add' expects two integers of the same type, got 'f64' and '{float}'"
+        );
+    }
+
+    #[test]
+    fn macros() {
+        check("macro square($x) { $x * $x } proc f(n: int) -> int { return square!(n + 1); }").unwrap();
+        // Variables declared by the template don't capture those of the caller
+        check("macro shadow($x) { let y := 1; $x } proc f(y: bool) -> bool { return shadow!(y); }").unwrap();
+        check("macro swap($p) { let (a, b) := $p; (b, a) } proc f(a: int) -> (bool, int) { return swap!((a, true)); }").unwrap();
+        // Free identifiers of the template name the globals it was defined with, not the caller's locals of the same name
+        check("const LIMIT := true; macro limit() { LIMIT } proc f(LIMIT: int) -> bool { return limit!(); }").unwrap();
+        assert_eq!(error("const LIMIT := true; macro limit() { LIMIT } proc f(LIMIT: int) -> int { return limit!(); }"), error("const LIMIT := true; proc f() -> int { return LIMIT; }"));
+        check("proc helper() -> bool { true } macro h() { helper() } proc f() -> bool { proc helper() -> int { 1 } return h!(); }").unwrap();
+        check("const LIMIT := 2; macro limit() { LIMIT } const proc f(LIMIT: int) -> int { 1 / (limit!() - LIMIT) } const X := f(3);").unwrap();
+        // Those it does not find there are left to the call site, like the procedures the template defines
+        check("macro local() { n + 1 } proc f(n: int) -> int { return local!(); }").unwrap();
+        // Invoked as a statement, the template may define items named by its arguments
+        check("macro identity($name, $T) { proc $name(x: $T) -> $T { return x; } } identity!(id, int); proc f() -> int { return id(1); }").unwrap();
+
+        assert!(parse_gecko("proc f() -> int { return square!(1); }").is_err());
+        assert!(parse_gecko("macro one() { 1 } proc f() -> int { return one!(1); }").is_err());
+        assert!(parse_gecko("proc f(n: int) -> int { return $n; }").is_err());
+        assert!(parse_gecko("macro bad() { $x } proc f() -> int { return bad!(); }").is_err());
+        assert!(parse_gecko("macro bad($x) { proc $x() -> int { return 1; } } bad!(1 + 2);").is_err());
+    }
+
+    #[test]
+    fn macro_spans() {
+        // Errors inside an expansion point at the template and the call
+        assert_eq!(
+            error("macro read($p) { unsafe { *$p } }\nproc f(n: int) -> int { return read!(n); }"),
+            "Cannot dereference a value of type 'i32', at 1:27 (in macro expanded at 2:32)"
+        );
+    }
+
+    #[test]
+    fn contracts() {
+        check("proc f(n: int) -> int requires n > 0 ensures result > n { assert(n != 0, \"n is zero\"); return n + 1; }").unwrap();
+        check("proc f(n: int) -> int\n    requires n > 0\n    requires n < 10\n{ return n; }").unwrap();
+        assert_eq!(error("proc f(n: int) -> int requires n { return n; }"), "'requires' expects a bool condition, got 'i32'");
+        assert_eq!(error("proc f() -> int { assert(true, 1); return 1; }"), "'assert' expects a str message, got '{integer}'");
+        assert_eq!(error("proc f() -> int ensures false { return 1; }"), "Postcondition `false` is always false, at 1:17");
+        assert_eq!(error("proc f(n: int) -> int requires 1 > 2 { return n; }"), "Precondition `1 > 2` is always false, at 1:23");
+        // Assertions that always fail mark unreachable code
+        check("proc f(n: int) -> int { if n > 0 { return n; } assert(false, \"n must be positive\"); return 0; }").unwrap();
+        // Only 'ensures' can see the return value
+        assert!(check("proc f() -> int requires result > 0 { return 1; }").is_err());
+    }
+}
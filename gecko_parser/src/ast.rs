@@ -8,15 +8,112 @@ use crate::Token;
 use crate::colored::*;
 
 use crate::precedence::*;
+use crate::macros::{Macro, Macros, is_metavariable};
 use pest_consume::{Error, match_nodes, Parser};
+use std::cell::RefCell;
 
 type Result<T> = std::result::Result<T, Error<Rule>>;
-type Node<'i> = pest_consume::Node<'i, Rule, ()>;
+// Macros are expanded as they are parsed, so the parser carries those defined so far
+type Node<'i> = pest_consume::Node<'i, Rule, &'i RefCell<Macros>>;
+
+// Brackets, bounds and range Token of a slice, before its operand is known
+type SliceParts = (Token, Option<Box<NodeType>>, Token, Option<Box<NodeType>>, Token);
 
 // Helper function for all grammar.pest rules that return a Token
 fn token_from_node(input: Node) -> Result<Token> {
     let span: Span = Span::from_span(input.as_span());
-    Ok(Token{ span, value: String::from(input.as_str().to_owned()) })
+    Ok(Token{ span, value: input.as_str().to_owned() })
+}
+
+// Helper function building a procedure Signature, spanning from its first modifier to its output
+fn signature(const_token: Option<Token>, unsafe_token: Option<Token>, func_token: Token, id: Identifier, params: ParameterList, output: Output, contracts: Vec<Contract>) -> Signature {
+    let start = const_token.as_ref().or(unsafe_token.as_ref()).unwrap_or(&func_token).span.start;
+    let span = Span{ start, end: output.span.end, call_site: None };
+    Signature{ const_token, unsafe_token, func_token, id, params, output, contracts, span }
+}
+
+// Source text of the first expression of an assertion or contract, reported when it fails
+fn condition_text(input: &Node) -> String {
+    input.children()
+        .find(|n| n.as_rule() == Rule::expression)
+        .map(|n| n.as_str().trim().to_owned())
+        .unwrap_or_default()
+}
+
+// Joins consecutive doc comments into the documentation of the item they precede
+fn join_docs(comments: Vec<DocComment>) -> Option<DocComment> {
+    let (first, last) = (comments.first()?.span, comments.last()?.span);
+    let text: Vec<String> = comments.into_iter().map(|comment| comment.text).collect();
+    Some(DocComment{ text: text.join("\n"), span: Span{ start: first.start, end: last.end, call_site: None } })
+}
+
+// Attaches doc comments to the statement following them, if it is an item that can be documented
+fn document(stmt: Option<&mut Box<NodeType>>, comments: &mut Vec<DocComment>) {
+    let docs: Option<DocComment> = join_docs(std::mem::take(comments));
+    match stmt.map(|stmt| &mut **stmt) {
+        Some(NodeType::FunctionDefinition(definition)) => definition.docs = docs,
+        Some(NodeType::FunctionDeclaration(declaration)) => declaration.docs = docs,
+        Some(NodeType::StructDefinition(definition)) => definition.docs = docs,
+        Some(NodeType::ConstantDefinition(definition)) => definition.docs = docs,
+        Some(NodeType::TypeAlias(alias)) => alias.docs = docs,
+        _ => {}
+    }
+}
+
+// Attaches attributes to the statement following them, if it is a procedure definition
+fn decorate(stmt: Option<&mut Box<NodeType>>, attributes: &mut Vec<Attribute>) {
+    let attributes: Vec<Attribute> = std::mem::take(attributes);
+    if let Some(NodeType::FunctionDefinition(definition)) = stmt.map(|stmt| &mut **stmt) {
+        definition.attributes = attributes;
+    }
+}
+
+// Text of a '/** */' comment, without the '*' that may start each line
+fn doc_block_lines(text: &str) -> String {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(|line| {
+            let line = line.trim();
+            let line = line.strip_prefix('*').unwrap_or(line);
+            line.strip_prefix(' ').unwrap_or(line)
+        })
+        .collect();
+    lines.join("\n").trim_matches('\n').to_owned()
+}
+
+// Resolves the escape sequences of string literal text
+fn unescape(text: &str) -> String {
+    let mut output = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('a') => output.push('\x07'),
+                Some('b') => output.push('\x08'),
+                Some('f') => output.push('\x0c'),
+                Some('n') => output.push('\n'),
+                Some('r') => output.push('\r'),
+                Some('t') => output.push('\t'),
+                Some('v') => output.push('\x0b'),
+                Some('u') => {
+                    let code: String = chars.by_ref().take(4).collect();
+                    output.push(u32::from_str_radix(&code, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .unwrap_or(char::REPLACEMENT_CHARACTER));
+                },
+                Some(escaped) => output.push(escaped),
+                None => {}
+            },
+            // Braces are doubled to be written literally
+            '{' | '}' => {
+                chars.next();
+                output.push(c);
+            },
+            _ => output.push(c)
+        }
+    }
+    output
 }
 
 // Gecko Parser derived from PEST
@@ -33,6 +130,18 @@ impl GeckoParser {
     fn type_token(input: Node) -> Result<Token> { token_from_node(input) }
     fn return_token(input: Node) -> Result<Token> { token_from_node(input) }
     fn extern_token(input: Node) -> Result<Token> { token_from_node(input) }
+    fn defer_token(input: Node) -> Result<Token> { token_from_node(input) }
+    fn try_token(input: Node) -> Result<Token> { token_from_node(input) }
+    fn new_token(input: Node) -> Result<Token> { token_from_node(input) }
+    fn delete_token(input: Node) -> Result<Token> { token_from_node(input) }
+    fn if_token(input: Node) -> Result<Token> { token_from_node(input) }
+    fn else_token(input: Node) -> Result<Token> { token_from_node(input) }
+    fn struct_token(input: Node) -> Result<Token> { token_from_node(input) }
+    fn const_token(input: Node) -> Result<Token> { token_from_node(input) }
+    fn unsafe_token(input: Node) -> Result<Token> { token_from_node(input) }
+    fn sizeof_token(input: Node) -> Result<Token> { token_from_node(input) }
+    fn alignof_token(input: Node) -> Result<Token> { token_from_node(input) }
+    fn offsetof_token(input: Node) -> Result<Token> { token_from_node(input) }
     
     fn rarrow(input: Node) -> Result<Token> { token_from_node(input) }
     fn larrow(input: Node) -> Result<Token> { token_from_node(input) }
@@ -44,16 +153,38 @@ impl GeckoParser {
 
     fn colon(input: Node) -> Result<Token> { token_from_node(input) }
     fn comma(input: Node) -> Result<Token> { token_from_node(input) }
+    fn question(input: Node) -> Result<Token> { token_from_node(input) }
 
     fn singlequote(input: Node) -> Result<Token> { token_from_node(input) }
     fn doublequote(input: Node) -> Result<Token> { token_from_node(input) }
 
     fn equals(input: Node) -> Result<Token> { token_from_node(input) }
+    fn semicolon(input: Node) -> Result<Token> { token_from_node(input) }
+    fn star(input: Node) -> Result<Token> { token_from_node(input) }
+    fn dot(input: Node) -> Result<Token> { token_from_node(input) }
+    fn range(input: Node) -> Result<Token> { token_from_node(input) }
+    fn ellipsis(input: Node) -> Result<Token> { token_from_node(input) }
+    fn lbracket(input: Node) -> Result<Token> { token_from_node(input) }
+    fn rbracket(input: Node) -> Result<Token> { token_from_node(input) }
+    fn macro_token(input: Node) -> Result<Token> { token_from_node(input) }
+    fn assert_token(input: Node) -> Result<Token> { token_from_node(input) }
+    fn requires_token(input: Node) -> Result<Token> { token_from_node(input) }
+    fn ensures_token(input: Node) -> Result<Token> { token_from_node(input) }
+    fn bang(input: Node) -> Result<Token> { token_from_node(input) }
     //
 
     fn identifier(input: Node) -> Result<Identifier> {
         let span: Span = Span::from_span(input.as_span());
-        Ok(Identifier{ span, name: input.as_str().to_owned() })
+        let id = Identifier{ span, name: input.as_str().to_owned(), global: false };
+        if is_metavariable(&id) && !input.user_data().borrow().in_template() {
+            return Err(input.error(format!("Macro variable '{}' used outside of a macro", id.name)));
+        }
+        Ok(id)
+    }
+
+    fn metavariable(input: Node) -> Result<Identifier> {
+        let span: Span = Span::from_span(input.as_span());
+        Ok(Identifier{ span, name: input.as_str().to_owned(), global: false })
     }
 
     fn boolean(input: Node) -> Result<Boolean> {
@@ -103,31 +234,50 @@ impl GeckoParser {
         })
     }
 
+    fn character_content(input: Node) -> Result<char> {
+        match input.as_str().parse::<char>() {
+            Ok(content) => Ok(content),
+            Err(error) => Err(input.error(format!("{}", error)))
+        }
+    }
+
     fn character(input: Node) -> Result<Character> {
         let span: Span = Span::from_span(input.as_span());
         
         Ok(match_nodes!(input.into_children();
-            [singlequote(lquote), character, singlequote(rquote)] => {
-                match character.as_str()
-                    .to_owned()
-                    .parse::<char>() {
-                    Ok(content) => Character{ lquote, value: content as u8, rquote, span },
-                    Err(error) => panic!{"{:?}", error}
-                }
+            [singlequote(lquote), character_content(content), singlequote(rquote)] => {
+                Character{ lquote, value: content as u8, rquote, span }
             }
         ))
     }
 
-    // String value
+    // String value, split into literal text and interpolated expressions
     fn string(input: Node) -> Result<Str> {
         let span: Span = Span::from_span(input.as_span());
-        Ok(Str{ span, string: String::from(input.as_str().to_owned()) })
+        let string: String = input.as_str().to_owned();
+        let mut segments: Vec<StringSegment> = vec!();
+
+        for n in input.into_children() {
+            match n.as_rule() {
+                Rule::string_content => segments.push(StringSegment::Text(unescape(n.as_str()))),
+                Rule::string_interpolation => segments.push(StringSegment::Interpolation(Self::string_interpolation(n)?)),
+                _ => {}
+            }
+        }
+
+        Ok(Str{ span, string, segments })
+    }
+
+    fn string_interpolation(input: Node) -> Result<Box<NodeType>> {
+        Ok(match_nodes!(input.into_children();
+            [lbrace(_), expression(expr), rbrace(_)] => expr.node
+        ))
     }
 
     // Expression
     // Climbs operator precedence defined in precedence.rs
     // TODO: Work out how to involve UnaryOperators in precedence
-    #[prec_climb(term, PRECCLIMBER)]
+    #[prec_climb(operand, PRECCLIMBER)]
     fn expression(left: Term, op: Node, right: Term) -> Result<Term> {
         match op.as_rule() {
             Rule::assignment
@@ -148,6 +298,7 @@ impl GeckoParser {
                 | Rule::minus
                 | Rule::multiply
                 | Rule::divide
+                | Rule::modulus
                 | Rule::exponent 
                 | Rule::cast
             => {
@@ -157,15 +308,25 @@ impl GeckoParser {
                         left: left.node,
                         op: Token { value: op.as_str().to_owned(), span },
                         right: right.node,
-                        span: Span{ start: left.span.start, end: right.span.end }
+                        span: Span{ start: left.span.start, end: right.span.end, call_site: None }
                     })),
-                    span: Span{ start: left.span.start, end: right.span.end }
+                    span: Span{ start: left.span.start, end: right.span.end, call_site: None }
                 })
             },
             r => Err(op.error(format!("Rule {:?} isn't an operator", r)))?
         }
     }
 
+    // Term with any number of postfix '?', each wrapping what precedes it in a Try
+    fn operand(input: Node) -> Result<Term> {
+        Ok(match_nodes!(input.into_children();
+            [term(term), question(questions)..] => questions.fold(term, |term, question| {
+                let span: Span = Span{ start: term.span.start, end: question.span.end, call_site: None };
+                Term{ node: Box::new(NodeType::Try(Try{ try_token: question, expr: term.node, span })), span }
+            })
+        ))
+    }
+
     // Term, returns wrapper struct for any Node involved in a binary operator
     fn term(input: Node) -> Result<Term> {
         Ok(match_nodes!(input.into_children();
@@ -177,6 +338,51 @@ impl GeckoParser {
                 let node_span: Span = id.span;
                 Term{ node: Box::new(NodeType::Identifier(id)), span: node_span }
             },
+            [call(call)] => {
+                let node_span: Span = call.span;
+                Term{ node: Box::new(NodeType::Call(call)), span: node_span }
+            },
+            [macro_call(block)] => {
+                let node_span: Span = block.span;
+                Term{ node: Box::new(NodeType::Block(block)), span: node_span }
+            },
+            [try_expression(try_expr)] => {
+                let node_span: Span = try_expr.span;
+                Term{ node: Box::new(NodeType::Try(try_expr)), span: node_span }
+            },
+            [postfix_expression(postfix)] => postfix,
+            [if_expression(if_expr)] => {
+                let node_span: Span = if_expr.span;
+                Term{ node: Box::new(NodeType::If(if_expr)), span: node_span }
+            },
+            [block(block)] => {
+                let node_span: Span = block.span;
+                Term{ node: Box::new(NodeType::Block(block)), span: node_span }
+            },
+            [unsafe_block(unsafe_block)] => {
+                let node_span: Span = unsafe_block.span;
+                Term{ node: Box::new(NodeType::Unsafe(unsafe_block)), span: node_span }
+            },
+            [dereference(deref)] => {
+                let node_span: Span = deref.span;
+                Term{ node: Box::new(NodeType::Dereference(deref)), span: node_span }
+            },
+            [type_query(query)] => {
+                let node_span: Span = query.span;
+                Term{ node: Box::new(NodeType::TypeQuery(query)), span: node_span }
+            },
+            [new_expression(new)] => {
+                let node_span: Span = new.span;
+                Term{ node: Box::new(NodeType::New(new)), span: node_span }
+            },
+            [array(array)] => {
+                let node_span: Span = array.span;
+                Term{ node: Box::new(NodeType::Array(array)), span: node_span }
+            },
+            [tuple(tuple)] => {
+                let node_span: Span = tuple.span;
+                Term{ node: Box::new(NodeType::Tuple(tuple)), span: node_span }
+            },
             [integer(int)] => {
                 let node_span: Span = int.span;
                 Term{ node: Box::new(NodeType::Integer(int)), span: node_span }
@@ -200,33 +406,275 @@ impl GeckoParser {
         ))
     }
 
+    // Call to a procedure by name
+    fn call(input: Node) -> Result<Call> {
+        let span: Span = Span::from_span(input.as_span());
+        Ok(match_nodes!(input.into_children();
+            [identifier(id), lparen(lparen), rparen(rparen)] => {
+                Call{ id, lparen, args: vec!(), rparen, span }
+            },
+            [identifier(id), lparen(lparen), call_parameter_list(args), rparen(rparen)] => {
+                Call{ id, lparen, args, rparen, span }
+            }
+        ))
+    }
+
+    fn call_parameter_list(input: Node) -> Result<Vec<Box<NodeType>>> {
+        let mut args: Vec<Box<NodeType>> = vec!();
+        for n in input.into_children() {
+            if n.as_rule() == Rule::call_parameter {
+                args.push(Self::call_parameter(n)?);
+            }
+        }
+        Ok(args)
+    }
+
+    fn call_parameter(input: Node) -> Result<Box<NodeType>> {
+        Ok(match_nodes!(input.into_children();
+            [expression(expr)] => expr.node
+        ))
+    }
+
+    // Read through a raw pointer
+    fn dereference(input: Node) -> Result<Dereference> {
+        let span: Span = Span::from_span(input.as_span());
+        Ok(match_nodes!(input.into_children();
+            [star(star), term(term)] => Dereference{ star, expr: term.node, span }
+        ))
+    }
+
+    // Block allowed to perform unsafe operations
+    fn unsafe_block(input: Node) -> Result<UnsafeBlock> {
+        let span: Span = Span::from_span(input.as_span());
+        Ok(match_nodes!(input.into_children();
+            [unsafe_token(unsafe_token), block(block)] => UnsafeBlock{ unsafe_token, block, span }
+        ))
+    }
+
+    fn try_expression(input: Node) -> Result<Try> {
+        let span: Span = Span::from_span(input.as_span());
+        Ok(match_nodes!(input.into_children();
+            [try_token(try_token), term(term)] => Try{ try_token, expr: term.node, span }
+        ))
+    }
+
+    // Heap allocation of a single value or an array
+    fn new_expression(input: Node) -> Result<New> {
+        let span: Span = Span::from_span(input.as_span());
+        Ok(match_nodes!(input.into_children();
+            [new_token(new_token), lbracket(_), type_specifier(ty), semicolon(_), expression(length), rbracket(_)] => {
+                New{ new_token, ty, args: vec!(), length: Some(length.node), span }
+            },
+            [new_token(new_token), type_specifier(ty), lparen(_), rparen(_)] => {
+                New{ new_token, ty, args: vec!(), length: None, span }
+            },
+            [new_token(new_token), type_specifier(ty), lparen(_), call_parameter_list(args), rparen(_)] => {
+                New{ new_token, ty, args, length: None, span }
+            }
+        ))
+    }
+
+    // Array literal
+    fn type_query(input: Node) -> Result<TypeQuery> {
+        let span: Span = Span::from_span(input.as_span());
+        Ok(match_nodes!(input.into_children();
+            [sizeof_token(token), lparen(lparen), type_specifier(ty), rparen(rparen)] => {
+                TypeQuery{ token, kind: TypeQueryKind::Size, lparen, ty, rparen, span }
+            },
+            [alignof_token(token), lparen(lparen), type_specifier(ty), rparen(rparen)] => {
+                TypeQuery{ token, kind: TypeQueryKind::Align, lparen, ty, rparen, span }
+            },
+            [offsetof_token(token), lparen(lparen), type_specifier(ty), comma(comma), identifier(field), rparen(rparen)] => {
+                TypeQuery{ token, kind: TypeQueryKind::Offset{ comma, field }, lparen, ty, rparen, span }
+            }
+        ))
+    }
+
+    fn array(input: Node) -> Result<Array> {
+        let span: Span = Span::from_span(input.as_span());
+        let mut lb: Option<Token> = None;
+        let mut elements: Vec<Box<NodeType>> = vec!();
+        let mut rb: Option<Token> = None;
+
+        for n in input.into_children() {
+            match n.as_rule() {
+                Rule::lbracket => lb = Some(Self::lbracket(n)?),
+                Rule::rbracket => rb = Some(Self::rbracket(n)?),
+                Rule::expression => elements.push(Self::expression(n)?.node),
+                _ => {}
+            }
+        }
+
+        Ok(Array{ lbracket: lb.unwrap(), elements, rbracket: rb.unwrap(), span })
+    }
+
+    fn tuple(input: Node) -> Result<Tuple> {
+        let span: Span = Span::from_span(input.as_span());
+        let mut lparen: Option<Token> = None;
+        let mut elements: Vec<Box<NodeType>> = vec!();
+        let mut rparen: Option<Token> = None;
+
+        for n in input.into_children() {
+            match n.as_rule() {
+                Rule::lparen => lparen = Some(Self::lparen(n)?),
+                Rule::rparen => rparen = Some(Self::rparen(n)?),
+                Rule::expression => elements.push(Self::expression(n)?.node),
+                _ => {}
+            }
+        }
+
+        Ok(Tuple{ lparen: lparen.unwrap(), elements, rparen: rparen.unwrap(), span })
+    }
+
+    // Chain of indexing, slicing and member access applied to a term
+    fn postfix_expression(input: Node) -> Result<Term> {
+        let mut term: Option<Term> = None;
+
+        for n in input.into_children() {
+            // Postfix operators extend the span of the operand they are applied to
+            let span: Span = match &term {
+                Some(operand) => Span{ start: operand.span.start, end: Span::from_span(n.as_span()).end, call_site: None },
+                None => Span::from_span(n.as_span())
+            };
+            let node: NodeType = match n.as_rule() {
+                Rule::call => NodeType::Call(Self::call(n)?),
+                Rule::macro_call => NodeType::Block(Self::macro_call(n)?),
+                Rule::array => NodeType::Array(Self::array(n)?),
+                Rule::identifier => NodeType::Identifier(Self::identifier(n)?),
+                Rule::expression => {
+                    term = Some(Self::expression(n)?);
+                    continue;
+                },
+                Rule::index => {
+                    let (lbracket, index, rbracket) = Self::index(n)?;
+                    NodeType::Index(Index{ expr: term.take().unwrap().node, lbracket, index, rbracket, span })
+                },
+                Rule::slice => {
+                    let (lbracket, start, range, end, rbracket) = Self::slice(n)?;
+                    NodeType::Slice(Slice{ expr: term.take().unwrap().node, lbracket, start, range, end, rbracket, span })
+                },
+                Rule::member => {
+                    let (dot, id) = Self::member(n)?;
+                    NodeType::Member(Member{ expr: term.take().unwrap().node, dot, id, span })
+                },
+                r => return Err(n.error(format!("Rule {:?} isn't a postfix operand", r)))
+            };
+            term = Some(Term{ node: Box::new(node), span });
+        }
+
+        Ok(term.unwrap())
+    }
+
+    fn index(input: Node) -> Result<(Token, Box<NodeType>, Token)> {
+        Ok(match_nodes!(input.into_children();
+            [lbracket(lbracket), expression(index), rbracket(rbracket)] => (lbracket, index.node, rbracket)
+        ))
+    }
+
+    fn slice(input: Node) -> Result<SliceParts> {
+        let mut lb: Option<Token> = None;
+        let mut start: Option<Box<NodeType>> = None;
+        let mut range: Option<Token> = None;
+        let mut end: Option<Box<NodeType>> = None;
+        let mut rb: Option<Token> = None;
+
+        for n in input.into_children() {
+            match n.as_rule() {
+                Rule::lbracket => lb = Some(Self::lbracket(n)?),
+                Rule::range => range = Some(Self::range(n)?),
+                Rule::expression if range.is_none() => start = Some(Self::expression(n)?.node),
+                Rule::expression => end = Some(Self::expression(n)?.node),
+                Rule::rbracket => rb = Some(Self::rbracket(n)?),
+                _ => {}
+            }
+        }
+
+        Ok((lb.unwrap(), start, range.unwrap(), end, rb.unwrap()))
+    }
+
+    fn member(input: Node) -> Result<(Token, Identifier)> {
+        Ok(match_nodes!(input.into_children();
+            [dot(dot), identifier(id)] => (dot, id)
+        ))
+    }
+
+    fn if_expression(input: Node) -> Result<If> {
+        let span: Span = Span::from_span(input.as_span());
+        Ok(match_nodes!(input.into_children();
+            [if_token(if_token), expression(condition), block(block)] => {
+                If{ if_token, condition: condition.node, block, else_token: None, else_branch: None, span }
+            },
+            [if_token(if_token), expression(condition), block(block), else_token(else_token), block(else_block)] => {
+                let else_branch = Box::new(NodeType::Block(else_block));
+                If{ if_token, condition: condition.node, block, else_token: Some(else_token), else_branch: Some(else_branch), span }
+            },
+            [if_token(if_token), expression(condition), block(block), else_token(else_token), if_expression(else_if)] => {
+                let else_branch = Box::new(NodeType::If(else_if));
+                If{ if_token, condition: condition.node, block, else_token: Some(else_token), else_branch: Some(else_branch), span }
+            }
+        ))
+    }
+
     // Code block
     fn block(input: Node) -> Result<Block> {
         let span: Span = Span::from_span(input.as_span());
         let mut lb: Option<Token> = None;
         let mut stmts: Vec<Box<NodeType>> = vec!();
+        let mut tail: Option<Box<NodeType>> = None;
         let mut rb: Option<Token> = None;
+        let mut comments: Vec<DocComment> = vec!();
+        let mut attributes: Vec<Attribute> = vec!();
 
         for n in input.into_children().peekable() {
+            if n.as_rule() == Rule::doc_comment {
+                comments.push(Self::doc_comment(n)?);
+                continue;
+            }
+            if n.as_rule() == Rule::decorator {
+                attributes.push(Self::decorator(n)?);
+                continue;
+            }
+            let count: usize = stmts.len();
             match n.as_rule() {
-                Rule::lbrace => lb = Some(Self::lbrace(n).unwrap()),
-                Rule::rbrace => rb = Some(Self::rbrace(n).unwrap()),
-
-                Rule::expression_statement => stmts.push(Box::new(NodeType::Expression(Self::expression_statement(n).unwrap()))),
-                Rule::return_statement => stmts.push(Box::new(NodeType::Return(Self::return_statement(n).unwrap()))),
-                Rule::variable_declaration => stmts.push(Box::new(NodeType::VariableDeclaration(Self::variable_declaration(n).unwrap()))),
-                Rule::variable_initialisation => stmts.push(Box::new(NodeType::VariableInitialisation(Self::variable_initialisation(n).unwrap()))),
-                Rule::function_definition => stmts.push(Box::new(NodeType::FunctionDefinition(Self::function_definition(n).unwrap()))),
+                Rule::lbrace => lb = Some(Self::lbrace(n)?),
+                Rule::rbrace => rb = Some(Self::rbrace(n)?),
+
+                Rule::expression_statement => stmts.push(Box::new(NodeType::Expression(Self::expression_statement(n)?))),
+                Rule::return_statement => stmts.push(Box::new(NodeType::Return(Self::return_statement(n)?))),
+                Rule::defer_statement => stmts.push(Box::new(NodeType::Defer(Self::defer_statement(n)?))),
+                Rule::delete_statement => stmts.push(Box::new(NodeType::Delete(Self::delete_statement(n)?))),
+                Rule::assert_statement => stmts.push(Box::new(NodeType::Assert(Self::assert_statement(n)?))),
+                Rule::const_definition => stmts.push(Box::new(NodeType::ConstantDefinition(Self::const_definition(n)?))),
+                Rule::type_statement => stmts.push(Box::new(NodeType::TypeAlias(Self::type_statement(n)?))),
+                Rule::variable_declaration => stmts.push(Box::new(NodeType::VariableDeclaration(Self::variable_declaration(n)?))),
+                Rule::variable_initialisation => stmts.push(Box::new(NodeType::VariableInitialisation(Self::variable_initialisation(n)?))),
+                Rule::function_definition => stmts.push(Box::new(NodeType::FunctionDefinition(Self::function_definition(n)?))),
+                Rule::function_declaration => stmts.push(Box::new(NodeType::FunctionDeclaration(Self::function_declaration(n)?))),
+                Rule::struct_definition => stmts.push(Box::new(NodeType::StructDefinition(Self::struct_definition(n)?))),
+                Rule::if_expression => stmts.push(Box::new(NodeType::If(Self::if_expression(n)?))),
+                Rule::block => stmts.push(Box::new(NodeType::Block(Self::block(n)?))),
+                Rule::unsafe_block => stmts.push(Box::new(NodeType::Unsafe(Self::unsafe_block(n)?))),
+                Rule::macro_definition => Self::macro_definition(n)?,
+                Rule::macro_statement => stmts.extend(Self::macro_statement(n)?),
+                Rule::expression => tail = Some(Self::expression(n)?.node),
                 _ => {}
             }
+            document(stmts.get_mut(count), &mut comments);
+            decorate(stmts.get_mut(count), &mut attributes);
         }
 
         // Ensure that both brace Tokens have been collected
-        assert_ne!(lb.is_none() || rb.is_none(), true);
+        assert!(!(lb.is_none() || rb.is_none()));
+
+        // An 'if' or block ending the block gives its value, as no semicolon follows it
+        if tail.is_none() && matches!(stmts.last().map(|stmt| &**stmt), Some(NodeType::If(_)) | Some(NodeType::Block(_)) | Some(NodeType::Unsafe(_))) {
+            tail = stmts.pop();
+        }
 
         Ok(Block{
             lbrace: lb.unwrap(),
             stmts,
+            tail,
             rbrace: rb.unwrap(),
             span 
         })
@@ -234,16 +682,104 @@ impl GeckoParser {
     
     // A typed parameter
     fn parameter(input: Node) -> Result<Parameter> {
+        let span: Span = Span::from_span(input.as_span());
+        let macros = input.user_data();
+        let parameter = match_nodes!(input.children();
+            [pattern(pattern), colon(c), type_specifier(ty)] => {
+                Parameter{ pattern, colon_token: c, ty, span }
+            }
+        );
+        macros.borrow_mut().bind(&parameter.pattern);
+        Ok(parameter)
+    }
+
+    // Pattern that binds the parts of a value to variables
+    fn pattern(input: Node) -> Result<Pattern> {
         let span: Span = Span::from_span(input.as_span());
         Ok(match_nodes!(input.into_children();
-            [identifier(id), colon(c), identifier(ty_s)] => {
-                let ty_span = ty_s.span;
-                let type_specifier = TypeSpecifier{ id: ty_s, span: ty_span };
-                Parameter{ id, colon_token: c, ty: type_specifier, span }
+            [identifier(id)] => Pattern{ kind: PatternKind::Identifier(id), span },
+            [wildcard_pattern(token)] => Pattern{ kind: PatternKind::Wildcard(token), span },
+            [tuple_pattern(pattern)] => pattern,
+            [struct_pattern(pattern)] => pattern
+        ))
+    }
+
+    fn wildcard_pattern(input: Node) -> Result<Token> {
+        token_from_node(input)
+    }
+
+    fn tuple_pattern(input: Node) -> Result<Pattern> {
+        let span: Span = Span::from_span(input.as_span());
+        let mut lparen: Option<Token> = None;
+        let mut elements: Vec<Pattern> = vec!();
+        let mut rparen: Option<Token> = None;
+
+        for n in input.into_children() {
+            match n.as_rule() {
+                Rule::lparen => lparen = Some(Self::lparen(n)?),
+                Rule::pattern => elements.push(Self::pattern(n)?),
+                Rule::rparen => rparen = Some(Self::rparen(n)?),
+                _ => {}
             }
+        }
+
+        Ok(Pattern{ kind: PatternKind::Tuple{ lparen: lparen.unwrap(), elements, rparen: rparen.unwrap() }, span })
+    }
+
+    fn struct_pattern(input: Node) -> Result<Pattern> {
+        let span: Span = Span::from_span(input.as_span());
+        let mut id: Option<Identifier> = None;
+        let mut lbrace: Option<Token> = None;
+        let mut fields: Vec<FieldPattern> = vec!();
+        let mut rest: Option<Token> = None;
+        let mut rbrace: Option<Token> = None;
+
+        for n in input.into_children() {
+            match n.as_rule() {
+                Rule::identifier => id = Some(Self::identifier(n)?),
+                Rule::lbrace => lbrace = Some(Self::lbrace(n)?),
+                Rule::field_pattern => fields.push(Self::field_pattern(n)?),
+                Rule::range => rest = Some(Self::range(n)?),
+                Rule::rbrace => rbrace = Some(Self::rbrace(n)?),
+                _ => {}
+            }
+        }
+
+        Ok(Pattern{
+            kind: PatternKind::Struct{ id: id.unwrap(), lbrace: lbrace.unwrap(), fields, rest, rbrace: rbrace.unwrap() },
+            span
+        })
+    }
+
+    fn field_pattern(input: Node) -> Result<FieldPattern> {
+        let span: Span = Span::from_span(input.as_span());
+        Ok(match_nodes!(input.into_children();
+            [identifier(id)] => FieldPattern{ id, pattern: None, span },
+            [identifier(id), colon(_), pattern(pattern)] => FieldPattern{ id, pattern: Some(pattern), span }
         ))
     }
 
+    // Typed field of a struct definition
+    fn field(input: Node) -> Result<Field> {
+        let span: Span = Span::from_span(input.as_span());
+        let mut comments: Vec<DocComment> = vec!();
+        let mut id: Option<Identifier> = None;
+        let mut colon_token: Option<Token> = None;
+        let mut ty: Option<TypeSpecifier> = None;
+
+        for n in input.into_children() {
+            match n.as_rule() {
+                Rule::doc_comment => comments.push(Self::doc_comment(n)?),
+                Rule::identifier => id = Some(Self::identifier(n)?),
+                Rule::colon => colon_token = Some(Self::colon(n)?),
+                Rule::type_specifier => ty = Some(Self::type_specifier(n)?),
+                _ => {}
+            }
+        }
+
+        Ok(Field{ docs: join_docs(comments), id: id.unwrap(), colon_token: colon_token.unwrap(), ty: ty.unwrap(), span })
+    }
+
     // List of typed parameters, used in Function Signature
     fn parameter_list(input: Node) -> Result<ParameterList> {
         let span: Span = Span::from_span(input.as_span());
@@ -251,6 +787,7 @@ impl GeckoParser {
         let mut lp: Option<Token> = None;
         let mut params: Vec<(Parameter, Option<Token>)> = vec!();
         let mut rp: Option<Token> = None;
+        let mut variadic: Option<Token> = None;
 
         let mut next_param: Option<Parameter> = None;
 
@@ -266,11 +803,15 @@ impl GeckoParser {
                     params.push((next_param.unwrap(), Some(Self::comma(n).unwrap())));
                     next_param = None;
                 },
+                Rule::ellipsis => {
+                    variadic = Some(Self::ellipsis(n).unwrap());
+                },
                 Rule::rparen => {
-                    assert_eq!(next_param.is_none(), false);
-                    params.push((next_param.unwrap(), None));
-                    next_param = None;
-                    
+                    // Empty parameter lists have no trailing parameter
+                    if let Some(param) = next_param.take() {
+                        params.push((param, None));
+                    }
+
                     rp = Some(
                         Self::rparen(n)
                             .unwrap()
@@ -281,23 +822,91 @@ impl GeckoParser {
         }
 
         // Ensure that both parenthesis Tokens are collected
-        assert_ne!(lp.is_none() || rp.is_none(), true);
+        assert!(!(lp.is_none() || rp.is_none()));
 
         Ok(ParameterList{
             lparen: lp.unwrap(),
             parameters: params,
+            variadic,
             rparen: rp.unwrap(),
-            span: span
+            span
         })
     }
 
+    // Type of a parameter, variable or function output
+    fn type_specifier(input: Node) -> Result<TypeSpecifier> {
+        Ok(match_nodes!(input.into_children();
+            [type_name(ty)] => ty,
+            [array_type(ty)] => ty,
+            [pointer_type(ty)] => ty,
+            [tuple_type(ty)] => ty
+        ))
+    }
+
+    // Tuple (T, U, ...)
+    fn tuple_type(input: Node) -> Result<TypeSpecifier> {
+        let span: Span = Span::from_span(input.as_span());
+        let mut lparen: Option<Token> = None;
+        let mut elements: Vec<TypeSpecifier> = vec!();
+        let mut rparen: Option<Token> = None;
+
+        for n in input.into_children() {
+            match n.as_rule() {
+                Rule::lparen => lparen = Some(Self::lparen(n)?),
+                Rule::type_specifier => elements.push(Self::type_specifier(n)?),
+                Rule::rparen => rparen = Some(Self::rparen(n)?),
+                _ => {}
+            }
+        }
+
+        Ok(TypeSpecifier{ kind: TypeKind::Tuple{ lparen: lparen.unwrap(), elements, rparen: rparen.unwrap() }, span })
+    }
+
+    // Slice [T] or fixed size array [T; N]
+    fn array_type(input: Node) -> Result<TypeSpecifier> {
+        let span: Span = Span::from_span(input.as_span());
+        Ok(match_nodes!(input.into_children();
+            [lbracket(lbracket), type_specifier(element), rbracket(rbracket)] => {
+                TypeSpecifier{ kind: TypeKind::Array{ lbracket, element: Box::new(element), length: None, rbracket }, span }
+            },
+            [lbracket(lbracket), type_specifier(element), semicolon(_), expression(length), rbracket(rbracket)] => {
+                TypeSpecifier{ kind: TypeKind::Array{ lbracket, element: Box::new(element), length: Some(length.node), rbracket }, span }
+            }
+        ))
+    }
+
+    // Pointer *T
+    fn pointer_type(input: Node) -> Result<TypeSpecifier> {
+        let span: Span = Span::from_span(input.as_span());
+        Ok(match_nodes!(input.into_children();
+            [star(star), type_specifier(pointee)] => {
+                TypeSpecifier{ kind: TypeKind::Pointer{ star, pointee: Box::new(pointee) }, span }
+            }
+        ))
+    }
+
+    // Named type with optional generic arguments, e.g. Result<int, str>
+    fn type_name(input: Node) -> Result<TypeSpecifier> {
+        let span: Span = Span::from_span(input.as_span());
+        let mut id: Option<Identifier> = None;
+        let mut generics: Vec<TypeSpecifier> = vec!();
+
+        for n in input.into_children() {
+            match n.as_rule() {
+                Rule::identifier => id = Some(Self::identifier(n)?),
+                Rule::type_specifier => generics.push(Self::type_specifier(n)?),
+                _ => {}
+            }
+        }
+
+        Ok(TypeSpecifier{ kind: TypeKind::Named{ id: id.unwrap(), generics }, span })
+    }
+
     // Return type of a function, used in Function Signature
     fn output(input: Node) -> Result<Output> {
         let span: Span = Span::from_span(input.as_span());
         Ok(match_nodes!(input.into_children();
-            [rarrow(rarrow), identifier(id)] => {
-                let ty_span = id.span;
-                let ty: TypeSpecifier = TypeSpecifier{ id, span: ty_span };
+            [rarrow(rarrow), type_specifier(ty)] => {
                 Output{
                     rarrow,
                     ty,
@@ -307,6 +916,33 @@ impl GeckoParser {
         ))
     }
 
+    // Single '///' line or '/** */' block of documentation
+    fn doc_comment(input: Node) -> Result<DocComment> {
+        let span: Span = Span::from_span(input.as_span());
+        Ok(match_nodes!(input.into_children();
+            [doc_line(text)] => DocComment{ text, span },
+            [doc_block(text)] => DocComment{ text, span }
+        ))
+    }
+
+    // '#[...]' attribute of the item following it
+    fn decorator(input: Node) -> Result<Attribute> {
+        let span: Span = Span::from_span(input.as_span());
+        Ok(match_nodes!(input.into_children();
+            [expression(expr)] => Attribute{ expr: expr.node, span }
+        ))
+    }
+
+    fn doc_line(input: Node) -> Result<String> {
+        let text: &str = input.as_str().trim_start_matches('/');
+        Ok(text.strip_prefix(' ').unwrap_or(text).trim_end().to_owned())
+    }
+
+    fn doc_block(input: Node) -> Result<String> {
+        let text: &str = input.as_str();
+        Ok(doc_block_lines(&text[3..text.len() - 2]))
+    }
+
     //
     // Statement Nodes
     //
@@ -314,48 +950,221 @@ impl GeckoParser {
     fn function_definition(input: Node) -> Result<FunctionDefinition> {
         let span: Span = Span::from_span(input.as_span());
         Ok(match_nodes!(input.into_children();
-            [proc_token(func_token), identifier(id), parameter_list(params), output(output), block(block)] => {
-                let sig_span = Span{ start: func_token.span.start, end: output.span.end };
-                let sig = Signature{ func_token, id, params, output, span: sig_span };
-                
-                FunctionDefinition{
-                    sig,
-                    block,
-                    span
-                }
+            [proc_token(func_token), identifier(id), parameter_list(params), output(output), contract(contracts).., block(block)] => {
+                let sig = signature(None, None, func_token, id, params, output, contracts.collect());
+                FunctionDefinition{ attributes: vec!(), docs: None, sig, block, span }
+            },
+            [const_token(const_token), proc_token(func_token), identifier(id), parameter_list(params), output(output), contract(contracts).., block(block)] => {
+                let sig = signature(Some(const_token), None, func_token, id, params, output, contracts.collect());
+                FunctionDefinition{ attributes: vec!(), docs: None, sig, block, span }
+            },
+            [unsafe_token(unsafe_token), proc_token(func_token), identifier(id), parameter_list(params), output(output), contract(contracts).., block(block)] => {
+                let sig = signature(None, Some(unsafe_token), func_token, id, params, output, contracts.collect());
+                FunctionDefinition{ attributes: vec!(), docs: None, sig, block, span }
+            },
+            [const_token(const_token), unsafe_token(unsafe_token), proc_token(func_token), identifier(id), parameter_list(params), output(output), contract(contracts).., block(block)] => {
+                let sig = signature(Some(const_token), Some(unsafe_token), func_token, id, params, output, contracts.collect());
+                FunctionDefinition{ attributes: vec!(), docs: None, sig, block, span }
             }
         ))
     }
 
-    fn variable_declaration(input: Node) -> Result<VariableDeclaration> {
+    fn function_declaration(input: Node) -> Result<FunctionDeclaration> {
         let span: Span = Span::from_span(input.as_span());
         Ok(match_nodes!(input.into_children();
-            [let_token(let_token), identifier(id), colon(colon), identifier(ty)] => {
-                let ty_span: Span = ty.span;
-                let ty = TypeSpecifier{ id: ty, span: ty_span };
-                
-                VariableDeclaration{ let_token, id, colon, ty, span }
+            [proc_token(func_token), identifier(id), parameter_list(params), output(output), semicolon(semicolon)] => {
+                let sig = signature(None, None, func_token, id, params, output, vec!());
+                FunctionDeclaration{ docs: None, extern_token: None, sig, semicolon, span }
+            },
+            [unsafe_token(unsafe_token), proc_token(func_token), identifier(id), parameter_list(params), output(output), semicolon(semicolon)] => {
+                let sig = signature(None, Some(unsafe_token), func_token, id, params, output, vec!());
+                FunctionDeclaration{ docs: None, extern_token: None, sig, semicolon, span }
+            },
+            [external(extern_token), proc_token(func_token), identifier(id), parameter_list(params), output(output), semicolon(semicolon)] => {
+                let mut sig = signature(None, None, func_token, id, params, output, vec!());
+                sig.span.start = extern_token.span.start;
+                FunctionDeclaration{ docs: None, extern_token: Some(extern_token), sig, semicolon, span }
+            },
+            [external(extern_token), unsafe_token(unsafe_token), proc_token(func_token), identifier(id), parameter_list(params), output(output), semicolon(semicolon)] => {
+                let mut sig = signature(None, Some(unsafe_token), func_token, id, params, output, vec!());
+                sig.span.start = extern_token.span.start;
+                FunctionDeclaration{ docs: None, extern_token: Some(extern_token), sig, semicolon, span }
             }
         ))
     }
 
-    fn variable_initialisation(input: Node) -> Result<VariableInitialisation> {
+    // 'requires' or 'ensures' clause of a procedure definition
+    fn contract(input: Node) -> Result<Contract> {
         let span: Span = Span::from_span(input.as_span());
+        let text: String = condition_text(&input);
+        Ok(match_nodes!(input.children();
+            [requires_token(token), expression(condition)] => {
+                Contract{ token, kind: ContractKind::Requires, condition: condition.node, text, span }
+            },
+            [ensures_token(token), expression(condition)] => {
+                Contract{ token, kind: ContractKind::Ensures, condition: condition.node, text, span }
+            }
+        ))
+    }
+
+    // Modifier making a procedure use the C calling convention and symbol name
+    fn external(input: Node) -> Result<Token> {
         Ok(match_nodes!(input.into_children();
-            [let_token(let_token), identifier(id), colon(colon), identifier(ty), equals(equals), expression(expr)] => {
-                let ty_span: Span = ty.span;
-                let ty = TypeSpecifier{ id: ty, span: ty_span };
+            [extern_token(extern_token)] => extern_token
+        ))
+    }
+
+    fn variable_declaration(input: Node) -> Result<VariableDeclaration> {
+        let span: Span = Span::from_span(input.as_span());
+        let macros = input.user_data();
+        let declaration = match_nodes!(input.children();
+            [let_token(let_token), identifier(id), colon(colon), type_specifier(ty)] => {
+                VariableDeclaration{ let_token, id, colon, ty, span }
+            }
+        );
+        macros.borrow_mut().bind_identifier(&declaration.id);
+        Ok(declaration)
+    }
 
+    fn variable_initialisation(input: Node) -> Result<VariableInitialisation> {
+        let span: Span = Span::from_span(input.as_span());
+        let macros = input.user_data();
+        let initialisation = match_nodes!(input.children();
+            [let_token(let_token), pattern(pattern), colon(colon), type_specifier(ty), equals(equals), expression(expr)] => {
                 let expr: Box<NodeType> = expr.node;
-                VariableInitialisation{ let_token, id, colon, ty: Some(ty), equals, expr: expr, span }
+                VariableInitialisation{ let_token, pattern, colon, ty: Some(ty), equals, expr, span }
             },
-            [let_token(let_token), identifier(id), colon(colon), equals(equals), expression(expr)] => {
+            [let_token(let_token), pattern(pattern), colon(colon), equals(equals), expression(expr)] => {
                 let expr: Box<NodeType> = expr.node;
-                VariableInitialisation{ let_token, id, colon, ty: None, equals, expr: expr, span }
+                VariableInitialisation{ let_token, pattern, colon, ty: None, equals, expr, span }
             },
+        );
+        macros.borrow_mut().bind(&initialisation.pattern);
+        Ok(initialisation)
+    }
+
+    //
+    // Macros
+    //
+
+    // Registers a macro, whose template is parsed but only checked once expanded
+    fn macro_definition(input: Node) -> Result<()> {
+        let span: Span = Span::from_span(input.as_span());
+        let macros = input.user_data();
+        macros.borrow_mut().begin_template().map_err(|error| input.error(error))?;
+
+        let mut id: Option<Identifier> = None;
+        let mut params: Vec<Identifier> = vec!();
+        let mut template: Option<Block> = None;
+
+        for n in input.children() {
+            let parsed = match n.as_rule() {
+                Rule::identifier => Self::identifier(n).map(|ident| id = Some(ident)),
+                Rule::macro_parameters => Self::macro_parameters(n).map(|list| params = list),
+                Rule::block => Self::block(n).map(|block| template = Some(block)),
+                _ => Ok(())
+            };
+            if parsed.is_err() {
+                macros.borrow_mut().end_template();
+                parsed?;
+            }
+        }
+
+        let bindings = macros.borrow_mut().end_template();
+        let definition = Macro{ id: id.unwrap(), params, template: template.unwrap(), bindings, span };
+        macros.borrow_mut().define(definition).map_err(|error| input.error(error))
+    }
+
+    fn macro_parameters(input: Node) -> Result<Vec<Identifier>> {
+        let mut params: Vec<Identifier> = vec!();
+        for n in input.into_children() {
+            if n.as_rule() == Rule::metavariable {
+                let param = Self::metavariable(n.clone())?;
+                if params.iter().any(|other| other.equals(&param)) {
+                    return Err(n.error(format!("Macro parameter '{}' is declared more than once", param.name)));
+                }
+                params.push(param);
+            }
+        }
+        Ok(params)
+    }
+
+    // Invocation of a macro, replaced by a block holding the expanded template
+    fn macro_call(input: Node) -> Result<Block> {
+        let span: Span = Span::from_span(input.as_span());
+        let macros = input.user_data();
+        let (id, args) = match_nodes!(input.children();
+            [identifier(id), bang(_), lparen(_), rparen(_)] => (id, vec!()),
+            [identifier(id), bang(_), lparen(_), call_parameter_list(args), rparen(_)] => (id, args)
+        );
+        macros.borrow_mut().expand(&id, args, span).map_err(|error| input.error(error))
+    }
+
+    // Invocation of a macro as a statement, whose template statements join the enclosing block
+    fn macro_statement(input: Node) -> Result<Vec<Box<NodeType>>> {
+        Ok(match_nodes!(input.into_children();
+            [macro_call(block), semicolon(_)] => {
+                let mut stmts: Vec<Box<NodeType>> = block.stmts;
+                if let Some(expr) = block.tail {
+                    let span: Span = expr.span().unwrap();
+                    stmts.push(Box::new(NodeType::Expression(ExpressionStatement{ expr, span })));
+                }
+                stmts
+            }
+        ))
+    }
+
+    fn const_definition(input: Node) -> Result<ConstantDefinition> {
+        let span: Span = Span::from_span(input.as_span());
+        Ok(match_nodes!(input.into_children();
+            [const_token(const_token), identifier(id), colon(colon), type_specifier(ty), equals(equals), expression(expr)] => {
+                ConstantDefinition{ docs: None, const_token, id, colon, ty: Some(ty), equals, expr: expr.node, span }
+            },
+            [const_token(const_token), identifier(id), colon(colon), equals(equals), expression(expr)] => {
+                ConstantDefinition{ docs: None, const_token, id, colon, ty: None, equals, expr: expr.node, span }
+            }
         ))
     }
 
+    fn type_statement(input: Node) -> Result<TypeAlias> {
+        let span: Span = Span::from_span(input.as_span());
+        Ok(match_nodes!(input.into_children();
+            [type_token(type_token), identifier(id), colon(colon), type_specifier(ty)] => {
+                TypeAlias{ docs: None, type_token, id, colon, ty, span }
+            }
+        ))
+    }
+
+    fn struct_definition(input: Node) -> Result<StructDefinition> {
+        let span: Span = Span::from_span(input.as_span());
+        let mut struct_token: Option<Token> = None;
+        let mut id: Option<Identifier> = None;
+        let mut lbrace: Option<Token> = None;
+        let mut fields: Vec<Field> = vec!();
+        let mut rbrace: Option<Token> = None;
+
+        for n in input.into_children() {
+            match n.as_rule() {
+                Rule::struct_token => struct_token = Some(Self::struct_token(n)?),
+                Rule::identifier => id = Some(Self::identifier(n)?),
+                Rule::lbrace => lbrace = Some(Self::lbrace(n)?),
+                Rule::field => fields.push(Self::field(n)?),
+                Rule::rbrace => rbrace = Some(Self::rbrace(n)?),
+                _ => {}
+            }
+        }
+
+        Ok(StructDefinition{
+            docs: None,
+            struct_token: struct_token.unwrap(),
+            id: id.unwrap(),
+            lbrace: lbrace.unwrap(),
+            fields,
+            rbrace: rbrace.unwrap(),
+            span
+        })
+    }
+
     fn return_statement(input: Node) -> Result<ReturnStatement> {
         let span: Span = Span::from_span(input.as_span());
         Ok(match_nodes!(input.into_children();
@@ -363,6 +1172,51 @@ impl GeckoParser {
         ))
     }
 
+    // Deferred statements wrap any other small statement
+    fn defer_statement(input: Node) -> Result<DeferStatement> {
+        let span: Span = Span::from_span(input.as_span());
+        Ok(match_nodes!(input.into_children();
+            [defer_token(defer_token), expression_statement(stmt)] => {
+                DeferStatement{ defer_token, stmt: Box::new(NodeType::Expression(stmt)), span }
+            },
+            [defer_token(defer_token), return_statement(stmt)] => {
+                DeferStatement{ defer_token, stmt: Box::new(NodeType::Return(stmt)), span }
+            },
+            [defer_token(defer_token), variable_declaration(stmt)] => {
+                DeferStatement{ defer_token, stmt: Box::new(NodeType::VariableDeclaration(stmt)), span }
+            },
+            [defer_token(defer_token), variable_initialisation(stmt)] => {
+                DeferStatement{ defer_token, stmt: Box::new(NodeType::VariableInitialisation(stmt)), span }
+            },
+            [defer_token(defer_token), defer_statement(stmt)] => {
+                DeferStatement{ defer_token, stmt: Box::new(NodeType::Defer(stmt)), span }
+            },
+            [defer_token(defer_token), delete_statement(stmt)] => {
+                DeferStatement{ defer_token, stmt: Box::new(NodeType::Delete(stmt)), span }
+            }
+        ))
+    }
+
+    fn assert_statement(input: Node) -> Result<AssertStatement> {
+        let span: Span = Span::from_span(input.as_span());
+        let text: String = condition_text(&input);
+        Ok(match_nodes!(input.children();
+            [assert_token(assert_token), lparen(_), expression(condition), rparen(_)] => {
+                AssertStatement{ assert_token, condition: condition.node, message: None, text, span }
+            },
+            [assert_token(assert_token), lparen(_), expression(condition), comma(_), expression(message), rparen(_)] => {
+                AssertStatement{ assert_token, condition: condition.node, message: Some(message.node), text, span }
+            }
+        ))
+    }
+
+    fn delete_statement(input: Node) -> Result<DeleteStatement> {
+        let span: Span = Span::from_span(input.as_span());
+        Ok(match_nodes!(input.into_children();
+            [delete_token(delete_token), expression(expr)] => DeleteStatement{ delete_token, expr: expr.node, span }
+        ))
+    }
+
     fn expression_statement(input: Node) -> Result<ExpressionStatement> {
         let span: Span = Span::from_span(input.as_span());
         Ok(match_nodes!(input.into_children();
@@ -404,21 +1258,46 @@ impl GeckoParser {
         let span: Span = Span::from_span(input.as_span());
         let nodes = { input.into_children() };
         let mut statements: Vec<Box<NodeType>> = Vec::new();
+        let mut comments: Vec<DocComment> = vec!();
+        let mut attributes: Vec<Attribute> = vec!();
         for node in nodes {
             let rule = node.as_rule();
+            if rule == Rule::doc_comment {
+                comments.push(Self::doc_comment(node)?);
+                continue;
+            }
+            if rule == Rule::decorator {
+                attributes.push(Self::decorator(node)?);
+                continue;
+            }
+            let count: usize = statements.len();
 
             // Match and build all Statements
             match rule {
                 Rule::EOI => {},
                 Rule::expression_statement => statements.push(Box::new(NodeType::Expression(Self::expression_statement(node)?))),
                 Rule::return_statement => statements.push(Box::new(NodeType::Return(Self::return_statement(node)?))),
+                Rule::defer_statement => statements.push(Box::new(NodeType::Defer(Self::defer_statement(node)?))),
+                Rule::delete_statement => statements.push(Box::new(NodeType::Delete(Self::delete_statement(node)?))),
+                Rule::assert_statement => statements.push(Box::new(NodeType::Assert(Self::assert_statement(node)?))),
+                Rule::const_definition => statements.push(Box::new(NodeType::ConstantDefinition(Self::const_definition(node)?))),
+                Rule::type_statement => statements.push(Box::new(NodeType::TypeAlias(Self::type_statement(node)?))),
                 Rule::variable_declaration => statements.push(Box::new(NodeType::VariableDeclaration(Self::variable_declaration(node)?))),
                 Rule::variable_initialisation => statements.push(Box::new(NodeType::VariableInitialisation(Self::variable_initialisation(node)?))),
                 Rule::function_definition => statements.push(Box::new(NodeType::FunctionDefinition(Self::function_definition(node)?))),
+                Rule::function_declaration => statements.push(Box::new(NodeType::FunctionDeclaration(Self::function_declaration(node)?))),
+                Rule::struct_definition => statements.push(Box::new(NodeType::StructDefinition(Self::struct_definition(node)?))),
+                Rule::if_expression => statements.push(Box::new(NodeType::If(Self::if_expression(node)?))),
+                Rule::block => statements.push(Box::new(NodeType::Block(Self::block(node)?))),
+                Rule::unsafe_block => statements.push(Box::new(NodeType::Unsafe(Self::unsafe_block(node)?))),
+                Rule::macro_definition => Self::macro_definition(node)?,
+                Rule::macro_statement => statements.extend(Self::macro_statement(node)?),
                 // Rule::import_statement => statements.push(Box::new(Self::import_statement(node)?)),
                 // Rule::use_statement => statements.push(Box::new(Self::use_statement(node)?)),
                 _ => {}
             }
+            document(statements.get_mut(count), &mut comments);
+            decorate(statements.get_mut(count), &mut attributes);
         }
 
         Ok(File{ stmts: statements, span: Some(span) })
@@ -447,7 +1326,9 @@ impl LineColumn {
 #[derive(Copy, Clone, PartialEq)]
 pub struct Span {
     start: LineColumn,
-    end: LineColumn
+    end: LineColumn,
+    // Start of the macro call that produced a node, whose location is in the macro definition
+    call_site: Option<LineColumn>
 }
 
 impl Span {
@@ -457,10 +1338,28 @@ impl Span {
 
         Self{
             start: LineColumn{ line: start.0, column: start.1 },
-            end: LineColumn{ line: end.0, column: end.1 }
+            end: LineColumn{ line: end.0, column: end.1 },
+            call_site: None
         }
     }
 
+    // Marks a Span of a macro template as part of the expansion at the call
+    pub fn expanded_from(&mut self, call: Span) {
+        self.call_site = Some(call.start);
+    }
+
+    // Line and column the Span starts at, counting from 1
+    pub fn line(&self) -> usize {
+        self.start.line
+    }
+    pub fn column(&self) -> usize {
+        self.start.column
+    }
+    // Line the Span ends on
+    pub fn end_line(&self) -> usize {
+        self.end.line
+    }
+
     pub fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
         let marker = if is_last { String::from("└──") } else { String::from("├──") };
         let mut output: String = format!("{}{}{}", indent, marker, "Span".color("green"));
@@ -469,15 +1368,32 @@ impl Span {
         let start_tree: String = self.start.display_tree(&mut indent, false);
         output = format!("{}\n{}{}{}: \n{}", output, indent, "├──", "start".color("blue"), start_tree);
 
-        let end_tree: String = self.end.display_tree(&mut indent, true);
+        let end_tree: String = self.end.display_tree(&mut indent, self.call_site.is_none());
         output = format!("{}\n{}{}{}: \n{}", output, indent, "├──", "end".color("blue"), end_tree);
+
+        if let Some(call_site) = &self.call_site {
+            let call_tree: String = call_site.display_tree(&mut indent, true);
+            output = format!("{}\n{}{}{}: \n{}", output, indent, "├──", "call_site".color("blue"), call_tree);
+        }
         output
     }
 }
 
+// Start of the Span as line:column, used to locate errors
+impl std::fmt::Display for Span {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(fmt, "{}:{}", self.start.line, self.start.column)?;
+        match &self.call_site {
+            Some(call_site) => write!(fmt, " (in macro expanded at {}:{})", call_site.line, call_site.column),
+            None => Ok(())
+        }
+    }
+}
+
 // Parse worker function
 pub fn parse_gecko(input_str: &str) -> Result<File> {
-    let inputs = GeckoParser::parse_with_userdata(Rule::file, input_str, ())?;
+    let macros: RefCell<Macros> = RefCell::new(Macros::default());
+    let inputs = GeckoParser::parse_with_userdata(Rule::file, input_str, &macros)?;
     let input = inputs.single()?;
 
     GeckoParser::file(input)
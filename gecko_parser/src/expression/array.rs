@@ -0,0 +1,35 @@
+use crate::{
+    ast::Span,
+    expression::Expression,
+    node::{ASTNode, NodeType},
+    Token
+};
+
+use crate::colored::*;
+
+// Array literal, e.g. [1, 2, 3]
+#[derive(Clone)]
+pub struct Array {
+    pub lbracket: Token,
+    pub elements: Vec<Box<NodeType>>,
+    pub rbracket: Token,
+    pub span: Span
+}
+
+impl Expression for Array {}
+
+impl ASTNode for Array {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "Array".color("green"));
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        output = format!("{}{}\n", output, self.lbracket.display_tree(&mut indent, false));
+        for element in &self.elements {
+            output = format!("{}{}\n", output, element.display_tree(&mut indent, false));
+        }
+        output = format!("{}{}\n", output, self.rbracket.display_tree(&mut indent, false));
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
@@ -8,6 +8,7 @@ use crate::{
 
 use crate::colored::*;
 
+#[derive(Clone)]
 pub struct BinaryOperator {
     pub left: Box<NodeType>,
     pub op: Token,
@@ -0,0 +1,36 @@
+use crate::{
+    ast::Span,
+    expression::{Expression, Identifier},
+    node::{ASTNode, NodeType},
+    Token
+};
+
+use crate::colored::*;
+
+#[derive(Clone)]
+pub struct Call {
+    pub id: Identifier,
+    pub lparen: Token,
+    pub args: Vec<Box<NodeType>>,
+    pub rparen: Token,
+    pub span: Span
+}
+
+impl Expression for Call {}
+
+impl ASTNode for Call {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "Call".color("green"));
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        output = format!("{}{}\n", output, self.id.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.lparen.display_tree(&mut indent, false));
+        for arg in &self.args {
+            output = format!("{}{}\n", output, arg.display_tree(&mut indent, false));
+        }
+        output = format!("{}{}\n", output, self.rparen.display_tree(&mut indent, false));
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
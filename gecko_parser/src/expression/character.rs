@@ -5,6 +5,7 @@ use crate::Token;
 
 use crate::colored::*;
 
+#[derive(Clone)]
 pub struct Character {
     pub lquote: Token,
     pub value: u8,
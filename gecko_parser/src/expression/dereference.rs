@@ -0,0 +1,31 @@
+use crate::{
+    ast::Span,
+    expression::Expression,
+    node::{ASTNode, NodeType},
+    Token
+};
+
+use crate::colored::*;
+
+// Value a raw pointer points to, *ptr
+#[derive(Clone)]
+pub struct Dereference {
+    pub star: Token,
+    pub expr: Box<NodeType>,
+    pub span: Span
+}
+
+impl Expression for Dereference {}
+
+impl ASTNode for Dereference {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "Dereference".color("green"));
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        output = format!("{}{}\n", output, self.star.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.expr.display_tree(&mut indent, false));
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
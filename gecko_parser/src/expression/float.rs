@@ -4,6 +4,7 @@ use crate::node::ASTNode;
 
 use crate::colored::*;
 
+#[derive(Clone)]
 pub struct Float {
     pub value: f64,
     pub span: Span
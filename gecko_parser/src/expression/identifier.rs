@@ -7,12 +7,15 @@ use crate::colored::*;
 #[derive(Clone, PartialEq)]
 pub struct Identifier {
     pub name: String,
-    pub span: Span
+    pub span: Span,
+    // Set by macro expansion on the free identifiers of a template, which name the global symbol
+    // they named where the macro was defined rather than a local of the call site
+    pub global: bool
 }
 
 impl Identifier {
     pub fn equals(&self, id: &Identifier) -> bool {
-        if self.name == id.name {true} else {false}
+        self.name == id.name
     }
 }
 
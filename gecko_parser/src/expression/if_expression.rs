@@ -0,0 +1,42 @@
+use crate::{
+    ast::Span,
+    expression::Expression,
+    node::{ASTNode, Block, NodeType},
+    Token
+};
+
+use crate::colored::*;
+
+// Conditional whose value is that of the branch taken
+#[derive(Clone)]
+pub struct If {
+    pub if_token: Token,
+    pub condition: Box<NodeType>,
+    pub block: Block,
+    pub else_token: Option<Token>,
+    // Either a Block or another If
+    pub else_branch: Option<Box<NodeType>>,
+    pub span: Span
+}
+
+impl Expression for If {}
+
+impl ASTNode for If {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "If".color("green"));
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        output = format!("{}{}\n", output, self.if_token.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.condition.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.block.display_tree(&mut indent, false));
+        if let Some(else_token) = &self.else_token {
+            output = format!("{}{}\n", output, else_token.display_tree(&mut indent, false));
+        }
+        if let Some(else_branch) = &self.else_branch {
+            output = format!("{}{}\n", output, else_branch.display_tree(&mut indent, false));
+        }
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{
+    ast::Span,
+    expression::Expression,
+    node::{ASTNode, NodeType},
+    Token
+};
+
+use crate::colored::*;
+
+// Element access, e.g. a[i]
+#[derive(Clone)]
+pub struct Index {
+    pub expr: Box<NodeType>,
+    pub lbracket: Token,
+    pub index: Box<NodeType>,
+    pub rbracket: Token,
+    pub span: Span
+}
+
+impl Expression for Index {}
+
+impl ASTNode for Index {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "Index".color("green"));
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        output = format!("{}{}\n", output, self.expr.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.lbracket.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.index.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.rbracket.display_tree(&mut indent, false));
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
+
+// Sub-range of an array or slice, e.g. a[lo..hi], a[..hi] or a[lo..]
+#[derive(Clone)]
+pub struct Slice {
+    pub expr: Box<NodeType>,
+    pub lbracket: Token,
+    pub start: Option<Box<NodeType>>,
+    pub range: Token,
+    pub end: Option<Box<NodeType>>,
+    pub rbracket: Token,
+    pub span: Span
+}
+
+impl Expression for Slice {}
+
+impl ASTNode for Slice {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "Slice".color("green"));
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        output = format!("{}{}\n", output, self.expr.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.lbracket.display_tree(&mut indent, false));
+        if let Some(start) = &self.start {
+            output = format!("{}{}\n", output, start.display_tree(&mut indent, false));
+        }
+        output = format!("{}{}\n", output, self.range.display_tree(&mut indent, false));
+        if let Some(end) = &self.end {
+            output = format!("{}{}\n", output, end.display_tree(&mut indent, false));
+        }
+        output = format!("{}{}\n", output, self.rbracket.display_tree(&mut indent, false));
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
@@ -4,6 +4,7 @@ use crate::node::ASTNode;
 
 use crate::colored::*;
 
+#[derive(Clone)]
 pub struct Integer {
     pub value: i128,
     pub span: Span
@@ -0,0 +1,33 @@
+use crate::{
+    ast::Span,
+    expression::{Expression, Identifier},
+    node::{ASTNode, NodeType},
+    Token
+};
+
+use crate::colored::*;
+
+// Member access, e.g. a.len
+#[derive(Clone)]
+pub struct Member {
+    pub expr: Box<NodeType>,
+    pub dot: Token,
+    pub id: Identifier,
+    pub span: Span
+}
+
+impl Expression for Member {}
+
+impl ASTNode for Member {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "Member".color("green"));
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        output = format!("{}{}\n", output, self.expr.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.dot.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.id.display_tree(&mut indent, false));
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
@@ -11,11 +11,44 @@ pub use boolean::Boolean;
 pub use character::Character;
 pub use integer::Integer;
 pub use float::Float;
-pub use string::Str;
+pub use string::{Str, StringSegment};
 
 pub mod binary_operator;
 pub use binary_operator::BinaryOperator;
 
+pub mod call;
+pub use call::Call;
+
+pub mod array;
+pub use array::Array;
+
+pub mod index;
+pub use index::{Index, Slice};
+
+pub mod member;
+pub use member::Member;
+
+pub mod new_expression;
+pub use new_expression::New;
+
+pub mod if_expression;
+pub use if_expression::If;
+
+pub mod tuple;
+pub use tuple::Tuple;
+
+pub mod type_query;
+pub use type_query::{TypeQuery, TypeQueryKind};
+
+pub mod unsafe_block;
+pub use unsafe_block::UnsafeBlock;
+
+pub mod dereference;
+pub use dereference::Dereference;
+
+pub mod try_expression;
+pub use try_expression::Try;
+
  // Expression trade for all nodes that are expressions
 pub trait Expression {
 
@@ -0,0 +1,40 @@
+use crate::{
+    ast::Span,
+    expression::Expression,
+    node::{ASTNode, NodeType, TypeSpecifier},
+    Token
+};
+
+use crate::colored::*;
+
+// Heap allocation, either new T(args) or new [T; length]
+#[derive(Clone)]
+pub struct New {
+    pub new_token: Token,
+    pub ty: TypeSpecifier,
+    pub args: Vec<Box<NodeType>>,
+    // Number of elements when allocating an array
+    pub length: Option<Box<NodeType>>,
+    pub span: Span
+}
+
+impl Expression for New {}
+
+impl ASTNode for New {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "New".color("green"));
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        output = format!("{}{}\n", output, self.new_token.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.ty.display_tree(&mut indent, false));
+        for arg in &self.args {
+            output = format!("{}{}\n", output, arg.display_tree(&mut indent, false));
+        }
+        if let Some(length) = &self.length {
+            output = format!("{}{}\n", output, length.display_tree(&mut indent, false));
+        }
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
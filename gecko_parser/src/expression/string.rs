@@ -1,11 +1,21 @@
 use crate::ast::Span;
 use crate::expression::Expression;
-use crate::node::ASTNode;
+use crate::node::{ASTNode, NodeType};
 
 use crate::colored::*;
 
+#[derive(Clone)]
+pub enum StringSegment {
+    // Literal text, with escape sequences already resolved
+    Text(String),
+    // Expression whose value is formatted into the string
+    Interpolation(Box<NodeType>),
+}
+
+#[derive(Clone)]
 pub struct Str {
     pub string: String,
+    pub segments: Vec<StringSegment>,
     pub span: Span
 }
 
@@ -17,8 +27,13 @@ impl ASTNode for Str {
         let mut output: String = format!("{}{}{}", indent, marker, "String".color("green"));
         let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
 
-        output = format!("{}\n{}{}{} {}", output, indent, "├──", "value:", self.string);
+        output = format!("{}\n{}{}{} {}\n", output, indent, "├──", "value:", self.string);
+        for segment in &self.segments {
+            if let StringSegment::Interpolation(expr) = segment {
+                output = format!("{}{}\n", output, expr.display_tree(&mut indent, false));
+            }
+        }
         output = format!("{}{}\n", output, self.span.display_tree(&mut indent, true));
         output
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,31 @@
+use crate::{
+    ast::Span,
+    expression::Expression,
+    node::{ASTNode, NodeType},
+    Token
+};
+
+use crate::colored::*;
+
+// Unwraps a Result, returning its error from the enclosing procedure
+#[derive(Clone)]
+pub struct Try {
+    pub try_token: Token,
+    pub expr: Box<NodeType>,
+    pub span: Span
+}
+
+impl Expression for Try {}
+
+impl ASTNode for Try {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "Try".color("green"));
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        output = format!("{}{}\n", output, self.try_token.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.expr.display_tree(&mut indent, false));
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
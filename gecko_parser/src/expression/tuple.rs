@@ -0,0 +1,35 @@
+use crate::{
+    ast::Span,
+    expression::Expression,
+    node::{ASTNode, NodeType},
+    Token
+};
+
+use crate::colored::*;
+
+// Tuple of two or more values, e.g. (1, "one")
+#[derive(Clone)]
+pub struct Tuple {
+    pub lparen: Token,
+    pub elements: Vec<Box<NodeType>>,
+    pub rparen: Token,
+    pub span: Span
+}
+
+impl Expression for Tuple {}
+
+impl ASTNode for Tuple {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "Tuple".color("green"));
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        output = format!("{}{}\n", output, self.lparen.display_tree(&mut indent, false));
+        for element in &self.elements {
+            output = format!("{}{}\n", output, element.display_tree(&mut indent, false));
+        }
+        output = format!("{}{}\n", output, self.rparen.display_tree(&mut indent, false));
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
@@ -0,0 +1,53 @@
+use crate::{
+    ast::Span,
+    expression::{Expression, Identifier},
+    node::{ASTNode, TypeSpecifier},
+    Token
+};
+
+use crate::colored::*;
+
+#[derive(Clone)]
+pub enum TypeQueryKind {
+    // sizeof(T)
+    Size,
+    // alignof(T)
+    Align,
+    // offsetof(T, field)
+    Offset {
+        comma: Token,
+        field: Identifier
+    },
+}
+
+// Compile time query of the layout of a type, which evaluates to an integer constant
+#[derive(Clone)]
+pub struct TypeQuery {
+    pub token: Token,
+    pub kind: TypeQueryKind,
+    pub lparen: Token,
+    pub ty: TypeSpecifier,
+    pub rparen: Token,
+    pub span: Span
+}
+
+impl Expression for TypeQuery {}
+
+impl ASTNode for TypeQuery {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "TypeQuery".color("green"));
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        output = format!("{}{}\n", output, self.token.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.lparen.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.ty.display_tree(&mut indent, false));
+        if let TypeQueryKind::Offset{ comma, field } = &self.kind {
+            output = format!("{}{}\n", output, comma.display_tree(&mut indent, false));
+            output = format!("{}{}\n", output, field.display_tree(&mut indent, false));
+        }
+        output = format!("{}{}\n", output, self.rparen.display_tree(&mut indent, false));
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
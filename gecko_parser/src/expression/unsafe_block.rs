@@ -0,0 +1,31 @@
+use crate::{
+    ast::Span,
+    expression::Expression,
+    node::{ASTNode, Block},
+    Token
+};
+
+use crate::colored::*;
+
+// Block allowed to perform unsafe operations, e.g. dereferencing raw pointers
+#[derive(Clone)]
+pub struct UnsafeBlock {
+    pub unsafe_token: Token,
+    pub block: Block,
+    pub span: Span
+}
+
+impl Expression for UnsafeBlock {}
+
+impl ASTNode for UnsafeBlock {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "UnsafeBlock".color("green"));
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        output = format!("{}{}\n", output, self.unsafe_token.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.block.display_tree(&mut indent, false));
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::result_large_err, clippy::vec_box, clippy::large_enum_variant)]
+
 #[macro_use]
 extern crate lazy_static;
 
@@ -19,5 +21,7 @@ pub mod token;
 pub mod expression;
 pub mod node;
 pub mod precedence;
+pub mod macros;
+pub mod print;
 
 pub use token::Token;
\ No newline at end of file
@@ -0,0 +1,711 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    ast::Span,
+    expression::*,
+    node::*,
+    statement::*,
+    Token
+};
+
+// Macro defined by 'macro name($a, $b) { template }' and invoked as 'name!(a, b)'
+// Variables declared by the template are renamed in each expansion, and the free variables and procedures
+// it refers to are marked global so they resolve as they would where the macro is defined, not to a local
+// of the caller. Types are not scoped, so they are left as they are
+#[derive(Clone)]
+pub struct Macro {
+    pub id: Identifier,
+    pub params: Vec<Identifier>,
+    pub template: Block,
+    // Variables declared by the template, renamed in every expansion so they can't capture the caller's
+    pub bindings: HashSet<String>,
+    pub span: Span
+}
+
+// Macros defined so far in the file being parsed
+#[derive(Default)]
+pub struct Macros {
+    definitions: HashMap<String, Macro>,
+    // Variables declared by the template currently being parsed, if any
+    template: Option<HashSet<String>>,
+    expansions: usize
+}
+
+impl Macros {
+    pub fn in_template(&self) -> bool {
+        self.template.is_some()
+    }
+
+    // Starts collecting the variables declared by a template
+    pub fn begin_template(&mut self) -> Result<(), String> {
+        if self.in_template() {
+            return Err(String::from("Macros cannot be defined inside other macros"));
+        }
+        self.template = Some(HashSet::new());
+        Ok(())
+    }
+
+    pub fn end_template(&mut self) -> HashSet<String> {
+        self.template.take().unwrap_or_default()
+    }
+
+    // Records the variables a pattern declares, if it is part of a template
+    pub fn bind(&mut self, pattern: &Pattern) {
+        if let Some(bindings) = &mut self.template {
+            bind_pattern(pattern, bindings);
+        }
+    }
+
+    pub fn bind_identifier(&mut self, id: &Identifier) {
+        if let Some(bindings) = &mut self.template {
+            if !is_metavariable(id) {
+                bindings.insert(id.name.clone());
+            }
+        }
+    }
+
+    pub fn define(&mut self, definition: Macro) -> Result<(), String> {
+        if self.definitions.contains_key(&definition.id.name) {
+            return Err(format!("Macro '{}' is already defined", definition.id.name));
+        }
+        self.definitions.insert(definition.id.name.clone(), definition);
+        Ok(())
+    }
+
+    // Copy of the template of a macro with the arguments of a call substituted for its parameters
+    pub fn expand(&mut self, id: &Identifier, args: Vec<Box<NodeType>>, span: Span) -> Result<Block, String> {
+        let definition = match self.definitions.get(&id.name) {
+            Some(definition) => definition,
+            None => return Err(format!("Unknown macro '{}'", id.name))
+        };
+        if args.len() != definition.params.len() {
+            return Err(format!("Macro '{}' expects {} arguments, got {}", id.name, definition.params.len(), args.len()));
+        }
+
+        self.expansions += 1;
+        let mut expansion = Expansion{
+            args: definition.params.iter().map(|param| param.name.clone()).zip(args).collect(),
+            bindings: &definition.bindings,
+            items: definition.template.stmts.iter().filter_map(|stmt| item_name(stmt)).collect(),
+            suffix: self.expansions,
+            call_site: span,
+            error: None
+        };
+
+        let mut block: Block = definition.template.clone();
+        block.expand(&mut expansion);
+        if let Some(error) = expansion.error {
+            return Err(format!("In expansion of macro '{}': {}", id.name, error));
+        }
+
+        // The block as a whole stands in for the call
+        block.span = span;
+        Ok(block)
+    }
+}
+
+pub fn is_metavariable(id: &Identifier) -> bool {
+    id.name.starts_with('$')
+}
+
+// Name of a procedure or constant a statement of a template defines, unless it is given by the caller
+fn item_name(stmt: &NodeType) -> Option<String> {
+    let id = match stmt {
+        NodeType::FunctionDefinition(definition) => &definition.sig.id,
+        NodeType::FunctionDeclaration(declaration) => &declaration.sig.id,
+        NodeType::ConstantDefinition(definition) => &definition.id,
+        _ => return None
+    };
+    (!is_metavariable(id)).then(|| id.name.clone())
+}
+
+fn bind_pattern(pattern: &Pattern, bindings: &mut HashSet<String>) {
+    match &pattern.kind {
+        PatternKind::Identifier(id) if !is_metavariable(id) => {
+            bindings.insert(id.name.clone());
+        },
+        PatternKind::Identifier(_) | PatternKind::Wildcard(_) => {},
+        PatternKind::Tuple{ elements, .. } => {
+            for element in elements {
+                bind_pattern(element, bindings);
+            }
+        },
+        PatternKind::Struct{ fields, .. } => {
+            for field in fields {
+                match &field.pattern {
+                    Some(pattern) => bind_pattern(pattern, bindings),
+                    None if !is_metavariable(&field.id) => {
+                        bindings.insert(field.id.name.clone());
+                    },
+                    None => {}
+                }
+            }
+        }
+    }
+}
+
+// State of a single expansion of a macro
+struct Expansion<'a> {
+    args: HashMap<String, Box<NodeType>>,
+    bindings: &'a HashSet<String>,
+    // Procedures and constants defined by the template itself, which are not global
+    items: HashSet<String>,
+    // Distinguishes the variables of this expansion from those of the caller and other expansions
+    suffix: usize,
+    call_site: Span,
+    error: Option<String>
+}
+
+impl<'a> Expansion<'a> {
+    // Points a span of the template back at the call as well as the definition
+    fn span(&mut self, span: &mut Span) {
+        span.expanded_from(self.call_site);
+    }
+
+    fn token(&mut self, token: &mut Token) {
+        self.span(&mut token.span);
+    }
+
+    fn tokens<'t>(&mut self, tokens: impl Iterator<Item = &'t mut Token>) {
+        for token in tokens {
+            self.token(token);
+        }
+    }
+
+    // Argument substituted for a metavariable, which is left untouched as it belongs to the caller
+    fn argument(&mut self, id: &Identifier) -> Option<Box<NodeType>> {
+        match self.args.get(&id.name) {
+            Some(arg) => Some(arg.clone()),
+            None => {
+                self.error.get_or_insert(format!("Unknown macro variable '{}'", id.name));
+                None
+            }
+        }
+    }
+
+    // Name of an item, field or type, which may only be replaced by an identifier argument
+    fn name(&mut self, id: &mut Identifier) {
+        if is_metavariable(id) {
+            match self.argument(id).map(|arg| *arg) {
+                Some(NodeType::Identifier(arg)) => *id = arg,
+                Some(_) => {
+                    self.error.get_or_insert(format!("Macro variable '{}' must be an identifier here", id.name));
+                },
+                None => {}
+            }
+        } else {
+            self.span(&mut id.span);
+        }
+    }
+
+    // Variable, renamed if it was declared by the template; free variables are marked global
+    fn variable(&mut self, id: &mut Identifier) {
+        if is_metavariable(id) {
+            self.name(id);
+        } else {
+            if self.bindings.contains(&id.name) {
+                id.name = format!("{}#{}", id.name, self.suffix);
+            } else if !self.items.contains(&id.name) {
+                id.global = true;
+            }
+            self.span(&mut id.span);
+        }
+    }
+
+    // Procedure called by the template, marked global unless the template defines it
+    fn procedure(&mut self, id: &mut Identifier) {
+        if !is_metavariable(id) && !self.items.contains(&id.name) {
+            id.global = true;
+        }
+        self.name(id);
+    }
+
+    fn node(&mut self, node: &mut Box<NodeType>) {
+        if let NodeType::Identifier(id) = &**node {
+            if is_metavariable(id) {
+                if let Some(arg) = self.argument(id) {
+                    *node = arg;
+                }
+                return;
+            }
+        }
+        node.expand(self);
+    }
+
+    fn nodes<'n>(&mut self, nodes: impl Iterator<Item = &'n mut Box<NodeType>>) {
+        for node in nodes {
+            self.node(node);
+        }
+    }
+}
+
+// Walks a copy of a template, substituting arguments and renaming variables
+trait Expand {
+    fn expand(&mut self, cx: &mut Expansion);
+}
+
+impl<T: Expand> Expand for Option<T> {
+    fn expand(&mut self, cx: &mut Expansion) {
+        if let Some(inner) = self {
+            inner.expand(cx);
+        }
+    }
+}
+
+impl Expand for NodeType {
+    fn expand(&mut self, cx: &mut Expansion) {
+        match self {
+            NodeType::Token(node) => cx.token(node),
+            NodeType::Array(node) => node.expand(cx),
+            NodeType::BinaryOperator(node) => node.expand(cx),
+            NodeType::Boolean(node) => cx.span(&mut node.span),
+            NodeType::Call(node) => node.expand(cx),
+            NodeType::Character(node) => node.expand(cx),
+            NodeType::Dereference(node) => node.expand(cx),
+            NodeType::Float(node) => cx.span(&mut node.span),
+            NodeType::Integer(node) => cx.span(&mut node.span),
+            NodeType::Identifier(node) => cx.variable(node),
+            NodeType::If(node) => node.expand(cx),
+            NodeType::Index(node) => node.expand(cx),
+            NodeType::Member(node) => node.expand(cx),
+            NodeType::New(node) => node.expand(cx),
+            NodeType::Slice(node) => node.expand(cx),
+            NodeType::Str(node) => node.expand(cx),
+            NodeType::Tuple(node) => node.expand(cx),
+            NodeType::TypeQuery(node) => node.expand(cx),
+            NodeType::Unsafe(node) => node.expand(cx),
+            NodeType::Try(node) => node.expand(cx),
+            NodeType::Block(node) => node.expand(cx),
+            NodeType::File(node) => cx.nodes(node.stmts.iter_mut()),
+            NodeType::Output(node) => node.expand(cx),
+            NodeType::Pattern(node) => node.expand(cx),
+            NodeType::ParameterList(node) => node.expand(cx),
+            NodeType::Parameter(node) => node.expand(cx),
+            NodeType::Term(node) => {
+                cx.node(&mut node.node);
+                cx.span(&mut node.span);
+            },
+            NodeType::TypeSpecifier(node) => node.expand(cx),
+            NodeType::Signature(node) => node.expand(cx),
+            NodeType::Assert(node) => node.expand(cx),
+            NodeType::Defer(node) => node.expand(cx),
+            NodeType::Delete(node) => node.expand(cx),
+            NodeType::Expression(node) => node.expand(cx),
+            NodeType::FunctionDefinition(node) => node.expand(cx),
+            NodeType::FunctionDeclaration(node) => node.expand(cx),
+            NodeType::Return(node) => node.expand(cx),
+            NodeType::ConstantDefinition(node) => node.expand(cx),
+            NodeType::StructDefinition(node) => node.expand(cx),
+            NodeType::TypeAlias(node) => node.expand(cx),
+            NodeType::VariableDeclaration(node) => node.expand(cx),
+            NodeType::VariableInitialisation(node) => node.expand(cx),
+        }
+    }
+}
+
+//
+// Expression Nodes
+//
+
+impl Expand for Array {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.tokens([&mut self.lbracket, &mut self.rbracket].into_iter());
+        cx.nodes(self.elements.iter_mut());
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for BinaryOperator {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.node(&mut self.left);
+        cx.token(&mut self.op);
+        cx.node(&mut self.right);
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for Call {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.procedure(&mut self.id);
+        cx.tokens([&mut self.lparen, &mut self.rparen].into_iter());
+        cx.nodes(self.args.iter_mut());
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for Character {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.tokens([&mut self.lquote, &mut self.rquote].into_iter());
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for Dereference {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.token(&mut self.star);
+        cx.node(&mut self.expr);
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for If {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.token(&mut self.if_token);
+        cx.node(&mut self.condition);
+        self.block.expand(cx);
+        cx.tokens(self.else_token.iter_mut());
+        cx.nodes(self.else_branch.iter_mut());
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for Index {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.node(&mut self.expr);
+        cx.tokens([&mut self.lbracket, &mut self.rbracket].into_iter());
+        cx.node(&mut self.index);
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for Slice {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.node(&mut self.expr);
+        cx.tokens([&mut self.lbracket, &mut self.range, &mut self.rbracket].into_iter());
+        cx.nodes(self.start.iter_mut().chain(self.end.iter_mut()));
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for Member {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.node(&mut self.expr);
+        cx.token(&mut self.dot);
+        cx.name(&mut self.id);
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for New {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.token(&mut self.new_token);
+        self.ty.expand(cx);
+        cx.nodes(self.args.iter_mut().chain(self.length.iter_mut()));
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for Str {
+    fn expand(&mut self, cx: &mut Expansion) {
+        for segment in &mut self.segments {
+            if let StringSegment::Interpolation(expr) = segment {
+                cx.node(expr);
+            }
+        }
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for Tuple {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.tokens([&mut self.lparen, &mut self.rparen].into_iter());
+        cx.nodes(self.elements.iter_mut());
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for TypeQuery {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.tokens([&mut self.token, &mut self.lparen, &mut self.rparen].into_iter());
+        if let TypeQueryKind::Offset{ comma, field } = &mut self.kind {
+            cx.token(comma);
+            cx.name(field);
+        }
+        self.ty.expand(cx);
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for UnsafeBlock {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.token(&mut self.unsafe_token);
+        self.block.expand(cx);
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for Try {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.token(&mut self.try_token);
+        cx.node(&mut self.expr);
+        cx.span(&mut self.span);
+    }
+}
+
+//
+// Nodes
+//
+
+impl Expand for Block {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.tokens([&mut self.lbrace, &mut self.rbrace].into_iter());
+        cx.nodes(self.stmts.iter_mut().chain(self.tail.iter_mut()));
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for Output {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.token(&mut self.rarrow);
+        self.ty.expand(cx);
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for Pattern {
+    fn expand(&mut self, cx: &mut Expansion) {
+        match &mut self.kind {
+            PatternKind::Identifier(id) => cx.variable(id),
+            PatternKind::Wildcard(token) => cx.token(token),
+            PatternKind::Tuple{ lparen, elements, rparen } => {
+                cx.tokens([lparen, rparen].into_iter());
+                for element in elements {
+                    element.expand(cx);
+                }
+            },
+            PatternKind::Struct{ id, lbrace, fields, rest, rbrace } => {
+                cx.name(id);
+                cx.tokens([lbrace, rbrace].into_iter().chain(rest.iter_mut()));
+                for field in fields {
+                    field.expand(cx);
+                }
+            }
+        }
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for FieldPattern {
+    fn expand(&mut self, cx: &mut Expansion) {
+        // A field bound to a variable of the same name keeps its name, and only the variable is renamed
+        if self.pattern.is_none() && !is_metavariable(&self.id) && cx.bindings.contains(&self.id.name) {
+            let kind = PatternKind::Identifier(self.id.clone());
+            self.pattern = Some(Pattern{ kind, span: self.id.span });
+        }
+        cx.name(&mut self.id);
+        self.pattern.expand(cx);
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for ParameterList {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.tokens([&mut self.lparen, &mut self.rparen].into_iter().chain(self.variadic.iter_mut()));
+        for (param, comma) in &mut self.parameters {
+            param.expand(cx);
+            cx.tokens(comma.iter_mut());
+        }
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for Parameter {
+    fn expand(&mut self, cx: &mut Expansion) {
+        self.pattern.expand(cx);
+        cx.token(&mut self.colon_token);
+        self.ty.expand(cx);
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for TypeSpecifier {
+    fn expand(&mut self, cx: &mut Expansion) {
+        match &mut self.kind {
+            TypeKind::Named{ id, generics } => {
+                cx.name(id);
+                for generic in generics {
+                    generic.expand(cx);
+                }
+            },
+            TypeKind::Array{ lbracket, element, length, rbracket } => {
+                cx.tokens([lbracket, rbracket].into_iter());
+                element.expand(cx);
+                cx.nodes(length.iter_mut());
+            },
+            TypeKind::Pointer{ star, pointee } => {
+                cx.token(star);
+                pointee.expand(cx);
+            },
+            TypeKind::Tuple{ lparen, elements, rparen } => {
+                cx.tokens([lparen, rparen].into_iter());
+                for element in elements {
+                    element.expand(cx);
+                }
+            }
+        }
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for Field {
+    fn expand(&mut self, cx: &mut Expansion) {
+        self.docs.expand(cx);
+        cx.name(&mut self.id);
+        cx.token(&mut self.colon_token);
+        self.ty.expand(cx);
+        cx.span(&mut self.span);
+    }
+}
+
+//
+// Statement Nodes
+//
+
+impl Expand for Signature {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.tokens(self.const_token.iter_mut().chain(self.unsafe_token.iter_mut()));
+        cx.token(&mut self.func_token);
+        cx.name(&mut self.id);
+        self.params.expand(cx);
+        self.output.expand(cx);
+        for contract in &mut self.contracts {
+            contract.expand(cx);
+        }
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for DocComment {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for Attribute {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.node(&mut self.expr);
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for Contract {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.token(&mut self.token);
+        cx.node(&mut self.condition);
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for AssertStatement {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.token(&mut self.assert_token);
+        cx.node(&mut self.condition);
+        cx.nodes(self.message.iter_mut());
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for DeferStatement {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.token(&mut self.defer_token);
+        cx.node(&mut self.stmt);
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for DeleteStatement {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.token(&mut self.delete_token);
+        cx.node(&mut self.expr);
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for ExpressionStatement {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.node(&mut self.expr);
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for FunctionDefinition {
+    fn expand(&mut self, cx: &mut Expansion) {
+        for attribute in &mut self.attributes {
+            attribute.expand(cx);
+        }
+        self.docs.expand(cx);
+        self.sig.expand(cx);
+        self.block.expand(cx);
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for FunctionDeclaration {
+    fn expand(&mut self, cx: &mut Expansion) {
+        self.docs.expand(cx);
+        cx.tokens(self.extern_token.iter_mut());
+        self.sig.expand(cx);
+        cx.token(&mut self.semicolon);
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for ReturnStatement {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.token(&mut self.return_token);
+        cx.node(&mut self.expr);
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for ConstantDefinition {
+    fn expand(&mut self, cx: &mut Expansion) {
+        self.docs.expand(cx);
+        cx.tokens([&mut self.const_token, &mut self.colon, &mut self.equals].into_iter());
+        cx.name(&mut self.id);
+        self.ty.expand(cx);
+        cx.node(&mut self.expr);
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for StructDefinition {
+    fn expand(&mut self, cx: &mut Expansion) {
+        self.docs.expand(cx);
+        cx.tokens([&mut self.struct_token, &mut self.lbrace, &mut self.rbrace].into_iter());
+        cx.name(&mut self.id);
+        for field in &mut self.fields {
+            field.expand(cx);
+        }
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for TypeAlias {
+    fn expand(&mut self, cx: &mut Expansion) {
+        self.docs.expand(cx);
+        cx.tokens([&mut self.type_token, &mut self.colon].into_iter());
+        cx.name(&mut self.id);
+        self.ty.expand(cx);
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for VariableDeclaration {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.tokens([&mut self.let_token, &mut self.colon].into_iter());
+        cx.variable(&mut self.id);
+        self.ty.expand(cx);
+        cx.span(&mut self.span);
+    }
+}
+
+impl Expand for VariableInitialisation {
+    fn expand(&mut self, cx: &mut Expansion) {
+        cx.tokens([&mut self.let_token, &mut self.colon, &mut self.equals].into_iter());
+        self.pattern.expand(cx);
+        self.ty.expand(cx);
+        cx.node(&mut self.expr);
+        cx.span(&mut self.span);
+    }
+}
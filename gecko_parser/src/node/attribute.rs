@@ -0,0 +1,33 @@
+use crate::ast::Span;
+use crate::node::{ASTNode, NodeType};
+
+use crate::colored::*;
+
+// '#[...]' before an item, such as '#[entry]' marking the procedure a program starts at
+#[derive(Clone)]
+pub struct Attribute {
+    pub expr: Box<NodeType>,
+    pub span: Span
+}
+
+impl Attribute {
+    // Name of an attribute written as a single identifier
+    pub fn name(&self) -> Option<&str> {
+        match &*self.expr {
+            NodeType::Identifier(id) => Some(&id.name),
+            _ => None
+        }
+    }
+}
+
+impl ASTNode for Attribute {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "Attribute".color("yellow").dimmed());
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        output = format!("{}{}\n", output, self.expr.display_tree(&mut indent, false));
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
@@ -1,4 +1,3 @@
-
 use crate::ast::Span;
 use crate::Token;
 // use crate::statement::Statement;
@@ -6,10 +5,13 @@ use crate::node::{ASTNode, NodeType};
 
 use crate::colored::*;
 
+#[derive(Clone)]
 pub struct Block {
     // pub attrs: Vec<Attribute>; 
     pub lbrace: Token,
     pub stmts: Vec<Box<NodeType>>,
+    // Expression without a semicolon at the end of the block, giving its value
+    pub tail: Option<Box<NodeType>>,
     pub rbrace: Token,
     pub span: Span,
 }
@@ -26,8 +28,12 @@ impl ASTNode for Block {
             output = format!("{}\n{}", output, stmt.display_tree(&mut indent, false));
         }
 
+        if let Some(tail) = &self.tail {
+            output = format!("{}\n{}", output, tail.display_tree(&mut indent, false));
+        }
+
         output = format!("{}\n{}", output, self.rbrace.display_tree(&mut indent, false));
         output = format!("{}\n{}", output, self.span.display_tree(&mut indent, true));
         output
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,47 @@
+use crate::ast::Span;
+use crate::Token;
+use crate::node::{ASTNode, NodeType};
+
+use crate::colored::*;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ContractKind {
+    // Must hold when the procedure is called
+    Requires,
+    // Must hold when the procedure returns, with its return value bound to 'result'
+    Ensures,
+}
+
+impl ContractKind {
+    // Name of the check, used when it fails
+    pub fn description(&self) -> &'static str {
+        match self {
+            ContractKind::Requires => "Precondition",
+            ContractKind::Ensures => "Postcondition"
+        }
+    }
+}
+
+// Clause of a procedure signature, e.g. 'requires n > 0' or 'ensures result != 0'
+#[derive(Clone)]
+pub struct Contract {
+    pub token: Token,
+    pub kind: ContractKind,
+    pub condition: Box<NodeType>,
+    // Source text of the condition, reported when it fails
+    pub text: String,
+    pub span: Span
+}
+
+impl ASTNode for Contract {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "Contract".color("yellow").dimmed());
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        output = format!("{}{}\n", output, self.token.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.condition.display_tree(&mut indent, false));
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
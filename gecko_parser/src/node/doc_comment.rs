@@ -0,0 +1,37 @@
+use crate::ast::Span;
+use crate::node::ASTNode;
+
+use crate::colored::*;
+
+// Documentation of an item, written in '///' or '/** */' comments before it
+#[derive(Clone)]
+pub struct DocComment {
+    // Text of the comments without their delimiters, one comment per line
+    pub text: String,
+    pub span: Span
+}
+
+impl DocComment {
+    // First paragraph of the text, used to summarise the item
+    pub fn summary(&self) -> String {
+        self.text
+            .split("\n\n")
+            .next()
+            .unwrap_or_default()
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+}
+
+impl ASTNode for DocComment {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}", indent, marker, "DocComment".color("yellow").dimmed());
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        output = format!("{}\n{}{}{}: {:?}\n", output, indent, "├──", "text".color("blue"), self.text);
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
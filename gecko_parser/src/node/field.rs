@@ -0,0 +1,33 @@
+use crate::ast::Span;
+use crate::expression::Identifier;
+use crate::node::{TypeSpecifier, DocComment, ASTNode};
+use crate::Token;
+
+use crate::colored::*;
+
+// Typed field of a struct definition
+#[derive(Clone)]
+pub struct Field {
+    pub docs: Option<DocComment>,
+    pub id: Identifier,
+    pub colon_token: Token,
+    pub ty: TypeSpecifier,
+    pub span: Span,
+}
+
+impl ASTNode for Field {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "Field".color("yellow").dimmed());
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        if let Some(docs) = &self.docs {
+            output = format!("{}{}\n", output, docs.display_tree(&mut indent, false));
+        }
+        output = format!("{}{}\n", output, self.id.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.colon_token.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.ty.display_tree(&mut indent, false));
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
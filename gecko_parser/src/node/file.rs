@@ -4,6 +4,7 @@ use crate::node::{ASTNode, NodeType};
 
 use crate::colored::*;
 
+#[derive(Clone)]
 pub struct File {
     pub stmts: Vec<Box<NodeType>>,
     pub span: Option<Span>,
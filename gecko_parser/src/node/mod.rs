@@ -8,7 +8,7 @@ pub mod parameter_list;
 pub use parameter_list::ParameterList;
 
 pub mod type_specifier;
-pub use type_specifier::TypeSpecifier;
+pub use type_specifier::{TypeSpecifier, TypeKind};
 
 pub mod output;
 pub use output::Output;
@@ -19,43 +19,129 @@ pub use file::File;
 pub mod term;
 pub use term::Term;
 
+pub mod pattern;
+pub use pattern::{Pattern, PatternKind, FieldPattern};
+
+pub mod field;
+pub use field::Field;
+
+pub mod contract;
+pub use contract::{Contract, ContractKind};
+
+pub mod doc_comment;
+pub use doc_comment::DocComment;
+
+pub mod attribute;
+pub use attribute::Attribute;
+
 pub use crate::colored::*;
 
 use crate::{
     Token,
+    ast::Span,
     expression,
     node,
     statement,
 };
 
 #[enum_dispatch]
+#[derive(Clone)]
 pub enum NodeType {
     Token(Token),
 
+    Array(expression::Array),
     BinaryOperator(expression::BinaryOperator),
     Boolean(expression::Boolean),
+    Call(expression::Call),
     Character(expression::Character),
+    Dereference(expression::Dereference),
     Float(expression::Float),
     Integer(expression::Integer),
     Identifier(expression::Identifier),
+    If(expression::If),
+    Index(expression::Index),
+    Member(expression::Member),
+    New(expression::New),
+    Slice(expression::Slice),
     Str(expression::Str),
+    Tuple(expression::Tuple),
+    TypeQuery(expression::TypeQuery),
+    Unsafe(expression::UnsafeBlock),
+    Try(expression::Try),
     
     Block(node::Block),
     File(node::File),
     Output(node::Output),
+    Pattern(node::Pattern),
     ParameterList(node::ParameterList),
     Parameter(node::Parameter),
     Term(node::Term),
     TypeSpecifier(node::TypeSpecifier),
     Signature(statement::function_definition::Signature),
 
+    Assert(statement::AssertStatement),
+    Defer(statement::DeferStatement),
+    Delete(statement::DeleteStatement),
     Expression(statement::ExpressionStatement),
     FunctionDefinition(statement::FunctionDefinition),
+    FunctionDeclaration(statement::FunctionDeclaration),
     Return(statement::ReturnStatement),
+    ConstantDefinition(statement::ConstantDefinition),
+    StructDefinition(statement::StructDefinition),
+    TypeAlias(statement::TypeAlias),
     VariableDeclaration(statement::VariableDeclaration),
     VariableInitialisation(statement::VariableInitialisation),
 }
 
+impl NodeType {
+    // Location of the node in the source, which only a File may not have
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            NodeType::Token(node) => Some(node.span),
+            NodeType::Array(node) => Some(node.span),
+            NodeType::BinaryOperator(node) => Some(node.span),
+            NodeType::Boolean(node) => Some(node.span),
+            NodeType::Call(node) => Some(node.span),
+            NodeType::Character(node) => Some(node.span),
+            NodeType::Dereference(node) => Some(node.span),
+            NodeType::Float(node) => Some(node.span),
+            NodeType::Integer(node) => Some(node.span),
+            NodeType::Identifier(node) => Some(node.span),
+            NodeType::If(node) => Some(node.span),
+            NodeType::Index(node) => Some(node.span),
+            NodeType::Member(node) => Some(node.span),
+            NodeType::New(node) => Some(node.span),
+            NodeType::Slice(node) => Some(node.span),
+            NodeType::Str(node) => Some(node.span),
+            NodeType::Tuple(node) => Some(node.span),
+            NodeType::TypeQuery(node) => Some(node.span),
+            NodeType::Unsafe(node) => Some(node.span),
+            NodeType::Try(node) => Some(node.span),
+            NodeType::Block(node) => Some(node.span),
+            NodeType::File(node) => node.span,
+            NodeType::Output(node) => Some(node.span),
+            NodeType::Pattern(node) => Some(node.span),
+            NodeType::ParameterList(node) => Some(node.span),
+            NodeType::Parameter(node) => Some(node.span),
+            NodeType::Term(node) => Some(node.span),
+            NodeType::TypeSpecifier(node) => Some(node.span),
+            NodeType::Signature(node) => Some(node.span),
+            NodeType::Assert(node) => Some(node.span),
+            NodeType::Defer(node) => Some(node.span),
+            NodeType::Delete(node) => Some(node.span),
+            NodeType::Expression(node) => Some(node.span),
+            NodeType::FunctionDefinition(node) => Some(node.span),
+            NodeType::FunctionDeclaration(node) => Some(node.span),
+            NodeType::Return(node) => Some(node.span),
+            NodeType::ConstantDefinition(node) => Some(node.span),
+            NodeType::StructDefinition(node) => Some(node.span),
+            NodeType::TypeAlias(node) => Some(node.span),
+            NodeType::VariableDeclaration(node) => Some(node.span),
+            NodeType::VariableInitialisation(node) => Some(node.span),
+        }
+    }
+}
+
 // Trait which all Gecko AST nodes implement
 #[enum_dispatch(NodeType)]
 pub trait ASTNode {
@@ -5,6 +5,7 @@ use crate::node::{TypeSpecifier, ASTNode};
 
 use crate::colored::*;
 
+#[derive(Clone)]
 pub struct Output {
     pub rarrow: Token,
     pub ty: TypeSpecifier,
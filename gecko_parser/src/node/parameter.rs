@@ -1,14 +1,14 @@
 
 use crate::ast::Span;
-use crate::expression::Identifier;
-use crate::node::{TypeSpecifier, ASTNode};
+use crate::node::{TypeSpecifier, Pattern, ASTNode};
 use crate::Token;
 
 use crate::colored::*;
 
+#[derive(Clone)]
 pub struct Parameter {
     // pub attrs: Vec<Attribute>; 
-    pub id: Identifier,
+    pub pattern: Pattern,
     pub colon_token: Token,
     pub ty: TypeSpecifier,
     pub span: Span,
@@ -20,7 +20,7 @@ impl ASTNode for Parameter {
         let mut output: String = format!("{}{}{}\n", indent, marker, "Parameter".color("yellow").dimmed());
         let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
 
-        output = format!("{}{}\n", output, self.id.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.pattern.display_tree(&mut indent, false));
         output = format!("{}{}\n", output, self.colon_token.display_tree(&mut indent, false));
         output = format!("{}{}\n", output, self.ty.display_tree(&mut indent, false));
         output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
@@ -5,9 +5,12 @@ use crate::Token;
 
 use crate::colored::*;
 
+#[derive(Clone)]
 pub struct ParameterList {
     pub lparen: Token,
     pub parameters: Vec<(Parameter, Option<Token>)>,
+    // Trailing '...' of a C variadic procedure, which accepts any number of extra arguments
+    pub variadic: Option<Token>,
     pub rparen: Token,
     pub span: Span
 }
@@ -22,6 +25,9 @@ impl ASTNode for ParameterList {
         for (param, _) in &self.parameters {
             output = format!("{}{}\n", output, param.display_tree(&mut indent, false));
         }
+        if let Some(variadic) = &self.variadic {
+            output = format!("{}{}\n", output, variadic.display_tree(&mut indent, false));
+        }
         output = format!("{}{}\n", output, self.rparen.display_tree(&mut indent, false));
 
         output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
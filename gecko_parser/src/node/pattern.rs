@@ -0,0 +1,123 @@
+use std::fmt;
+
+use crate::ast::Span;
+use crate::expression::Identifier;
+use crate::node::ASTNode;
+use crate::Token;
+
+use crate::colored::*;
+
+#[derive(Clone)]
+pub enum PatternKind {
+    // Binds the whole value to a variable
+    Identifier(Identifier),
+    // Ignores the value, written as _
+    Wildcard(Token),
+    // Destructures a tuple, e.g. (a, b)
+    Tuple {
+        lparen: Token,
+        elements: Vec<Pattern>,
+        rparen: Token
+    },
+    // Destructures a struct, e.g. Point { x, y: (a, b), .. }
+    Struct {
+        id: Identifier,
+        lbrace: Token,
+        fields: Vec<FieldPattern>,
+        // Token of the trailing '..', which ignores the remaining fields
+        rest: Option<Token>,
+        rbrace: Token
+    },
+}
+
+#[derive(Clone)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub span: Span
+}
+
+// Field of a struct pattern, which binds a variable of the field's name if no pattern is given
+#[derive(Clone)]
+pub struct FieldPattern {
+    pub id: Identifier,
+    pub pattern: Option<Pattern>,
+    pub span: Span
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            PatternKind::Identifier(id) => write!(fmt, "{}", id.name),
+            PatternKind::Wildcard(_) => write!(fmt, "_"),
+            PatternKind::Tuple{ elements, .. } => {
+                let elements: Vec<String> = elements.iter().map(|element| element.to_string()).collect();
+                write!(fmt, "({})", elements.join(", "))
+            },
+            PatternKind::Struct{ id, fields, rest, .. } => {
+                let mut fields: Vec<String> = fields
+                    .iter()
+                    .map(|field| match &field.pattern {
+                        Some(pattern) => format!("{}: {}", field.id.name, pattern),
+                        None => field.id.name.clone()
+                    })
+                    .collect();
+                if rest.is_some() {
+                    fields.push(String::from(".."));
+                }
+                write!(fmt, "{} {{ {} }}", id.name, fields.join(", "))
+            }
+        }
+    }
+}
+
+impl ASTNode for Pattern {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "Pattern".color("yellow").dimmed());
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        match &self.kind {
+            PatternKind::Identifier(id) => {
+                output = format!("{}{}\n", output, id.display_tree(&mut indent, false));
+            },
+            PatternKind::Wildcard(token) => {
+                output = format!("{}{}\n", output, token.display_tree(&mut indent, false));
+            },
+            PatternKind::Tuple{ lparen, elements, rparen } => {
+                output = format!("{}{}\n", output, lparen.display_tree(&mut indent, false));
+                for element in elements {
+                    output = format!("{}{}\n", output, element.display_tree(&mut indent, false));
+                }
+                output = format!("{}{}\n", output, rparen.display_tree(&mut indent, false));
+            },
+            PatternKind::Struct{ id, lbrace, fields, rest, rbrace } => {
+                output = format!("{}{}\n", output, id.display_tree(&mut indent, false));
+                output = format!("{}{}\n", output, lbrace.display_tree(&mut indent, false));
+                for field in fields {
+                    output = format!("{}{}\n", output, field.display_tree(&mut indent, false));
+                }
+                if let Some(rest) = rest {
+                    output = format!("{}{}\n", output, rest.display_tree(&mut indent, false));
+                }
+                output = format!("{}{}\n", output, rbrace.display_tree(&mut indent, false));
+            }
+        }
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
+
+impl ASTNode for FieldPattern {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "FieldPattern".color("yellow").dimmed());
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        output = format!("{}{}\n", output, self.id.display_tree(&mut indent, false));
+        if let Some(pattern) = &self.pattern {
+            output = format!("{}{}\n", output, pattern.display_tree(&mut indent, false));
+        }
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
@@ -5,6 +5,7 @@ use crate::expression::Expression;
 
 use crate::colored::*;
 
+#[derive(Clone)]
 pub struct Term {
     // pub attrs: Vec<Attribute>; 
     pub node: Box<NodeType>,
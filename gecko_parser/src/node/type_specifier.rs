@@ -1,13 +1,42 @@
-
 use crate::ast::Span;
 use crate::expression::Identifier;
-use crate::node::ASTNode;
+use crate::node::{ASTNode, NodeType};
+use crate::Token;
 
 use crate::colored::*;
 
+#[derive(Clone)]
+pub enum TypeKind {
+    // Named type with optional generic arguments, e.g. int or Result<int, str>
+    Named {
+        id: Identifier,
+        generics: Vec<TypeSpecifier>
+    },
+    // Slice [T] or fixed size array [T; N]
+    Array {
+        lbracket: Token,
+        element: Box<TypeSpecifier>,
+        // Constant expression, e.g. [u8; sizeof(Point)]
+        length: Option<Box<NodeType>>,
+        rbracket: Token
+    },
+    // Pointer *T
+    Pointer {
+        star: Token,
+        pointee: Box<TypeSpecifier>
+    },
+    // Tuple (T, U)
+    Tuple {
+        lparen: Token,
+        elements: Vec<TypeSpecifier>,
+        rparen: Token
+    },
+}
+
+#[derive(Clone)]
 pub struct TypeSpecifier {
     // pub attrs: Vec<Attribute>; 
-    pub id: Identifier,
+    pub kind: TypeKind,
     pub span: Span
 }
 
@@ -17,8 +46,34 @@ impl ASTNode for TypeSpecifier {
         let mut output: String = format!("{}{}{}\n", indent, marker, "TypeSpecifier".color("yellow").dimmed());
         let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
 
-        output = format!("{}{}\n", output, self.id.display_tree(&mut indent, false));
+        match &self.kind {
+            TypeKind::Named{ id, generics } => {
+                output = format!("{}{}\n", output, id.display_tree(&mut indent, false));
+                for generic in generics {
+                    output = format!("{}{}\n", output, generic.display_tree(&mut indent, false));
+                }
+            },
+            TypeKind::Array{ lbracket, element, length, rbracket } => {
+                output = format!("{}{}\n", output, lbracket.display_tree(&mut indent, false));
+                output = format!("{}{}\n", output, element.display_tree(&mut indent, false));
+                if let Some(length) = length {
+                    output = format!("{}{}\n", output, length.display_tree(&mut indent, false));
+                }
+                output = format!("{}{}\n", output, rbracket.display_tree(&mut indent, false));
+            },
+            TypeKind::Pointer{ star, pointee } => {
+                output = format!("{}{}\n", output, star.display_tree(&mut indent, false));
+                output = format!("{}{}\n", output, pointee.display_tree(&mut indent, false));
+            },
+            TypeKind::Tuple{ lparen, elements, rparen } => {
+                output = format!("{}{}\n", output, lparen.display_tree(&mut indent, false));
+                for element in elements {
+                    output = format!("{}{}\n", output, element.display_tree(&mut indent, false));
+                }
+                output = format!("{}{}\n", output, rparen.display_tree(&mut indent, false));
+            }
+        }
         output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
         output
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,748 @@
+use crate::{
+    expression::*,
+    node::*,
+    statement::*,
+    Token
+};
+
+// Source text of a file, which shows what its macros expanded to. Variables a template declared
+// keep the 'name#N' they were renamed to in each expansion
+pub fn source(file: &File) -> String {
+    let mut out = Printer::default();
+    for (index, stmt) in file.stmts.iter().enumerate() {
+        if index > 0 {
+            out.line();
+        }
+        out.statement(stmt);
+        out.line();
+    }
+    out.text
+}
+
+#[derive(Default)]
+struct Printer {
+    text: String,
+    indent: usize
+}
+
+impl Printer {
+    fn write(&mut self, text: &str) {
+        self.text.push_str(text);
+    }
+
+    // Starts a new line at the current indentation
+    fn line(&mut self) {
+        self.text.push('\n');
+        self.text.push_str(&"    ".repeat(self.indent));
+    }
+
+    fn list<'p, T: Print + 'p>(&mut self, items: impl IntoIterator<Item = &'p T>) {
+        for (index, item) in items.into_iter().enumerate() {
+            if index > 0 {
+                self.write(", ");
+            }
+            item.print(self);
+        }
+    }
+
+    // Elements of a tuple, of which a single one is followed by a comma
+    fn tuple<T: Print>(&mut self, elements: &[T]) {
+        self.write("(");
+        self.list(elements);
+        if elements.len() == 1 {
+            self.write(",");
+        }
+        self.write(")");
+    }
+
+    // Statements other than items and blocks end with a semicolon
+    fn statement(&mut self, stmt: &NodeType) {
+        stmt.print(self);
+        match stmt {
+            NodeType::FunctionDefinition(_)
+                | NodeType::FunctionDeclaration(_)
+                | NodeType::StructDefinition(_)
+                | NodeType::If(_)
+                | NodeType::Block(_)
+                | NodeType::Unsafe(_) => {},
+            _ => self.write(";")
+        }
+    }
+
+    fn docs(&mut self, docs: &Option<DocComment>) {
+        if let Some(docs) = docs {
+            for line in docs.text.lines() {
+                self.write(if line.is_empty() { "///" } else { "/// " });
+                self.write(line);
+                self.line();
+            }
+        }
+    }
+
+    // Operand of a binary operator, bracketed if it would otherwise bind to its neighbours
+    fn operand(&mut self, node: &NodeType, op: &str, right: bool) {
+        let bracketed = match node {
+            NodeType::BinaryOperator(inner) => {
+                let (outer, inner) = (precedence(op), precedence(&inner.op.value));
+                inner < outer || (inner == outer && right != right_associative(op))
+            },
+            _ => false
+        };
+        self.bracketed(node, bracketed);
+    }
+
+    // Operand of a prefix or postfix operator, which only applies to a term
+    fn term(&mut self, node: &NodeType) {
+        self.bracketed(node, matches!(node, NodeType::BinaryOperator(_)));
+    }
+
+    // Operand of indexing, slicing or member access, which only follow calls, arrays, variables and brackets
+    fn postfix(&mut self, node: &NodeType) {
+        let bracketed = !matches!(node, NodeType::Call(_) | NodeType::Array(_) | NodeType::Identifier(_) | NodeType::Index(_) | NodeType::Slice(_) | NodeType::Member(_));
+        self.bracketed(node, bracketed);
+    }
+
+    fn bracketed(&mut self, node: &NodeType, bracketed: bool) {
+        if bracketed {
+            self.write("(");
+        }
+        node.print(self);
+        if bracketed {
+            self.write(")");
+        }
+    }
+}
+
+// Binding power of a binary operator, following precedence.rs
+fn precedence(op: &str) -> usize {
+    match op {
+        "||" => 1,
+        "&&" => 2,
+        "==" | "!=" => 3,
+        ">=" | "<=" | ">" | "<" => 4,
+        "^" | "|" => 5,
+        "&" => 6,
+        ">>" | "<<" => 7,
+        "+" | "-" => 8,
+        "%" | "/" | "*" => 9,
+        "**" => 10,
+        "as" => 11,
+        _ => 0
+    }
+}
+
+fn right_associative(op: &str) -> bool {
+    matches!(op, "==" | "!=" | "**")
+}
+
+// Text of a string literal segment with the characters string literals escape escaped again
+fn escape(text: &str) -> String {
+    let mut output = String::new();
+    for c in text.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\x07' => output.push_str("\\a"),
+            '\x08' => output.push_str("\\b"),
+            '\x0c' => output.push_str("\\f"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            '\x0b' => output.push_str("\\v"),
+            '{' => output.push_str("{{"),
+            '}' => output.push_str("}}"),
+            c if c.is_control() => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c)
+        }
+    }
+    output
+}
+
+// Writes a node as source text
+trait Print {
+    fn print(&self, out: &mut Printer);
+}
+
+impl<T: Print> Print for Box<T> {
+    fn print(&self, out: &mut Printer) {
+        (**self).print(out);
+    }
+}
+
+impl Print for NodeType {
+    fn print(&self, out: &mut Printer) {
+        match self {
+            NodeType::Token(node) => node.print(out),
+            NodeType::Array(node) => node.print(out),
+            NodeType::BinaryOperator(node) => node.print(out),
+            NodeType::Boolean(node) => out.write(if node.value { "true" } else { "false" }),
+            NodeType::Call(node) => node.print(out),
+            NodeType::Character(node) => out.write(&format!("'{}'", node.value as char)),
+            NodeType::Dereference(node) => {
+                out.write("*");
+                out.term(&node.expr);
+            },
+            NodeType::Float(node) => out.write(&format!("{:?}", node.value)),
+            NodeType::Integer(node) => out.write(&node.value.to_string()),
+            NodeType::Identifier(node) => node.print(out),
+            NodeType::If(node) => node.print(out),
+            NodeType::Index(node) => node.print(out),
+            NodeType::Member(node) => node.print(out),
+            NodeType::New(node) => node.print(out),
+            NodeType::Slice(node) => node.print(out),
+            NodeType::Str(node) => node.print(out),
+            NodeType::Tuple(node) => out.tuple(&node.elements),
+            NodeType::TypeQuery(node) => node.print(out),
+            NodeType::Unsafe(node) => {
+                out.write("unsafe ");
+                node.block.print(out);
+            },
+            NodeType::Try(node) => node.print(out),
+            NodeType::Block(node) => node.print(out),
+            NodeType::File(node) => out.write(&source(node)),
+            NodeType::Output(node) => node.print(out),
+            NodeType::Pattern(node) => node.print(out),
+            NodeType::ParameterList(node) => node.print(out),
+            NodeType::Parameter(node) => node.print(out),
+            NodeType::Term(node) => node.node.print(out),
+            NodeType::TypeSpecifier(node) => node.print(out),
+            NodeType::Signature(node) => node.print(out),
+            NodeType::Assert(node) => node.print(out),
+            NodeType::Defer(node) => {
+                out.write("defer ");
+                node.stmt.print(out);
+            },
+            NodeType::Delete(node) => {
+                out.write("delete ");
+                node.expr.print(out);
+            },
+            NodeType::Expression(node) => node.expr.print(out),
+            NodeType::FunctionDefinition(node) => node.print(out),
+            NodeType::FunctionDeclaration(node) => node.print(out),
+            NodeType::Return(node) => {
+                out.write(&node.return_token.value);
+                out.write(" ");
+                node.expr.print(out);
+            },
+            NodeType::ConstantDefinition(node) => node.print(out),
+            NodeType::StructDefinition(node) => node.print(out),
+            NodeType::TypeAlias(node) => node.print(out),
+            NodeType::VariableDeclaration(node) => node.print(out),
+            NodeType::VariableInitialisation(node) => node.print(out),
+        }
+    }
+}
+
+impl Print for Token {
+    fn print(&self, out: &mut Printer) {
+        out.write(&self.value);
+    }
+}
+
+impl Print for Identifier {
+    fn print(&self, out: &mut Printer) {
+        out.write(&self.name);
+    }
+}
+
+impl Print for Array {
+    fn print(&self, out: &mut Printer) {
+        out.write("[");
+        out.list(&self.elements);
+        out.write("]");
+    }
+}
+
+impl Print for BinaryOperator {
+    fn print(&self, out: &mut Printer) {
+        out.operand(&self.left, &self.op.value, false);
+        out.write(&format!(" {} ", self.op.value));
+        out.operand(&self.right, &self.op.value, true);
+    }
+}
+
+impl Print for Call {
+    fn print(&self, out: &mut Printer) {
+        self.id.print(out);
+        out.write("(");
+        out.list(&self.args);
+        out.write(")");
+    }
+}
+
+impl Print for If {
+    fn print(&self, out: &mut Printer) {
+        out.write("if ");
+        self.condition.print(out);
+        out.write(" ");
+        self.block.print(out);
+        if let Some(else_branch) = &self.else_branch {
+            out.write(" else ");
+            else_branch.print(out);
+        }
+    }
+}
+
+impl Print for Index {
+    fn print(&self, out: &mut Printer) {
+        out.postfix(&self.expr);
+        out.write("[");
+        self.index.print(out);
+        out.write("]");
+    }
+}
+
+impl Print for Slice {
+    fn print(&self, out: &mut Printer) {
+        out.postfix(&self.expr);
+        out.write("[");
+        if let Some(start) = &self.start {
+            start.print(out);
+        }
+        self.range.print(out);
+        if let Some(end) = &self.end {
+            end.print(out);
+        }
+        out.write("]");
+    }
+}
+
+impl Print for Member {
+    fn print(&self, out: &mut Printer) {
+        out.postfix(&self.expr);
+        out.write(".");
+        self.id.print(out);
+    }
+}
+
+impl Print for New {
+    fn print(&self, out: &mut Printer) {
+        out.write("new ");
+        match &self.length {
+            Some(length) => {
+                out.write("[");
+                self.ty.print(out);
+                out.write("; ");
+                length.print(out);
+                out.write("]");
+            },
+            None => {
+                self.ty.print(out);
+                out.write("(");
+                out.list(&self.args);
+                out.write(")");
+            }
+        }
+    }
+}
+
+impl Print for Str {
+    fn print(&self, out: &mut Printer) {
+        out.write("\"");
+        for segment in &self.segments {
+            match segment {
+                StringSegment::Text(text) => out.write(&escape(text)),
+                StringSegment::Interpolation(expr) => {
+                    out.write("{");
+                    expr.print(out);
+                    out.write("}");
+                }
+            }
+        }
+        out.write("\"");
+    }
+}
+
+impl Print for TypeQuery {
+    fn print(&self, out: &mut Printer) {
+        self.token.print(out);
+        out.write("(");
+        self.ty.print(out);
+        if let TypeQueryKind::Offset{ field, .. } = &self.kind {
+            out.write(", ");
+            field.print(out);
+        }
+        out.write(")");
+    }
+}
+
+impl Print for Try {
+    fn print(&self, out: &mut Printer) {
+        if self.try_token.value == "?" {
+            out.term(&self.expr);
+            out.write("?");
+        } else {
+            out.write("try ");
+            out.term(&self.expr);
+        }
+    }
+}
+
+impl Print for Block {
+    fn print(&self, out: &mut Printer) {
+        if self.stmts.is_empty() && self.tail.is_none() {
+            out.write("{}");
+            return;
+        }
+        out.write("{");
+        out.indent += 1;
+        for stmt in &self.stmts {
+            out.line();
+            out.statement(stmt);
+        }
+        if let Some(tail) = &self.tail {
+            out.line();
+            tail.print(out);
+        }
+        out.indent -= 1;
+        out.line();
+        out.write("}");
+    }
+}
+
+impl Print for Output {
+    fn print(&self, out: &mut Printer) {
+        out.write("-> ");
+        self.ty.print(out);
+    }
+}
+
+impl Print for Pattern {
+    fn print(&self, out: &mut Printer) {
+        match &self.kind {
+            PatternKind::Identifier(id) => id.print(out),
+            PatternKind::Wildcard(token) => token.print(out),
+            PatternKind::Tuple{ elements, .. } => out.tuple(elements),
+            PatternKind::Struct{ id, fields, rest, .. } => {
+                id.print(out);
+                if fields.is_empty() && rest.is_none() {
+                    out.write(" {}");
+                    return;
+                }
+                out.write(" { ");
+                out.list(fields);
+                if let Some(rest) = rest {
+                    if !fields.is_empty() {
+                        out.write(", ");
+                    }
+                    rest.print(out);
+                }
+                out.write(" }");
+            }
+        }
+    }
+}
+
+impl Print for FieldPattern {
+    fn print(&self, out: &mut Printer) {
+        self.id.print(out);
+        if let Some(pattern) = &self.pattern {
+            out.write(": ");
+            pattern.print(out);
+        }
+    }
+}
+
+impl Print for ParameterList {
+    fn print(&self, out: &mut Printer) {
+        out.write("(");
+        let parameters: Vec<&Parameter> = self.parameters.iter().map(|(param, _)| param).collect();
+        out.list(parameters);
+        if let Some(variadic) = &self.variadic {
+            out.write(", ");
+            variadic.print(out);
+        }
+        out.write(")");
+    }
+}
+
+impl Print for Parameter {
+    fn print(&self, out: &mut Printer) {
+        self.pattern.print(out);
+        out.write(": ");
+        self.ty.print(out);
+    }
+}
+
+impl Print for TypeSpecifier {
+    fn print(&self, out: &mut Printer) {
+        match &self.kind {
+            TypeKind::Named{ id, generics } => {
+                id.print(out);
+                if !generics.is_empty() {
+                    out.write("<");
+                    out.list(generics);
+                    out.write(">");
+                }
+            },
+            TypeKind::Array{ element, length, .. } => {
+                out.write("[");
+                element.print(out);
+                if let Some(length) = length {
+                    out.write("; ");
+                    length.print(out);
+                }
+                out.write("]");
+            },
+            TypeKind::Pointer{ pointee, .. } => {
+                out.write("*");
+                pointee.print(out);
+            },
+            TypeKind::Tuple{ elements, .. } => out.tuple(elements)
+        }
+    }
+}
+
+impl Print for Signature {
+    fn print(&self, out: &mut Printer) {
+        if self.const_token.is_some() {
+            out.write("const ");
+        }
+        if self.unsafe_token.is_some() {
+            out.write("unsafe ");
+        }
+        out.write("proc ");
+        self.id.print(out);
+        self.params.print(out);
+        out.write(" ");
+        self.output.print(out);
+        out.indent += 1;
+        for contract in &self.contracts {
+            out.line();
+            contract.token.print(out);
+            out.write(" ");
+            contract.condition.print(out);
+        }
+        out.indent -= 1;
+    }
+}
+
+impl Print for AssertStatement {
+    fn print(&self, out: &mut Printer) {
+        out.write("assert(");
+        self.condition.print(out);
+        if let Some(message) = &self.message {
+            out.write(", ");
+            message.print(out);
+        }
+        out.write(")");
+    }
+}
+
+impl Print for FunctionDefinition {
+    fn print(&self, out: &mut Printer) {
+        out.docs(&self.docs);
+        for attribute in &self.attributes {
+            out.write("#[");
+            attribute.expr.print(out);
+            out.write("]");
+            out.line();
+        }
+        self.sig.print(out);
+        // The body of a procedure with contracts starts on the line after them
+        if self.sig.contracts.is_empty() {
+            out.write(" ");
+        } else {
+            out.line();
+        }
+        self.block.print(out);
+    }
+}
+
+impl Print for FunctionDeclaration {
+    fn print(&self, out: &mut Printer) {
+        out.docs(&self.docs);
+        if self.extern_token.is_some() {
+            out.write("extern ");
+        }
+        self.sig.print(out);
+        out.write(";");
+    }
+}
+
+impl Print for ConstantDefinition {
+    fn print(&self, out: &mut Printer) {
+        out.docs(&self.docs);
+        out.write("const ");
+        self.id.print(out);
+        match &self.ty {
+            Some(ty) => {
+                out.write(": ");
+                ty.print(out);
+                out.write(" = ");
+            },
+            None => out.write(" := ")
+        }
+        self.expr.print(out);
+    }
+}
+
+impl Print for StructDefinition {
+    fn print(&self, out: &mut Printer) {
+        out.docs(&self.docs);
+        out.write("struct ");
+        self.id.print(out);
+        if self.fields.is_empty() {
+            out.write(" {}");
+            return;
+        }
+        out.write(" {");
+        out.indent += 1;
+        for field in &self.fields {
+            out.line();
+            out.docs(&field.docs);
+            field.id.print(out);
+            out.write(": ");
+            field.ty.print(out);
+            out.write(",");
+        }
+        out.indent -= 1;
+        out.line();
+        out.write("}");
+    }
+}
+
+impl Print for TypeAlias {
+    fn print(&self, out: &mut Printer) {
+        out.docs(&self.docs);
+        out.write("type ");
+        self.id.print(out);
+        out.write(": ");
+        self.ty.print(out);
+    }
+}
+
+impl Print for VariableDeclaration {
+    fn print(&self, out: &mut Printer) {
+        out.write("let ");
+        self.id.print(out);
+        out.write(": ");
+        self.ty.print(out);
+    }
+}
+
+impl Print for VariableInitialisation {
+    fn print(&self, out: &mut Printer) {
+        out.write("let ");
+        self.pattern.print(out);
+        match &self.ty {
+            Some(ty) => {
+                out.write(": ");
+                ty.print(out);
+                out.write(" = ");
+            },
+            None => out.write(" := ")
+        }
+        self.expr.print(out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::parse_gecko;
+    use super::source;
+
+    // Printed source parses back into a file that prints the same
+    fn round_trip(text: &str) -> String {
+        let printed: String = source(&parse_gecko(text).unwrap());
+        let reparsed = parse_gecko(&printed).unwrap_or_else(|error| panic!("{}\n{}", printed, error));
+        assert_eq!(source(&reparsed), printed);
+        printed
+    }
+
+    #[test]
+    fn prints_source() {
+        let text = "
+            /// Largest value
+            ///
+            /// Of two
+            const proc max(a: int, b: int) -> int requires a >= 0 ensures result >= a { if a > b { a } else { b } }
+            extern unsafe proc printf(format: *char, ...) -> i32;
+            struct Point { /// Across
+            x: int, y: [u8; 2 * 2] }
+            const LIMIT: u8 = (1 + 2) * 3 ** 2 ** 1;
+            /// Distance
+            type Meters: Result<(int, *u8), [bool]>;
+            #[entry]
+            proc main() -> int {
+                let Point{ x, .. } := Point(1, [1, 2, 3, 4]);
+                let (a, _): (int, bool) = (x as u8 as int, true);
+                let text := \"a {a}\\n \\\"{{x}}\\\"\";
+                let slice := [1, 2, 3][1..];
+                defer unsafe { printf(\"%c\", 'q'); };
+                let p: *int = new int(1);
+                delete p;
+                assert((a - (1 - 2)) == sizeof(Point), \"size\");
+                {}
+                return try Ok(2.5)? + offsetof(Point, y);
+            }
+        ";
+        assert_eq!(round_trip(text), "\
+/// Largest value
+///
+/// Of two
+const proc max(a: int, b: int) -> int
+    requires a >= 0
+    ensures result >= a
+{
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+extern unsafe proc printf(format: *char, ...) -> i32;
+
+struct Point {
+    /// Across
+    x: int,
+    y: [u8; 2 * 2],
+}
+
+const LIMIT: u8 = (1 + 2) * 3 ** 2 ** 1;
+
+/// Distance
+type Meters: Result<(int, *u8), [bool]>;
+
+#[entry]
+proc main() -> int {
+    let Point { x, .. } := Point(1, [1, 2, 3, 4]);
+    let (a, _): (int, bool) = (x as u8 as int, true);
+    let text := \"a {a}\\n \\\"{{x}}\\\"\";
+    let slice := [1, 2, 3][1..];
+    defer unsafe {
+        printf(\"%c\", 'q');
+    };
+    let p: *int = new int(1);
+    delete p;
+    assert(a - (1 - 2) == sizeof(Point), \"size\");
+    {}
+    return try Ok(2.5)? + offsetof(Point, y);
+}
+");
+    }
+
+    // Renamed variables are not valid source, so the expansion is not parsed again
+    #[test]
+    fn prints_expansions() {
+        let text = "
+            macro square($x) { let y := $x; y * y }
+            macro identity($name, $T) { proc $name(x: $T) -> $T { return x; } }
+            identity!(id, int);
+            proc f(n: int) -> int { square!(n + 1) }
+        ";
+        assert_eq!(source(&parse_gecko(text).unwrap()), "\
+proc id(x#1: int) -> int {
+    return x#1;
+}
+
+proc f(n: int) -> int {
+    {
+        let y#2 := n + 1;
+        y#2 * y#2
+    }
+}
+");
+    }
+}
@@ -0,0 +1,36 @@
+use crate::ast::Span;
+use crate::statement::Statement;
+use crate::node::{ASTNode, NodeType};
+use crate::Token;
+
+use crate::colored::*;
+
+// Checks that a condition holds at runtime, e.g. assert(n > 0, "n must be positive")
+#[derive(Clone)]
+pub struct AssertStatement {
+    pub assert_token: Token,
+    pub condition: Box<NodeType>,
+    // Optional str explaining the failure
+    pub message: Option<Box<NodeType>>,
+    // Source text of the condition, reported when it fails
+    pub text: String,
+    pub span: Span
+}
+
+impl Statement for AssertStatement {}
+
+impl ASTNode for AssertStatement {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "AssertStatement".color("red").dimmed());
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        output = format!("{}{}\n", output, self.assert_token.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.condition.display_tree(&mut indent, false));
+        if let Some(message) = &self.message {
+            output = format!("{}{}\n", output, message.display_tree(&mut indent, false));
+        }
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
@@ -0,0 +1,46 @@
+use crate::ast::Span;
+use crate::statement::Statement;
+use crate::node::{ASTNode, DocComment, NodeType, TypeSpecifier};
+use crate::expression::Identifier;
+use crate::Token;
+
+use crate::colored::*;
+
+// Named value that is known at compile time
+#[derive(Clone)]
+pub struct ConstantDefinition {
+    pub docs: Option<DocComment>,
+    pub const_token: Token,
+    pub id: Identifier,
+    pub colon: Token,
+    pub ty: Option<TypeSpecifier>,
+    pub equals: Token,
+    pub expr: Box<NodeType>,
+    pub span: Span
+}
+
+impl Statement for ConstantDefinition {}
+
+impl ASTNode for ConstantDefinition {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "ConstantDefinition".color("red").dimmed());
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        if let Some(docs) = &self.docs {
+            output = format!("{}{}\n", output, docs.display_tree(&mut indent, false));
+        }
+        output = format!("{}{}\n", output, self.const_token.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.id.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.colon.display_tree(&mut indent, false));
+
+        if let Some(ty) = &self.ty {
+            output = format!("{}{}\n", output, ty.display_tree(&mut indent, false));
+        }
+
+        output = format!("{}{}\n", output, self.equals.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.expr.display_tree(&mut indent, false));
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
@@ -0,0 +1,29 @@
+use crate::ast::Span;
+use crate::statement::Statement;
+use crate::node::{ASTNode, NodeType};
+use crate::Token;
+
+use crate::colored::*;
+
+// Statement that is run when the enclosing Block exits
+#[derive(Clone)]
+pub struct DeferStatement {
+    pub defer_token: Token,
+    pub stmt: Box<NodeType>,
+    pub span: Span
+}
+
+impl Statement for DeferStatement {}
+
+impl ASTNode for DeferStatement {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "DeferStatement".color("red").dimmed());
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        output = format!("{}{}\n", output, self.defer_token.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.stmt.display_tree(&mut indent, false));
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
@@ -0,0 +1,29 @@
+use crate::ast::Span;
+use crate::statement::Statement;
+use crate::node::{ASTNode, NodeType};
+use crate::Token;
+
+use crate::colored::*;
+
+// Frees memory allocated with 'new'
+#[derive(Clone)]
+pub struct DeleteStatement {
+    pub delete_token: Token,
+    pub expr: Box<NodeType>,
+    pub span: Span
+}
+
+impl Statement for DeleteStatement {}
+
+impl ASTNode for DeleteStatement {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "DeleteStatement".color("red").dimmed());
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        output = format!("{}{}\n", output, self.delete_token.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.expr.display_tree(&mut indent, false));
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
@@ -4,6 +4,7 @@ use crate::node::{ASTNode, NodeType};
 
 use crate::colored::*;
 
+#[derive(Clone)]
 pub struct ExpressionStatement {
     pub expr: Box<NodeType>,
     pub span: Span
@@ -0,0 +1,37 @@
+use crate::ast::Span;
+use crate::statement::{Statement, Signature};
+use crate::node::{ASTNode, DocComment};
+use crate::Token;
+
+use crate::colored::*;
+
+// Procedure without a body, defined elsewhere, e.g. 'extern proc puts(s: *char) -> int;'
+#[derive(Clone)]
+pub struct FunctionDeclaration {
+    pub docs: Option<DocComment>,
+    pub extern_token: Option<Token>,
+    pub sig: Signature,
+    pub semicolon: Token,
+    pub span: Span
+}
+
+impl Statement for FunctionDeclaration {}
+
+impl ASTNode for FunctionDeclaration {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "FunctionDeclaration".color("red").dimmed());
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        if let Some(docs) = &self.docs {
+            output = format!("{}{}\n", output, docs.display_tree(&mut indent, false));
+        }
+        if let Some(extern_token) = &self.extern_token {
+            output = format!("{}{}\n", output, extern_token.display_tree(&mut indent, false));
+        }
+        output = format!("{}{}", output, self.sig.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.semicolon.display_tree(&mut indent, false));
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
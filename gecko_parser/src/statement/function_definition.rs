@@ -1,6 +1,6 @@
 use crate::ast::Span;
 use crate::statement::Statement;
-use crate::node::{ParameterList, Output, Block, ASTNode};
+use crate::node::{ParameterList, Output, Contract, DocComment, Attribute, Block, ASTNode};
 use crate::Token;
 
 use crate::expression::Identifier;
@@ -10,9 +10,10 @@ use crate::expression::Identifier;
 
 use crate::colored::*;
 
+#[derive(Clone)]
 pub struct FunctionDefinition {
-    
-    // pub attributes: Vec<Attribute>,
+    pub attributes: Vec<Attribute>,
+    pub docs: Option<DocComment>,
     pub sig: Signature,
     pub block: Block,
     pub span: Span
@@ -20,12 +21,25 @@ pub struct FunctionDefinition {
 
 impl Statement for FunctionDefinition {}
 
+impl FunctionDefinition {
+    // Whether the procedure is marked with an attribute such as '#[entry]'
+    pub fn has_attribute(&self, name: &str) -> bool {
+        self.attributes.iter().any(|attribute| attribute.name() == Some(name))
+    }
+}
+
 impl ASTNode for FunctionDefinition {
     fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
         let marker = if is_last { String::from("└──") } else { String::from("├──") };
         let mut output: String = format!("{}{}{}\n", indent, marker, "FunctionDefinition".color("red").dimmed());
         let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
 
+        if let Some(docs) = &self.docs {
+            output = format!("{}{}\n", output, docs.display_tree(&mut indent, false));
+        }
+        for attribute in &self.attributes {
+            output = format!("{}{}\n", output, attribute.display_tree(&mut indent, false));
+        }
         output = format!("{}{}", output, self.sig.display_tree(&mut indent, false));
         output = format!("{}{}", output, self.block.display_tree(&mut indent, false));
         output = format!("{}\n{}", output, self.span.display_tree(&mut indent, true));
@@ -33,12 +47,19 @@ impl ASTNode for FunctionDefinition {
     }
 }
 
+#[derive(Clone)]
 pub struct Signature {
+    // Marks a procedure that can be evaluated at compile time
+    pub const_token: Option<Token>,
+    // Marks a procedure whose body may perform unsafe operations, and whose calls are unsafe
+    pub unsafe_token: Option<Token>,
     pub func_token: Token,
     pub id: Identifier,
     // pub generics: Vec<Generic>,
     pub params: ParameterList,
     pub output: Output,
+    // 'requires' and 'ensures' clauses, checked when the procedure is called and when it returns
+    pub contracts: Vec<Contract>,
     pub span: Span,
 }
 
@@ -48,10 +69,19 @@ impl ASTNode for Signature {
         let mut output: String = format!("{}{}{}\n", indent, marker, "Signature");
         let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
 
+        if let Some(const_token) = &self.const_token {
+            output = format!("{}{}\n", output, const_token.display_tree(&mut indent, false));
+        }
+        if let Some(unsafe_token) = &self.unsafe_token {
+            output = format!("{}{}\n", output, unsafe_token.display_tree(&mut indent, false));
+        }
         output = format!("{}{}\n", output, self.func_token.display_tree(&mut indent, false));
         output = format!("{}{}\n", output, self.id.display_tree(&mut indent, false));
         output = format!("{}{}\n", output, self.params.display_tree(&mut indent, false));
         output = format!("{}{}\n", output, self.output.display_tree(&mut indent, false));
+        for contract in &self.contracts {
+            output = format!("{}{}\n", output, contract.display_tree(&mut indent, false));
+        }
         output = format!("{}{}\n", output, self.span.display_tree(&mut indent, true));
         output
     }
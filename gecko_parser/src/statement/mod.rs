@@ -3,6 +3,9 @@ pub mod function_definition;
 pub use function_definition::FunctionDefinition;
 pub use function_definition::Signature;
 
+pub mod function_declaration;
+pub use function_declaration::FunctionDeclaration;
+
 pub mod variable_declaration;
 pub mod variable_initialisation;
 pub use variable_declaration::VariableDeclaration;
@@ -14,6 +17,24 @@ pub use return_statement::ReturnStatement;
 pub mod expression_statement;
 pub use expression_statement::ExpressionStatement;
 
+pub mod defer_statement;
+pub use defer_statement::DeferStatement;
+
+pub mod delete_statement;
+pub use delete_statement::DeleteStatement;
+
+pub mod assert_statement;
+pub use assert_statement::AssertStatement;
+
+pub mod struct_definition;
+pub use struct_definition::StructDefinition;
+
+pub mod constant_definition;
+pub use constant_definition::ConstantDefinition;
+
+pub mod type_alias;
+pub use type_alias::TypeAlias;
+
  // trait that all AST Nodes that are Statements implement
 pub trait Statement {
 
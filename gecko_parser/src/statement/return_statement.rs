@@ -6,6 +6,7 @@ use crate::Token;
 
 use crate::colored::*;
 
+#[derive(Clone)]
 pub struct ReturnStatement {
     pub return_token: Token,
     pub expr: Box<NodeType>,
@@ -0,0 +1,41 @@
+use crate::ast::Span;
+use crate::statement::Statement;
+use crate::node::{ASTNode, DocComment, Field};
+use crate::expression::Identifier;
+use crate::Token;
+
+use crate::colored::*;
+
+#[derive(Clone)]
+pub struct StructDefinition {
+    pub docs: Option<DocComment>,
+    pub struct_token: Token,
+    pub id: Identifier,
+    pub lbrace: Token,
+    pub fields: Vec<Field>,
+    pub rbrace: Token,
+    pub span: Span
+}
+
+impl Statement for StructDefinition {}
+
+impl ASTNode for StructDefinition {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "StructDefinition".color("red").dimmed());
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        if let Some(docs) = &self.docs {
+            output = format!("{}{}\n", output, docs.display_tree(&mut indent, false));
+        }
+        output = format!("{}{}\n", output, self.struct_token.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.id.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.lbrace.display_tree(&mut indent, false));
+        for field in &self.fields {
+            output = format!("{}{}\n", output, field.display_tree(&mut indent, false));
+        }
+        output = format!("{}{}\n", output, self.rbrace.display_tree(&mut indent, false));
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
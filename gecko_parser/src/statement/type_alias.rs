@@ -0,0 +1,38 @@
+use crate::ast::Span;
+use crate::statement::Statement;
+use crate::node::{ASTNode, DocComment, TypeSpecifier};
+use crate::expression::Identifier;
+use crate::Token;
+
+use crate::colored::*;
+
+// Another name for a type, 'type Meters: int'
+#[derive(Clone)]
+pub struct TypeAlias {
+    pub docs: Option<DocComment>,
+    pub type_token: Token,
+    pub id: Identifier,
+    pub colon: Token,
+    pub ty: TypeSpecifier,
+    pub span: Span
+}
+
+impl Statement for TypeAlias {}
+
+impl ASTNode for TypeAlias {
+    fn display_tree(&self, indent: &mut String, is_last: bool) -> String {
+        let marker = if is_last { String::from("└──") } else { String::from("├──") };
+        let mut output: String = format!("{}{}{}\n", indent, marker, "TypeAlias".color("red").dimmed());
+        let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
+
+        if let Some(docs) = &self.docs {
+            output = format!("{}{}\n", output, docs.display_tree(&mut indent, false));
+        }
+        output = format!("{}{}\n", output, self.type_token.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.id.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.colon.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.ty.display_tree(&mut indent, false));
+        output = format!("{}{}", output, self.span.display_tree(&mut indent, true));
+        output
+    }
+}
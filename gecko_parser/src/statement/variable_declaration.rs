@@ -7,6 +7,7 @@ use crate::Token;
 
 use crate::colored::*;
 
+#[derive(Clone)]
 pub struct VariableDeclaration {
     pub let_token: Token,
     pub id: Identifier,
@@ -1,15 +1,15 @@
 
 use crate::ast::Span;
 use crate::statement::Statement;
-use crate::node::{ASTNode, NodeType, TypeSpecifier};
-use crate::expression::Identifier;
+use crate::node::{ASTNode, NodeType, TypeSpecifier, Pattern};
 use crate::Token;
 
 use crate::colored::*;
 
+#[derive(Clone)]
 pub struct VariableInitialisation {
     pub let_token: Token,
-    pub id: Identifier,
+    pub pattern: Pattern,
     pub colon: Token,
     pub ty: Option<TypeSpecifier>,
     pub equals: Token,
@@ -26,11 +26,11 @@ impl ASTNode for VariableInitialisation {
         let mut indent: String = if is_last { (*indent).clone() + "    " } else { (*indent).clone() + "│   " };
 
         output = format!("{}{}\n", output, self.let_token.display_tree(&mut indent, false));
-        output = format!("{}{}\n", output, self.id.display_tree(&mut indent, false));
+        output = format!("{}{}\n", output, self.pattern.display_tree(&mut indent, false));
         output = format!("{}{}\n", output, self.colon.display_tree(&mut indent, false));
 
-        if !self.ty.is_none() {
-            output = format!("{}{}\n", output, self.ty.as_ref().unwrap().display_tree(&mut indent, false));
+        if let Some(ty) = &self.ty {
+            output = format!("{}{}\n", output, ty.display_tree(&mut indent, false));
         }
 
         output = format!("{}{}\n", output, self.equals.display_tree(&mut indent, false));
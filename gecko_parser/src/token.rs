@@ -3,6 +3,7 @@ use crate::node::ASTNode;
 
 use crate::colored::*;
 
+#[derive(Clone)]
 pub struct Token {
     pub value: String,
     pub span: Span
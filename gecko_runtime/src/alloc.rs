@@ -0,0 +1,101 @@
+use std::{
+    alloc::{alloc_zeroed, dealloc, handle_alloc_error, Layout},
+    mem::size_of,
+    process,
+    ptr
+};
+
+// Every allocation is prefixed with a header holding its size and alignment,
+// so that it can be freed from its pointer alone
+const HEADER_SIZE: usize = 2 * size_of::<usize>();
+
+// None when the alignment is not a power of two or the allocation would not fit in a usize
+fn layout(size: usize, align: usize) -> Option<(Layout, usize)> {
+    // Offset of the value from the start of the allocation. Alignments are
+    // powers of two, so the larger of the two is a multiple of both
+    if !align.is_power_of_two() {
+        return None;
+    }
+    let offset = align.max(HEADER_SIZE);
+    let layout = Layout::from_size_align(offset.checked_add(size)?, offset).ok()?;
+    Some((layout, offset))
+}
+
+/// Allocates zeroed memory for 'new'. Returns null if `size` and `align` do not
+/// describe a valid allocation
+///
+/// # Safety
+///
+/// The returned memory must only be freed with `__gecko_free`.
+#[no_mangle]
+pub unsafe extern "C" fn __gecko_alloc(size: usize, align: usize) -> *mut u8 {
+    let (layout, offset) = match layout(size, align) {
+        Some(layout) => layout,
+        None => return ptr::null_mut()
+    };
+    let base = alloc_zeroed(layout);
+    if base.is_null() {
+        handle_alloc_error(layout);
+    }
+
+    let ptr = base.add(offset);
+    let header = ptr.sub(HEADER_SIZE) as *mut usize;
+    header.write(size);
+    header.add(1).write(align);
+    ptr
+}
+
+/// Frees memory for 'delete'. Null pointers are ignored
+///
+/// # Safety
+///
+/// `ptr` must be null or have been returned by `__gecko_alloc` and not freed since.
+#[no_mangle]
+pub unsafe extern "C" fn __gecko_free(ptr: *mut u8) {
+    if ptr.is_null() {
+        return;
+    }
+
+    // Headers of valid allocations always hold a valid layout, so this one was overwritten
+    let header = ptr.sub(HEADER_SIZE) as *const usize;
+    let (layout, offset) = match layout(header.read(), header.add(1).read()) {
+        Some(layout) => layout,
+        None => {
+            eprintln!("Freed a pointer whose allocation header is corrupt");
+            process::abort()
+        }
+    };
+    dealloc(ptr.sub(offset), layout);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{__gecko_alloc, __gecko_free};
+
+    #[test]
+    fn aligned_and_zeroed() {
+        for align in [1, 8, 64, 4096] {
+            unsafe {
+                let ptr = __gecko_alloc(100, align);
+                assert_eq!(ptr as usize % align, 0);
+                assert!(std::slice::from_raw_parts(ptr, 100).iter().all(|byte| *byte == 0));
+                ptr.write_bytes(0xff, 100);
+                __gecko_free(ptr);
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_layouts() {
+        unsafe {
+            assert!(__gecko_alloc(usize::MAX, 8).is_null());
+            assert!(__gecko_alloc(isize::MAX as usize, 8).is_null());
+            assert!(__gecko_alloc(8, 3).is_null());
+        }
+    }
+
+    #[test]
+    fn free_null() {
+        unsafe { __gecko_free(std::ptr::null_mut()) };
+    }
+}
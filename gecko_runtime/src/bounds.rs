@@ -0,0 +1,16 @@
+use std::process;
+
+/// Reports an index out of the bounds of an array or slice of `length` elements and aborts
+#[no_mangle]
+pub extern "C" fn __gecko_index_out_of_bounds(index: isize, length: usize, line: u32, column: u32) -> ! {
+    eprintln!("Index {} is out of bounds for length {} at {}:{}", index, length, line, column);
+    process::abort()
+}
+
+/// Reports a slice `start..end` of an array or slice of `length` elements that is out of its
+/// bounds or reversed, and aborts
+#[no_mangle]
+pub extern "C" fn __gecko_slice_out_of_bounds(start: usize, end: usize, length: usize, line: u32, column: u32) -> ! {
+    eprintln!("Slice {}..{} is out of bounds for length {} at {}:{}", start, end, length, line, column);
+    process::abort()
+}
@@ -0,0 +1,24 @@
+use std::{process, slice};
+
+/// Reports a failed assertion (0), precondition (1) or postcondition (2) with the text and location
+/// of its condition, then aborts
+///
+/// # Safety
+///
+/// `text` must point to `text_len` bytes of UTF-8, and `message` to `message_len` bytes unless it is null.
+#[no_mangle]
+pub unsafe extern "C" fn __gecko_contract_failed(check: u32, text: *const u8, text_len: usize, message: *const u8, message_len: usize, line: u32, column: u32) -> ! {
+    let description = match check {
+        0 => "Assertion",
+        1 => "Precondition",
+        _ => "Postcondition"
+    };
+    let text = String::from_utf8_lossy(slice::from_raw_parts(text, text_len));
+    if message.is_null() {
+        eprintln!("{} `{}` failed at {}:{}", description, text, line, column);
+    } else {
+        let message = String::from_utf8_lossy(slice::from_raw_parts(message, message_len));
+        eprintln!("{} `{}` failed at {}:{}: {}", description, text, line, column, message);
+    }
+    process::abort()
+}
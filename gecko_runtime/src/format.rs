@@ -0,0 +1,133 @@
+use std::{ptr, slice};
+
+use crate::alloc::__gecko_alloc;
+
+// String being built from the segments of an interpolated string literal
+pub struct Formatter {
+    buffer: String
+}
+
+/// Starts building an interpolated string
+#[no_mangle]
+pub extern "C" fn __gecko_format_begin() -> *mut Formatter {
+    Box::into_raw(Box::new(Formatter{ buffer: String::new() }))
+}
+
+/// Appends literal text
+///
+/// # Safety
+///
+/// `formatter` must come from `__gecko_format_begin`, and `text` must point to `len` bytes of UTF-8.
+#[no_mangle]
+pub unsafe extern "C" fn __gecko_format_str(formatter: *mut Formatter, text: *const u8, len: usize) {
+    let text = slice::from_raw_parts(text, len);
+    (*formatter).buffer.push_str(&String::from_utf8_lossy(text));
+}
+
+/// Appends text `count` times, or not at all if `count` is negative
+///
+/// # Safety
+///
+/// `formatter` must come from `__gecko_format_begin`, and `text` must point to `len` bytes of UTF-8.
+#[no_mangle]
+pub unsafe extern "C" fn __gecko_format_repeat(formatter: *mut Formatter, text: *const u8, len: usize, count: i64) {
+    let text = String::from_utf8_lossy(slice::from_raw_parts(text, len));
+    (*formatter).buffer.push_str(&text.repeat(count.max(0) as usize));
+}
+
+/// Appends an integer in decimal
+///
+/// # Safety
+///
+/// `formatter` must come from `__gecko_format_begin`.
+#[no_mangle]
+pub unsafe extern "C" fn __gecko_format_int(formatter: *mut Formatter, value: i64) {
+    (*formatter).buffer.push_str(&value.to_string());
+}
+
+/// Appends an unsigned integer in decimal
+///
+/// # Safety
+///
+/// `formatter` must come from `__gecko_format_begin`.
+#[no_mangle]
+pub unsafe extern "C" fn __gecko_format_uint(formatter: *mut Formatter, value: u64) {
+    (*formatter).buffer.push_str(&value.to_string());
+}
+
+/// Appends a float
+///
+/// # Safety
+///
+/// `formatter` must come from `__gecko_format_begin`.
+#[no_mangle]
+pub unsafe extern "C" fn __gecko_format_float(formatter: *mut Formatter, value: f64) {
+    (*formatter).buffer.push_str(&value.to_string());
+}
+
+/// Appends 'true' or 'false'
+///
+/// # Safety
+///
+/// `formatter` must come from `__gecko_format_begin`.
+#[no_mangle]
+pub unsafe extern "C" fn __gecko_format_bool(formatter: *mut Formatter, value: bool) {
+    (*formatter).buffer.push_str(if value { "true" } else { "false" });
+}
+
+/// Appends a single character
+///
+/// # Safety
+///
+/// `formatter` must come from `__gecko_format_begin`.
+#[no_mangle]
+pub unsafe extern "C" fn __gecko_format_char(formatter: *mut Formatter, value: u8) {
+    (*formatter).buffer.push(value as char);
+}
+
+/// Finishes the string, returning its bytes allocated as if by 'new' and
+/// writing their length to `len`
+///
+/// # Safety
+///
+/// `formatter` must come from `__gecko_format_begin` and is freed by this call.
+/// `len` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn __gecko_format_end(formatter: *mut Formatter, len: *mut usize) -> *mut u8 {
+    let formatter = Box::from_raw(formatter);
+    let bytes = formatter.buffer.as_bytes();
+
+    let data = __gecko_alloc(bytes.len(), 1);
+    ptr::copy_nonoverlapping(bytes.as_ptr(), data, bytes.len());
+    len.write(bytes.len());
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use std::slice;
+
+    use super::*;
+    use crate::alloc::__gecko_free;
+
+    #[test]
+    fn segments() {
+        unsafe {
+            let formatter = __gecko_format_begin();
+            let text = "x = ";
+            __gecko_format_str(formatter, text.as_ptr(), text.len());
+            __gecko_format_int(formatter, -42);
+            __gecko_format_char(formatter, b',');
+            __gecko_format_bool(formatter, true);
+            __gecko_format_uint(formatter, u64::MAX);
+            __gecko_format_float(formatter, 1.5);
+            __gecko_format_repeat(formatter, text.as_ptr(), 1, 3);
+            __gecko_format_repeat(formatter, text.as_ptr(), 1, -1);
+
+            let mut len: usize = 0;
+            let data = __gecko_format_end(formatter, &mut len);
+            assert_eq!(slice::from_raw_parts(data, len), format!("x = -42,true{}1.5xxx", u64::MAX).as_bytes());
+            __gecko_free(data);
+        }
+    }
+}
@@ -0,0 +1,30 @@
+// Runtime library that compiled Gecko programs link against
+
+pub mod alloc;
+pub mod bounds;
+pub mod contract;
+pub mod format;
+pub mod overflow;
+
+// Address of a runtime procedure by its symbol, for programs compiled in memory by 'gecko run'
+// instead of being linked against the library
+pub fn symbol(name: &str) -> Option<*const u8> {
+    Some(match name {
+        "__gecko_alloc" => alloc::__gecko_alloc as *const u8,
+        "__gecko_free" => alloc::__gecko_free as *const u8,
+        "__gecko_index_out_of_bounds" => bounds::__gecko_index_out_of_bounds as *const u8,
+        "__gecko_slice_out_of_bounds" => bounds::__gecko_slice_out_of_bounds as *const u8,
+        "__gecko_contract_failed" => contract::__gecko_contract_failed as *const u8,
+        "__gecko_format_begin" => format::__gecko_format_begin as *const u8,
+        "__gecko_format_str" => format::__gecko_format_str as *const u8,
+        "__gecko_format_repeat" => format::__gecko_format_repeat as *const u8,
+        "__gecko_format_int" => format::__gecko_format_int as *const u8,
+        "__gecko_format_uint" => format::__gecko_format_uint as *const u8,
+        "__gecko_format_float" => format::__gecko_format_float as *const u8,
+        "__gecko_format_bool" => format::__gecko_format_bool as *const u8,
+        "__gecko_format_char" => format::__gecko_format_char as *const u8,
+        "__gecko_format_end" => format::__gecko_format_end as *const u8,
+        "__gecko_overflow" => overflow::__gecko_overflow as *const u8,
+        _ => return None
+    })
+}
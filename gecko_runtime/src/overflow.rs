@@ -0,0 +1,8 @@
+use std::process;
+
+/// Reports an integer overflow in a debug build and aborts
+#[no_mangle]
+pub extern "C" fn __gecko_overflow(line: u32, column: u32) -> ! {
+    eprintln!("Integer overflow at {}:{}", line, column);
+    process::abort()
+}
@@ -0,0 +1,152 @@
+use std::{env, fs, path::{Path, PathBuf}, process::Command};
+
+use gecko_compiler::{
+    codegen::{emit_object, entry, generate_ir, object_isa, BuildMode, OptLevel},
+    tree_type::annotate_file
+};
+use gecko_parser::ast::parse_gecko;
+use target_lexicon::Triple;
+
+// Executables, built by 'gecko build <file> [-o <path>] [-O<level>] [-g] [-l <library>] [-L <directory>]'.
+// The file is compiled to an object file with a startup stub calling its '#[entry]' procedure,
+// which the system C compiler links with the Gecko runtime and the C library
+
+// Static library of gecko_runtime, found next to the compiler unless GECKO_RUNTIME names it
+const RUNTIME: &str = "libgecko_runtime.a";
+// Libraries the runtime and float operators depend on
+const SYSTEM_LIBRARIES: [&str; 3] = ["-lm", "-lpthread", "-ldl"];
+
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub source: String,
+    // Path of the executable, the source's without its extension by default
+    pub output: String,
+    pub mode: BuildMode,
+    // '-O' flag, or the default of the build mode
+    pub opt_level: OptLevel,
+    // '-g', to write DWARF debug information into the executable
+    pub debug_info: bool,
+    // '-l' and '-L' flags passed on to the linker, in order
+    pub linker_flags: Vec<String>,
+}
+
+impl Options {
+    // Options from the arguments following 'build'
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+        let mut source: Option<String> = None;
+        let mut output: Option<String> = None;
+        let mut mode = BuildMode::Debug;
+        let mut opt_level: Option<OptLevel> = None;
+        let mut debug_info = false;
+        let mut linker_flags: Vec<String> = vec!();
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--release" => mode = BuildMode::Release,
+                flag if flag.starts_with("-O") => {
+                    opt_level = Some(OptLevel::parse(flag).ok_or(format!("Unknown optimisation level '{}'", flag))?);
+                },
+                "-g" => debug_info = true,
+                "-o" => output = Some(args.next().ok_or("Expected a path after '-o'")?.clone()),
+                "-l" | "-L" => {
+                    let value = args.next().ok_or(format!("Expected a value after '{}'", arg))?;
+                    linker_flags.push(format!("{}{}", arg, value));
+                },
+                flag if flag.starts_with("-l") || flag.starts_with("-L") => linker_flags.push(flag.to_owned()),
+                flag if flag.starts_with('-') => return Err(format!("Unknown option '{}'", flag)),
+                path if source.is_none() => source = Some(path.to_owned()),
+                path => return Err(format!("Unexpected argument '{}'", path))
+            }
+        }
+
+        let source = source.ok_or("Please specify source file path.")?;
+        let output = match output {
+            Some(output) => output,
+            None => source.strip_suffix(".gk").ok_or("File specified was not a Gecko (.gk) source file.")?.to_owned()
+        };
+        let opt_level = opt_level.unwrap_or(OptLevel::default_for(mode));
+        Ok(Options{ source, output, mode, opt_level, debug_info, linker_flags })
+    }
+}
+
+// Path of the runtime library
+fn runtime() -> Result<PathBuf, String> {
+    if let Some(path) = env::var_os("GECKO_RUNTIME") {
+        return Ok(PathBuf::from(path));
+    }
+    let executable = env::current_exe().map_err(|error| error.to_string())?;
+    executable.parent()
+        .map(|directory| directory.join(RUNTIME))
+        .filter(|path| path.exists())
+        .ok_or_else(|| format!("Unable to find '{}' next to the compiler, build gecko_runtime or set GECKO_RUNTIME", RUNTIME))
+}
+
+// Compiles a source file and links it into an executable
+pub fn build(options: &Options) -> Result<(), String> {
+    let source = fs::read_to_string(&options.source)
+        .map_err(|error| format!("Unable to read '{}': {}", options.source, error))?;
+    let file = parse_gecko(&source)
+        .map_err(|error| format!("Unable to parse '{}':\n{}", options.source, error))?;
+    let (_, symbol_table) = annotate_file(&file).map_err(|error| error.to_string())?;
+
+    let entry_point = entry::entry_point(&file)
+        .and_then(|definition| symbol_table.function(&definition.sig.id))
+        .map_err(|error| error.to_string())?;
+    let triple = Triple::host();
+    let mut program = generate_ir(&file, symbol_table, options.mode, options.opt_level, &triple).map_err(|error| error.to_string())?;
+    entry::startup(&mut program, &entry_point, &triple).map_err(|error| error.to_string())?;
+    let object = object_isa(triple, options.opt_level)
+        .and_then(|isa| emit_object(&program, &*isa, options.debug_info.then(|| Path::new(&options.source))))
+        .map_err(|error| error.to_string())?;
+
+    let object_path = format!("{}.o", options.output);
+    fs::write(&object_path, object)
+        .map_err(|error| format!("Unable to write '{}': {}", object_path, error))?;
+    let linker = env::var("CC").unwrap_or_else(|_| String::from("cc"));
+    let status = Command::new(&linker)
+        .arg(&object_path)
+        .arg(runtime()?)
+        .arg("-o")
+        .arg(&options.output)
+        .args(&options.linker_flags)
+        .args(SYSTEM_LIBRARIES)
+        .status()
+        .map_err(|error| format!("Unable to run the linker '{}': {}", linker, error));
+    fs::remove_file(Path::new(&object_path)).ok();
+    match status? {
+        status if status.success() => Ok(()),
+        status => Err(format!("The linker '{}' failed with {}", linker, status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gecko_compiler::codegen::{BuildMode, OptLevel};
+
+    use super::Options;
+
+    fn parse(args: &str) -> Result<Options, String> {
+        Options::parse(&args.split_whitespace().map(String::from).collect::<Vec<String>>())
+    }
+
+    #[test]
+    fn options() {
+        assert_eq!(parse("main.gk -o app -l ssl -L /opt/lib -lz -L. --release"), Ok(Options{
+            source: String::from("main.gk"),
+            output: String::from("app"),
+            mode: BuildMode::Release,
+            opt_level: OptLevel::O2,
+            debug_info: false,
+            linker_flags: vec!(String::from("-lssl"), String::from("-L/opt/lib"), String::from("-lz"), String::from("-L."))
+        }));
+        assert_eq!(parse("src/main.gk").unwrap().output, "src/main");
+        assert_eq!(parse("main.gk").unwrap().opt_level, OptLevel::O0);
+        assert_eq!(parse("main.gk --release -Os").unwrap().opt_level, OptLevel::Os);
+        assert!(parse("main.gk -g").unwrap().debug_info);
+        assert_eq!(parse("main.gk -O3").err().unwrap(), "Unknown optimisation level '-O3'");
+        assert_eq!(parse("main.gk -o").err().unwrap(), "Expected a path after '-o'");
+        assert_eq!(parse("main.gk -x").err().unwrap(), "Unknown option '-x'");
+        assert_eq!(parse("main.c").err().unwrap(), "File specified was not a Gecko (.gk) source file.");
+    }
+}
@@ -0,0 +1,503 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf}
+};
+
+use gecko_parser::{
+    ast::parse_gecko,
+    expression::TypeQueryKind,
+    node::{DocComment, File, NodeType, TypeKind, TypeSpecifier},
+    statement::{ConstantDefinition, Signature, StructDefinition, TypeAlias}
+};
+
+// Static HTML documentation of a module tree, generated by 'gecko doc <path> [--out=<dir>]'.
+// A file is a single module, and a directory a module for each .gk file below it, named by its
+// path. Every module gets a page, and index.html lists them with a search over all items
+
+pub const DEFAULT_OUTPUT: &str = "doc";
+
+// Source file parsed as a module, e.g. ["std", "io"] for std/io.gk
+pub struct Module {
+    pub path: Vec<String>,
+    pub file: File
+}
+
+impl Module {
+    fn name(&self) -> String {
+        self.path.join("::")
+    }
+
+    // Page of the module, kept flat so every page links to the others the same way
+    fn page(&self) -> String {
+        format!("{}.html", self.path.join("."))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ItemKind {
+    Struct,
+    Type,
+    Proc,
+    Const
+}
+
+impl ItemKind {
+    fn name(&self) -> &'static str {
+        match self {
+            ItemKind::Struct => "struct",
+            ItemKind::Type => "type",
+            ItemKind::Proc => "proc",
+            ItemKind::Const => "const"
+        }
+    }
+}
+
+// Documented item of a module
+pub(crate) enum Item<'a> {
+    Struct(&'a StructDefinition),
+    Type(&'a TypeAlias),
+    Proc{ sig: &'a Signature, external: bool, docs: &'a Option<DocComment> },
+    Const(&'a ConstantDefinition)
+}
+
+impl<'a> Item<'a> {
+    pub(crate) fn of(stmt: &'a NodeType) -> Option<Self> {
+        match stmt {
+            NodeType::StructDefinition(definition) => Some(Item::Struct(definition)),
+            NodeType::TypeAlias(alias) => Some(Item::Type(alias)),
+            NodeType::FunctionDefinition(definition) => Some(Item::Proc{ sig: &definition.sig, external: false, docs: &definition.docs }),
+            NodeType::FunctionDeclaration(declaration) => {
+                Some(Item::Proc{ sig: &declaration.sig, external: declaration.extern_token.is_some(), docs: &declaration.docs })
+            },
+            NodeType::ConstantDefinition(definition) => Some(Item::Const(definition)),
+            _ => None
+        }
+    }
+
+    fn kind(&self) -> ItemKind {
+        match self {
+            Item::Struct(_) => ItemKind::Struct,
+            Item::Type(_) => ItemKind::Type,
+            Item::Proc{ .. } => ItemKind::Proc,
+            Item::Const(_) => ItemKind::Const
+        }
+    }
+
+    pub(crate) fn name(&self) -> &'a str {
+        match self {
+            Item::Struct(definition) => &definition.id.name,
+            Item::Type(alias) => &alias.id.name,
+            Item::Proc{ sig, .. } => &sig.id.name,
+            Item::Const(definition) => &definition.id.name
+        }
+    }
+
+    pub(crate) fn docs(&self) -> &'a Option<DocComment> {
+        match self {
+            Item::Struct(definition) => &definition.docs,
+            Item::Type(alias) => &alias.docs,
+            Item::Proc{ docs, .. } => docs,
+            Item::Const(definition) => &definition.docs
+        }
+    }
+
+    // Fragment of the item on its module page
+    fn anchor(&self) -> String {
+        format!("{}.{}", self.kind().name(), self.name())
+    }
+}
+
+fn items(module: &Module) -> Vec<Item<'_>> {
+    module.file.stmts.iter().filter_map(|stmt| Item::of(stmt)).collect()
+}
+
+// Links of struct and type alias names to their documentation. A name defined by several modules links to the first
+type Links = HashMap<String, String>;
+
+fn links(modules: &[Module]) -> Links {
+    let mut links = Links::new();
+    for module in modules {
+        for item in items(module) {
+            if matches!(item.kind(), ItemKind::Struct | ItemKind::Type) {
+                links.entry(item.name().to_owned()).or_insert_with(|| format!("{}#{}", module.page(), item.anchor()));
+            }
+        }
+    }
+    links
+}
+
+// Parses the modules below 'root' and writes their documentation to 'output'
+pub fn document(root: &Path, output: &Path) -> Result<(), String> {
+    let mut modules: Vec<Module> = vec!();
+    for (path, file) in sources(root)? {
+        let source = fs::read_to_string(&file)
+            .map_err(|error| format!("Unable to read '{}': {}", file.display(), error))?;
+        let file = parse_gecko(&source)
+            .map_err(|error| format!("Unable to parse '{}':\n{}", file.display(), error))?;
+        modules.push(Module{ path, file });
+    }
+
+    fs::create_dir_all(output)
+        .map_err(|error| format!("Unable to create '{}': {}", output.display(), error))?;
+    for (name, contents) in generate(&modules) {
+        fs::write(output.join(&name), contents)
+            .map_err(|error| format!("Unable to write '{}': {}", name, error))?;
+    }
+    Ok(())
+}
+
+// Source files of the module tree at 'root' with their module paths, sorted by path
+pub(crate) fn sources(root: &Path) -> Result<Vec<(Vec<String>, PathBuf)>, String> {
+    if root.is_file() {
+        let stem = root.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        return Ok(vec!((vec!(stem), root.to_path_buf())));
+    }
+
+    let mut sources: Vec<(Vec<String>, PathBuf)> = vec!();
+    let mut directories: Vec<PathBuf> = vec!(root.to_path_buf());
+    while let Some(directory) = directories.pop() {
+        let entries = fs::read_dir(&directory)
+            .map_err(|error| format!("Unable to read '{}': {}", directory.display(), error))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                directories.push(path);
+            } else if path.extension().is_some_and(|extension| extension == "gk") {
+                let module: Vec<String> = path.strip_prefix(root)
+                    .unwrap()
+                    .with_extension("")
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy().into_owned())
+                    .collect();
+                sources.push((module, path));
+            }
+        }
+    }
+    sources.sort();
+    Ok(sources)
+}
+
+// Name and contents of every file of the site
+pub fn generate(modules: &[Module]) -> Vec<(String, String)> {
+    let links = links(modules);
+    let mut files: Vec<(String, String)> = vec!(
+        (String::from("index.html"), index_page(modules)),
+        (String::from("search-index.js"), search_index(modules)),
+        (String::from("style.css"), String::from(STYLE))
+    );
+    for module in modules {
+        files.push((module.page(), module_page(module, &links)));
+    }
+    files
+}
+
+//
+// Pages
+//
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<link rel=\"stylesheet\" href=\"style.css\">\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title), body
+    )
+}
+
+fn index_page(modules: &[Module]) -> String {
+    let mut body = String::from("<h1>Documentation</h1>\n");
+    body.push_str("<input id=\"search\" type=\"search\" placeholder=\"Search\" autocomplete=\"off\">\n<ul id=\"results\"></ul>\n");
+    body.push_str("<h2>Modules</h2>\n");
+    body.push_str(&module_tree(modules, &[]));
+    body.push_str("<script src=\"search-index.js\"></script>\n");
+    body.push_str(&format!("<script>\n{}</script>\n", SEARCH));
+    page("Documentation", &body)
+}
+
+// Nested list of the modules below 'prefix'. Directories without a module of their own are plain text
+fn module_tree(modules: &[Module], prefix: &[String]) -> String {
+    let mut children: Vec<&String> = modules
+        .iter()
+        .filter(|module| module.path.len() > prefix.len() && module.path.starts_with(prefix))
+        .map(|module| &module.path[prefix.len()])
+        .collect();
+    children.dedup();
+    if children.is_empty() {
+        return String::new();
+    }
+
+    let mut list = String::from("<ul>\n");
+    for child in children {
+        let path: Vec<String> = prefix.iter().cloned().chain([child.clone()]).collect();
+        match modules.iter().find(|module| module.path == path) {
+            Some(module) => list.push_str(&format!("<li><a href=\"{}\">{}</a>", module.page(), escape(child))),
+            None => list.push_str(&format!("<li>{}", escape(child)))
+        }
+        list.push_str(&module_tree(modules, &path));
+        list.push_str("</li>\n");
+    }
+    list.push_str("</ul>\n");
+    list
+}
+
+fn module_page(module: &Module, links: &Links) -> String {
+    let items = items(module);
+    let mut body = format!("<p><a href=\"index.html\">Index</a></p>\n<h1>Module <code>{}</code></h1>\n", escape(&module.name()));
+    for (kind, heading) in [(ItemKind::Struct, "Structs"), (ItemKind::Type, "Type aliases"), (ItemKind::Proc, "Procedures"), (ItemKind::Const, "Constants")] {
+        let section: Vec<&Item> = items.iter().filter(|item| item.kind() == kind).collect();
+        if section.is_empty() {
+            continue;
+        }
+        body.push_str(&format!("<h2>{}</h2>\n", heading));
+        for item in section {
+            body.push_str(&item_html(item, links));
+        }
+    }
+    page(&module.name(), &body)
+}
+
+fn item_html(item: &Item, links: &Links) -> String {
+    let mut html = format!("<section class=\"item\" id=\"{}\">\n", escape(&item.anchor()));
+    html.push_str(&format!("<pre class=\"signature\">{}</pre>\n", declaration_html(item, links)));
+    if let Some(docs) = item.docs() {
+        html.push_str(&docs_html(docs));
+    }
+    if let Item::Struct(definition) = item {
+        if !definition.fields.is_empty() {
+            html.push_str("<dl class=\"fields\">\n");
+            for field in &definition.fields {
+                html.push_str(&format!("<dt><code>{}: {}</code></dt>\n", escape(&field.id.name), type_html(&field.ty, links)));
+                if let Some(docs) = &field.docs {
+                    html.push_str(&format!("<dd>{}</dd>\n", docs_html(docs)));
+                }
+            }
+            html.push_str("</dl>\n");
+        }
+    }
+    html.push_str("</section>\n");
+    html
+}
+
+// Declaration of an item as it is written in source, without any body
+fn declaration_html(item: &Item, links: &Links) -> String {
+    match item {
+        Item::Struct(definition) => format!("struct {}", escape(&definition.id.name)),
+        Item::Type(alias) => format!("type {}: {}", escape(&alias.id.name), type_html(&alias.ty, links)),
+        Item::Proc{ sig, external, .. } => signature_html(sig, *external, links),
+        Item::Const(definition) => match &definition.ty {
+            Some(ty) => format!("const {}: {}", escape(&definition.id.name), type_html(ty, links)),
+            None => format!("const {}", escape(&definition.id.name))
+        }
+    }
+}
+
+fn signature_html(sig: &Signature, external: bool, links: &Links) -> String {
+    let mut html = String::new();
+    if external {
+        html.push_str("extern ");
+    }
+    if sig.const_token.is_some() {
+        html.push_str("const ");
+    }
+    if sig.unsafe_token.is_some() {
+        html.push_str("unsafe ");
+    }
+
+    let mut params: Vec<String> = sig.params.parameters
+        .iter()
+        .map(|(param, _)| format!("{}: {}", escape(&param.pattern.to_string()), type_html(&param.ty, links)))
+        .collect();
+    if sig.params.variadic.is_some() {
+        params.push(String::from("..."));
+    }
+    html.push_str(&format!("proc {}({}) -&gt; {}", escape(&sig.id.name), params.join(", "), type_html(&sig.output.ty, links)));
+
+    for contract in &sig.contracts {
+        html.push_str(&format!("\n    {} {}", escape(&contract.token.value), escape(&contract.text)));
+    }
+    html
+}
+
+// Type as written in source, with struct and type alias names linked to their documentation
+fn type_html(ty: &TypeSpecifier, links: &Links) -> String {
+    match &ty.kind {
+        TypeKind::Named{ id, generics } => {
+            let name = match links.get(&id.name) {
+                Some(href) => format!("<a href=\"{}\">{}</a>", escape(href), escape(&id.name)),
+                None => escape(&id.name)
+            };
+            if generics.is_empty() {
+                return name;
+            }
+            let generics: Vec<String> = generics.iter().map(|generic| type_html(generic, links)).collect();
+            format!("{}&lt;{}&gt;", name, generics.join(", "))
+        },
+        TypeKind::Array{ element, length: Some(length), .. } => format!("[{}; {}]", type_html(element, links), constant_html(length, links)),
+        TypeKind::Array{ element, length: None, .. } => format!("[{}]", type_html(element, links)),
+        TypeKind::Pointer{ pointee, .. } => format!("*{}", type_html(pointee, links)),
+        TypeKind::Tuple{ elements, .. } => {
+            let elements: Vec<String> = elements.iter().map(|element| type_html(element, links)).collect();
+            format!("({})", elements.join(", "))
+        }
+    }
+}
+
+// Constant expression giving the length of an array type
+fn constant_html(node: &NodeType, links: &Links) -> String {
+    match node {
+        NodeType::Integer(integer) => integer.value.to_string(),
+        NodeType::Identifier(id) => escape(&id.name),
+        NodeType::BinaryOperator(operator) => {
+            format!("{} {} {}", constant_html(&operator.left, links), escape(&operator.op.value), constant_html(&operator.right, links))
+        },
+        NodeType::TypeQuery(query) => match &query.kind {
+            TypeQueryKind::Size => format!("sizeof({})", type_html(&query.ty, links)),
+            TypeQueryKind::Align => format!("alignof({})", type_html(&query.ty, links)),
+            TypeQueryKind::Offset{ field, .. } => format!("offsetof({}, {})", type_html(&query.ty, links), escape(&field.name))
+        },
+        _ => String::from("_")
+    }
+}
+
+// Paragraphs of a doc comment, with `code` spans
+fn docs_html(docs: &DocComment) -> String {
+    docs.text
+        .split("\n\n")
+        .filter(|paragraph| !paragraph.trim().is_empty())
+        .map(|paragraph| {
+            let text: Vec<String> = escape(paragraph.trim())
+                .split('`')
+                .enumerate()
+                .map(|(index, part)| if index % 2 == 1 { format!("<code>{}</code>", part) } else { part.to_owned() })
+                .collect();
+            format!("<p>{}</p>\n", text.concat())
+        })
+        .collect()
+}
+
+//
+// Search
+//
+
+// Script defining SEARCH_INDEX, an entry for every item of every module
+fn search_index(modules: &[Module]) -> String {
+    let mut entries: Vec<String> = vec!();
+    for module in modules {
+        for item in items(module) {
+            let summary = item.docs().as_ref().map(|docs| docs.summary()).unwrap_or_default();
+            entries.push(format!(
+                "{{\"name\":{},\"kind\":{},\"module\":{},\"href\":{},\"summary\":{}}}",
+                json_string(item.name()),
+                json_string(item.kind().name()),
+                json_string(&module.name()),
+                json_string(&format!("{}#{}", module.page(), item.anchor())),
+                json_string(&summary)
+            ));
+        }
+    }
+    format!("var SEARCH_INDEX = [\n{}\n];\n", entries.join(",\n"))
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            // Keeps the script from being closed by the text
+            '<' => json.push_str("\\u003c"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c)
+        }
+    }
+    json.push('"');
+    json
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const SEARCH: &str = r#"var search = document.getElementById("search");
+var results = document.getElementById("results");
+search.addEventListener("input", function() {
+    var query = search.value.trim().toLowerCase();
+    results.innerHTML = "";
+    if (query === "") {
+        return;
+    }
+    SEARCH_INDEX
+        .filter(function(entry) { return entry.name.toLowerCase().indexOf(query) !== -1; })
+        .slice(0, 50)
+        .forEach(function(entry) {
+            var link = document.createElement("a");
+            link.href = entry.href;
+            link.textContent = entry.module + "::" + entry.name;
+            var item = document.createElement("li");
+            item.appendChild(document.createTextNode(entry.kind + " "));
+            item.appendChild(link);
+            if (entry.summary !== "") {
+                item.appendChild(document.createTextNode(" - " + entry.summary));
+            }
+            results.appendChild(item);
+        });
+});
+"#;
+
+const STYLE: &str = "body { font-family: sans-serif; max-width: 60em; margin: 2em auto; padding: 0 1em; line-height: 1.5; }
+pre.signature { background: #f4f4f4; padding: 0.5em 1em; overflow-x: auto; }
+section.item { margin-bottom: 2em; }
+dl.fields dt { margin-top: 0.5em; }
+a { color: #2a6db0; text-decoration: none; }
+#search { width: 100%; padding: 0.5em; font-size: 1em; }
+";
+
+#[cfg(test)]
+mod tests {
+    use gecko_parser::ast::parse_gecko;
+
+    use super::{generate, Module};
+
+    fn module(path: &[&str], source: &str) -> Module {
+        Module{ path: path.iter().map(|part| part.to_string()).collect(), file: parse_gecko(source).unwrap() }
+    }
+
+    fn file<'a>(files: &'a [(String, String)], name: &str) -> &'a str {
+        &files.iter().find(|(file, _)| file == name).unwrap().1
+    }
+
+    #[test]
+    fn pages() {
+        let modules = vec!(
+            module(&["geometry"], "/// Point on a plane\nstruct Point {\n    /// Horizontal position\n    x: int,\n    y: int\n}"),
+            module(&["geometry", "ops"], "/** Moves `p` right\n *\n * Returns a copy */\nproc shift(p: Point, by: Meters) -> Point\n    requires by > 0\n{ return p; }\nconst LIMIT: [u8; 4] = [1, 2, 3, 4];\n/// Distance along an axis\ntype Meters: int;\ntype Path: [*Point];")
+        );
+        let files = generate(&modules);
+
+        let ops = file(&files, "geometry.ops.html");
+        assert!(ops.contains("proc shift(p: <a href=\"geometry.html#struct.Point\">Point</a>, by: <a href=\"geometry.ops.html#type.Meters\">Meters</a>) -&gt; <a href=\"geometry.html#struct.Point\">Point</a>\n    requires by &gt; 0"));
+        assert!(ops.contains("<h2>Type aliases</h2>\n<section class=\"item\" id=\"type.Meters\">\n<pre class=\"signature\">type Meters: int</pre>\n<p>Distance along an axis</p>"));
+        assert!(ops.contains("type Path: [*<a href=\"geometry.html#struct.Point\">Point</a>]"));
+        assert!(ops.contains("<p>Moves <code>p</code> right</p>\n<p>Returns a copy</p>"));
+        assert!(ops.contains("const LIMIT: [u8; 4]"));
+
+        let geometry = file(&files, "geometry.html");
+        assert!(geometry.contains("<section class=\"item\" id=\"struct.Point\">"));
+        assert!(geometry.contains("<dt><code>x: int</code></dt>\n<dd><p>Horizontal position</p>\n</dd>"));
+
+        let index = file(&files, "index.html");
+        assert!(index.contains("<li><a href=\"geometry.html\">geometry</a><ul>\n<li><a href=\"geometry.ops.html\">ops</a></li>"));
+    }
+
+    #[test]
+    fn search_index() {
+        let files = generate(&[module(&["io"], "/// Writes \"text\"\n///\n/// More\nextern proc puts(s: *u8) -> int;\n/// Descriptor\ntype Handle: i32;")]);
+        assert_eq!(
+            file(&files, "search-index.js"),
+            "var SEARCH_INDEX = [\n{\"name\":\"puts\",\"kind\":\"proc\",\"module\":\"io\",\"href\":\"io.html#proc.puts\",\"summary\":\"Writes \\\"text\\\"\"},\n{\"name\":\"Handle\",\"kind\":\"type\",\"module\":\"io\",\"href\":\"io.html#type.Handle\",\"summary\":\"Descriptor\"}\n];\n"
+        );
+    }
+}
@@ -0,0 +1,310 @@
+use std::{env, fmt::Display, fs, path::Path, process::{self, Command}};
+
+use gecko_compiler::codegen::{BuildMode, OptLevel};
+use gecko_parser::{ast::parse_gecko, node::File};
+
+use crate::{doc::{self, Item}, run};
+
+// Examples in doc comments, run by 'gecko test <path>'. Every ```gecko block becomes the body of
+// a procedure added to the end of its module, which is compiled and then run by 'gecko run
+// --entry' in a process of its own, as failed assertions abort. The test starts at that procedure
+// instead of the module's own '#[entry]' procedure. A block marked 'no_run' is only compiled, and
+// one marked 'should_fail' passes only if it compiles and then fails when run
+
+// Procedure the code of a doc-test is wrapped in, the entry point of the test
+const WRAPPER: &str = "doctest_entry";
+
+// ```gecko block of a doc comment
+pub struct DocTest {
+    // Item whose documentation the block is part of
+    pub item: String,
+    // Line of the opening fence
+    pub line: usize,
+    // Lines of code, with the column each starts at in the source
+    pub code: Vec<(usize, String)>,
+    pub no_run: bool,
+    pub should_fail: bool
+}
+
+// Text of a line of a doc comment without its delimiters, with the column it starts at
+fn doc_line(line: &str) -> (usize, &str) {
+    let mut text: &str = line.trim_start();
+    text = text.strip_prefix("///")
+        .or_else(|| text.strip_prefix("/**"))
+        .or_else(|| if text.starts_with("*/") { None } else { text.strip_prefix('*') })
+        .unwrap_or(text);
+    text = text.strip_prefix(' ').unwrap_or(text);
+    let column = line.len() - text.len() + 1;
+    let text = text.trim_end();
+    (column, text.strip_suffix("*/").unwrap_or(text))
+}
+
+// Doc-tests of the documented items of a file, found in its source by the spans of their comments
+pub fn extract(source: &str, file: &File) -> Vec<DocTest> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut tests: Vec<DocTest> = vec!();
+    for item in file.stmts.iter().filter_map(|stmt| Item::of(stmt)) {
+        let docs = match item.docs() {
+            Some(docs) => docs,
+            None => continue
+        };
+
+        let mut test: Option<DocTest> = None;
+        for line in docs.span.line()..=docs.span.end_line().min(lines.len()) {
+            let (column, text) = doc_line(lines[line - 1]);
+            let fence = text.trim_start().strip_prefix("```");
+            match (&mut test, fence) {
+                (None, Some(info)) => {
+                    let attributes: Vec<&str> = info.split(|c: char| c == ',' || c.is_whitespace()).collect();
+                    if attributes.contains(&"gecko") {
+                        test = Some(DocTest{
+                            item: item.name().to_owned(),
+                            line,
+                            code: vec!(),
+                            no_run: attributes.contains(&"no_run"),
+                            should_fail: attributes.contains(&"should_fail")
+                        });
+                    }
+                },
+                (Some(_), Some(_)) => tests.extend(test.take()),
+                (Some(test), None) => test.code.push((column, text.to_owned())),
+                (None, None) => {}
+            }
+        }
+    }
+    tests
+}
+
+// Why a doc-test did not pass
+enum Failure {
+    // The test does not parse, type-check or lower, so it never ran
+    Compile(String),
+    // The test was compiled but failed when it ran
+    Run(String)
+}
+
+impl DocTest {
+    // Runs the test as part of the module it was written in with the 'gecko' executable, and
+    // reports why it failed. The code is a block of the wrapper, which returns 0 after it
+    pub fn run(&self, source: &str, path: &str, gecko: &Path) -> Result<(), String> {
+        let module = format!("{}\n\n", source);
+        let program = format!(
+            "{}proc {}() -> int {{ {{\n{}\n}};\n0\n}}\n",
+            module,
+            WRAPPER,
+            self.code.iter().map(|(_, code)| code.as_str()).collect::<Vec<&str>>().join("\n")
+        );
+        let locations = Locations{ path, header: module.matches('\n').count() + 1, test: self };
+
+        let result = parse_gecko(&program)
+            .map_err(|error| Failure::Compile(locations.parse_error(error)))
+            .and_then(|file| compile(&file).map_err(|error| Failure::Compile(locations.relocate(&error))))
+            .and_then(|_| match self.no_run {
+                true => Ok(()),
+                false => execute(&program, gecko, self.line).map_err(|error| Failure::Run(locations.relocate(&error)))
+            });
+        // Tests that do not compile fail whether or not they should, as they test nothing
+        match (result, self.should_fail) {
+            (Ok(()), false) | (Err(Failure::Run(_)), true) => Ok(()),
+            (Ok(()), true) => Err(String::from("Expected the test to fail, but it passed")),
+            (Err(Failure::Compile(error)), _) => Err(format!("The test failed to compile: {}", error)),
+            (Err(Failure::Run(error)), false) => Err(error)
+        }
+    }
+}
+
+// Compiles a program the way 'gecko run --entry' does, so errors are reported without running it
+fn compile(file: &File) -> Result<(), String> {
+    let mode = BuildMode::Debug;
+    run::load(file, mode, OptLevel::default_for(mode), Some(WRAPPER))
+        .map(drop)
+        .map_err(|error| error.text)
+}
+
+// Runs a program with 'gecko run', returning what it printed if it failed
+fn execute(program: &str, gecko: &Path, line: usize) -> Result<(), String> {
+    let file = env::temp_dir().join(format!("gecko_doctest_{}_{}.gk", process::id(), line));
+    fs::write(&file, program).map_err(|error| format!("Unable to write '{}': {}", file.display(), error))?;
+    let output = Command::new(gecko).arg("run").arg(format!("--entry={}", WRAPPER)).arg(&file).output();
+    fs::remove_file(&file).ok();
+
+    let output = output.map_err(|error| format!("Unable to run '{}': {}", gecko.display(), error))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    // What the test printed, followed by the reason it stopped
+    let mut lines: Vec<String> = vec!();
+    for stream in [&output.stdout, &output.stderr] {
+        let text = String::from_utf8_lossy(stream);
+        if !text.trim_end().is_empty() {
+            lines.push(text.trim_end().to_owned());
+        }
+    }
+    if output.stderr.is_empty() {
+        lines.push(format!("The test exited with {}", output.status));
+    }
+    Err(lines.join("\n"))
+}
+
+// Maps positions in the program a doc-test is run as back to the source it was written in
+struct Locations<'a> {
+    path: &'a str,
+    // Line of the program the wrapper starts on
+    header: usize,
+    test: &'a DocTest
+}
+
+impl<'a> Locations<'a> {
+    fn original(&self, line: usize, column: usize) -> (usize, usize) {
+        match line.checked_sub(self.header + 1).and_then(|index| self.test.code.get(index)) {
+            Some((start, _)) => (self.test.line + line - self.header, column + start - 1),
+            // The module itself is unchanged, and the wrapper's braces are reported at the fence
+            None if line < self.header => (line, column),
+            None => (self.test.line, 1)
+        }
+    }
+
+    // Rewrites every 'at line:column' of a diagnostic as 'at path:line:column' in the source.
+    // A diagnostic without a position is given the position of the block
+    fn relocate(&self, text: &str) -> String {
+        let mut output = String::new();
+        let mut rest: &str = text;
+        let mut located = false;
+        while let Some(index) = rest.find("at ") {
+            output.push_str(&rest[..index + 3]);
+            rest = &rest[index + 3..];
+            if let Some((line, column, length)) = position(rest) {
+                let (line, column) = self.original(line, column);
+                output.push_str(&format!("{}:{}:{}", self.path, line, column));
+                rest = &rest[length..];
+                located = true;
+            }
+        }
+        output.push_str(rest);
+        if !located {
+            output.push_str(&format!(", in the doc-test at {}:{}", self.path, self.test.line));
+        }
+        output
+    }
+
+    // Message of a parse error at its position in the source, without the snippet of the program
+    fn parse_error(&self, error: impl Display) -> String {
+        let text = error.to_string();
+        let message: Vec<&str> = text.lines().filter_map(|line| line.trim().strip_prefix("= ")).collect();
+        match text.lines().next().and_then(|line| line.trim().strip_prefix("--> ")).and_then(position) {
+            Some((line, column, _)) => self.relocate(&format!("{}, at {}:{}", message.join(" "), line, column)),
+            None => text
+        }
+    }
+}
+
+// 'line:column' at the start of the text, and its length
+fn position(text: &str) -> Option<(usize, usize, usize)> {
+    let line_length = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let line: usize = text[..line_length].parse().ok()?;
+    let rest: &str = text[line_length..].strip_prefix(':')?;
+    let column_length = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    let column: usize = rest[..column_length].parse().ok()?;
+    Some((line, column, line_length + 1 + column_length))
+}
+
+// Runs the doc-tests of the modules below 'root', returning whether all of them passed
+pub fn test(root: &Path) -> Result<bool, String> {
+    let gecko = env::current_exe().map_err(|error| format!("Unable to find the gecko executable: {}", error))?;
+    let (mut passed, mut failed) = (0, 0);
+    for (module, file) in doc::sources(root)? {
+        let path = file.display().to_string();
+        let source = fs::read_to_string(&file)
+            .map_err(|error| format!("Unable to read '{}': {}", path, error))?;
+        let tree = parse_gecko(&source)
+            .map_err(|error| format!("Unable to parse '{}':\n{}", path, error))?;
+
+        for test in extract(&source, &tree) {
+            let name = format!("{}::{} ({}:{})", module.join("::"), test.item, path, test.line);
+            match test.run(&source, &path, &gecko) {
+                Ok(()) => {
+                    println!("test {} ... ok", name);
+                    passed += 1;
+                },
+                Err(error) => {
+                    println!("test {} ... FAILED\n    {}", name, error.replace('\n', "\n    "));
+                    failed += 1;
+                }
+            }
+        }
+    }
+    println!("\ndoc-test result: {} passed; {} failed", passed, failed);
+    Ok(failed == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use gecko_parser::ast::parse_gecko;
+
+    use super::{extract, DocTest};
+
+    fn tests(source: &str) -> Vec<DocTest> {
+        extract(source, &parse_gecko(source).unwrap())
+    }
+
+    #[test]
+    fn extraction() {
+        let source = "\
+/// Doubles `n`
+///
+/// ```gecko
+/// assert(double(2) == 4);
+/// ```
+///
+/// ```text
+/// not a test
+/// ```
+const proc double(n: int) -> int { n * 2 }
+
+/**
+ * ```gecko,no_run
+ * let p := Point{ x: 1 };
+ * ```
+ * ```gecko should_fail
+ *     assert(false);
+ * ```
+ */
+struct Point { x: int }";
+        let tests = tests(source);
+        assert_eq!(tests.len(), 3);
+        assert_eq!((tests[0].item.as_str(), tests[0].line), ("double", 3));
+        assert_eq!(tests[0].code, vec!((5, String::from("assert(double(2) == 4);"))));
+        assert!(!tests[0].no_run && !tests[0].should_fail);
+        assert_eq!((tests[1].item.as_str(), tests[1].line, tests[1].no_run), ("Point", 13, true));
+        assert_eq!((tests[2].line, tests[2].should_fail), (16, true));
+        assert_eq!(tests[2].code, vec!((4, String::from("    assert(false);"))));
+    }
+
+    // Tests that are only compiled never start the gecko executable, see tests/cli.rs for the rest
+    #[test]
+    fn compile() {
+        let source = "\
+/// ```gecko,no_run
+/// let x: int = runtime();
+/// ```
+/// ```gecko
+/// let x: int = true;
+/// ```
+/// ```gecko should_fail
+/// let x: int = true;
+/// ```
+/// ```gecko should_fail
+/// let x: int = ;
+/// ```
+proc runtime() -> int { return 1; }";
+        let gecko = Path::new("gecko");
+        let results: Vec<Result<(), String>> = tests(source).iter().map(|test| test.run(source, "lib.gk", gecko)).collect();
+        assert_eq!(results[0], Ok(()));
+        assert_eq!(results[1], Err(String::from("The test failed to compile: Type mismatch. Expected 'i32', got 'bool', in the doc-test at lib.gk:4")));
+        // Only failing when run passes a 'should_fail' test
+        assert_eq!(results[2], Err(String::from("The test failed to compile: Type mismatch. Expected 'i32', got 'bool', in the doc-test at lib.gk:7")));
+        assert_eq!(results[3], Err(String::from("The test failed to compile: expected term, at lib.gk:11:18")));
+    }
+}
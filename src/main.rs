@@ -2,23 +2,122 @@ extern crate gecko_parser;
 extern crate gecko_compiler;
 use gecko_parser::{
     ast::parse_gecko,
-    node::ASTNode
+    node::ASTNode,
+    print
 };
 use gecko_compiler::{
-    tree_type::annotate_file,
-    codegen::generate_ir
+    tree_type::annotate_file_for,
+    codegen::{dump, emit_object, generate_ir, object_isa, parse_target, BuildMode, OptLevel}
 };
-use std::{env, fs};
+use std::{env, fs, path::Path, process};
+
+use target_lexicon::Triple;
+
+mod build;
+mod doc;
+mod doctest;
+mod run;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    let argc = args.len();
-    if argc < 2 {
-        println!("Please specify source file path.");
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    // 'gecko doc <path> [--out=<dir>]' documents a module tree instead of compiling it
+    if args.first().is_some_and(|arg| arg == "doc") {
+        let out = args.iter().find_map(|arg| arg.strip_prefix("--out=")).unwrap_or(doc::DEFAULT_OUTPUT);
+        let root = match args.iter().skip(1).find(|arg| !arg.starts_with("--")) {
+            Some(root) => root,
+            None => {
+                println!("Please specify source file or directory path.");
+                return;
+            }
+        };
+        match doc::document(Path::new(root), Path::new(out)) {
+            Ok(()) => println!("Documentation written to '{}'.", out),
+            Err(e) => println!("{}", e)
+        }
+        return;
+    }
+    // 'gecko test <path>' runs the examples in the doc comments of a module tree
+    if args.first().is_some_and(|arg| arg == "test") {
+        let root = match args.get(1) {
+            Some(root) => root,
+            None => {
+                println!("Please specify source file or directory path.");
+                return;
+            }
+        };
+        match doctest::test(Path::new(root)) {
+            Ok(true) => {},
+            Ok(false) => process::exit(1),
+            Err(e) => {
+                println!("{}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+    // 'gecko build <file> [-o <path>]' links an executable starting at the '#[entry]' procedure
+    if args.first().is_some_and(|arg| arg == "build") {
+        let result = build::Options::parse(&args[1..]).and_then(|options| build::build(&options));
+        if let Err(e) = result {
+            println!("{}", e);
+            process::exit(1);
+        }
         return;
     }
 
-    let path = &args[1];
+    // 'gecko run <file> [args...]' runs the program in memory, exiting with its exit code
+    if args.first().is_some_and(|arg| arg == "run") {
+        match run::run(&args[1..]) {
+            Ok(code) => process::exit(code),
+            Err(e) => {
+                println!("{}", e);
+                process::exit(1);
+            }
+        }
+    }
+
+    // Release builds wrap on integer overflow instead of trapping
+    let mode = if args.iter().any(|arg| arg == "--release") { BuildMode::Release } else { BuildMode::Debug };
+    // '-O0', '-O1', '-O2' or '-Os', by default -O0 for debug builds and -O2 for release builds
+    let opt_level = match args.iter().find(|arg| arg.starts_with("-O")) {
+        Some(flag) => match OptLevel::parse(flag) {
+            Some(opt_level) => opt_level,
+            None => {
+                println!("Unknown optimisation level '{}'", flag);
+                process::exit(1);
+            }
+        },
+        None => OptLevel::default_for(mode)
+    };
+    // Prints the source once macros have been expanded, without compiling it
+    let emit_expanded = args.iter().any(|arg| arg == "--emit=expanded");
+    // Writes a relocatable object file, to '-o <path>' or next to the source file
+    let emit_object_file = args.iter().any(|arg| arg == "--emit=obj");
+    // Writes DWARF debug information into the object file
+    let debug_info = args.iter().any(|arg| arg == "-g");
+    let output = args.iter().position(|arg| arg == "-o").and_then(|index| args.get(index + 1));
+    // Prints the Cranelift IR or the disassembly of every procedure, or only of '--dump-fn <name>'
+    let emit_clif = args.iter().any(|arg| arg == "--emit=clif");
+    let emit_asm = args.iter().any(|arg| arg == "--emit=asm");
+    let dump_fn = args.iter().position(|arg| arg == "--dump-fn").and_then(|index| args.get(index + 1)).map(String::as_str);
+    // Compiles for '--target=<name>', such as x86_64-windows, instead of the host
+    let triple = match args.iter().find_map(|arg| arg.strip_prefix("--target=")).map(parse_target) {
+        Some(Ok(triple)) => triple,
+        Some(Err(error)) => {
+            println!("{}", error);
+            process::exit(1);
+        },
+        None => Triple::host()
+    };
+    let takes_value = |arg: &String| arg == "-o" || arg == "--dump-fn";
+    let path = match args.iter().enumerate().find(|(index, arg)| !arg.starts_with('-') && (*index == 0 || !takes_value(&args[index - 1]))).map(|(_, arg)| arg) {
+        Some(path) => path,
+        None => {
+            println!("Please specify source file path.");
+            return;
+        }
+    };
     if !path.ends_with(".gk") {
         println!("File specified was not a Gecko (.gk) source file.");
         return;
@@ -29,17 +128,61 @@ fn main() {
     let file = parse_gecko(&source);
     match file {
         Ok(_) => {
-            println!("Successfully constructed AST.");
-
-            let mut indent: String = String::from("");
             let file = file.unwrap();
-            println!("{}", (&file).display_tree(&mut indent, true));
+            if emit_expanded {
+                print!("{}", print::source(&file));
+                return;
+            }
+            // Dumps are printed alone, so they can be piped
+            if !emit_clif && !emit_asm {
+                println!("Successfully constructed AST.");
+                let mut indent: String = String::from("");
+                println!("{}", file.display_tree(&mut indent, true));
+            }
 
             // Type check & annotate tree
-            let symbols = annotate_file(&file)
-                .unwrap();
+            let symbols = match annotate_file_for(&file, &triple) {
+                Ok(symbols) => symbols,
+                Err(error) => {
+                    println!("{}", error);
+                    process::exit(1);
+                }
+            };
 
-            generate_ir(&file, symbols.1);
+            let program = match generate_ir(&file, symbols.1, mode, opt_level, &triple) {
+                Ok(program) => program,
+                Err(error) => {
+                    println!("{}", error);
+                    process::exit(1);
+                }
+            };
+            if emit_clif || emit_asm {
+                let dump = match emit_clif {
+                    true => dump::clif(&program, &source, dump_fn),
+                    false => object_isa(triple.clone(), opt_level).and_then(|isa| dump::asm(&program, &*isa, &source, dump_fn))
+                };
+                match dump {
+                    Ok(dump) => print!("{}", dump),
+                    Err(error) => {
+                        println!("{}", error);
+                        process::exit(1);
+                    }
+                }
+            }
+            if emit_object_file {
+                let object = object_isa(triple.clone(), opt_level).and_then(|isa| emit_object(&program, &*isa, debug_info.then(|| Path::new(path))));
+                let output = output.cloned().unwrap_or_else(|| format!("{}.o", path.trim_end_matches(".gk")));
+                match object {
+                    Ok(object) => {
+                        fs::write(&output, object).expect("Unable to write object file.");
+                        println!("Object file written to '{}'.", output);
+                    },
+                    Err(error) => {
+                        println!("{}", error);
+                        process::exit(1);
+                    }
+                }
+            }
         },
         Err(e) => println!("Parsing Unsuccessful: \n {:?}", e)
     }
@@ -0,0 +1,71 @@
+use std::{ffi::CString, fs, os::raw::c_char};
+
+use gecko_compiler::{
+    codegen::{entry, generate_ir, jit::{self, JitProgram}, BuildMode, OptLevel},
+    tree_type::annotate_file,
+    error::TypeError
+};
+use gecko_parser::{ast::parse_gecko, node::File};
+use target_lexicon::Triple;
+
+// Programs run without being linked, by 'gecko run [--release] [-O<level>] [--entry=<name>] <file>
+// [args...]'. The file is compiled into memory and its startup stub called in the compiler's
+// process, with the file and the arguments following it as argv. A '#!' line starting the file is
+// skipped, so scripts can be run directly. '--entry' starts the program at the procedure of that
+// name instead of its '#[entry]' procedure, as doc-tests are run
+
+// Startup stub of a program, see entry::startup
+type Startup = unsafe extern "C" fn(i32, *const *const c_char) -> i32;
+
+// Compiles and runs a program, returning its exit code
+pub fn run(args: &[String]) -> Result<i32, String> {
+    let mut mode = BuildMode::Debug;
+    let mut opt_level: Option<OptLevel> = None;
+    let mut entry: Option<&str> = None;
+    // Options come before the file, as the arguments following it are the program's
+    let options = args.iter().take_while(|arg| arg.starts_with('-')).count();
+    for option in &args[..options] {
+        match option.as_str() {
+            "--release" => mode = BuildMode::Release,
+            option if option.starts_with("--entry=") => entry = option.strip_prefix("--entry="),
+            flag => opt_level = Some(OptLevel::parse(flag).ok_or(format!("Unknown option '{}'", flag))?)
+        }
+    }
+    let opt_level = opt_level.unwrap_or(OptLevel::default_for(mode));
+    let args = &args[options..];
+    let path = args.first().ok_or("Please specify source file path.")?;
+
+    let source = fs::read_to_string(path)
+        .map_err(|error| format!("Unable to read '{}': {}", path, error))?;
+    let file = parse_gecko(&source)
+        .map_err(|error| format!("Unable to parse '{}':\n{}", path, error))?;
+    let loaded = load(&file, mode, opt_level, entry).map_err(|error| error.to_string())?;
+
+    let arguments: Vec<CString> = args.iter()
+        .map(|arg| CString::new(arg.as_str()).map_err(|_| format!("Argument '{}' contains a null character", arg)))
+        .collect::<Result<_, _>>()?;
+    let mut argv: Vec<*const c_char> = arguments.iter().map(|arg| arg.as_ptr()).collect();
+    argv.push(std::ptr::null());
+
+    let startup = loaded.function(entry::STARTUP)
+        .ok_or_else(|| format!("'{}' was not compiled", entry::STARTUP))?;
+    let startup: Startup = unsafe { std::mem::transmute(startup) };
+    Ok(unsafe { startup(arguments.len() as i32, argv.as_ptr()) })
+}
+
+// Compiles a program into memory with its startup stub, calling the procedure named 'entry' if
+// there is one and the '#[entry]' procedure otherwise
+pub fn load(file: &File, mode: BuildMode, opt_level: OptLevel, entry: Option<&str>) -> Result<JitProgram, TypeError> {
+    let (_, symbol_table) = annotate_file(file)?;
+    let entry_point = match entry {
+        Some(name) => entry::entry_named(file, name),
+        None => entry::entry_point(file)
+    };
+    let entry_point = symbol_table.function(&entry_point?.sig.id)?;
+    let mut program = generate_ir(file, symbol_table, mode, opt_level, &Triple::host())?;
+    match entry {
+        Some(_) => entry::startup_named(&mut program, &entry_point, &Triple::host())?,
+        None => entry::startup(&mut program, &entry_point, &Triple::host())?
+    }
+    jit::load(&program, &*jit::jit_isa(opt_level)?, gecko_runtime::symbol)
+}
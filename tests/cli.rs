@@ -0,0 +1,271 @@
+use std::{env, fs, path::PathBuf, process::{Command, Output}};
+
+// Writes 'source' to a file of the temporary directory named after the test
+fn source_file(name: &str, source: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("gecko_cli_{}_{}.gk", name, std::process::id()));
+    fs::write(&path, source).unwrap();
+    path
+}
+
+fn gecko(args: &[&str], path: &PathBuf) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_gecko"))
+        .args(args)
+        .arg(path)
+        .output()
+        .unwrap()
+}
+
+const ILL_TYPED: &str = "#[entry]\nproc main() -> int {\n    return true;\n}\n";
+
+#[test]
+fn type_errors_are_reported() {
+    let path = source_file("ill_typed", ILL_TYPED);
+    for args in [&["run"][..], &["build"], &[]] {
+        let output = gecko(args, &path);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert_eq!(output.status.code(), Some(1), "gecko {:?}: {}", args, stdout);
+        assert!(stdout.ends_with("Type mismatch. Expected return type 'i32', got 'bool'\n"), "gecko {:?}: {}", args, stdout);
+        assert!(output.stderr.is_empty(), "gecko {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    }
+    fs::remove_file(path).unwrap();
+}
+
+const RESULTS: &str = "extern proc printf(format: *char, ...) -> i32;
+
+proc parse(n: int) -> Result<int, str> {
+    if n < 0 { return Err(\"negative\"); }
+    Ok(n * 2)
+}
+
+proc shifted(n: int) -> Result<int, str> {
+    defer unsafe { printf(\"shifted %d\\n\", n); };
+    let doubled := try parse(n);
+    let sum := checked_add(doubled, 2147483640)?;
+    Ok(sum - 2147483640 + 1)
+}
+
+proc show(result: Result<int, str>) -> i32 {
+    unsafe {
+        if result == Ok(5) { printf(\"five\\n\"); }
+        if result == Err(\"negative\") { printf(\"negative\\n\"); }
+        if result == Err(\"integer overflow\") { printf(\"overflow\\n\"); }
+    }
+    0
+}
+
+#[entry]
+proc main() -> i32 {
+    show(shifted(2));
+    show(shifted(0 - 1));
+    show(shifted(4));
+    0
+}
+";
+
+#[test]
+fn try_returns_errors() {
+    let path = source_file("results", RESULTS);
+    for args in [&["run"][..], &["run", "-O2"], &["run", "--release"]] {
+        let output = gecko(args, &path);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert_eq!(output.status.code(), Some(0), "gecko {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+        assert_eq!(stdout, "shifted 2\nfive\nshifted -1\nnegative\nshifted 4\noverflow\n", "gecko {:?}", args);
+    }
+    fs::remove_file(path).unwrap();
+}
+
+const OUT_OF_BOUNDS: &str = "#[entry]
+proc main(argc: int, argv: **char) -> int {
+    let values: [int; 3] = [1, 2, 3];
+    values[..argc + 1][argc + 1]
+}
+";
+
+#[test]
+fn indices_are_bounds_checked() {
+    let path = source_file("out_of_bounds", OUT_OF_BOUNDS);
+    for args in [&["run"][..], &["run", "--release"]] {
+        let output = gecko(args, &path);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success(), "gecko {:?}", args);
+        assert!(stderr.starts_with("Index 2 is out of bounds for length 2 at 4:5\n"), "gecko {:?}: {}", args, stderr);
+    }
+    fs::remove_file(path).unwrap();
+}
+
+const INTERPOLATION: &str = "#[entry]
+proc main() -> int {
+    let x: u8 = 200;
+    let y: i16 = 0 - 7;
+    let f: f32 = 1.5;
+    let name := \"gecko\";
+    assert(\"{name}: {x} {y} {x as int + y as int} {f} {x > 3} {'z'}\" == \"gecko: 200 -7 193 1.5 true z\");
+    assert(name + 42 + \" \" + true == \"gecko42 true\");
+    assert(\"ab\" * 3 + 2 * \"-\" == \"ababab--\");
+    assert(false, \"x = {x}\");
+    0
+}
+";
+
+#[test]
+fn strings_are_formatted() {
+    let path = source_file("interpolation", INTERPOLATION);
+    for args in [&["run"][..], &["run", "-O2"]] {
+        let output = gecko(args, &path);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success(), "gecko {:?}", args);
+        assert!(stderr.starts_with("Assertion `false` failed at 10:5: x = 200\n"), "gecko {:?}: {}", args, stderr);
+    }
+    fs::remove_file(path).unwrap();
+}
+
+const CONST_ARITHMETIC: &str = "\
+const proc shifted(x: u8) -> u8 { (x << 4) >> 4 }
+const proc mixed(a: i16, b: i16) -> i16 { (a * b) / 7 % 100 ^ (a >> 3) | b ** 2 & 255 }
+const proc unsigned(a: u32, b: u32) -> u32 { (a - b) / 3 + (a << 20) % 1000 + (b >> 1) }
+const SHIFTED: u8 = shifted(255);
+const MIXED: i16 = mixed(0 - 300, 90);
+const UNSIGNED: u32 = unsigned(4000000000, 7);
+
+#[entry]
+proc main() -> int {
+    assert(SHIFTED == 15);
+    assert(SHIFTED == shifted(255));
+    assert(MIXED == mixed(0 - 300, 90));
+    assert(UNSIGNED == unsigned(4000000000, 7));
+    0
+}
+";
+
+const CONST_OVERFLOW: &str = "\
+const proc add(a: u8, b: u8) -> int { (a + b) as int }
+const A: int = add(200, 100);
+
+#[entry]
+proc main() -> int {
+    add(200, 100)
+}
+";
+
+#[test]
+fn const_evaluation_matches_runtime() {
+    let path = source_file("const_arithmetic", CONST_ARITHMETIC);
+    for args in [&["run"][..], &["run", "-O2"], &["run", "--release"]] {
+        let output = gecko(args, &path);
+        assert_eq!(output.status.code(), Some(0), "gecko {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    }
+    fs::remove_file(path).unwrap();
+
+    // Arithmetic that traps at runtime is an error when it is evaluated at compile time
+    let path = source_file("const_overflow", CONST_OVERFLOW);
+    let output = gecko(&["run"], &path);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(stdout.contains("Integer overflow in '+' on 'u8'"), "{}", stdout);
+    let source = CONST_OVERFLOW.replace("const A: int = add(200, 100);\n", "");
+    fs::write(&path, source).unwrap();
+    let output = gecko(&["run"], &path);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.starts_with("Integer overflow at 1:42"), "{}", stderr);
+    fs::remove_file(path).unwrap();
+}
+
+const ALLOCATION_OVERFLOW: &str = "\
+proc allocate(n: int) -> int {
+    unsafe { let p: *i64 = new [i64; n]; delete p; }
+    0
+}
+
+#[entry]
+proc main() -> int {
+    allocate(2) + allocate(0 - 1)
+}
+";
+
+#[test]
+fn invalid_allocation_lengths_trap() {
+    let path = source_file("allocation_overflow", ALLOCATION_OVERFLOW);
+    let large = ALLOCATION_OVERFLOW.replace("n: int", "n: u64").replace("0 - 1", "1 << 62");
+    for source in [ALLOCATION_OVERFLOW.to_string(), large] {
+        fs::write(&path, source).unwrap();
+        for args in [&["run"][..], &["run", "--release"]] {
+            let output = gecko(args, &path);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            assert!(!output.status.success());
+            assert!(stderr.starts_with("Integer overflow at 2:28"), "gecko {:?}: {}", args, stderr);
+        }
+    }
+    fs::remove_file(path).unwrap();
+}
+
+const DOC_TESTS: &str = "\
+extern proc printf(format: *char, ...) -> i32;
+extern proc exit(code: i32) -> i32;
+
+/// ```gecko
+/// assert(half(4) == 2);
+/// ```
+/// ```gecko
+/// let n: int = 3;
+/// assert(half(n) == 2, \"rounds down\");
+/// ```
+/// ```gecko should_fail
+/// assert(half(3) == 2);
+/// ```
+/// ```gecko
+/// assert(\"{half(5)}\" == \"2\" && [1, 2][half(2)] == 2);
+/// ```
+/// ```gecko should_fail
+/// let n: int = half(true);
+/// ```
+/// ```gecko
+/// unsafe { printf(\"half of 3 is %d\\n\", half(3)); exit(3); }
+/// ```
+const proc half(n: int) -> int { n / 2 }
+
+// The tests start at their own entry procedure
+#[entry]
+proc main() -> int { half(0 - 2) }
+";
+
+#[test]
+fn doc_tests_are_compiled_and_run() {
+    let path = source_file("doc_tests", DOC_TESTS);
+    let output = gecko(&["test"], &path);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let results: Vec<&str> = stdout.lines().filter(|line| line.starts_with("test ") || line.starts_with("    ")).collect();
+    let file = path.display().to_string();
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert_eq!(results.len(), 10, "{}", stdout);
+    assert!(results[0].ends_with(&format!("({}:4) ... ok", file)), "{}", stdout);
+    assert!(results[1].ends_with(&format!("({}:7) ... FAILED", file)), "{}", stdout);
+    assert_eq!(results[2], format!("    Assertion `half(n) == 2` failed at {}:9:5: rounds down", file));
+    assert!(results[3].ends_with(&format!("({}:11) ... ok", file)), "{}", stdout);
+    assert!(results[4].ends_with(&format!("({}:14) ... ok", file)), "{}", stdout);
+    // Failing to compile is not the failure 'should_fail' expects
+    assert!(results[5].ends_with(&format!("({}:17) ... FAILED", file)), "{}", stdout);
+    assert!(results[6].starts_with("    The test failed to compile: "), "{}", stdout);
+    // What the test printed is reported with how it stopped
+    assert!(results[7].ends_with(&format!("({}:20) ... FAILED", file)), "{}", stdout);
+    assert_eq!(results[8], "    half of 3 is 1");
+    assert_eq!(results[9], format!("    The test exited with exit status: 3, in the doc-test at {}:20", file));
+    fs::remove_file(path).unwrap();
+}
+
+const MACROS: &str = "\
+macro twice($x) { let y := $x; y + y }
+
+#[entry]
+proc main() -> int { twice!(1 + 2) }
+";
+
+#[test]
+fn expanded_source_is_emitted() {
+    let path = source_file("macros", MACROS);
+    let output = gecko(&["--emit=expanded"], &path);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+    assert_eq!(stdout, "#[entry]\nproc main() -> int {\n    {\n        let y#1 := 1 + 2;\n        y#1 + y#1\n    }\n}\n");
+    fs::remove_file(path).unwrap();
+}